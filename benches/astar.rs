@@ -3,6 +3,10 @@ use std::marker::PhantomData;
 use criterion::{criterion_group, criterion_main, Criterion};
 
 use game_lib::actor::Direction;
+use game_lib::path::bucket::{BoundedCostModel, BucketAStar};
+use game_lib::path::dary::DaryHeap;
+use game_lib::path::fringe::FringeSearch;
+use game_lib::path::ida::IdaStar;
 use game_lib::path::{self, astar, HeuristicModel, Model, Optimizer, Sampler};
 use game_lib::Position;
 
@@ -135,6 +139,12 @@ impl<H: Heuristic> HeuristicModel for BenchModel<H> {
     }
 }
 
+impl<H: Heuristic> BoundedCostModel for BenchModel<H> {
+    fn max_edge_cost(&self) -> Self::Cost {
+        3
+    }
+}
+
 #[derive(Debug, Clone)]
 struct Cardinal;
 
@@ -208,6 +218,25 @@ fn map<H: Heuristic>() -> BenchModel<H> {
     BenchModel::new(width, height, map)
 }
 
+/// A walled map split by more walls, each with a gap at the opposite end from the last
+fn large_map<H: Heuristic>() -> BenchModel<H> {
+    let (width, height) = (256, 256);
+    let map = (0..width * height)
+        .map(|i| {
+            let (x, y) = (i % width, i / width);
+            let border = x == 0 || y == 0 || x == width - 1 || y == height - 1;
+            let gap = if (x / 32) % 2 == 0 { y < 8 } else { y >= height - 8 };
+            if border || (x % 32 == 16 && !gap) {
+                Tile::W
+            } else {
+                Tile::O
+            }
+        })
+        .collect();
+
+    BenchModel::new(width, height, map)
+}
+
 macro_rules! full_path_bench {
     ($($name:ident, $title:expr, $samp:expr, $heuristic:ty {$start:expr => $goal:expr}),*) => {
         $(
@@ -245,6 +274,61 @@ full_path_bench! {
     }
 }
 
+fn bucket_octile(c: &mut Criterion) {
+    let mut map: BenchModel<Diagonal> = map();
+    let start = Position::new(30, 12);
+    let goal = Position::new(0, 15);
+    let mut sampler = Octile;
+
+    c.bench_function("Full Admissable Octile Path with a bucket queue", move |b| {
+        b.iter(|| {
+            let mut planner = BucketAStar::for_model(&map);
+            planner.optimize(&mut map, &start, &goal, &mut sampler);
+        });
+    });
+}
+
+macro_rules! queue_bench {
+    ($($name:ident, $title:expr, $queue:expr),*) => {
+        $(
+            fn $name(c: &mut Criterion) {
+                let mut map: BenchModel<Diagonal> = large_map();
+                let start = Position::new(2, 128);
+                let goal = Position::new(253, 128);
+                let mut sampler = Octile;
+
+                c.bench_function($title, move |b| {
+                    b.iter(|| {
+                        let mut planner = astar::AStar::with_queue($queue);
+                        planner.optimize(&mut map, &start, &goal, &mut sampler);
+                    });
+                });
+            }
+        )*
+    };
+}
+
+queue_bench! {
+    large_binary, "Large octile grid with a binary heap", DaryHeap::<_, 2>::new(),
+    large_quaternary, "Large octile grid with a 4-ary heap", DaryHeap::<_, 4>::new(),
+    large_octonary, "Large octile grid with an 8-ary heap", DaryHeap::<_, 8>::new()
+}
+
+fn large_fringe(c: &mut Criterion) {
+    let mut map: BenchModel<Diagonal> = large_map();
+    let start = Position::new(2, 128);
+    let goal = Position::new(253, 128);
+    let mut sampler = Octile;
+
+    // The same maze as the open lists above, with no open list at all
+    c.bench_function("Large octile grid with Fringe Search", move |b| {
+        b.iter(|| {
+            let mut planner = FringeSearch::new();
+            planner.optimize(&mut map, &start, &goal, &mut sampler);
+        });
+    });
+}
+
 fn single_iter(c: &mut Criterion) {
     let mut map = map();
     let start = Position::new(31, 15);
@@ -263,7 +347,24 @@ fn single_iter(c: &mut Criterion) {
     });
 }
 
-criterion_group!(octile, full_octile, full_dijkstra_octile);
+fn ida_octile(c: &mut Criterion) {
+    let mut map: BenchModel<Diagonal> = map();
+    let start = Position::new(30, 12);
+    let goal = Position::new(20, 16);
+    let mut sampler = Octile;
+
+    // The controls of every node are copied into buffers which the optimizer keeps
+    let mut planner = IdaStar::new();
+    c.bench_function("Short octile path with IDA*", move |b| {
+        b.iter(|| {
+            planner.clear();
+            planner.optimize(&mut map, &start, &goal, &mut sampler);
+        });
+    });
+}
+
+criterion_group!(octile, full_octile, full_dijkstra_octile, bucket_octile, ida_octile);
 criterion_group!(cardinal, full_cardinal, full_dijkstra_cardinal);
 criterion_group!(single_path, single_iter);
-criterion_main!(octile, cardinal, single_path);
+criterion_group!(queues, large_binary, large_quaternary, large_octonary, large_fringe);
+criterion_main!(octile, cardinal, single_path, queues);
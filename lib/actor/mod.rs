@@ -1,5 +1,10 @@
 use super::Position;
 use crate::map::Map;
+use crate::path::bidirectional::ReversibleModel;
+use crate::path::bucket::BoundedCostModel;
+use crate::path::jps::{Connectivity, GridModel};
+use crate::path::random::RandomModel;
+use crate::path::spacetime::WaitableModel;
 use crate::path::{self, HeuristicModel, Model, Optimizer, PathResult, Sampler, State};
 
 use std::fmt::{self, Display};
@@ -11,6 +16,7 @@ pub trait Action {
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Actor {
     pub pos: Position,
     pub mana: usize,
@@ -77,6 +83,7 @@ impl Actor {
 }
 
 #[derive(Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Direction {
     North,
     NorthEast,
@@ -122,6 +129,21 @@ impl Direction {
             NorthWest => (x - 1, y + 1),
         }
     }
+
+    /// The direction which undoes a step in this direction
+    pub fn opposite(self) -> Self {
+        use Direction::*;
+        match self {
+            North => South,
+            NorthEast => SouthWest,
+            East => West,
+            SouthEast => NorthWest,
+            South => North,
+            SouthWest => NorthEast,
+            West => East,
+            NorthWest => SouthEast,
+        }
+    }
 }
 
 impl Default for Direction {
@@ -131,6 +153,7 @@ impl Default for Direction {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Movement {
     Teleport(Direction),
     Walk(Direction),
@@ -285,6 +308,11 @@ impl State for Actor {
     fn grid_position(&self) -> Self::Position {
         self.pos.clone()
     }
+
+    /// Only the position moves, the mana is kept from this state
+    fn interpolate(&self, other: &Self, t: f64) -> Self {
+        Actor { pos: self.pos.interpolate(&other.pos, t), ..self.clone() }
+    }
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -382,6 +410,11 @@ impl Model for TurnOptimal {
     #[inline(always)]
     fn init(&mut self, _: &Self::State) {}
 
+    /// Actors stand on the map, and never inside its walls
+    fn is_valid(&self, state: &Self::State) -> bool {
+        self.map.pos(&state.pos).is_some_and(|tile| !tile.is_wall())
+    }
+
     #[inline(always)]
     fn cost(
         &self,
@@ -398,6 +431,109 @@ impl Model for TurnOptimal {
     }
 }
 
+impl BoundedCostModel for TurnOptimal {
+    /// Diagonal steps are the most expensive
+    fn max_edge_cost(&self) -> Self::Cost {
+        3
+    }
+}
+
+impl GridModel for TurnOptimal {
+    /// Walking reaches every neighbour, including the diagonal ones
+    fn connectivity(&self) -> Connectivity {
+        Connectivity::Eight
+    }
+
+    fn cell(&self, state: &Self::State) -> (i64, i64) {
+        (i64::from(state.pos.x), i64::from(state.pos.y))
+    }
+
+    fn is_blocked(&self, x: i64, y: i64) -> bool {
+        if x < 0 || y < 0 || x > i64::from(u32::MAX) || y > i64::from(u32::MAX) {
+            return true;
+        }
+
+        self.map.get(x as u32, y as u32).is_none_or(|tile| tile.is_blocking())
+    }
+
+    fn control(&self, dx: i64, dy: i64) -> Self::Control {
+        use Direction::*;
+        use Movement::*;
+        match (dx, dy) {
+            (0, 1) => Walk(North),
+            (1, 1) => Walk(NorthEast),
+            (1, 0) => Walk(East),
+            (1, -1) => Walk(SouthEast),
+            (0, -1) => Walk(South),
+            (-1, -1) => Walk(SouthWest),
+            (-1, 0) => Walk(West),
+            (-1, 1) => Walk(NorthWest),
+            _ => None,
+        }
+    }
+}
+
+impl RandomModel for TurnOptimal {
+    /// Walking in any of the eight directions
+    fn random_control<R: rand::Rng + ?Sized>(&self, _: &Actor, rng: &mut R) -> Movement {
+        loop {
+            let (dx, dy) = (rng.gen_range(-1, 2), rng.gen_range(-1, 2));
+            if (dx, dy) != (0, 0) {
+                return self.control(dx, dy);
+            }
+        }
+    }
+
+    /// Walking in the direction of the goal
+    fn steer(&self, state: &Actor, goal: &Actor) -> Movement {
+        let (x, y) = self.cell(state);
+        let (gx, gy) = self.cell(goal);
+        self.control((gx - x).signum(), (gy - y).signum())
+    }
+}
+
+impl ReversibleModel for TurnOptimal {
+    /// Only walking is reversed, since a teleport is cut short by the edge of the map
+    fn integrate_reverse(
+        &self,
+        state: &Self::State,
+        control: &Self::Control,
+    ) -> Option<Self::State> {
+        match control {
+            Movement::Walk(direction) => {
+                let (x, y) = direction.opposite().step_from(state.pos.x, state.pos.y);
+                match self.map.get(x, y) {
+                    Some(tile) if !tile.is_blocking() => {
+                        Some(Actor { pos: Position { x, y }, ..state.clone() })
+                    }
+                    _ => None,
+                }
+            }
+            Movement::Teleport(_) => None,
+            Movement::None => Some(state.clone()),
+        }
+    }
+
+    /// The open positions around the goal, since convergence occurs adjacent to the goal
+    fn goal_states(&self, goal: &Self::State) -> Vec<Self::State> {
+        let mut states = Vec::with_capacity(9);
+        for x in goal.pos.x.saturating_sub(1)..=goal.pos.x.saturating_add(1) {
+            for y in goal.pos.y.saturating_sub(1)..=goal.pos.y.saturating_add(1) {
+                if self.map.get(x, y).is_some_and(|tile| !tile.is_blocking()) {
+                    states.push(Actor { pos: Position { x, y }, ..goal.clone() });
+                }
+            }
+        }
+        states
+    }
+}
+
+impl WaitableModel for TurnOptimal {
+    fn wait(&self) -> Movement {
+        Movement::None
+    }
+}
+
 impl HeuristicModel for TurnOptimal {
     /// Reasonable estimate for the number of turns required to reach the player
     fn heuristic(&self, current: &Self::State, goal: &Self::State) -> Self::Cost {
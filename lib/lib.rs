@@ -6,6 +6,7 @@ use std::ops::{Add, Mul, Sub};
 
 /// An (x,y) position in the game world
 #[derive(Clone, PartialEq, Eq, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Position {
     pub x: u32,
     pub y: u32,
@@ -37,6 +38,28 @@ impl path::State for Position {
     fn grid_position(&self) -> Self::Position {
         self.clone()
     }
+
+    /// Interpolate linearly, rounding to the nearest position
+    fn interpolate(&self, other: &Self, t: f64) -> Self {
+        let lerp = |a: u32, b: u32| (f64::from(a) + (f64::from(b) - f64::from(a)) * t).round();
+        Position { x: lerp(self.x, other.x) as u32, y: lerp(self.y, other.y) as u32 }
+    }
+}
+
+impl path::BoundedPosition for Position {
+    fn within(&self, min: &Self, max: &Self) -> bool {
+        (min.x..=max.x).contains(&self.x) && (min.y..=max.y).contains(&self.y)
+    }
+}
+
+impl path::heuristics::Coord2D for Position {
+    fn x(&self) -> i64 {
+        i64::from(self.x)
+    }
+
+    fn y(&self) -> i64 {
+        i64::from(self.y)
+    }
 }
 
 impl Add for Position {
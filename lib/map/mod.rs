@@ -411,3 +411,30 @@ where
     }
     map
 }
+
+/// Maps shared by the unit tests
+#[cfg(test)]
+pub(crate) mod fixtures {
+    use super::{Map, Tile};
+
+    /// A map of floor, with a border of walls all around
+    pub(crate) fn room(width: u32, height: u32) -> Map {
+        let mut map = Map::new(width, height);
+        for y in 1..height.saturating_sub(1) {
+            for x in 1..width.saturating_sub(1) {
+                map[(x, y)] = Tile::FLOOR;
+            }
+        }
+        map
+    }
+
+    /// A room of 20 by 20 tiles split by a wall down the middle, which leaves a gap at the top
+    /// and at the bottom
+    pub(crate) fn divided_room() -> Map {
+        let mut map = room(20, 20);
+        for y in 3..17 {
+            map[(10, y)] = Tile::WALL;
+        }
+        map
+    }
+}
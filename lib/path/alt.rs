@@ -0,0 +1,202 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::fmt::{Debug, Formatter};
+use std::ops::Sub;
+
+use fnv::FnvHashMap;
+
+use super::bidirectional::ReversibleModel;
+use super::*;
+
+type Position<M> = <<M as Model>::State as State>::Position;
+
+/// Lower bounds on the cost between any two positions, from the costs to and from landmarks
+///
+/// The costs from each landmark to every position it reaches, and from every position back to
+/// it, are found ahead of time with Dijkstra's algorithm.  By the triangle inequality the
+/// cheapest trajectory from a state to the goal costs at least the difference between what
+/// the two cost from, or to, any one landmark, and the heuristic is the largest of these
+/// differences.  This is admissible and consistent for trajectories which converge at the
+/// position of the goal, and a few landmarks around the edges of the map are often much
+/// better informed than the distance in a straight line, especially when costs vary.
+///
+/// The precomputation visits everything reachable from the landmarks, so it pays off over
+/// many queries on the same static map.
+///
+/// ```
+/// # use game_lib::path::alt::AltHeuristic;
+/// # use game_lib::path::grid::{Cell, GridModel, GridSampler};
+/// let grid = GridModel::new(20, 20);
+/// let corners = [Cell::new(0, 0), Cell::new(19, 0), Cell::new(0, 19), Cell::new(19, 19)];
+/// let alt = AltHeuristic::precompute(&grid, &corners, &mut GridSampler);
+/// // Nineteen diagonal steps from one corner to the other
+/// assert_eq!(alt.heuristic(&Cell::new(0, 0), &Cell::new(19, 19)), 19 * 3);
+/// ```
+pub struct AltHeuristic<M>
+where
+    M: Model,
+{
+    /// For each landmark, the cost from the landmark to every position it reaches
+    from: Vec<FnvHashMap<Position<M>, M::Cost>>,
+    /// For each landmark, the cost from every position which reaches it to the landmark
+    to: Vec<FnvHashMap<Position<M>, M::Cost>>,
+}
+
+impl<M> AltHeuristic<M>
+where
+    M: ReversibleModel,
+    M::Cost: Copy + Sub<Output = M::Cost>,
+{
+    /// Find the costs to and from each of the `landmarks`, stepping with `sampler`
+    pub fn precompute<S>(model: &M, landmarks: &[M::State], sampler: &mut S) -> Self
+    where
+        S: Sampler<M>,
+    {
+        let mut from = Vec::with_capacity(landmarks.len());
+        let mut to = Vec::with_capacity(landmarks.len());
+        for landmark in landmarks {
+            from.push(flood(model, landmark, sampler, |state, control| {
+                let next = model.integrate(state, control)?;
+                let cost = model.cost(state, control, &next);
+                Some((next, cost))
+            }));
+            to.push(flood(model, landmark, sampler, |state, control| {
+                let previous = model.integrate_reverse(state, control)?;
+                let cost = model.cost(&previous, control, state);
+                Some((previous, cost))
+            }));
+        }
+
+        AltHeuristic { from, to }
+    }
+
+    /// A lower bound on the cost from `current` to `goal`
+    ///
+    /// Landmarks which cannot reach, or be reached from, either state give no bound.
+    pub fn heuristic(&self, current: &M::State, goal: &M::State) -> M::Cost {
+        let (current, goal) = (current.grid_position(), goal.grid_position());
+
+        let mut best = M::Cost::default();
+        for (from, to) in self.from.iter().zip(&self.to) {
+            // The landmark reaches the goal no cheaper than it reaches the current state and
+            // then goes on to the goal
+            if let (Some(&current), Some(&goal)) = (from.get(&current), from.get(&goal)) {
+                if goal > current && goal - current > best {
+                    best = goal - current;
+                }
+            }
+            // Likewise the current state reaches the landmark no cheaper than through the goal
+            if let (Some(&current), Some(&goal)) = (to.get(&current), to.get(&goal)) {
+                if current > goal && current - goal > best {
+                    best = current - goal;
+                }
+            }
+        }
+
+        best
+    }
+
+    pub fn landmarks(&self) -> usize {
+        self.from.len()
+    }
+}
+
+/// The cost from `source` to every position it reaches through `expand`, with Dijkstra's
+/// algorithm
+fn flood<M, S, E>(
+    model: &M,
+    source: &M::State,
+    sampler: &mut S,
+    mut expand: E,
+) -> FnvHashMap<Position<M>, M::Cost>
+where
+    M: Model,
+    M::Cost: Copy,
+    S: Sampler<M>,
+    E: FnMut(&M::State, &M::Control) -> Option<(M::State, M::Cost)>,
+{
+    let mut costs = FnvHashMap::default();
+    costs.insert(source.grid_position(), M::Cost::default());
+
+    // States are kept aside so that the queue only needs to order the costs
+    let mut states = vec![source.clone()];
+    let mut queue = BinaryHeap::new();
+    queue.push((Reverse(M::Cost::default()), 0));
+
+    let mut controls = Vec::new();
+    while let Some((Reverse(cost), index)) = queue.pop() {
+        let state = states[index].clone();
+        if costs.get(&state.grid_position()).is_some_and(|&best| best < cost) {
+            continue;
+        }
+
+        sampler.sample_into(model, &state, &mut controls);
+        for control in &controls {
+            if let Some((next, step)) = expand(&state, control) {
+                let next_cost = cost + step;
+                let position = next.grid_position();
+                if costs.get(&position).is_none_or(|&best| next_cost < best) {
+                    costs.insert(position, next_cost);
+                    queue.push((Reverse(next_cost), states.len()));
+                    states.push(next);
+                }
+            }
+        }
+    }
+
+    costs
+}
+
+impl<M> Debug for AltHeuristic<M>
+where
+    M: Model,
+{
+    fn fmt(&self, fmt: &mut Formatter) -> Result<(), std::fmt::Error> {
+        fmt.debug_struct("AltHeuristic")
+            .field("landmarks", &self.from.len())
+            .field("positions", &self.from.iter().map(FnvHashMap::len).sum::<usize>())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::path::astar::AStar;
+    use crate::path::grid::{Cell, GridSampler};
+    use crate::path::testing::*;
+
+    /// How many times the usual cost of a cell it is to cross, down the middle of a swamp
+    fn swamp(cell: &Cell) -> usize {
+        if (6..14).contains(&cell.x) && cell.y < 16 {
+            6
+        } else {
+            1
+        }
+    }
+
+    #[test]
+    fn landmarks_expand_less_than_the_distance_in_a_straight_line() {
+        // The distance in a straight line knows nothing of the swamp
+        let mut model = TestGrid::new(20, 20);
+        model.weight = |current, next| swamp(current) + swamp(next);
+        model.inflation = 2;
+        let corners = [Cell::new(0, 0), Cell::new(19, 0), Cell::new(0, 19), Cell::new(19, 19)];
+        let alt = AltHeuristic::precompute(&model, &corners, &mut GridSampler);
+
+        let (start, goal) = (Cell::new(1, 8), Cell::new(18, 8));
+        let mut straight = AStar::new();
+        let mut landmarks = AStar::new();
+        let estimate = |cell: &Cell| alt.heuristic(cell, &goal);
+        let a = expect_final(straight.optimize(&mut model, &start, &goal, &mut GridSampler));
+        let b = expect_final(landmarks.optimize_to(
+            &mut model,
+            &start,
+            |c| *c == goal,
+            estimate,
+            &mut GridSampler,
+        ));
+        assert_eq!(a.cost, b.cost);
+        assert!(landmarks.stats().expanded < straight.stats().expanded);
+    }
+}
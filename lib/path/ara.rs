@@ -0,0 +1,347 @@
+use std::fmt::{Debug, Formatter};
+
+use fnv::{FnvHashMap, FnvHashSet};
+use std::collections::hash_map::Entry;
+use std::collections::BinaryHeap;
+
+use super::astar::{Id, Node};
+use super::*;
+
+/// Anytime Repairing A*, which finds a rough trajectory quickly and then keeps improving it
+///
+/// Each call to [`improve`](#method.improve) runs weighted A* with the next inflation factor
+/// from the schedule, returning a trajectory which costs at most `epsilon` times the optimal
+/// cost.  Rather than starting from scratch, every search picks up the open set left by the
+/// last one, along with the nodes which were found to be cheaper after they had already been
+/// expanded.  Once the schedule reaches `1.0` the trajectory is optimal.
+///
+/// ```
+/// # use game_lib::path::ara::AraStar;
+/// # use game_lib::path::grid::{Cell, GridModel, GridSampler};
+/// let (mut ara, mut grid) = (AraStar::new(), GridModel::new(20, 20));
+/// ara.set_schedule(vec![2.0, 1.0]);
+/// let (start, goal) = (Cell::new(1, 1), Cell::new(18, 12));
+/// while !ara.is_finished() {
+///     assert!(ara.improve(&mut grid, &start, &goal, &mut GridSampler).is_final());
+/// }
+/// ```
+pub struct AraStar<M>
+where
+    M: HeuristicModel,
+    M::Cost: Copy,
+{
+    queue: BinaryHeap<Node<M>>,
+    inconsistent: Vec<Node<M>>,
+    parent_map: FnvHashMap<Id<M>, Node<M>>,
+    grid: FnvHashMap<<<M as Model>::State as State>::Position, Id<M>>,
+    closed: FnvHashSet<<<M as Model>::State as State>::Position>,
+    incumbent: Option<Node<M>>,
+    id_counter: usize,
+    schedule: Vec<f64>,
+    iteration: usize,
+}
+
+impl<M> AraStar<M>
+where
+    M: HeuristicModel,
+    M::Cost: Copy + Weighted,
+{
+    /// Create a new ARA* optimizer with the schedule `[2.5, 2.0, 1.5, 1.0]`
+    pub fn new() -> Self {
+        AraStar {
+            queue: BinaryHeap::new(),
+            inconsistent: Vec::new(),
+            parent_map: FnvHashMap::default(),
+            grid: FnvHashMap::default(),
+            closed: FnvHashSet::default(),
+            incumbent: None,
+            id_counter: 0,
+            schedule: vec![2.5, 2.0, 1.5, 1.0],
+            iteration: 0,
+        }
+    }
+
+    /// Replace the inflation factors used by successive calls to `improve`
+    ///
+    /// The factors should decrease towards `1.0`; an empty schedule searches with `1.0` alone.
+    /// Changing the schedule starts the search over.
+    pub fn set_schedule(&mut self, epsilons: Vec<f64>) {
+        self.schedule = if epsilons.is_empty() { vec![1.0] } else { epsilons };
+        self.clear();
+    }
+
+    pub fn clear(&mut self) {
+        self.queue.clear();
+        self.inconsistent.clear();
+        self.parent_map.clear();
+        self.grid.clear();
+        self.closed.clear();
+        self.incumbent = None;
+        self.iteration = 0;
+    }
+
+    /// The inflation factor of the search the next call to `improve` will run
+    pub fn epsilon(&self) -> f64 {
+        self.schedule[self.iteration.min(self.schedule.len() - 1)]
+    }
+
+    /// Whether every inflation factor in the schedule has been searched with
+    pub fn is_finished(&self) -> bool {
+        self.iteration >= self.schedule.len()
+    }
+
+    pub fn inspect_queue(&self) -> impl Iterator<Item = (&M::State, &M::Control)> {
+        self.queue.iter().map(|node| (&node.state, &node.control))
+    }
+
+    pub fn inspect_discovered(
+        &self,
+    ) -> impl Iterator<Item = &<<M as Model>::State as State>::Position> {
+        self.grid.keys()
+    }
+
+    /// Search with the next inflation factor and return the improved trajectory
+    ///
+    /// Every trajectory costs no more than the one returned before it.  Once the schedule is
+    /// finished the last trajectory is returned again without searching.
+    pub fn improve<S>(
+        &mut self,
+        model: &mut M,
+        start: &M::State,
+        goal: &M::State,
+        sampler: &mut S,
+    ) -> PathResult<M>
+    where
+        S: Sampler<M>,
+    {
+        use PathFindingErr::*;
+        use PathResult::*;
+
+        if model.converge(start, goal) {
+            self.iteration = self.schedule.len();
+            return Final(Trajectory {
+                cost: Default::default(),
+                trajectory: vec![(start.clone(), Default::default())],
+                expansions: 0,
+            });
+        }
+
+        if !self.is_finished() {
+            if self.parent_map.is_empty() && self.queue.is_empty() {
+                self.seed(model, start, goal);
+            } else {
+                self.repair(model, goal);
+            }
+
+            self.search(model, goal, sampler);
+            self.iteration += 1;
+        }
+
+        match &self.incumbent {
+            Some(best) => Final(self.unwind_trajectory(best.clone())),
+            None => Err(Unreachable),
+        }
+    }
+
+    /// Expand nodes until none of them can lead to a cheaper trajectory than the incumbent
+    fn search<S>(&mut self, model: &M, goal: &M::State, sampler: &mut S)
+    where
+        S: Sampler<M>,
+    {
+        let weight = self.epsilon();
+
+        while let Some(current) = self.pop() {
+            if let Some(best) = &self.incumbent {
+                if best.id.g <= current.id.f.0 {
+                    self.queue.push(current);
+                    return;
+                }
+            }
+
+            if model.converge(&current.state, goal) {
+                if self.incumbent.as_ref().is_none_or(|best| current.id.g < best.id.g) {
+                    self.incumbent = Some(current);
+                }
+                continue;
+            }
+
+            if !self.closed.insert(current.state.grid_position()) {
+                continue;
+            }
+
+            for control in sampler.sample(model, &current.state) {
+                if let Some(child_state) = model.integrate(&current.state, control) {
+                    self.id_counter += 1;
+
+                    let cost =
+                        current.id.g() + model.cost(&current.state, control, &child_state);
+                    let f = cost + Self::estimate(model, &child_state, goal, weight);
+
+                    let child = Node::<M> {
+                        id: Id::new(self.id_counter, f, cost),
+                        state: child_state,
+                        control: control.clone(),
+                    };
+
+                    match self.grid.entry(child.state.grid_position()) {
+                        Entry::Occupied(mut best) => {
+                            let best = best.get_mut();
+                            if best.g <= child.id.g {
+                                continue;
+                            } else {
+                                *best = child.id.clone();
+                            }
+                        }
+                        Entry::Vacant(empty) => {
+                            empty.insert(child.id.clone());
+                        }
+                    }
+
+                    self.parent_map.insert(child.id.clone(), current.clone());
+
+                    // Closed nodes wait for the next search instead of being expanded again
+                    if self.closed.contains(&child.state.grid_position()) {
+                        self.inconsistent.push(child);
+                    } else {
+                        self.queue.push(child);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Prepare for the next search by merging the inconsistent nodes back into the queue and
+    /// ordering everything by the new inflation factor
+    fn repair(&mut self, model: &M, goal: &M::State) {
+        let weight = self.epsilon();
+        let mut nodes = std::mem::take(&mut self.queue).into_vec();
+        nodes.append(&mut self.inconsistent);
+
+        for mut node in nodes {
+            let current = self.grid.get(&node.state.grid_position());
+            if current.is_some_and(|best| best.g < node.id.g) {
+                continue;
+            }
+
+            let f = node.id.g() + Self::estimate(model, &node.state, goal, weight);
+            node.id = Id::new(node.id.id, f, node.id.g());
+            self.queue.push(node);
+        }
+
+        self.closed.clear();
+    }
+
+    /// The inflated heuristic, which is zero for states which have already converged
+    #[inline(always)]
+    fn estimate(model: &M, state: &M::State, goal: &M::State, weight: f64) -> M::Cost {
+        if model.converge(state, goal) {
+            Default::default()
+        } else {
+            model.heuristic(state, goal).scale(weight)
+        }
+    }
+
+    /// Pop the next node from the queue, discarding stale entries along the way
+    fn pop(&mut self) -> Option<Node<M>> {
+        while let Some(node) = self.queue.pop() {
+            let stale = self
+                .grid
+                .get(&node.state.grid_position())
+                .is_some_and(|best| best.g < node.id.g);
+
+            if !stale {
+                return Some(node);
+            }
+        }
+
+        None
+    }
+
+    /// Follow the parents from the goal node up to the start node
+    fn unwind_trajectory(&self, mut current: Node<M>) -> Trajectory<M> {
+        let cost = current.id.g();
+        let mut result = Vec::new();
+        result.push((current.state.clone(), current.control.clone()));
+
+        while let Some(p) = self.parent_map.get(&current.id) {
+            current = (*p).clone();
+            result.push((current.state.clone(), current.control.clone()));
+        }
+
+        result.reverse();
+
+        Trajectory { cost, trajectory: result, expansions: 0 }
+    }
+
+    fn seed(&mut self, model: &M, start: &M::State, goal: &M::State) {
+        let f = Self::estimate(model, start, goal, self.epsilon());
+        self.queue.push(Node {
+            id: Id::new(0, f, Default::default()),
+            state: start.clone(),
+            control: Default::default(),
+        });
+    }
+}
+
+impl<M> Debug for AraStar<M>
+where
+    M: HeuristicModel,
+    M::State: Debug,
+    M::Control: Debug,
+    M::Cost: Debug + Copy,
+{
+    fn fmt(&self, fmt: &mut Formatter) -> Result<(), std::fmt::Error> {
+        fmt.debug_struct("AraStar")
+            .field("counter", &self.id_counter)
+            .field("schedule", &self.schedule)
+            .field("iteration", &self.iteration)
+            .field("incumbent", &self.incumbent)
+            .field("next", &self.queue.peek())
+            .field("queue", &self.queue)
+            .field("inconsistent", &self.inconsistent)
+            .field("grid", &self.grid)
+            .field("closed", &self.closed)
+            .field("parent_map", &self.parent_map)
+            .finish()
+    }
+}
+
+impl<M> Default for AraStar<M>
+where
+    M: HeuristicModel,
+    M::Cost: Copy + Weighted,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::actor::{Heuristic, TurnOptimal, WalkSampler};
+    use crate::path::astar::AStar;
+    use crate::path::testing::*;
+
+    #[test]
+    fn each_improvement_costs_no_more_until_the_optimum() {
+        let mut model = TurnOptimal::new(walled_room());
+        model.set_heuristic(Heuristic::Chebyshev);
+        let (start, goal) = (walker(3, 15), walker(27, 14));
+
+        let mut ara = AraStar::new();
+        ara.set_schedule(vec![3.0, 2.0, 1.5, 1.0]);
+        let mut sampler = WalkSampler::new();
+        let mut costs = Vec::new();
+        while !ara.is_finished() {
+            costs
+                .push(expect_final(ara.improve(&mut model, &start, &goal, &mut sampler)).cost);
+        }
+        assert_eq!(costs.len(), 4);
+        assert!(costs.windows(2).all(|pair| pair[1] <= pair[0]));
+
+        let optimal =
+            expect_final(AStar::new().optimize(&mut model, &start, &goal, &mut sampler));
+        assert_eq!(costs.last(), Some(&optimal.cost));
+    }
+}
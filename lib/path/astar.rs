@@ -1,14 +1,32 @@
 use std::fmt::{Debug, Formatter};
 
-use fnv::FnvHashMap;
+use fnv::{FnvBuildHasher, FnvHashMap, FnvHashSet};
 use radix_heap::RadixHeapMap;
 use std::cmp::{Ord, Ordering, PartialEq, PartialOrd, Reverse};
 use std::collections::hash_map::Entry;
-use std::collections::BinaryHeap;
-use std::hash::{Hash, Hasher};
+use std::collections::{BinaryHeap, HashMap};
+use std::hash::{BuildHasher, Hash, Hasher};
+use std::time::Instant;
 
+use super::heuristics::Coord2D;
 use super::*;
 
+type Position<M> = <<M as Model>::State as State>::Position;
+
+/// Measures how far a position strays from the line between the start and the goal
+type TieBreaker<M> = fn(&Position<M>, &Position<M>, &Position<M>) -> u64;
+
+/// Whether a position lies within the box between two corners
+type Within<M> = fn(&Position<M>, &Position<M>, &Position<M>) -> bool;
+
+/// The corners of the box the search is confined to, and the test against them
+type Bounds<M> = (Position<M>, Position<M>, Within<M>);
+
+/// A successor sampled during an expansion: its control, state, the cost of the step to it,
+/// its estimate, and whether it reaches the goal
+type Successor<M> =
+    (<M as Model>::Control, <M as Model>::State, <M as Model>::Cost, <M as Model>::Cost, bool);
+
 pub struct OptimalAStar<M>
 where
     M: HeuristicModel,
@@ -18,6 +36,8 @@ where
     parent_map: FnvHashMap<Id<M>, Node<M>>,
     grid: FnvHashMap<<<M as Model>::State as State>::Position, Id<M>>,
     id_counter: usize,
+    /// Whether the start was queued, so that an empty queue means the search has finished
+    seeded: bool,
 }
 
 impl<M> OptimalAStar<M>
@@ -32,6 +52,7 @@ where
             parent_map: FnvHashMap::default(),
             grid: FnvHashMap::default(),
             id_counter: 0,
+            seeded: false,
         }
     }
 
@@ -39,6 +60,7 @@ where
         self.queue.clear();
         self.parent_map.clear();
         self.grid.clear();
+        self.seeded = false;
     }
 
     pub fn inspect_queue(&self) -> impl Iterator<Item = (&M::State, &M::Control)> {
@@ -111,14 +133,15 @@ where
 
         // build up the trajectory by following the parent nodes
         while let Some(p) = self.parent_map.get(&current.id) {
-            cost = cost + model.cost(&current.state, &current.control, &p.state);
+            // The control of each node is the one which led to it from its parent
+            cost = cost + model.cost(&p.state, &current.control, &current.state);
             current = (*p).clone();
             result.push((current.state.clone(), current.control.clone()));
         }
 
         result.reverse();
 
-        Trajectory { cost, trajectory: result }
+        Trajectory { cost, trajectory: result, expansions: 0 }
     }
 }
 
@@ -128,6 +151,11 @@ where
     M::Cost: radix_heap::Radix + Copy,
     S: Sampler<M>,
 {
+    /// Expand the next node in the queue
+    ///
+    /// The start is queued by the first call only, so once the queue runs out the search has
+    /// finished and every later call stops with `PathFindingErr::Unreachable` as well, rather
+    /// than beginning the search again.
     fn next_trajectory(
         &mut self,
         model: &mut M,
@@ -138,7 +166,12 @@ where
         use PathFindingErr::*;
         use PathResult::*;
 
-        if self.parent_map.is_empty() && self.queue.is_empty() {
+        if let Some(invalid) = invalid_endpoint(model, start, goal) {
+            return Err(invalid);
+        }
+
+        if !self.seeded {
+            self.seeded = true;
             let heuristic = model.heuristic(start, goal);
             let start_id = Id::new(0, heuristic, Default::default());
             self.queue.push(
@@ -154,6 +187,7 @@ where
                 Intermediate(self.unwind_trajectory(model, current))
             }
         } else {
+            // Only a search which expanded everything it could reach runs out of nodes
             Err(Unreachable)
         }
     }
@@ -168,14 +202,20 @@ where
         use PathFindingErr::*;
         use PathResult::*;
 
+        if let Some(invalid) = invalid_endpoint(model, start, goal) {
+            return Err(invalid);
+        }
+
         if model.converge(start, goal) {
             return Final(Trajectory {
                 cost: Default::default(),
                 trajectory: vec![(start.clone(), Default::default())],
+                expansions: 0,
             });
         }
 
-        if self.queue.top().is_none() {
+        if !self.seeded {
+            self.seeded = true;
             let start_id = Id::new(0, model.heuristic(start, goal), Default::default());
             self.queue.push(
                 Default::default(),
@@ -221,370 +261,4079 @@ where
     }
 }
 
-pub struct AStar<M>
+/// A* optimizer which always expands the node with the lowest estimated total cost
+///
+/// Nodes with the same estimate are expanded in a fixed order, preferring the node which
+/// has come furthest and then the node which was found first.  Which of several equally cheap
+/// trajectories is returned depends on this order, but the same query always returns the
+/// same trajectory:
+///
+/// ```
+/// # use game_lib::path::astar::AStar;
+/// # use game_lib::path::grid::{Cell, GridModel, GridSampler};
+/// # use game_lib::path::Optimizer;
+/// // An open grid has many trajectories of the same cost
+/// let (mut grid, start, goal) = (GridModel::new(20, 20), Cell::new(2, 2), Cell::new(17, 12));
+/// let first = AStar::new().optimize(&mut grid, &start, &goal, &mut GridSampler);
+/// let second = AStar::new().optimize(&mut grid, &start, &goal, &mut GridSampler);
+/// assert_eq!(format!("{:?}", first), format!("{:?}", second));
+/// ```
+///
+/// The cost of the trajectory is charged in the direction it is travelled, so models whose
+/// steps cost something different one way than the other, like climbing a hill instead of
+/// going down it, report what following the trajectory actually costs.
+pub struct AStar<M, B = FnvBuildHasher, Q = BinaryHeap<Node<M>>>
 where
     M: HeuristicModel,
     M::Cost: Copy,
 {
-    queue: BinaryHeap<Node<M>>,
-    parent_map: FnvHashMap<Id<M>, Node<M>>,
-    grid: FnvHashMap<<<M as Model>::State as State>::Position, Id<M>>,
+    queue: Q,
+    parent_map: HashMap<Id<M>, Node<M>, B>,
+    /// The cheapest node found at each position, and the queue's handle to it if it has one
+    grid: HashMap<Position<M>, (Node<M>, Option<usize>), B>,
+    /// The node last expanded at each position, which is kept to find trajectories to it
+    closed: HashMap<<<M as Model>::State as State>::Position, Node<M>, B>,
     id_counter: usize,
+    weight: f64,
+    /// The `epsilon` and anticipated depth of dynamic weighting, in place of `weight`
+    dynamic_weight: Option<(f64, usize)>,
+    stats: Stats,
+    clock_interval: usize,
+    closest: Option<(M::Cost, Node<M>)>,
+    overflow_checks: bool,
+    consistency_checks: bool,
+    progress_checks: bool,
+    reopen_closed: bool,
+    goal_tolerance: Option<f64>,
+    /// The number of successors with the lowest estimates kept from each expansion
+    max_successors: Option<usize>,
+    ceiling: Option<M::Cost>,
+    pruned: bool,
+    /// The cost of the cheapest trajectory to the goal found so far
+    incumbent: Option<M::Cost>,
+    incumbent_pruning: bool,
+    /// Breaks ties in favour of positions closer to the line from the start to the goal
+    tie_breaker: Option<TieBreaker<M>>,
+    /// Successors outside of the box are discarded, see `set_bounds`
+    bounds: Option<Bounds<M>>,
+    /// Set by `reachable`, whose search has no parents to find trajectories by
+    parentless: bool,
+    /// Whether a start was queued since the optimizer was last cleared
+    seeded: bool,
+    /// The positions of the start and goal the search in progress was begun with
+    query: Option<(Position<M>, Position<M>)>,
+    /// Positions discovered since the last call to `drain_discovered`
+    discovered: Vec<Position<M>>,
 }
 
-impl<M> AStar<M>
+/// Produces the successors of a state for an expansion, each with its control and the cost of
+/// the step to it, and hands them to `f` in the order the controls were sampled
+trait Successors<M>
+where
+    M: Model,
+{
+    fn successors<F>(
+        &mut self,
+        model: &M,
+        state: &M::State,
+        f: F,
+    ) -> Result<(), PathFindingErr>
+    where
+        F: FnMut(&M::Control, M::State, M::Cost) -> Result<(), PathFindingErr>;
+}
+
+/// Integrates the controls of a sampler one after another, as every search but
+/// `optimize_parallel` does
+struct Serial<'a, S>(&'a mut S);
+
+impl<M, S> Successors<M> for Serial<'_, S>
+where
+    M: Model,
+    S: Sampler<M>,
+{
+    #[inline(always)]
+    fn successors<F>(
+        &mut self,
+        model: &M,
+        state: &M::State,
+        mut f: F,
+    ) -> Result<(), PathFindingErr>
+    where
+        F: FnMut(&M::Control, M::State, M::Cost) -> Result<(), PathFindingErr>,
+    {
+        for control in self.0.sample(model, state) {
+            if let Some(child_state) = model.integrate(state, control) {
+                let cost = model.cost(state, control, &child_state);
+                f(control, child_state, cost)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Integrates the controls of a sampler on rayon's thread pool, see `optimize_parallel`
+#[cfg(feature = "rayon")]
+struct Parallel<'a, S>(&'a mut S);
+
+#[cfg(feature = "rayon")]
+impl<M, S> Successors<M> for Parallel<'_, S>
+where
+    M: Model + Sync,
+    M::State: Send + Sync,
+    M::Control: Sync,
+    M::Cost: Send,
+    S: Sampler<M>,
+{
+    fn successors<F>(
+        &mut self,
+        model: &M,
+        state: &M::State,
+        mut f: F,
+    ) -> Result<(), PathFindingErr>
+    where
+        F: FnMut(&M::Control, M::State, M::Cost) -> Result<(), PathFindingErr>,
+    {
+        use rayon::prelude::*;
+
+        let successors: Vec<_> = self
+            .0
+            .sample(model, state)
+            .par_iter()
+            .filter_map(|control| {
+                let child_state = model.integrate(state, control)?;
+                let cost = model.cost(state, control, &child_state);
+                Some((control, child_state, cost))
+            })
+            .collect();
+        for (control, child_state, cost) in successors {
+            f(control, child_state, cost)?;
+        }
+        Ok(())
+    }
+}
+
+/// Whether `state` has reached the goal, or come within `tolerance` of it when there is one
+#[inline(always)]
+fn converged<M>(model: &M, state: &M::State, goal: &M::State, tolerance: Option<f64>) -> bool
 where
+    M: Model,
+{
+    match tolerance {
+        Some(tolerance) => model.converge_within(state, goal, tolerance),
+        None => model.converge(state, goal),
+    }
+}
+
+/// Panic when the heuristic drops by more than the cost of the step to a successor
+#[inline(always)]
+fn check_consistency<M>(
+    current: &Node<M>,
+    child: &M::State,
+    current_estimate: M::Cost,
+    cost: M::Cost,
+    estimate: M::Cost,
+) where
     M: HeuristicModel,
     M::Cost: Copy,
+{
+    if cost.checked_add(estimate).is_some_and(|bound| current_estimate > bound) {
+        panic!(
+            "inconsistent heuristic: the estimate of {:?} from {:?} exceeds the cost of the step \
+             {:?} plus the estimate {:?} of the successor {:?}",
+            current_estimate, current.state, cost, estimate, child
+        );
+    }
+}
+
+impl<M> AStar<M>
+where
+    M: HeuristicModel,
+    M::Cost: Copy + Weighted,
 {
     /// Create a new AStar optimizer
     pub fn new() -> Self {
+        Self::with_capacity(0)
+    }
+
+    /// Create a new AStar optimizer with room for at least `nodes` nodes
+    ///
+    /// Reserving up front avoids growing the queue and maps over and over during a large
+    /// search; the width times the height of a map is a good upper bound for grid planning.
+    ///
+    /// ```
+    /// # use game_lib::path::astar::AStar;
+    /// # use game_lib::path::grid::{Cell, GridModel, GridSampler};
+    /// # use game_lib::path::Optimizer;
+    /// let mut grid = GridModel::new(40, 40);
+    /// let mut astar = AStar::with_capacity(40 * 40);
+    /// let (start, goal) = (Cell::new(2, 3), Cell::new(37, 35));
+    /// assert!(astar.optimize(&mut grid, &start, &goal, &mut GridSampler).is_final());
+    /// ```
+    pub fn with_capacity(nodes: usize) -> Self {
+        let mut astar = Self::with_hasher(Default::default());
+        astar.reserve(nodes);
+        astar
+    }
+
+    /// Create a weighted A* optimizer which inflates the heuristic by `epsilon`
+    ///
+    /// Nodes are ordered by `f = g + epsilon * h`, which expands fewer nodes as the search is
+    /// pulled more greedily towards the goal.  With an admissible heuristic and
+    /// `epsilon >= 1.0`, the returned trajectory costs at most `epsilon` times the optimal
+    /// cost; an `epsilon` of exactly `1.0` is plain A*.
+    ///
+    /// ```
+    /// # use game_lib::path::astar::AStar;
+    /// # use game_lib::path::grid::{Cell, GridModel, GridSampler};
+    /// # use game_lib::path::Optimizer;
+    /// let mut grid = GridModel::new(20, 20);
+    /// let (start, goal) = (Cell::new(1, 1), Cell::new(18, 12));
+    /// let mut astar = AStar::with_weight(2.0);
+    /// assert!(astar.optimize(&mut grid, &start, &goal, &mut GridSampler).is_final());
+    /// ```
+    pub fn with_weight(epsilon: f64) -> Self {
+        let mut astar = Self::with_capacity(0);
+        astar.weight = epsilon;
+        astar
+    }
+}
+
+impl<M, B> AStar<M, B>
+where
+    M: HeuristicModel,
+    M::Cost: Copy + Weighted,
+    B: BuildHasher + Clone,
+{
+    /// Create a new AStar optimizer which hashes positions and nodes with `hasher`
+    ///
+    /// The default hasher is FNV, which is fast for the small keys of grid planning; any
+    /// other hasher produces the same trajectories.
+    ///
+    /// ```
+    /// # use game_lib::path::astar::AStar;
+    /// # use game_lib::path::grid::{Cell, GridModel, GridSampler};
+    /// # use game_lib::path::Optimizer;
+    /// use std::collections::hash_map::DefaultHasher;
+    /// use std::hash::BuildHasherDefault;
+    ///
+    /// let mut sip = AStar::with_hasher(BuildHasherDefault::<DefaultHasher>::default());
+    /// let (start, goal) = (Cell::new(1, 1), Cell::new(10, 9));
+    /// assert!(sip.optimize(&mut GridModel::new(12, 12), &start, &goal, &mut GridSampler).is_final());
+    /// ```
+    pub fn with_hasher(hasher: B) -> Self {
+        Self::from_parts(hasher, BinaryHeap::new())
+    }
+}
+
+impl<M, Q> AStar<M, FnvBuildHasher, Q>
+where
+    M: HeuristicModel,
+    M::Cost: Copy + Weighted,
+    Q: PriorityQueue<Node<M>>,
+{
+    /// Create a new AStar optimizer which keeps the open list in `queue`
+    ///
+    /// Any [`PriorityQueue`] finds the same trajectories as the default binary heap; what
+    /// changes is how quickly nodes go in and out.
+    pub fn with_queue(queue: Q) -> Self {
+        Self::from_parts(Default::default(), queue)
+    }
+}
+
+impl<M, B, Q> AStar<M, B, Q>
+where
+    M: HeuristicModel,
+    M::Cost: Copy + Weighted,
+    B: BuildHasher + Clone,
+    Q: PriorityQueue<Node<M>>,
+{
+    fn from_parts(hasher: B, queue: Q) -> Self {
         AStar {
-            queue: BinaryHeap::new(),
-            parent_map: FnvHashMap::default(),
-            grid: FnvHashMap::default(),
+            queue,
+            parent_map: HashMap::with_hasher(hasher.clone()),
+            grid: HashMap::with_hasher(hasher.clone()),
+            closed: HashMap::with_hasher(hasher),
             id_counter: 0,
+            weight: 1.0,
+            dynamic_weight: None,
+            stats: Stats::default(),
+            clock_interval: 256,
+            closest: None,
+            overflow_checks: true,
+            consistency_checks: false,
+            progress_checks: cfg!(debug_assertions),
+            reopen_closed: false,
+            goal_tolerance: None,
+            max_successors: None,
+            ceiling: None,
+            pruned: false,
+            incumbent: None,
+            incumbent_pruning: false,
+            tie_breaker: None,
+            bounds: None,
+            parentless: false,
+            seeded: false,
+            query: None,
+            discovered: Vec::new(),
         }
     }
 
+    /// Change the heuristic inflation factor, see [`with_weight`](#method.with_weight)
+    ///
+    /// This replaces any [dynamic weighting](#method.set_dynamic_weight).
+    pub fn set_weight(&mut self, epsilon: f64) {
+        self.weight = epsilon;
+        self.dynamic_weight = None;
+    }
+
+    /// Inflate the heuristic less for deeper nodes, down to plain A* at `anticipated_depth`
+    ///
+    /// Nodes are ordered by `f = g + (1 + epsilon * (1 - d / anticipated_depth)) * h`, where
+    /// `d` is the number of steps from the start to the node, and no less than `g + h` once
+    /// `d` passes the anticipated depth.  The search dives greedily away from the start, where
+    /// the heuristic is furthest from the truth, and settles into plain A* as it nears the
+    /// goal, often expanding fewer nodes than weighted A* with the same `epsilon` without
+    /// giving up its bound: with an admissible heuristic, the trajectory costs at most
+    /// `1 + epsilon` times the optimal cost.  The anticipated depth is best set to the number
+    /// of steps an optimal trajectory is expected to take.
+    ///
+    /// This replaces the static weight until [`set_weight`](#method.set_weight) is called.
+    ///
+    /// ```
+    /// # use game_lib::path::astar::AStar;
+    /// # use game_lib::path::grid::{Cell, GridModel, GridSampler};
+    /// # use game_lib::path::Optimizer;
+    /// let (mut grid, start, goal) = (GridModel::new(30, 30), Cell::new(3, 15), Cell::new(27, 14));
+    /// let mut astar = AStar::new();
+    /// astar.set_dynamic_weight(1.0, 24);
+    /// assert!(astar.optimize(&mut grid, &start, &goal, &mut GridSampler).is_final());
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// When the anticipated depth is zero.
+    pub fn set_dynamic_weight(&mut self, epsilon: f64, anticipated_depth: usize) {
+        assert!(anticipated_depth > 0, "dynamic weighting needs an anticipated depth");
+        self.dynamic_weight = Some((epsilon, anticipated_depth));
+    }
+
+    /// Forget the previous search so that a new one can begin
+    ///
+    /// The queue and maps keep their allocations, so once an optimizer has grown large enough
+    /// for the searches it runs, repeating a search after clearing allocates nothing inside
+    /// the optimizer; only the returned trajectory is allocated.  Use
+    /// [`shrink_to_fit`](#method.shrink_to_fit) to give the memory back.
+    ///
+    /// ```
+    /// # use game_lib::path::astar::AStar;
+    /// # use game_lib::path::grid::{Cell, GridModel, GridSampler};
+    /// # use game_lib::path::Optimizer;
+    /// let (mut astar, mut grid) = (AStar::new(), GridModel::new(30, 30));
+    /// astar.optimize(&mut grid, &Cell::new(2, 2), &Cell::new(27, 25), &mut GridSampler);
+    /// astar.clear();
+    /// assert!(astar.capacity() > 0);
+    /// ```
     pub fn clear(&mut self) {
         self.queue.clear();
         self.parent_map.clear();
         self.grid.clear();
+        self.closed.clear();
+        self.stats = Stats::default();
+        self.closest = None;
+        self.pruned = false;
+        self.incumbent = None;
+        self.parentless = false;
+        self.seeded = false;
+        self.query = None;
+        self.discovered.clear();
+    }
+
+    /// Forget the previous search and queue `start` to begin a new one towards `goal`
+    ///
+    /// A search which is not reset is resumed by the next call to `next_trajectory` or
+    /// `optimize`, and one for another start or goal fails with
+    /// [`InvalidQuery`](../enum.PathFindingErr.html#variant.InvalidQuery), so reset the
+    /// optimizer before stepping through a new query.  Only a new or cleared optimizer begins
+    /// a search by itself.
+    ///
+    /// ```
+    /// # use game_lib::path::astar::AStar;
+    /// # use game_lib::path::grid::{Cell, GridModel, GridSampler};
+    /// # use game_lib::path::Optimizer;
+    /// let (mut grid, start, goal) = (GridModel::new(10, 10), Cell::new(0, 0), Cell::new(3, 0));
+    /// let mut astar = AStar::new();
+    /// astar.reset(&grid, &start, &goal);
+    /// while !astar.next_trajectory(&mut grid, &start, &goal, &mut GridSampler).is_final() {}
+    /// ```
+    pub fn reset(&mut self, model: &M, start: &M::State, goal: &M::State) {
+        self.clear();
+        self.query = Some((start.grid_position(), goal.grid_position()));
+        // A start the goal cannot be reached from is left unqueued, for the search to report
+        let _ = self.try_seed(model, start, model.heuristic_opt(start, goal));
+    }
+
+    /// Make room for at least `nodes` more nodes in the queue and in each of the maps
+    fn reserve(&mut self, nodes: usize) {
+        self.queue.reserve(nodes);
+        self.parent_map.reserve(nodes);
+        self.grid.reserve(nodes);
+        self.closed.reserve(nodes);
+    }
+
+    /// Release the memory held by the queue and maps which is not in use
+    pub fn shrink_to_fit(&mut self) {
+        self.queue.shrink_to_fit();
+        self.parent_map.shrink_to_fit();
+        self.grid.shrink_to_fit();
+        self.closed.shrink_to_fit();
+        self.discovered.shrink_to_fit();
+    }
+
+    /// Number of nodes the optimizer can hold before the queue or any of the maps has to grow
+    pub fn capacity(&self) -> usize {
+        self.queue
+            .capacity()
+            .min(self.parent_map.capacity())
+            .min(self.grid.capacity())
+            .min(self.closed.capacity())
+    }
+
+    /// Counters describing the work done since the optimizer was created or last cleared
+    ///
+    /// ```
+    /// # use game_lib::path::astar::AStar;
+    /// # use game_lib::path::grid::{Cell, GridModel, GridSampler};
+    /// # use game_lib::path::Optimizer;
+    /// let mut astar = AStar::new();
+    /// astar.optimize(&mut GridModel::new(12, 12), &Cell::new(1, 1), &Cell::new(10, 9), &mut GridSampler);
+    /// assert!(astar.stats().expanded > 0);
+    /// ```
+    pub fn stats(&self) -> &Stats {
+        &self.stats
+    }
+
+    /// Number of grid positions which have been expanded and will not be expanded again
+    ///
+    /// ```
+    /// # use game_lib::path::astar::AStar;
+    /// # use game_lib::path::grid::{Cell, GridModel, GridSampler};
+    /// # use game_lib::path::Optimizer;
+    /// let mut astar = AStar::new();
+    /// astar.optimize(&mut GridModel::new(12, 12), &Cell::new(1, 1), &Cell::new(10, 9), &mut GridSampler);
+    /// assert!(astar.closed_count() > 0);
+    /// ```
+    pub fn closed_count(&self) -> usize {
+        self.closed.len()
+    }
+
+    /// The number of entries held by the queue and maps, and an estimate of their size
+    ///
+    /// ```
+    /// # use game_lib::path::grid::{Cell, GridModel, GridSampler};
+    /// # use game_lib::path::{astar::AStar, Optimizer};
+    /// let (mut grid, start, goal) = (GridModel::new(20, 20), Cell::new(2, 2), Cell::new(17, 15));
+    /// let mut astar = AStar::new();
+    /// astar.optimize(&mut grid, &start, &goal, &mut GridSampler);
+    /// assert_eq!(astar.memory_report().closed_len, astar.closed_count());
+    /// ```
+    pub fn memory_report(&self) -> MemoryReport {
+        use std::mem::size_of;
+
+        let position = size_of::<<<M as Model>::State as State>::Position>();
+        let (id, node) = (size_of::<Id<M>>(), size_of::<Node<M>>());
+        let best = size_of::<(Node<M>, Option<usize>)>();
+
+        MemoryReport {
+            queue_len: self.queue.len(),
+            parent_map_len: self.parent_map.len(),
+            grid_len: self.grid.len(),
+            closed_len: self.closed.len(),
+            bytes: self.queue.len() * node
+                + self.parent_map.len() * (id + node)
+                + self.grid.len() * (position + best)
+                + self.closed.len() * (position + node)
+                + self.discovered.len() * position,
+        }
     }
 
     pub fn inspect_queue(&self) -> impl Iterator<Item = (&M::State, &M::Control)> {
         self.queue.iter().map(|node| (&node.state, &node.control))
     }
 
+    /// The node the search will take up next, with the estimated cost it is ordered by
+    ///
+    /// This is the node with the lowest `f` in the queue, which the next step expands, unless
+    /// it reaches the goal, or its position was already expanded with a heuristic which is
+    /// not consistent and it is skipped.  Stale nodes on top of the queue, for which a cheaper
+    /// path was found since, are dropped along the way, as the next step would drop them too.
+    ///
+    /// ```
+    /// # use game_lib::path::astar::AStar;
+    /// # use game_lib::path::grid::{Cell, GridModel};
+    /// let (grid, start, goal) = (GridModel::new(10, 10), Cell::new(0, 0), Cell::new(3, 0));
+    /// let mut astar = AStar::new();
+    /// astar.reset(&grid, &start, &goal);
+    /// assert_eq!(astar.peek_next(&grid).map(|(&cell, _, &f)| (cell, f)), Some((start, 6)));
+    /// ```
+    pub fn peek_next(&mut self, model: &M) -> Option<(&M::State, &M::Control, &M::Cost)> {
+        while self.queue.peek().is_some_and(|top| self.is_stale(model, top)) {
+            self.queue.pop();
+        }
+
+        self.queue.peek().map(|node| (&node.state, &node.control, &node.id.f.0))
+    }
+
     pub fn inspect_discovered(
         &self,
     ) -> impl Iterator<Item = &<<M as Model>::State as State>::Position> {
         self.grid.keys()
     }
 
+    /// The positions discovered since the last call, in the order they were discovered
+    ///
+    /// Unlike [`inspect_discovered`](#method.inspect_discovered), which goes over every
+    /// position each time, this only hands out each position once, so drawing a large search
+    /// as it grows takes work in proportion to what is new.  Positions pile up until they are
+    /// drained, or the optimizer is cleared.
+    ///
+    /// ```
+    /// # use game_lib::path::grid::{Cell, GridModel, GridSampler};
+    /// # use game_lib::path::{astar::AStar, Optimizer};
+    /// let (mut grid, start, goal) = (GridModel::new(20, 20), Cell::new(2, 2), Cell::new(17, 15));
+    /// let mut astar = AStar::new();
+    /// astar.next_trajectory(&mut grid, &start, &goal, &mut GridSampler);
+    /// assert!(astar.drain_discovered().count() > 0);
+    /// // Nothing new since the last call
+    /// assert_eq!(astar.drain_discovered().count(), 0);
+    /// ```
+    pub fn drain_discovered(&mut self) -> impl Iterator<Item = Position<M>> + '_ {
+        self.discovered.drain(..)
+    }
+
+    /// The cost of the cheapest path found from the start to every discovered position
+    ///
+    /// The costs of positions which have been expanded are final, while the costs of positions
+    /// still waiting in the queue may drop as the search goes on.  Reading the costs after a
+    /// search gives a map of distances from the start without planning again.
+    ///
+    /// ```
+    /// # use game_lib::path::grid::{Cell, GridModel, GridSampler};
+    /// # use game_lib::path::{astar::AStar, Optimizer};
+    /// let (mut grid, start, goal) = (GridModel::new(12, 12), Cell::new(2, 5), Cell::new(10, 5));
+    /// let mut astar = AStar::new();
+    /// astar.optimize(&mut grid, &start, &goal, &mut GridSampler);
+    /// // Eight straight steps to the goal
+    /// assert!(astar.cost_to_come().any(|(cell, &cost)| *cell == goal && cost == 16));
+    /// ```
+    pub fn cost_to_come(
+        &self,
+    ) -> impl Iterator<Item = (&<<M as Model>::State as State>::Position, &M::Cost)> {
+        self.grid.iter().map(|(position, (node, _))| (position, &node.id.g))
+    }
+
+    /// Whether the search has discovered `position`, expanded or not
+    ///
+    /// ```
+    /// # use game_lib::path::grid::{Cell, GridModel, GridSampler};
+    /// # use game_lib::path::{astar::AStar, Optimizer};
+    /// let (mut grid, start, goal) = (GridModel::new(12, 12), Cell::new(2, 5), Cell::new(10, 5));
+    /// let mut astar = AStar::new();
+    /// astar.optimize(&mut grid, &start, &goal, &mut GridSampler);
+    /// assert!(astar.contains(&goal) && !astar.contains(&Cell::new(2, 0)));
+    /// ```
+    pub fn contains(&self, position: &Position<M>) -> bool {
+        self.grid.contains_key(position)
+    }
+
+    /// The cost of the cheapest path found so far from the start to `position`
+    ///
+    /// This is the cost [`cost_to_come`](#method.cost_to_come) lists for the position, looked
+    /// up directly.  It is final once the position has been expanded, and `None` when the
+    /// position was never discovered.
+    ///
+    /// ```
+    /// # use game_lib::path::grid::{Cell, GridModel, GridSampler};
+    /// # use game_lib::path::{astar::AStar, Optimizer};
+    /// let (mut grid, start, goal) = (GridModel::new(12, 12), Cell::new(2, 5), Cell::new(10, 5));
+    /// let mut astar = AStar::new();
+    /// astar.optimize(&mut grid, &start, &goal, &mut GridSampler);
+    /// assert_eq!(astar.best_cost_to(&goal), Some(&16));
+    /// ```
+    pub fn best_cost_to(&self, position: &Position<M>) -> Option<&M::Cost> {
+        self.grid.get(position).map(|(node, _)| &node.id.g)
+    }
+
     #[inline(always)]
-    fn step<S>(
+    fn step<S, G, H>(
         &mut self,
         current: &Node<M>,
-        model: &mut M,
-        goal: &M::State,
+        model: &M,
         sampler: &mut S,
-    ) -> bool
+        is_goal: &mut G,
+        heuristic: &mut H,
+    ) -> Result<bool, PathFindingErr>
     where
-        S: Sampler<M>,
+        S: Successors<M>,
+        G: FnMut(&M::State) -> bool,
+        H: FnMut(&M::State) -> Option<M::Cost>,
     {
-        if model.converge(&current.state, goal) {
-            return true;
+        if is_goal(&current.state) {
+            self.improve_incumbent(current.id.g());
+            return Ok(true);
         }
 
-        for control in sampler.sample(model, &current.state) {
-            if let Some(child_state) = model.integrate(&current.state, &control) {
-                self.id_counter += 1;
+        if let Some(incumbent) = self.incumbent.filter(|_| self.incumbent_pruning) {
+            // Nothing through this node can beat the trajectory to the goal already found
+            let bound = match heuristic(&current.state) {
+                Some(estimate) => self.accumulate(current.id.g(), estimate)?,
+                None => return Ok(false),
+            };
+            if bound >= incumbent {
+                return Ok(false);
+            }
+        }
 
-                let cost = current.id.g() + model.cost(&current.state, &control, &child_state);
-                let heuristic = model.heuristic(&child_state, goal);
+        if !self.close(model, current) {
+            return Ok(false);
+        }
+        self.stats.expanded += 1;
 
-                let child = Node::<M> {
-                    id: Id::new(self.id_counter, cost + heuristic, cost),
-                    state: child_state,
-                    control: control.clone(),
-                };
+        let current_estimate =
+            if self.consistency_checks { heuristic(&current.state) } else { None };
 
-                let position = self.grid.entry(child.state.grid_position());
+        let position = model.canonicalize(&current.state);
+        let mut successors = Vec::new();
+        sampler.successors(model, &current.state, |control, child_state, cost| {
+            if !self.in_bounds(&child_state) {
+                return Ok(());
+            }
+            // Staying in place, like waiting without time in the state, can never reach the
+            // position more cheaply than `current` already has
+            if model.canonicalize(&child_state) == position {
+                if self.progress_checks && cost == Default::default() {
+                    return Err(PathFindingErr::NonProgressingEdge);
+                }
+                return Ok(());
+            }
 
-                match position {
-                    Entry::Occupied(mut best) => {
-                        let best = best.get_mut();
-                        if best.g <= child.id.g {
-                            continue;
-                        } else {
-                            *best = child.id.clone();
-                        }
-                    }
-                    Entry::Vacant(empty) => {
-                        empty.insert(child.id.clone());
-                    }
+            let estimate = match heuristic(&child_state) {
+                Some(estimate) => estimate,
+                // The model knows the goal cannot be reached from here
+                None => return Ok(()),
+            };
+            if let Some(current_estimate) = current_estimate {
+                check_consistency(current, &child_state, current_estimate, cost, estimate);
+            }
+            if let Some(ceiling) = self.ceiling {
+                // A goal state is only ever charged what it took to get there
+                let g = self.accumulate(current.id.g(), cost)?;
+                let bound =
+                    if is_goal(&child_state) { g } else { self.accumulate(g, estimate)? };
+                if bound > ceiling {
+                    self.pruned = true;
+                    return Ok(());
                 }
+            }
+            let reaches_goal = self.incumbent_pruning && is_goal(&child_state);
+            if let Some(incumbent) = self.incumbent.filter(|_| self.incumbent_pruning) {
+                let g = self.accumulate(current.id.g(), cost)?;
+                if !reaches_goal && self.accumulate(g, estimate)? >= incumbent {
+                    return Ok(());
+                }
+            }
+            let successor = (control.clone(), child_state, cost, estimate, reaches_goal);
+            match self.max_successors {
+                Some(_) => successors.push(successor),
+                None => self.queue_successor(model, current, successor)?,
+            }
+            Ok(())
+        })?;
 
-                self.parent_map.insert(child.id.clone(), current.clone());
-                self.queue.push(child);
+        if let Some(limit) = self.max_successors {
+            // Successors whose estimate overflows sort first, so that relaxing reports it
+            let (g, depth) = (current.id.g(), current.id.depth + 1);
+            successors.sort_by_cached_key(|(_, _, cost, estimate, _)| {
+                g.checked_add(*cost)
+                    .and_then(|g| g.checked_add(self.inflate(*estimate, depth)))
+            });
+            successors.truncate(limit);
+            for successor in successors {
+                self.queue_successor(model, current, successor)?;
             }
         }
 
-        false
+        Ok(false)
     }
 
-    /// Follow the parents from the goal node up to the start node
-    fn unwind_trajectory(&self, model: &M, mut current: Node<M>) -> Trajectory<M> {
-        let mut result = Vec::new();
-        result.push((current.state.clone(), current.control.clone()));
-        let mut cost = M::Cost::default();
-
-        // build up the trajectory by following the parent nodes
-        while let Some(p) = self.parent_map.get(&current.id) {
-            cost = cost + model.cost(&current.state, &current.control, &p.state);
-            current = (*p).clone();
-            result.push((current.state.clone(), current.control.clone()));
+    /// Relax a successor sampled by `step`, which becomes the incumbent when it reaches the
+    /// goal more cheaply than any trajectory before it
+    #[inline(always)]
+    fn queue_successor(
+        &mut self,
+        model: &M,
+        current: &Node<M>,
+        (control, child_state, cost, estimate, reaches_goal): Successor<M>,
+    ) -> Result<(), PathFindingErr> {
+        self.relax(model, current, &control, child_state, cost, estimate)?;
+        if reaches_goal {
+            let g = self.accumulate(current.id.g(), cost)?;
+            self.improve_incumbent(g);
         }
+        Ok(())
+    }
 
-        result.reverse();
+    #[inline(always)]
+    fn improve_incumbent(&mut self, cost: M::Cost) {
+        if self.incumbent.is_none_or(|incumbent| cost < incumbent) {
+            self.incumbent = Some(cost);
+        }
+    }
 
-        Trajectory { cost, trajectory: result }
+    /// Mark the position of `current` as expanded, returning whether it should be expanded
+    ///
+    /// A position is settled the first time it is expanded, and later duplicates are skipped.
+    /// Only the cheapest node found for a position makes it past `pop`, so when a closed
+    /// position comes up again a strictly cheaper path to it was found after it was expanded;
+    /// with reopening turned on it is expanded again.
+    #[inline(always)]
+    fn close(&mut self, model: &M, current: &Node<M>) -> bool {
+        match self.closed.entry(model.canonicalize(&current.state)) {
+            Entry::Vacant(empty) => {
+                empty.insert(current.clone());
+                true
+            }
+            Entry::Occupied(mut expanded) => {
+                // Expanded or not, it is now the cheapest known way to the position
+                expanded.insert(current.clone());
+                if self.reopen_closed {
+                    self.stats.reopened += 1;
+                }
+                self.reopen_closed
+            }
+        }
     }
-}
 
-impl<M, S> Optimizer<M, S> for AStar<M>
-where
-    M: HeuristicModel,
-    M::Cost: radix_heap::Radix + Copy,
-    S: Sampler<M>,
-{
-    fn next_trajectory(
+    /// Queue a successor of `current`, unless its position was already reached more cheaply
+    ///
+    /// Returns the id of the queued successor, or `None` when it was not queued.
+    #[inline(always)]
+    fn relax(
         &mut self,
-        model: &mut M,
-        start: &M::State,
-        goal: &M::State,
-        sampler: &mut S,
-    ) -> PathResult<M> {
-        use PathFindingErr::*;
-        use PathResult::*;
+        model: &M,
+        current: &Node<M>,
+        control: &M::Control,
+        child_state: M::State,
+        step_cost: M::Cost,
+        estimate: M::Cost,
+    ) -> Result<Option<Id<M>>, PathFindingErr> {
+        self.id_counter += 1;
+        self.stats.generated += 1;
 
-        if self.parent_map.is_empty() && self.queue.is_empty() {
-            let heuristic = model.heuristic(start, goal);
-            let start_id = Id::new(0, heuristic, Default::default());
-            self.queue.push(Node {
-                id: start_id,
-                state: start.clone(),
-                control: Default::default(),
-            });
-        }
+        let cost = self.accumulate(current.id.g(), step_cost)?;
+        let depth = current.id.depth + 1;
+        let f = self.accumulate(cost, self.inflate(estimate, depth))?;
+        let position = model.canonicalize(&child_state);
+        let tie = match (self.tie_breaker, &self.query) {
+            (Some(tie_breaker), Some((start, goal))) => {
+                tie_breaker(&child_state.grid_position(), start, goal)
+            }
+            _ => 0,
+        };
+        let child = Node::<M> {
+            id: Id::new(self.id_counter, f, cost).at_depth(depth).with_tie(tie),
+            state: child_state,
+            control: control.clone(),
+        };
 
-        if let Some(current) = self.queue.pop() {
-            if self.step(&current, model, &goal, sampler) {
-                Final(self.unwind_trajectory(model, current))
-            } else {
-                Intermediate(self.unwind_trajectory(model, current))
+        let handle = match self.grid.entry(position.clone()) {
+            Entry::Occupied(mut best) => {
+                let (best, handle) = best.get_mut();
+                if best.id.g <= child.id.g {
+                    return Ok(None);
+                }
+                // A lower `g` may still carry a higher `f`, as when the weight falls off with
+                // depth or the merged states differ in their estimates, and then the node
+                // cannot be raised in place
+                let replace = child.id >= best.id;
+                *best = child.clone();
+                handle.filter(|_| replace)
             }
+            Entry::Vacant(empty) => {
+                self.discovered.push(empty.key().clone());
+                empty.insert((child.clone(), None));
+                None
+            }
+        };
+
+        if !self.parentless {
+            self.parent_map.insert(child.id.clone(), current.clone());
+        }
+        self.approach(estimate, &child);
+        let id = child.id.clone();
+        let queued = match handle {
+            // Replace the costlier node in place rather than leave it behind, stale
+            Some(handle) => self.queue.decrease_key(handle, child),
+            // Either nothing to replace, or the old node is left behind to be skipped
+            None => self.queue.push_handle(child),
+        };
+        if queued.is_some() {
+            if let Some((_, handle)) = self.grid.get_mut(&position) {
+                *handle = queued;
+            }
+        }
+        self.stats.max_queue_len = self.stats.max_queue_len.max(self.queue.len());
+
+        Ok(Some(id))
+    }
+
+    /// Add two costs, checking for overflow unless the checks have been turned off
+    #[inline(always)]
+    fn accumulate(&self, a: M::Cost, b: M::Cost) -> Result<M::Cost, PathFindingErr> {
+        if self.overflow_checks {
+            a.checked_add(b).ok_or(PathFindingErr::CostOverflow)
         } else {
-            Err(Unreachable)
+            Ok(a + b)
         }
     }
 
-    fn optimize(
+    /// Calculate an optimal trajectory, expanding at most `max_expansions` nodes
+    ///
+    /// When the budget runs out before the goal is reached the search stops with
+    /// `PathFindingErr::BudgetExhausted`, leaving the queue intact so that the search can be
+    /// resumed by calling this method again.  The path to the most promising node found so far
+    /// is available from [`partial_trajectory`](#method.partial_trajectory).
+    ///
+    /// ```
+    /// # use game_lib::path::astar::AStar;
+    /// # use game_lib::path::grid::{Cell, GridModel, GridSampler};
+    /// # use game_lib::path::PathFindingErr;
+    /// let (mut astar, mut grid) = (AStar::new(), GridModel::new(30, 30));
+    /// let (start, goal) = (Cell::new(1, 1), Cell::new(28, 28));
+    /// let result = astar.optimize_with_budget(&mut grid, &start, &goal, &mut GridSampler, 5);
+    /// assert_eq!(result.into_trajectory().unwrap_err(), PathFindingErr::BudgetExhausted(5));
+    /// ```
+    pub fn optimize_with_budget<S>(
         &mut self,
         model: &mut M,
         start: &M::State,
         goal: &M::State,
         sampler: &mut S,
-    ) -> PathResult<M> {
-        use PathFindingErr::*;
-        use PathResult::*;
-
-        if model.converge(start, goal) {
-            return Final(Trajectory {
-                cost: Default::default(),
-                trajectory: vec![(start.clone(), Default::default())],
-            });
-        }
-
-        if self.queue.pop().is_none() {
-            let start_id = Id::new(0, model.heuristic(start, goal), Default::default());
-            self.queue.push(Node {
-                id: start_id,
-                state: start.clone(),
-                control: Default::default(),
-            });
-        }
-
-        while let Some(current) = self.queue.pop() {
-            if self.step(&current, model, &goal, sampler) {
-                return Final(self.unwind_trajectory(model, current));
-            }
-        }
+        max_expansions: usize,
+    ) -> PathResult<M>
+    where
+        S: Sampler<M>,
+    {
+        self.search_goal(
+            model,
+            start,
+            goal,
+            &mut Serial(sampler),
+            max_expansions,
+            None,
+            &mut |_: &M::State, _: &M::Cost| {},
+        )
+    }
 
-        Err(Unreachable)
+    /// Calculate an optimal trajectory, giving up once `deadline` has passed
+    ///
+    /// The clock is only read every [`set_clock_interval`](#method.set_clock_interval)
+    /// expansions, so the search may overrun the deadline slightly.  A search which finishes in
+    /// time returns exactly what `optimize` would.  When time runs out the search stops with
+    /// `PathFindingErr::TimedOut`, and can be resumed or inspected the same way as
+    /// [`optimize_with_budget`](#method.optimize_with_budget).
+    ///
+    /// ```
+    /// # use game_lib::path::astar::AStar;
+    /// # use game_lib::path::grid::{Cell, GridModel, GridSampler};
+    /// # use game_lib::path::PathFindingErr;
+    /// # use std::time::Instant;
+    /// let (mut astar, mut grid) = (AStar::new(), GridModel::new(30, 30));
+    /// astar.set_clock_interval(1);
+    /// let (start, goal) = (Cell::new(1, 1), Cell::new(28, 28));
+    /// let result = astar.optimize_until(&mut grid, &start, &goal, &mut GridSampler, Instant::now());
+    /// assert_eq!(result.into_trajectory().unwrap_err(), PathFindingErr::TimedOut);
+    /// ```
+    pub fn optimize_until<S>(
+        &mut self,
+        model: &mut M,
+        start: &M::State,
+        goal: &M::State,
+        sampler: &mut S,
+        deadline: Instant,
+    ) -> PathResult<M>
+    where
+        S: Sampler<M>,
+    {
+        self.search_goal(
+            model,
+            start,
+            goal,
+            &mut Serial(sampler),
+            usize::MAX,
+            Some(deadline),
+            &mut |_: &M::State, _: &M::Cost| {},
+        )
     }
-}
 
-impl<M> Debug for AStar<M>
-where
-    M: HeuristicModel,
-    M::State: Debug,
-    M::Control: Debug,
+    /// Calculate an optimal trajectory, calling `on_expand` with every node as it is expanded
+    ///
+    /// The hook is given the state of the node and its cost-to-come, in the order the search
+    /// expands them, which is enough to draw the search as it happens without stepping
+    /// through it with `next_trajectory`.  Nodes discarded as stale are not expanded, and
+    /// neither is the goal, which ends the search as soon as it is reached.  The hook only
+    /// borrows what it is given, so it can collect into anything outside the optimizer.
+    ///
+    /// ```
+    /// # use game_lib::path::grid::{Cell, GridModel, GridSampler};
+    /// # use game_lib::path::{astar::AStar, Optimizer};
+    /// let (mut grid, start, goal) = (GridModel::new(12, 12), Cell::new(2, 5), Cell::new(10, 6));
+    /// let (mut astar, mut expanded) = (AStar::new(), Vec::new());
+    /// astar.optimize_with_hook(&mut grid, &start, &goal, &mut GridSampler, |cell, _| {
+    ///     expanded.push(*cell)
+    /// });
+    /// assert_eq!(expanded.len(), astar.stats().expanded);
+    /// ```
+    pub fn optimize_with_hook<S, F>(
+        &mut self,
+        model: &mut M,
+        start: &M::State,
+        goal: &M::State,
+        sampler: &mut S,
+        mut on_expand: F,
+    ) -> PathResult<M>
+    where
+        S: Sampler<M>,
+        F: FnMut(&M::State, &M::Cost),
+    {
+        self.search_goal(
+            model,
+            start,
+            goal,
+            &mut Serial(sampler),
+            usize::MAX,
+            None,
+            &mut on_expand,
+        )
+    }
+
+    /// Calculate an optimal trajectory, provided that it costs no more than `max_cost`
+    ///
+    /// Successors whose estimated total cost, the cost so far plus the uninflated heuristic,
+    /// exceeds the ceiling are never queued, so the search stays within the region that a
+    /// trajectory under the ceiling could pass through.  The returned trajectory reports its
+    /// actual cost.  When the goal is unreachable without exceeding the ceiling the search
+    /// stops with `PathFindingErr::CostLimitExceeded`, or with `Unreachable` if nothing was
+    /// pruned and the goal cannot be reached at any cost.  An inadmissible heuristic can prune
+    /// trajectories which are in fact under the ceiling.
+    ///
+    /// ```
+    /// # use game_lib::path::grid::{Cell, GridModel, GridSampler};
+    /// # use game_lib::path::{astar::AStar, Optimizer, PathFindingErr, PathResult};
+    /// let (mut grid, start, goal) = (GridModel::new(10, 10), Cell::new(0, 0), Cell::new(5, 0));
+    /// // Five straight steps cost 10
+    /// let mut astar = AStar::new();
+    /// let result = astar.optimize_within(&mut grid, &start, &goal, &mut GridSampler, 9);
+    /// assert!(matches!(result, PathResult::Err(PathFindingErr::CostLimitExceeded)));
+    /// ```
+    pub fn optimize_within<S>(
+        &mut self,
+        model: &mut M,
+        start: &M::State,
+        goal: &M::State,
+        sampler: &mut S,
+        max_cost: M::Cost,
+    ) -> PathResult<M>
+    where
+        S: Sampler<M>,
+    {
+        self.ceiling = Some(max_cost);
+        self.pruned = false;
+        let result = self.search_goal(
+            model,
+            start,
+            goal,
+            &mut Serial(sampler),
+            usize::MAX,
+            None,
+            &mut |_: &M::State, _: &M::Cost| {},
+        );
+        self.ceiling = None;
+        result
+    }
+
+    /// Turn the checks for overflowing costs on or off
+    ///
+    /// With the checks on, which is the default, a search whose costs grow too large for the
+    /// model's cost type stops with `PathFindingErr::CostOverflow` rather than returning a
+    /// trajectory ordered by wrapped costs.  Turning them off saves a branch per successor, and
+    /// leaves overflow to the usual integer behavior: a panic in debug builds, and wrapping in
+    /// release builds.
+    pub fn set_overflow_checks(&mut self, enabled: bool) {
+        self.overflow_checks = enabled;
+    }
+
+    /// Turn the checks that the heuristic is consistent on or off
+    ///
+    /// With the checks on, every successor of an expanded node is checked against
+    /// `h(current) <= cost(current, successor) + h(successor)`, and the search panics with the
+    /// offending states when a heuristic overestimates the cost of a step.  An inconsistent
+    /// heuristic silently leads to trajectories which are not optimal, so the checks are worth
+    /// turning on while working on a model.  They are off by default, and cost nothing then.
+    ///
+    /// ```
+    /// # use game_lib::path::grid::{Cell, GridModel, GridSampler};
+    /// # use game_lib::path::{astar::AStar, Optimizer};
+    /// let (mut grid, start, goal) = (GridModel::new(10, 10), Cell::new(1, 1), Cell::new(8, 8));
+    /// let mut astar = AStar::new();
+    /// astar.set_consistency_checks(true);
+    /// // The octile distance never overestimates a step, so the search runs to the end
+    /// assert!(astar.optimize(&mut grid, &start, &goal, &mut GridSampler).is_final());
+    /// ```
+    pub fn set_consistency_checks(&mut self, enabled: bool) {
+        self.consistency_checks = enabled;
+    }
+
+    /// Turn the checks that every step makes progress on or off
+    ///
+    /// A step must either cost something or move to another position.  One which does
+    /// neither is skipped like any other step which stays in place, but a control that does
+    /// nothing for free is almost always a mistake in the model, and a search which leans on
+    /// such steps can go round in circles without the cost ever growing.  With the checks on,
+    /// the search fails with [`NonProgressingEdge`] instead.  The checks are on by default in
+    /// debug builds only.
+    ///
+    /// [`NonProgressingEdge`]: ../enum.PathFindingErr.html#variant.NonProgressingEdge
+    pub fn set_progress_checks(&mut self, enabled: bool) {
+        self.progress_checks = enabled;
+    }
+
+    /// Turn the reopening of positions which were already expanded on or off
+    ///
+    /// A consistent heuristic reaches every position by its cheapest path first, so expanding
+    /// each position once is enough; this is the default.  An admissible heuristic which is not
+    /// consistent can lead to a cheaper path to a position after it was expanded, and unless
+    /// the position is reopened and expanded again the trajectory may not be optimal.  Every
+    /// reopening is counted in [`Stats::reopened`].  Inconsistent heuristics also trip the
+    /// [consistency checks](#method.set_consistency_checks), so those have to stay off.
+    ///
+    /// ```
+    /// # use game_lib::path::grid::{Cell, GridModel, GridSampler};
+    /// # use game_lib::path::{astar::AStar, Optimizer};
+    /// let (mut grid, start, goal) = (GridModel::new(10, 10), Cell::new(1, 1), Cell::new(8, 6));
+    /// let mut astar = AStar::new();
+    /// astar.set_reopen_closed(true);
+    /// assert!(astar.optimize(&mut grid, &start, &goal, &mut GridSampler).is_final());
+    /// // The octile distance is consistent, so nothing needed reopening
+    /// assert_eq!(astar.stats().reopened, 0);
+    /// ```
+    pub fn set_reopen_closed(&mut self, enabled: bool) {
+        self.reopen_closed = enabled;
+    }
+
+    /// Stop expanding nodes which cannot lead to a cheaper trajectory than the incumbent
+    ///
+    /// The incumbent is the cheapest trajectory to the goal found so far, which is known as
+    /// soon as a successor reaching the goal is generated, well before it is popped.  With
+    /// pruning on, nodes whose cost so far plus the uninflated heuristic is no less than the
+    /// incumbent's cost are neither queued nor expanded, which spares the nodes A* would
+    /// otherwise expand while the successor reaching the goal waits its turn in the queue.
+    /// The heuristic has to be admissible, or trajectories cheaper than the incumbent may be
+    /// pruned, which is why this is off by default.
+    ///
+    /// With [reopening](#method.set_reopen_closed) on as well, the search no longer ends at
+    /// the first trajectory to the goal: it goes on until no node in the queue could beat the
+    /// incumbent, which turns a [weighted](#method.with_weight) search into one which is
+    /// optimal, having been pulled to the goal greedily first.
+    ///
+    /// ```
+    /// # use game_lib::path::astar::AStar;
+    /// # use game_lib::path::grid::{Cell, GridModel, GridSampler};
+    /// # use game_lib::path::Optimizer;
+    /// let (mut grid, start, goal) = (GridModel::new(10, 10), Cell::new(0, 0), Cell::new(3, 0));
+    /// let mut astar = AStar::new();
+    /// astar.set_incumbent_pruning(true);
+    /// astar.optimize(&mut grid, &start, &goal, &mut GridSampler);
+    /// assert_eq!(astar.incumbent_cost(), Some(&6));
+    /// ```
+    pub fn set_incumbent_pruning(&mut self, enabled: bool) {
+        self.incumbent_pruning = enabled;
+    }
+
+    /// The cost of the cheapest trajectory to the goal the search has found so far
+    ///
+    /// Once a search ends at the goal this is the cost of the returned trajectory.  Before
+    /// then, it is only known early with [incumbent pruning](#method.set_incumbent_pruning)
+    /// on, as the successors reaching the goal are not looked for otherwise.
+    pub fn incumbent_cost(&self) -> Option<&M::Cost> {
+        self.incumbent.as_ref()
+    }
+
+    /// Accept states within `tolerance` of the goal, by the model's `converge_within`, or
+    /// only those which `converge` when there is none, which is the default
+    ///
+    /// A continuous model which only converges on the goal itself leaves the search to run
+    /// out of states, as none of them ever lands exactly on it; a tolerance ends the search
+    /// at the first state close enough.
+    pub fn set_goal_tolerance(&mut self, tolerance: Option<f64>) {
+        self.goal_tolerance = tolerance;
+    }
+
+    /// Keep at most `limit` successors of each expanded node, those with the lowest estimates
+    ///
+    /// Every control is still sampled and integrated, but only the successors with the lowest
+    /// `f` are queued, a beam over the successors of each node rather than over the whole
+    /// search; the rest are dropped and never counted as generated.  With samplers which
+    /// produce many controls this saves most of the work of queueing nodes that the search
+    /// would never come back to.  It gives up completeness, and optimality with it: when the
+    /// only way to the goal runs through a dropped successor, and no other node reaches its
+    /// position, the goal is reported as unreachable.  `None`, the default, keeps every
+    /// successor.
+    ///
+    /// ```
+    /// # use game_lib::path::astar::AStar;
+    /// # use game_lib::path::grid::{Cell, GridModel, GridSampler};
+    /// # use game_lib::path::Optimizer;
+    /// let (mut grid, start, goal) = (GridModel::new(20, 20), Cell::new(3, 10), Cell::new(17, 9));
+    /// let mut astar = AStar::new();
+    /// astar.set_max_successors(Some(3));
+    /// assert!(astar.optimize(&mut grid, &start, &goal, &mut GridSampler).is_final());
+    /// ```
+    pub fn set_max_successors(&mut self, limit: Option<usize>) {
+        self.max_successors = limit;
+    }
+
+    /// Let the search go anywhere again, after [`set_bounds`](#method.set_bounds)
+    pub fn remove_bounds(&mut self) {
+        self.bounds = None;
+    }
+
+    /// Change how many nodes are expanded between reads of the clock in `optimize_until`
+    ///
+    /// Defaults to 256, which keeps the cost of reading the clock negligible.
+    pub fn set_clock_interval(&mut self, expansions: usize) {
+        self.clock_interval = expansions.max(1);
+    }
+
+    /// Calculate an optimal trajectory to any state in a goal region
+    ///
+    /// Rather than converging on a single goal state, the search ends at the first expanded
+    /// state for which `is_goal` returns `true`.  The `heuristic` estimates the cost from a
+    /// state to the closest state in the region, and must be admissible for the trajectory to
+    /// be optimal.
+    ///
+    /// ```
+    /// # use game_lib::path::astar::AStar;
+    /// # use game_lib::path::grid::{Cell, GridModel, GridSampler};
+    /// let (mut astar, mut grid) = (AStar::new(), GridModel::new(20, 20));
+    /// let (in_column, estimate) = (|c: &Cell| c.x == 15, |c: &Cell| 2 * (15 - c.x) as usize);
+    /// let result = astar.optimize_to(&mut grid, &Cell::new(2, 12), in_column, estimate, &mut GridSampler);
+    /// assert_eq!(result.into_trajectory().unwrap().trajectory.last().unwrap().0.x, 15);
+    /// ```
+    pub fn optimize_to<S, G, H>(
+        &mut self,
+        model: &mut M,
+        start: &M::State,
+        mut is_goal: G,
+        mut heuristic: H,
+        sampler: &mut S,
+    ) -> PathResult<M>
+    where
+        S: Sampler<M>,
+        G: FnMut(&M::State) -> bool,
+        H: FnMut(&M::State) -> M::Cost,
+    {
+        self.search(
+            model,
+            start,
+            &mut Serial(sampler),
+            &mut is_goal,
+            &mut |state: &M::State| Some(heuristic(state)),
+            usize::MAX,
+            None,
+            &mut |_: &M::State, _: &M::Cost| {},
+        )
+    }
+
+    /// Calculate an optimal trajectory to whichever of the `goals` is cheapest to reach
+    ///
+    /// A state has reached the goal when it converges to any of the goals, and the heuristic
+    /// is the smallest estimate to any of them.  An empty set of goals can never be reached.
+    ///
+    /// ```
+    /// # use game_lib::path::astar::AStar;
+    /// # use game_lib::path::grid::{Cell, GridModel, GridSampler};
+    /// let (mut astar, mut grid) = (AStar::new(), GridModel::new(20, 20));
+    /// let goals = [Cell::new(2, 2), Cell::new(17, 12)];
+    /// let result = astar.optimize_multi(&mut grid, &Cell::new(14, 10), &goals, &mut GridSampler);
+    /// assert_eq!(result.into_trajectory().unwrap().trajectory.last().unwrap().0, goals[1]);
+    /// ```
+    pub fn optimize_multi<S>(
+        &mut self,
+        model: &mut M,
+        start: &M::State,
+        goals: &[M::State],
+        sampler: &mut S,
+    ) -> PathResult<M>
+    where
+        S: Sampler<M>,
+    {
+        if goals.is_empty() {
+            return PathResult::Err(PathFindingErr::Unreachable);
+        }
+
+        let (model, tolerance): (&M, _) = (model, self.goal_tolerance);
+        let mut is_goal =
+            |state: &M::State| goals.iter().any(|g| converged(model, state, g, tolerance));
+        // Goals which cannot be reached from a state play no part in its estimate
+        let mut heuristic = |state: &M::State| {
+            goals.iter().filter_map(|g| model.heuristic_opt(state, g)).min()
+        };
+        self.search(
+            model,
+            start,
+            &mut Serial(sampler),
+            &mut is_goal,
+            &mut heuristic,
+            usize::MAX,
+            None,
+            &mut |_: &M::State, _: &M::Cost| {},
+        )
+    }
+
+    /// Calculate an optimal trajectory to each of the `goals`, all from one search
+    ///
+    /// Rather than searching once for every goal, the search spreads out from the start in
+    /// order of cost until every goal has been reached, and each trajectory is unwound from
+    /// the one tree they share.  The heuristic is ignored: it only ever estimates the cost to
+    /// a single goal, and guiding the search towards one of them would leave the trajectories
+    /// to the others longer than they need to be.  Each trajectory is optimal whether or not
+    /// the heuristic is admissible.  This pays off once the goals are several, as one search
+    /// at the pace of Dijkstra's algorithm replaces many.
+    ///
+    /// The results are in the order of the goals.  Each trajectory counts the expansions it
+    /// took the shared search to reach its goal.
+    ///
+    /// ```
+    /// # use game_lib::path::astar::AStar;
+    /// # use game_lib::path::grid::{Cell, GridModel, GridSampler};
+    /// let (mut grid, start) = (GridModel::new(10, 10), Cell::new(0, 0));
+    /// let goals = [Cell::new(3, 0), Cell::new(0, 5)];
+    /// let results = AStar::new().optimize_many(&mut grid, &start, &goals, &mut GridSampler);
+    /// assert_eq!(results[1].trajectory().map(|t| t.cost), Some(10));
+    /// ```
+    pub fn optimize_many<S>(
+        &mut self,
+        model: &mut M,
+        start: &M::State,
+        goals: &[M::State],
+        sampler: &mut S,
+    ) -> Vec<PathResult<M>>
+    where
+        S: Sampler<M>,
+    {
+        use PathResult::*;
+
+        let mut results: Vec<_> =
+            goals.iter().map(|goal| invalid_endpoint(model, start, goal).map(Err)).collect();
+        let mut unsettled = results.iter().filter(|result| result.is_none()).count();
+
+        self.clear();
+        if unsettled > 0 {
+            self.seed(model, start, Default::default());
+        }
+
+        let (model, tolerance): (&M, _) = (model, self.goal_tolerance);
+        while unsettled > 0 {
+            let current = match self.pop(model) {
+                Some(current) => current,
+                None => break,
+            };
+
+            for (result, goal) in results.iter_mut().zip(goals) {
+                if result.is_none() && converged(model, &current.state, goal, tolerance) {
+                    *result = Some(Final(self.unwind_trajectory(model, current.clone())));
+                    unsettled -= 1;
+                }
+            }
+            if unsettled == 0 {
+                break;
+            }
+
+            let mut is_goal = |_: &M::State| false;
+            let mut heuristic = |_: &M::State| Some(M::Cost::default());
+            if let Result::Err(overflow) =
+                self.step(&current, model, &mut Serial(sampler), &mut is_goal, &mut heuristic)
+            {
+                for result in results.iter_mut().filter(|result| result.is_none()) {
+                    *result = Some(Err(overflow.clone()));
+                }
+                break;
+            }
+        }
+
+        results
+            .into_iter()
+            .map(|result| result.unwrap_or(Err(PathFindingErr::Unreachable)))
+            .collect()
+    }
+
+    /// Calculate every optimal trajectory from `start` to `goal`, up to `max_paths` of them
+    ///
+    /// Where `optimize` keeps a single parent for each position, this keeps every parent
+    /// reaching it at the lowest cost, and goes on expanding until no node left could reach
+    /// the goal as cheaply, so that every trajectory to it of the optimal cost is found.  The
+    /// trajectories are then enumerated from the goal back, stopping at `max_paths`, as there
+    /// can be exponentially many of them: on an open grid, every way of interleaving the
+    /// steps along each axis is as short as any other.  The order is the same from one call
+    /// to the next.
+    ///
+    /// The heuristic has to be consistent, and is never inflated whatever the weight is set
+    /// to.  Trajectories are told apart by the positions they pass through, as merged by
+    /// `Model::canonicalize`, so a model which has more than one state for some position only
+    /// sees one trajectory beyond it.  Steps which cost nothing only ever lead to the position
+    /// through its cheapest parent.  Any previous search is cleared, and this one keeps its
+    /// parents to itself, so nothing else can find trajectories by them.
+    ///
+    /// ```
+    /// # use game_lib::path::astar::AStar;
+    /// # use game_lib::path::grid::{Cell, GridModel, GridSampler};
+    /// # use game_lib::path::jps::Connectivity;
+    /// let (mut grid, start, goal) = (GridModel::new(3, 3), Cell::new(0, 0), Cell::new(1, 1));
+    /// grid.set_connectivity(Connectivity::Four);
+    /// // Across, then down, or down, then across
+    /// let paths = AStar::new().optimize_all(&mut grid, &start, &goal, &mut GridSampler, 10);
+    /// assert_eq!(paths.map(|paths| paths.len()), Ok(2));
+    /// ```
+    pub fn optimize_all<S>(
+        &mut self,
+        model: &mut M,
+        start: &M::State,
+        goal: &M::State,
+        sampler: &mut S,
+        max_paths: usize,
+    ) -> Result<Vec<Trajectory<M>>, PathFindingErr>
+    where
+        S: Sampler<M>,
+    {
+        if let Some(invalid) = invalid_endpoint(model, start, goal) {
+            return Err(invalid);
+        }
+
+        self.clear();
+        let (model, tolerance): (&M, _) = (model, self.goal_tolerance);
+        if converged(model, start, goal, tolerance) {
+            let trajectory = vec![(start.clone(), Default::default())];
+            return Ok(vec![Trajectory {
+                cost: Default::default(),
+                trajectory,
+                expansions: 0,
+            }]);
+        }
+        let estimate = model.heuristic_opt(start, goal).ok_or(PathFindingErr::Unreachable)?;
+
+        let mut parents: Parents<M> = FnvHashMap::default();
+        let mut closed = FnvHashSet::default();
+        let mut queue: BinaryHeap<Node<M>> = BinaryHeap::new();
+        parents.insert(model.canonicalize(start), (Default::default(), Vec::new()));
+        queue.push(Node {
+            id: Id::new(0, estimate, Default::default()),
+            state: start.clone(),
+            control: Default::default(),
+        });
+
+        let mut optimal: Option<M::Cost> = None;
+        let mut goals = Vec::new();
+        while let Some(current) = queue.pop() {
+            if optimal.is_some_and(|optimal| current.id.f.0 > optimal) {
+                break;
+            }
+            let position = model.canonicalize(&current.state);
+            if !closed.insert(position.clone()) {
+                continue;
+            }
+
+            if converged(model, &current.state, goal, tolerance) {
+                // Nothing beyond the goal is needed to reach it
+                optimal.get_or_insert(current.id.g);
+                goals.push(position);
+                continue;
+            }
+
+            self.stats.expanded += 1;
+            for control in sampler.sample(model, &current.state) {
+                let child_state = match model.integrate(&current.state, control) {
+                    Some(child_state) => child_state,
+                    None => continue,
+                };
+                let child_position = model.canonicalize(&child_state);
+                if child_position == position || !self.in_bounds(&child_state) {
+                    continue;
+                }
+                let step_cost = model.cost(&current.state, control, &child_state);
+                let cost = self.accumulate(current.id.g, step_cost)?;
+                let estimate = match model.heuristic_opt(&child_state, goal) {
+                    Some(estimate) => estimate,
+                    None => continue,
+                };
+                self.stats.generated += 1;
+
+                let parent = (position.clone(), control.clone(), child_state.clone());
+                match parents.entry(child_position) {
+                    Entry::Occupied(mut best) => {
+                        let (best, reaching) = best.get_mut();
+                        if cost < *best {
+                            *best = cost;
+                            *reaching = vec![parent];
+                        } else {
+                            if cost == *best && step_cost > Default::default() {
+                                reaching.push(parent);
+                            }
+                            continue;
+                        }
+                    }
+                    Entry::Vacant(empty) => {
+                        empty.insert((cost, vec![parent]));
+                    }
+                }
+
+                self.id_counter += 1;
+                let f = self.accumulate(cost, estimate)?;
+                queue.push(Node {
+                    id: Id::new(self.id_counter, f, cost),
+                    state: child_state,
+                    control: control.clone(),
+                });
+                self.stats.max_queue_len = self.stats.max_queue_len.max(queue.len());
+            }
+        }
+
+        let optimal = optimal.ok_or(PathFindingErr::Unreachable)?;
+        let mut paths = Vec::new();
+        for position in goals {
+            if parents[&position].0 == optimal {
+                let mut suffix = Vec::new();
+                enumerate_paths::<M>(
+                    &parents,
+                    &position,
+                    &mut suffix,
+                    start,
+                    &mut paths,
+                    max_paths,
+                );
+            }
+        }
+
+        let expansions = self.stats.expanded;
+        Ok(paths
+            .into_iter()
+            .map(|trajectory| Trajectory { cost: optimal, trajectory, expansions })
+            .collect())
+    }
+
+    /// Search towards the single `goal` state using the model's convergence and heuristic
+    #[allow(clippy::too_many_arguments)]
+    fn search_goal<S, E>(
+        &mut self,
+        model: &M,
+        start: &M::State,
+        goal: &M::State,
+        sampler: &mut S,
+        max_expansions: usize,
+        deadline: Option<Instant>,
+        on_expand: &mut E,
+    ) -> PathResult<M>
+    where
+        S: Successors<M>,
+        E: FnMut(&M::State, &M::Cost),
+    {
+        let invalid = invalid_endpoint(model, start, goal);
+        if let Some(invalid) = invalid.or_else(|| self.check_query(start, goal).err()) {
+            return PathResult::Err(invalid);
+        }
+
+        let tolerance = self.goal_tolerance;
+        let mut is_goal = |state: &M::State| converged(model, state, goal, tolerance);
+        let mut heuristic = |state: &M::State| model.heuristic_opt(state, goal);
+        self.search(
+            model,
+            start,
+            sampler,
+            &mut is_goal,
+            &mut heuristic,
+            max_expansions,
+            deadline,
+            on_expand,
+        )
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn search<S, G, H, E>(
+        &mut self,
+        model: &M,
+        start: &M::State,
+        sampler: &mut S,
+        is_goal: &mut G,
+        heuristic: &mut H,
+        max_expansions: usize,
+        deadline: Option<Instant>,
+        on_expand: &mut E,
+    ) -> PathResult<M>
+    where
+        S: Successors<M>,
+        G: FnMut(&M::State) -> bool,
+        H: FnMut(&M::State) -> Option<M::Cost>,
+        E: FnMut(&M::State, &M::Cost),
+    {
+        use PathFindingErr::*;
+        use PathResult::*;
+
+        if is_goal(start) {
+            return Final(Trajectory {
+                cost: Default::default(),
+                trajectory: vec![(start.clone(), Default::default())],
+                expansions: 0,
+            });
+        }
+
+        if self.is_fresh() {
+            if let Some(err) = self.try_seed(model, start, heuristic(start)).err() {
+                return Err(err);
+            }
+        }
+
+        // With reopening, the first trajectory to the goal is kept while the search goes on to
+        // try and beat it, see `set_incumbent_pruning`
+        let proving = self.incumbent_pruning && self.reopen_closed;
+        let mut best: Option<Node<M>> = None;
+
+        let already_expanded = self.stats.expanded;
+        while let Some(current) = self.pop(model) {
+            if let (Some(_), Some(incumbent)) = (&best, self.incumbent) {
+                // Every node left estimates at least `f / weight`, which is the incumbent
+                if current.id.f.0 >= incumbent.max(self.inflate(incumbent, 0)) {
+                    self.queue.push(current);
+                    break;
+                }
+            }
+
+            // Reaching the goal expands nothing, so neither the budget nor the clock holds it up
+            let reached = is_goal(&current.state);
+            let expanded = self.stats.expanded - already_expanded;
+            if !reached && expanded >= max_expansions {
+                // Keep the node so the search can pick up where it left off
+                self.queue.push(current);
+                return Err(BudgetExhausted(max_expansions));
+            }
+
+            if let Some(deadline) = deadline.filter(|_| !reached) {
+                if expanded > 0
+                    && expanded.is_multiple_of(self.clock_interval)
+                    && Instant::now() >= deadline
+                {
+                    self.queue.push(current);
+                    return Err(TimedOut);
+                }
+            }
+
+            let before = self.stats.expanded;
+            let result = self.step(&current, model, sampler, is_goal, heuristic);
+            if self.stats.expanded > before {
+                on_expand(&current.state, &current.id.g());
+            }
+            match result {
+                Ok(true) if proving => {
+                    if best.as_ref().is_none_or(|best| current.id.g < best.id.g) {
+                        best = Some(current);
+                    }
+                }
+                Ok(true) => return Final(self.unwind_trajectory(model, current)),
+                Ok(false) => {}
+                Result::Err(overflow) => return Err(overflow),
+            }
+        }
+
+        if let Some(best) = best {
+            Final(self.unwind_trajectory(model, best))
+        } else if self.ceiling.is_some() && self.pruned {
+            Err(CostLimitExceeded)
+        } else {
+            Err(Unreachable)
+        }
+    }
+
+    /// The cheapest trajectory found so far to a discovered position
+    ///
+    /// Works for any position in [`inspect_discovered`](#method.inspect_discovered), whether
+    /// it has been expanded or is still waiting in the queue, and for the goal a search ended
+    /// at.  This makes it possible to find the way to many positions with a single search.
+    /// Returns `None` for positions which were never discovered.
+    ///
+    /// ```
+    /// # use game_lib::path::grid::{Cell, GridModel, GridSampler};
+    /// # use game_lib::path::{astar::AStar, Optimizer};
+    /// let (mut grid, start, goal) = (GridModel::new(20, 20), Cell::new(2, 2), Cell::new(17, 3));
+    /// let mut astar = AStar::new();
+    /// astar.optimize(&mut grid, &start, &goal, &mut GridSampler);
+    /// // A neighbour of the start, away from the goal
+    /// assert_eq!(astar.trajectory_to(&grid, &Cell::new(1, 2)).map(|t| t.cost), Some(2));
+    /// ```
+    pub fn trajectory_to(
+        &self,
+        model: &M,
+        position: &<<M as Model>::State as State>::Position,
+    ) -> Option<Trajectory<M>> {
+        if self.parentless {
+            return None;
+        }
+        let (node, _) = self.grid.get(position)?;
+        Some(self.unwind_trajectory(model, node.clone()))
+    }
+
+    /// The trajectory to the node which would be expanded next
+    ///
+    /// Useful when a search was stopped early, as this is the most promising node found so
+    /// far.  Returns `None` when there is nothing left in the queue.
+    pub fn partial_trajectory(&self, model: &M) -> Option<Trajectory<M>> {
+        if self.parentless {
+            return None;
+        }
+        self.queue.peek().map(|node| self.unwind_trajectory(model, node.clone()))
+    }
+
+    /// Prepare the next search by queueing every state along a previous trajectory
+    ///
+    /// Useful for replanning when the goal has moved a little: the queued states carry the
+    /// cost of reaching them along the old trajectory, so the search picks up close to where
+    /// the old trajectory ended instead of expanding everything from the start again.  The
+    /// trajectory must begin at the start state of the next search, which happens to be the
+    /// case for any trajectory returned by an optimizer.
+    ///
+    /// Any previous search is cleared.  Each step of the trajectory is checked against the
+    /// model as the search would check it.  A step which the model no longer allows fails with
+    /// `PathFindingErr::Discontinuous` and a cost overflow with `PathFindingErr::CostOverflow`,
+    /// keeping the states queued before it.  Seeding stops without an error at a state outside
+    /// the bounds, at a dead end, or where a state was already reached more cheaply.  The
+    /// seeded states do not need to lead to the new `goal`, in which case the search carries
+    /// on from the other queued states as usual.
+    ///
+    /// ```
+    /// # use game_lib::path::astar::AStar;
+    /// # use game_lib::path::grid::{Cell, GridModel, GridSampler};
+    /// # use game_lib::path::Optimizer;
+    /// let (mut astar, mut grid) = (AStar::new(), GridModel::new(30, 30));
+    /// let (start, old_goal, goal) = (Cell::new(1, 1), Cell::new(20, 25), Cell::new(22, 25));
+    /// let previous = astar.optimize(&mut grid, &start, &old_goal, &mut GridSampler);
+    /// astar.seed_from_trajectory(&mut grid, &previous.into_trajectory().unwrap(), &goal).unwrap();
+    /// assert!(astar.optimize(&mut grid, &start, &goal, &mut GridSampler).is_final());
+    /// ```
+    pub fn seed_from_trajectory(
+        &mut self,
+        model: &mut M,
+        traj: &Trajectory<M>,
+        goal: &M::State,
+    ) -> Result<(), PathFindingErr> {
+        self.clear();
+
+        let mut steps = traj.trajectory.iter();
+        let start = match steps.next() {
+            Some((start, _)) => start,
+            None => return Ok(()),
+        };
+        if let Some(err) = invalid_endpoint(model, start, goal) {
+            return Err(err);
+        }
+        self.query = Some((start.grid_position(), goal.grid_position()));
+        self.try_seed(model, start, model.heuristic_opt(start, goal))?;
+        let mut parent = self.queue.peek().cloned().expect("the start was just queued");
+
+        for (state, control) in steps {
+            // The model may have changed since the trajectory was planned
+            let next = match model.integrate(&parent.state, control) {
+                Some(next)
+                    if model.is_valid(&next)
+                        && next.grid_position() == state.grid_position() =>
+                {
+                    next
+                }
+                _ => return Err(PathFindingErr::Discontinuous),
+            };
+            if !self.in_bounds(&next) {
+                break;
+            }
+            let estimate = match model.heuristic_opt(&next, goal) {
+                Some(estimate) => estimate,
+                None => break,
+            };
+
+            let cost = model.cost(&parent.state, control, &next);
+            let id = match self.relax(model, &parent, control, next.clone(), cost, estimate)? {
+                Some(id) => id,
+                // The rest of the trajectory is reached from the cheaper node anyway
+                None => break,
+            };
+            parent = Node::<M> { id, state: next, control: control.clone() };
+        }
+
+        Ok(())
+    }
+
+    /// The trajectory to the node which came closest to the goal, by the heuristic
+    ///
+    /// When the goal cannot be reached this shows how far the search got, and doubles as a
+    /// way to get as close to the goal as possible.  Returns `None` before a search starts.
+    ///
+    /// ```
+    /// # use game_lib::path::astar::AStar;
+    /// # use game_lib::path::grid::{Cell, GridModel, GridSampler};
+    /// let (mut astar, mut grid) = (AStar::new(), GridModel::new(30, 30));
+    /// let (start, goal) = (Cell::new(1, 1), Cell::new(28, 28));
+    /// astar.optimize_with_budget(&mut grid, &start, &goal, &mut GridSampler, 5);
+    /// let closest = astar.best_effort_trajectory(&grid).unwrap();
+    /// assert_eq!(closest.trajectory.first().unwrap().0, start);
+    /// ```
+    pub fn best_effort_trajectory(&self, model: &M) -> Option<Trajectory<M>> {
+        if self.parentless {
+            return None;
+        }
+        self.closest.as_ref().map(|(_, node)| self.unwind_trajectory(model, node.clone()))
+    }
+
+    /// Remember the node if it is the closest to the goal seen so far
+    #[inline(always)]
+    fn approach(&mut self, estimate: M::Cost, node: &Node<M>) {
+        if self.closest.as_ref().is_none_or(|(closest, _)| estimate < *closest) {
+            self.closest = Some((estimate, node.clone()));
+        }
+    }
+
+    /// Whether there is no search to resume, clearing away one left by `reachable` first
+    fn is_fresh(&mut self) -> bool {
+        if self.parentless {
+            self.clear();
+        }
+        !self.seeded
+    }
+
+    /// Remember which start and goal a new search is for, or check that a search being resumed
+    /// was begun for the same ones
+    fn check_query(
+        &mut self,
+        start: &M::State,
+        goal: &M::State,
+    ) -> Result<(), PathFindingErr> {
+        let query = (start.grid_position(), goal.grid_position());
+        if self.is_fresh() {
+            self.query = Some(query);
+        } else if self.query.as_ref().is_some_and(|begun| *begun != query) {
+            return Err(PathFindingErr::InvalidQuery);
+        }
+        Ok(())
+    }
+
+    /// Queue the start node to begin a new search with the estimate from the start, unless
+    /// there is none because the model knows the goal cannot be reached, or it is above the
+    /// cost ceiling
+    fn try_seed(
+        &mut self,
+        model: &M,
+        start: &M::State,
+        estimate: Option<M::Cost>,
+    ) -> Result<(), PathFindingErr> {
+        let estimate = estimate.ok_or(PathFindingErr::Unreachable)?;
+        if self.ceiling.is_some_and(|ceiling| estimate > ceiling) {
+            self.pruned = true;
+            return Err(PathFindingErr::CostLimitExceeded);
+        }
+        self.seed(model, start, estimate);
+        Ok(())
+    }
+
+    /// Queue the start node to begin a new search
+    fn seed(&mut self, model: &M, start: &M::State, heuristic: M::Cost) {
+        self.seeded = true;
+        let start_id = Id::new(0, self.inflate(heuristic, 0), Default::default());
+        let position = model.canonicalize(start);
+        let start = Node { id: start_id, state: start.clone(), control: Default::default() };
+        self.approach(heuristic, &start);
+        let handle = self.queue.push_handle(start.clone());
+        self.grid.insert(position.clone(), (start, handle));
+        self.discovered.push(position);
+    }
+
+    /// Scale a heuristic estimate by the weight for a node `depth` steps from the start,
+    /// leaving it untouched for plain A*
+    #[inline(always)]
+    fn inflate(&self, heuristic: M::Cost, depth: usize) -> M::Cost {
+        if let Some((epsilon, anticipated)) = self.dynamic_weight {
+            let remaining = 1.0 - (depth as f64 / anticipated as f64).min(1.0);
+            heuristic.scale(1.0 + epsilon * remaining)
+        } else if (self.weight - 1.0).abs() < f64::EPSILON {
+            heuristic
+        } else {
+            heuristic.scale(self.weight)
+        }
+    }
+
+    /// Pop the next node from the queue, discarding stale entries along the way
+    ///
+    /// A node is stale when a cheaper path to its grid position was found after it was
+    /// queued; the cheaper node is queued as well, so the stale one is simply dropped.
+    fn pop(&mut self, model: &M) -> Option<Node<M>> {
+        while let Some(node) = self.queue.pop() {
+            if !self.is_stale(model, &node) {
+                return Some(node);
+            }
+        }
+
+        None
+    }
+
+    /// Whether `state` lies within the bounds set by `set_bounds`, if any
+    #[inline(always)]
+    fn in_bounds(&self, state: &M::State) -> bool {
+        self.bounds
+            .as_ref()
+            .is_none_or(|(min, max, within)| within(&state.grid_position(), min, max))
+    }
+
+    /// Whether a cheaper path to the position of `node` was found after it was queued
+    #[inline(always)]
+    fn is_stale(&self, model: &M, node: &Node<M>) -> bool {
+        self.grid
+            .get(&model.canonicalize(&node.state))
+            .is_some_and(|(best, _)| best.id.g < node.id.g)
+    }
+
+    /// Follow the parents from the goal node up to the start node
+    fn unwind_trajectory(&self, model: &M, mut current: Node<M>) -> Trajectory<M> {
+        let mut result = Vec::new();
+        result.push((current.state.clone(), current.control.clone()));
+        let mut cost = M::Cost::default();
+
+        // build up the trajectory by following the parent nodes
+        while let Some(p) = self.parent_map.get(&current.id) {
+            // The control of each node is the one which led to it from its parent
+            cost = cost + model.cost(&p.state, &current.control, &current.state);
+            current = (*p).clone();
+            result.push((current.state.clone(), current.control.clone()));
+        }
+
+        result.reverse();
+
+        Trajectory { cost, trajectory: result, expansions: self.stats.expanded }
+    }
+}
+
+/// Where a position was reached from: the parent's position, the control applied there, and
+/// the state it led to
+type Parent<M> = (Position<M>, <M as Model>::Control, <M as Model>::State);
+
+/// The cheapest cost found to each position, with every parent reaching it at that cost
+type Parents<M> = FnvHashMap<Position<M>, (<M as Model>::Cost, Vec<Parent<M>>)>;
+
+/// Follow every parent of `position` back to the start, adding a trajectory for each way
+/// until there are `max_paths` of them
+fn enumerate_paths<M: Model>(
+    parents: &Parents<M>,
+    position: &Position<M>,
+    suffix: &mut Vec<(M::State, M::Control)>,
+    start: &M::State,
+    paths: &mut Vec<Vec<(M::State, M::Control)>>,
+    max_paths: usize,
+) {
+    if paths.len() >= max_paths {
+        return;
+    }
+
+    let reaching = &parents[position].1;
+    if reaching.is_empty() {
+        // Only the start has no parent
+        let mut path = vec![(start.clone(), Default::default())];
+        path.extend(suffix.iter().rev().cloned());
+        paths.push(path);
+        return;
+    }
+
+    for (parent, control, state) in reaching {
+        suffix.push((state.clone(), control.clone()));
+        enumerate_paths::<M>(parents, parent, suffix, start, paths, max_paths);
+        suffix.pop();
+    }
+}
+
+/// Twice the area of the triangle between a position, the start and the goal, which grows
+/// with the distance of the position from the line through the other two
+fn cross_product<P: Coord2D>(position: &P, start: &P, goal: &P) -> u64 {
+    let (dx, dy) = (position.x() - goal.x(), position.y() - goal.y());
+    let (line_x, line_y) = (start.x() - goal.x(), start.y() - goal.y());
+    (dx * line_y - line_x * dy).unsigned_abs()
+}
+
+impl<M, B, Q> AStar<M, B, Q>
+where
+    M: HeuristicModel,
+    M::Cost: Copy + Weighted,
+    B: BuildHasher + Clone,
+    Q: PriorityQueue<Node<M>>,
+    Position<M>: Coord2D,
+{
+    /// Break ties between nodes with the same `f` and `g` in favour of those closer to the
+    /// straight line from the start to the goal
+    ///
+    /// On a grid, a great many trajectories share the optimal cost.  Preferring the highest
+    /// `g` already sends the search straight at the goal across open ground, but once an
+    /// obstacle turns it aside, the nodes left with the same `f` and `g` are taken in the
+    /// order they were found, which lets the search wander off along the obstacles.  The
+    /// distance from the line is measured by a cross product, which is cheap, and it only
+    /// ever orders nodes which tie on both, so the cost of the trajectory does not change.
+    /// Off by default, as it needs the grid coordinates of the start and goal.
+    ///
+    /// ```
+    /// # use game_lib::path::astar::AStar;
+    /// # use game_lib::path::grid::{Cell, GridModel, GridSampler};
+    /// # use game_lib::path::Optimizer;
+    /// let (mut grid, start, goal) = (GridModel::new(30, 30), Cell::new(1, 28), Cell::new(28, 3));
+    /// let mut astar = AStar::new();
+    /// astar.set_straight_line_ties(true);
+    /// assert!(astar.optimize(&mut grid, &start, &goal, &mut GridSampler).is_final());
+    /// ```
+    pub fn set_straight_line_ties(&mut self, enabled: bool) {
+        self.tie_breaker = if enabled { Some(cross_product::<Position<M>>) } else { None };
+    }
+}
+
+impl<M, B, Q> AStar<M, B, Q>
+where
+    M: HeuristicModel,
+    M::Cost: Copy + Weighted,
+    B: BuildHasher + Clone,
+    Q: PriorityQueue<Node<M>>,
+    Position<M>: BoundedPosition,
+{
+    /// Confine the search to the box between the positions `min` and `max`, both included
+    ///
+    /// Successors whose positions fall outside of the box are discarded as they are sampled,
+    /// so the search never holds more than the positions inside it, however large the map,
+    /// which keeps the time and memory of replanning around an actor in check.  A goal
+    /// outside of the box cannot be reached, and neither can one which is only reached by
+    /// leaving the box and coming back.  The bounds apply to the next search as well, until
+    /// they are removed with [`remove_bounds`](#method.remove_bounds).
+    ///
+    /// ```
+    /// # use game_lib::path::grid::{Cell, GridModel, GridSampler};
+    /// # use game_lib::path::{astar::AStar, Optimizer};
+    /// let (mut grid, start, goal) = (GridModel::new(12, 12), Cell::new(2, 5), Cell::new(10, 5));
+    /// let mut astar = AStar::new();
+    /// astar.set_bounds(Cell::new(0, 0), Cell::new(6, 11));
+    /// assert!(!astar.optimize(&mut grid, &start, &goal, &mut GridSampler).is_final());
+    /// ```
+    pub fn set_bounds(&mut self, min: Position<M>, max: Position<M>) {
+        self.bounds = Some((min, max, Position::<M>::within));
+    }
+}
+
+#[cfg(feature = "rayon")]
+impl<M, B, Q> AStar<M, B, Q>
+where
+    M: HeuristicModel + Sync,
+    M::State: Send + Sync,
+    M::Control: Send + Sync,
+    M::Cost: Copy + Weighted + Send + Sync,
+    B: BuildHasher + Clone,
+    Q: PriorityQueue<Node<M>>,
+{
+    /// Calculate an optimal trajectory, integrating the successors of each node in parallel
+    ///
+    /// Only available with the `rayon` feature.  Integrating the controls, and calculating the
+    /// costs of the steps to the resulting states, is spread over rayon's thread pool.  The
+    /// successors are then checked and queued one at a time in the order they were sampled,
+    /// exactly as by `optimize`, so the result and everything the search honours, from the
+    /// checks of the start and goal to the cost ceiling, is the same.  This pays off when
+    /// `integrate` is expensive.
+    ///
+    /// The model is shared between threads, so it must be `Sync`, and the states, controls and
+    /// costs it produces must be `Send` and `Sync` to be collected and shared.
+    ///
+    /// ```
+    /// # #[cfg(feature = "rayon")]
+    /// # {
+    /// # use game_lib::path::astar::AStar;
+    /// # use game_lib::path::grid::{Cell, GridModel, GridSampler};
+    /// let (mut astar, mut grid) = (AStar::new(), GridModel::new(30, 30));
+    /// let (start, goal) = (Cell::new(3, 15), Cell::new(27, 14));
+    /// assert!(astar.optimize_parallel(&mut grid, &start, &goal, &mut GridSampler).is_final());
+    /// # }
+    /// ```
+    pub fn optimize_parallel<S>(
+        &mut self,
+        model: &mut M,
+        start: &M::State,
+        goal: &M::State,
+        sampler: &mut S,
+    ) -> PathResult<M>
+    where
+        S: Sampler<M>,
+    {
+        self.search_goal(
+            model,
+            start,
+            goal,
+            &mut Parallel(sampler),
+            usize::MAX,
+            None,
+            &mut |_: &M::State, _: &M::Cost| {},
+        )
+    }
+}
+
+impl<M, S, B, Q> Optimizer<M, S> for AStar<M, B, Q>
+where
+    M: HeuristicModel,
+    M::Cost: radix_heap::Radix + Copy + Weighted,
+    S: Sampler<M>,
+    B: BuildHasher + Clone,
+    Q: PriorityQueue<Node<M>>,
+{
+    /// Expand exactly one node and return the trajectory to it
+    ///
+    /// Stale queue entries are discarded before expanding and do not count as a step, so
+    /// every `Intermediate` result ends at a position which has not been expanded before.
+    ///
+    /// Each call continues the search begun by the first, so it must be for the same start
+    /// and goal.  A search resumed with a different one fails with
+    /// `PathFindingErr::InvalidQuery` rather than carrying on with the old search, until
+    /// [`clear`](#method.clear) makes way for the new one.
+    ///
+    /// ```
+    /// # use game_lib::path::grid::{Cell, GridModel, GridSampler};
+    /// # use game_lib::path::{astar::AStar, Optimizer, PathFindingErr, PathResult};
+    /// let (mut grid, start, goal) = (GridModel::new(12, 12), Cell::new(1, 1), Cell::new(10, 9));
+    /// let mut astar = AStar::new();
+    /// let result = astar.next_trajectory(&mut grid, &start, &goal, &mut GridSampler);
+    /// assert!(matches!(result, PathResult::Intermediate(_)));
+    /// // Carrying on from somewhere else
+    /// let result = astar.next_trajectory(&mut grid, &Cell::new(5, 5), &goal, &mut GridSampler);
+    /// assert!(matches!(result, PathResult::Err(PathFindingErr::InvalidQuery)));
+    /// ```
+    fn next_trajectory(
+        &mut self,
+        model: &mut M,
+        start: &M::State,
+        goal: &M::State,
+        sampler: &mut S,
+    ) -> PathResult<M> {
+        use PathFindingErr::*;
+        use PathResult::*;
+
+        let invalid = invalid_endpoint(model, start, goal);
+        if let Some(invalid) = invalid.or_else(|| self.check_query(start, goal).err()) {
+            return Err(invalid);
+        }
+
+        if self.is_fresh() {
+            let estimate = model.heuristic_opt(start, goal);
+            if let Some(err) = self.try_seed(model, start, estimate).err() {
+                return Err(err);
+            }
+        }
+
+        match self.expand_one(model, goal, sampler) {
+            ExploreStep::Expanded(state) => {
+                let expanded = self.closed[&model.canonicalize(&state)].clone();
+                Intermediate(self.unwind_trajectory(model, expanded))
+            }
+            ExploreStep::Goal(t) => Final(t),
+            ExploreStep::Exhausted => Err(Unreachable),
+            ExploreStep::Failed(overflow) => Err(overflow),
+        }
+    }
+
+    fn optimize(
+        &mut self,
+        model: &mut M,
+        start: &M::State,
+        goal: &M::State,
+        sampler: &mut S,
+    ) -> PathResult<M> {
+        self.optimize_with_budget(model, start, goal, sampler, usize::MAX)
+    }
+}
+
+impl<M, S, B, Q> Explore<M, S> for AStar<M, B, Q>
+where
+    M: HeuristicModel,
+    M::Cost: radix_heap::Radix + Copy + Weighted,
+    S: Sampler<M>,
+    B: BuildHasher + Clone,
+    Q: PriorityQueue<Node<M>>,
+{
+    /// Expand the next node in the queue, discarding stale entries along the way
+    ///
+    /// Start a search with [`begin`](#method.begin), or let a first call to
+    /// `next_trajectory` do it.
+    fn expand_one(
+        &mut self,
+        model: &mut M,
+        goal: &M::State,
+        sampler: &mut S,
+    ) -> ExploreStep<M> {
+        let (model, tolerance): (&M, _) = (model, self.goal_tolerance);
+        let mut is_goal = |state: &M::State| converged(model, state, goal, tolerance);
+        let mut heuristic = |state: &M::State| model.heuristic_opt(state, goal);
+        let mut sampler = Serial(sampler);
+        while let Some(current) = self.pop(model) {
+            let before = self.stats.expanded;
+            match self.step(&current, model, &mut sampler, &mut is_goal, &mut heuristic) {
+                Ok(true) => return ExploreStep::Goal(self.unwind_trajectory(model, current)),
+                // Already expanded, and not to be reopened
+                Ok(false) if self.stats.expanded == before => {}
+                Ok(false) => return ExploreStep::Expanded(current.state),
+                Result::Err(overflow) => return ExploreStep::Failed(overflow),
+            }
+        }
+
+        ExploreStep::Exhausted
+    }
+}
+
+impl<M, B, Q> AStar<M, B, Q>
+where
+    M: HeuristicModel,
+    M::Cost: radix_heap::Radix + Copy + Weighted,
+    B: BuildHasher + Clone,
+    Q: PriorityQueue<Node<M>>,
+{
+    /// Forget any previous search and begin a new one from `start` towards `goal`
+    ///
+    /// The same as [`reset`](#method.reset), which is needed to drive the search through
+    /// [`Explore`](../trait.Explore.html), as it is never told the start.
+    pub fn begin(&mut self, model: &M, start: &M::State, goal: &M::State) {
+        self.reset(model, start, goal);
+    }
+
+    /// The cost of an optimal trajectory from `start` to `goal`, without the trajectory
+    ///
+    /// The search is the same as `optimize`, but no node remembers its parent, which saves
+    /// storing one for every node generated.  It always begins a new search, and leaves
+    /// nothing behind to find trajectories by: [`trajectory_to`] and the like return `None`
+    /// until the next search, which starts over.
+    ///
+    /// [`trajectory_to`]: #method.trajectory_to
+    ///
+    /// ```
+    /// # use game_lib::path::astar::AStar;
+    /// # use game_lib::path::grid::{Cell, GridModel, GridSampler};
+    /// let (mut grid, start, goal) = (GridModel::new(10, 10), Cell::new(0, 0), Cell::new(3, 0));
+    /// let cost = AStar::new().reachable(&mut grid, &start, &goal, &mut GridSampler);
+    /// assert_eq!(cost, Ok(6));
+    /// ```
+    pub fn reachable<S>(
+        &mut self,
+        model: &mut M,
+        start: &M::State,
+        goal: &M::State,
+        sampler: &mut S,
+    ) -> Result<M::Cost, PathFindingErr>
+    where
+        S: Sampler<M>,
+    {
+        if let Some(invalid) = invalid_endpoint(model, start, goal) {
+            return Err(invalid);
+        }
+
+        self.clear();
+        let (model, tolerance): (&M, _) = (model, self.goal_tolerance);
+        if converged(model, start, goal, tolerance) {
+            return Ok(Default::default());
+        }
+
+        self.parentless = true;
+        self.query = Some((start.grid_position(), goal.grid_position()));
+        self.try_seed(model, start, model.heuristic_opt(start, goal))?;
+
+        let mut is_goal = |state: &M::State| converged(model, state, goal, tolerance);
+        let mut heuristic = |state: &M::State| model.heuristic_opt(state, goal);
+        let mut sampler = Serial(sampler);
+        while let Some(current) = self.pop(model) {
+            if self.step(&current, model, &mut sampler, &mut is_goal, &mut heuristic)? {
+                return Ok(current.id.g());
+            }
+        }
+
+        Err(PathFindingErr::Unreachable)
+    }
+
+    /// Step through the search one expansion at a time
+    ///
+    /// The iterator yields the same results as repeated calls to `next_trajectory`: every
+    /// `Intermediate` trajectory followed by a single `Final` or `Err`, after which it ends.
+    ///
+    /// ```
+    /// # use game_lib::path::astar::AStar;
+    /// # use game_lib::path::grid::{Cell, GridModel, GridSampler};
+    /// let (mut astar, mut grid) = (AStar::new(), GridModel::new(12, 12));
+    /// let (start, goal) = (Cell::new(1, 1), Cell::new(10, 9));
+    /// let last = astar.iter(&mut grid, &start, &goal, &mut GridSampler).last().unwrap();
+    /// assert!(last.is_final());
+    /// ```
+    pub fn iter<'a, S>(
+        &'a mut self,
+        model: &'a mut M,
+        start: &'a M::State,
+        goal: &'a M::State,
+        sampler: &'a mut S,
+    ) -> AStarSteps<'a, M, S, B, Q>
+    where
+        S: Sampler<M>,
+    {
+        AStarSteps { astar: self, model, start, goal, sampler, done: false }
+    }
+}
+
+/// Iterator over the steps of an A* search, see [`AStar::iter`]
+///
+/// [`AStar::iter`]: struct.AStar.html#method.iter
+pub struct AStarSteps<'a, M, S, B = FnvBuildHasher, Q = BinaryHeap<Node<M>>>
+where
+    M: HeuristicModel,
+    M::Cost: radix_heap::Radix + Copy + Weighted,
+    S: Sampler<M>,
+    B: BuildHasher + Clone,
+    Q: PriorityQueue<Node<M>>,
+{
+    astar: &'a mut AStar<M, B, Q>,
+    model: &'a mut M,
+    start: &'a M::State,
+    goal: &'a M::State,
+    sampler: &'a mut S,
+    done: bool,
+}
+
+impl<'a, M, S, B, Q> Iterator for AStarSteps<'a, M, S, B, Q>
+where
+    M: HeuristicModel,
+    M::Cost: radix_heap::Radix + Copy + Weighted,
+    S: Sampler<M>,
+    B: BuildHasher + Clone,
+    Q: PriorityQueue<Node<M>>,
+{
+    type Item = PathResult<M>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let result =
+            self.astar.next_trajectory(self.model, self.start, self.goal, self.sampler);
+        self.done = !matches!(result, PathResult::Intermediate(_));
+
+        Some(result)
+    }
+}
+
+/// A search begun ahead of time, to be advanced a few expansions at a time
+///
+/// The handle owns the optimizer along with the start and the goal, so a game can begin the
+/// search once, store the handle with whatever asked for the trajectory, and advance it by a
+/// fixed number of expansions each frame until it finishes.  Unlike driving the optimizer
+/// through `next_trajectory`, nothing relies on the optimizer being empty to tell a new search
+/// from one which is under way: the search is seeded once, when the handle is created, and
+/// every call to [`advance`](#method.advance) picks up where the last one left off.
+///
+/// ```
+/// # use game_lib::path::astar::SearchHandle;
+/// # use game_lib::path::grid::{Cell, GridModel, GridSampler};
+/// let (mut grid, start, goal) = (GridModel::new(20, 20), Cell::new(3, 10), Cell::new(17, 9));
+/// let mut handle = SearchHandle::begin(&grid, &start, &goal);
+/// // A few expansions every frame
+/// while !handle.advance(&mut grid, &mut GridSampler, 5).is_final() {}
+/// ```
+pub struct SearchHandle<M, B = FnvBuildHasher, Q = BinaryHeap<Node<M>>>
+where
+    M: HeuristicModel,
+    M::Cost: radix_heap::Radix + Copy + Weighted,
+    B: BuildHasher + Clone,
+    Q: PriorityQueue<Node<M>>,
+{
+    astar: AStar<M, B, Q>,
+    start: M::State,
+    goal: M::State,
+    /// What the search finished with, once it has
+    outcome: Option<PathResult<M>>,
+}
+
+impl<M> SearchHandle<M>
+where
+    M: HeuristicModel,
+    M::Cost: radix_heap::Radix + Copy + Weighted,
+{
+    /// Begin a search from `start` towards `goal` with a new optimizer
+    pub fn begin(model: &M, start: &M::State, goal: &M::State) -> Self {
+        Self::with_optimizer(AStar::new(), model, start, goal)
+    }
+}
+
+impl<M, B, Q> SearchHandle<M, B, Q>
+where
+    M: HeuristicModel,
+    M::Cost: radix_heap::Radix + Copy + Weighted,
+    B: BuildHasher + Clone,
+    Q: PriorityQueue<Node<M>>,
+{
+    /// Begin a search from `start` towards `goal` with an optimizer which was set up already
+    ///
+    /// Any search the optimizer held is forgotten, but its settings are kept.
+    pub fn with_optimizer(
+        mut astar: AStar<M, B, Q>,
+        model: &M,
+        start: &M::State,
+        goal: &M::State,
+    ) -> Self {
+        astar.reset(model, start, goal);
+        SearchHandle { astar, start: start.clone(), goal: goal.clone(), outcome: None }
+    }
+
+    /// Expand up to `steps` more nodes
+    ///
+    /// Returns an `Intermediate` trajectory to the most promising node while the search is
+    /// still under way, and the `Final` trajectory or the error the search finished with
+    /// once it has, which every later call returns again without expanding anything.
+    pub fn advance<S>(&mut self, model: &mut M, sampler: &mut S, steps: usize) -> PathResult<M>
+    where
+        S: Sampler<M>,
+    {
+        if let Some(outcome) = &self.outcome {
+            return replay(outcome);
+        }
+
+        let (start, goal) = (&self.start, &self.goal);
+        match self.astar.optimize_with_budget(model, start, goal, sampler, steps) {
+            PathResult::Err(PathFindingErr::BudgetExhausted(_)) => {
+                let model: &M = model;
+                PathResult::Intermediate(self.astar.partial_trajectory(model).unwrap_or_else(
+                    || Trajectory {
+                        cost: Default::default(),
+                        trajectory: vec![(start.clone(), Default::default())],
+                        expansions: self.astar.stats().expanded,
+                    },
+                ))
+            }
+            result => {
+                self.outcome = Some(replay(&result));
+                result
+            }
+        }
+    }
+
+    /// Whether the search has finished, with a trajectory or without
+    pub fn is_finished(&self) -> bool {
+        self.outcome.is_some()
+    }
+
+    pub fn start(&self) -> &M::State {
+        &self.start
+    }
+
+    pub fn goal(&self) -> &M::State {
+        &self.goal
+    }
+
+    /// The optimizer running the search, to inspect its progress
+    pub fn optimizer(&self) -> &AStar<M, B, Q> {
+        &self.astar
+    }
+
+    /// Give up the handle, keeping the optimizer along with whatever it found
+    pub fn into_optimizer(self) -> AStar<M, B, Q> {
+        self.astar
+    }
+}
+
+/// A copy of a result, which `Clone` only offers for models which are `Clone` themselves
+fn replay<M: Model>(result: &PathResult<M>) -> PathResult<M> {
+    let copy = |t: &Trajectory<M>| Trajectory {
+        cost: t.cost.clone(),
+        trajectory: t.trajectory.clone(),
+        expansions: t.expansions,
+    };
+
+    match result {
+        PathResult::Final(t) => PathResult::Final(copy(t)),
+        PathResult::Intermediate(t) => PathResult::Intermediate(copy(t)),
+        PathResult::Err(err) => PathResult::Err(err.clone()),
+    }
+}
+
+impl<M, B, Q> Debug for SearchHandle<M, B, Q>
+where
+    M: HeuristicModel,
+    M::State: Debug,
+    M::Control: Debug,
+    M::Cost: radix_heap::Radix + Copy + Weighted + Debug,
+    B: BuildHasher + Clone,
+    Q: PriorityQueue<Node<M>> + Debug,
+{
+    fn fmt(&self, fmt: &mut Formatter) -> Result<(), std::fmt::Error> {
+        fmt.debug_struct("SearchHandle")
+            .field("start", &self.start)
+            .field("goal", &self.goal)
+            .field("finished", &self.is_finished())
+            .field("astar", &self.astar)
+            .finish()
+    }
+}
+
+impl<M, B, Q> Debug for AStar<M, B, Q>
+where
+    M: HeuristicModel,
+    M::State: Debug,
+    M::Control: Debug,
     M::Cost: Debug + Copy,
+    Q: PriorityQueue<Node<M>> + Debug,
+{
+    fn fmt(&self, fmt: &mut Formatter) -> Result<(), std::fmt::Error> {
+        fmt.debug_struct("AStar")
+            .field("counter", &self.id_counter)
+            .field("weight", &self.weight)
+            .field("dynamic_weight", &self.dynamic_weight)
+            .field("next", &self.queue.peek())
+            .field("queue", &self.queue)
+            .field("grid", &self.grid)
+            .field("closed", &self.closed)
+            .field("stats", &self.stats)
+            .field("closest", &self.closest)
+            .field("query", &self.query)
+            .field("parent_map", &self.parent_map)
+            .finish()
+    }
+}
+
+impl<M, B, Q> Default for AStar<M, B, Q>
+where
+    M: HeuristicModel,
+    M::Cost: Copy + Weighted,
+    B: BuildHasher + Clone + Default,
+    Q: PriorityQueue<Node<M>> + Default,
+{
+    fn default() -> Self {
+        Self::from_parts(Default::default(), Default::default())
+    }
+}
+
+/// Configures an [`AStar`] optimizer in a single chain of calls
+///
+/// Every setting starts out the same as [`AStar::new`], so the builder only needs to be told
+/// what is different.
+///
+/// ```
+/// # use game_lib::path::astar::{AStar, AStarBuilder};
+/// # use game_lib::path::grid::GridModel;
+/// let astar: AStar<GridModel> =
+///     AStarBuilder::new().weight(1.5).reopen_closed(true).capacity(30 * 30).build();
+/// assert!(astar.capacity() >= 30 * 30);
+/// ```
+#[derive(Clone, Debug)]
+pub struct AStarBuilder<B = FnvBuildHasher> {
+    weight: f64,
+    reopen_closed: bool,
+    capacity: usize,
+    hasher: B,
+}
+
+impl AStarBuilder {
+    pub fn new() -> Self {
+        AStarBuilder {
+            weight: 1.0,
+            reopen_closed: false,
+            capacity: 0,
+            hasher: Default::default(),
+        }
+    }
+}
+
+impl<B> AStarBuilder<B>
+where
+    B: BuildHasher + Clone,
+{
+    /// Inflate the heuristic by `epsilon`, see [`AStar::with_weight`]
+    pub fn weight(mut self, epsilon: f64) -> Self {
+        self.weight = epsilon;
+        self
+    }
+
+    /// Reopen positions which were already expanded, see [`AStar::set_reopen_closed`]
+    pub fn reopen_closed(mut self, enabled: bool) -> Self {
+        self.reopen_closed = enabled;
+        self
+    }
+
+    /// Reserve room for at least `nodes` nodes, see [`AStar::with_capacity`]
+    pub fn capacity(mut self, nodes: usize) -> Self {
+        self.capacity = nodes;
+        self
+    }
+
+    /// Hash positions and nodes with `hasher`, see [`AStar::with_hasher`]
+    pub fn hasher<H>(self, hasher: H) -> AStarBuilder<H>
+    where
+        H: BuildHasher + Clone,
+    {
+        AStarBuilder {
+            weight: self.weight,
+            reopen_closed: self.reopen_closed,
+            capacity: self.capacity,
+            hasher,
+        }
+    }
+
+    pub fn build<M>(self) -> AStar<M, B>
+    where
+        M: HeuristicModel,
+        M::Cost: Copy + Weighted,
+    {
+        let mut astar = AStar::with_hasher(self.hasher);
+        astar.reserve(self.capacity);
+        astar.set_weight(self.weight);
+        astar.set_reopen_closed(self.reopen_closed);
+        astar
+    }
+}
+
+impl Default for AStarBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The open list of an [`AStar`] search, which hands out the greatest item first
+///
+/// A* only ever pushes, pops and peeks at the best node, so any structure which can do that
+/// will do: `AStar` is generic over its queue, and uses the standard `BinaryHeap` unless told
+/// otherwise through [`AStar::with_queue`].  The nodes order the same way however they are
+/// queued, ties included, so every queue finds the same trajectories in the same number of
+/// expansions.
+///
+/// ```
+/// # use game_lib::path::astar::{AStar, PriorityQueue};
+/// # use game_lib::path::grid::{Cell, GridModel, GridSampler};
+/// # use game_lib::path::Optimizer;
+/// /// Keeps every item in order, with the greatest at the end
+/// #[derive(Debug)]
+/// struct SortedVec<T>(Vec<T>);
+///
+/// impl<T: Ord> PriorityQueue<T> for SortedVec<T> {
+///     fn push(&mut self, item: T) {
+///         let at = self.0.binary_search(&item).unwrap_or_else(|at| at);
+///         self.0.insert(at, item);
+///     }
+///
+///     fn pop(&mut self) -> Option<T> {
+///         self.0.pop()
+///     }
+///     // ...
+/// #     fn peek(&self) -> Option<&T> {
+/// #         self.0.last()
+/// #     }
+/// #     fn len(&self) -> usize {
+/// #         self.0.len()
+/// #     }
+/// #     fn clear(&mut self) {
+/// #         self.0.clear()
+/// #     }
+/// #     fn iter(&self) -> Box<dyn Iterator<Item = &T> + '_> {
+/// #         Box::new(self.0.iter())
+/// #     }
+/// }
+///
+/// let (mut grid, start, goal) = (GridModel::new(20, 20), Cell::new(3, 10), Cell::new(17, 9));
+/// let mut sorted = AStar::with_queue(SortedVec(Vec::new()));
+/// assert!(sorted.optimize(&mut grid, &start, &goal, &mut GridSampler).is_final());
+/// ```
+pub trait PriorityQueue<T> {
+    fn push(&mut self, item: T);
+
+    /// Remove the greatest item
+    fn pop(&mut self) -> Option<T>;
+
+    /// The greatest item, which is the next to be popped
+    fn peek(&self) -> Option<&T>;
+
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn clear(&mut self);
+
+    /// Every item still queued, in no particular order
+    fn iter(&self) -> Box<dyn Iterator<Item = &T> + '_>;
+
+    /// Make room for at least `additional` more items, where the queue can
+    fn reserve(&mut self, _additional: usize) {}
+
+    /// Release the memory which is not in use, where the queue can
+    fn shrink_to_fit(&mut self) {}
+
+    /// Number of items the queue can hold before it has to grow
+    fn capacity(&self) -> usize {
+        self.len()
+    }
+
+    /// Queue `item`, handing out a handle to it when the queue can find it again
+    ///
+    /// A* keeps the handle of the cheapest node at each position, and when it finds a cheaper
+    /// way there it replaces the queued node through [`decrease_key`].  Queues which cannot
+    /// do that hand out no handles, and A* queues the cheaper node alongside the old one,
+    /// which is skipped once it is popped.
+    ///
+    /// [`decrease_key`]: #method.decrease_key
+    fn push_handle(&mut self, item: T) -> Option<usize> {
+        self.push(item);
+        None
+    }
+
+    /// Replace the item behind `handle` with `item`, and hand out the handle to it
+    ///
+    /// The name follows the usual min-queue terms: the cost of a node decreases, so `item`
+    /// must compare at least as great as the one it replaces.  An item which was popped in
+    /// the meantime is no longer there to replace, so `item` is queued as if it were new.
+    fn decrease_key(&mut self, handle: usize, item: T) -> Option<usize> {
+        let _ = handle;
+        self.push_handle(item)
+    }
+}
+
+impl<T: Ord> PriorityQueue<T> for BinaryHeap<T> {
+    #[inline]
+    fn push(&mut self, item: T) {
+        BinaryHeap::push(self, item)
+    }
+
+    #[inline]
+    fn pop(&mut self) -> Option<T> {
+        BinaryHeap::pop(self)
+    }
+
+    #[inline]
+    fn peek(&self) -> Option<&T> {
+        BinaryHeap::peek(self)
+    }
+
+    fn len(&self) -> usize {
+        BinaryHeap::len(self)
+    }
+
+    fn clear(&mut self) {
+        BinaryHeap::clear(self)
+    }
+
+    fn iter(&self) -> Box<dyn Iterator<Item = &T> + '_> {
+        Box::new(BinaryHeap::iter(self))
+    }
+
+    fn reserve(&mut self, additional: usize) {
+        BinaryHeap::reserve(self, additional)
+    }
+
+    fn shrink_to_fit(&mut self) {
+        BinaryHeap::shrink_to_fit(self)
+    }
+
+    fn capacity(&self) -> usize {
+        BinaryHeap::capacity(self)
+    }
+}
+
+/// The Id which identifies a particular node and allows for comparisons
+pub(super) struct Id<M>
+where
+    M: Model,
+{
+    /// Simple integer ID which must be unique
+    pub(super) id: usize,
+    /// Estimated cost including the heuristic
+    pub(super) f: Reverse<M::Cost>,
+    /// Cost to arrive at this node following the parents
+    pub(super) g: M::Cost,
+    /// Number of steps from the start following the parents
+    pub(super) depth: usize,
+    /// How far the node strays from the line between the start and the goal, which breaks
+    /// ties when the optimizer is told to, and is zero otherwise
+    pub(super) tie: u64,
+}
+
+impl<M> Id<M>
+where
+    M: Model,
+{
+    pub fn new(id: usize, f: M::Cost, g: M::Cost) -> Self {
+        Id { id, f: Reverse(f), g, depth: 0, tie: 0 }
+    }
+
+    /// The same id, for a node `depth` steps from the start
+    #[inline(always)]
+    pub fn at_depth(self, depth: usize) -> Self {
+        Id { depth, ..self }
+    }
+
+    /// The same id, for a node which strays `tie` from the line between the start and goal
+    #[inline(always)]
+    pub fn with_tie(self, tie: u64) -> Self {
+        Id { tie, ..self }
+    }
+
+    #[inline(always)]
+    pub fn g(&self) -> M::Cost {
+        self.g.clone()
+    }
+}
+
+impl<M> Clone for Id<M>
+where
+    M: Model,
+{
+    fn clone(&self) -> Self {
+        Id {
+            id: self.id,
+            f: self.f.clone(),
+            g: self.g.clone(),
+            depth: self.depth,
+            tie: self.tie,
+        }
+    }
+}
+
+impl<M> Hash for Id<M>
+where
+    M: Model,
+{
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.id.hash(state);
+    }
+}
+
+impl<M> PartialEq for Id<M>
+where
+    M: Model,
+{
+    /// Ids are equal only when they identify the same node, which keeps `Eq` consistent with
+    /// `Hash` for lookups in the `parent_map`; ordering by cost is left to `Ord`
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl<M> Eq for Id<M> where M: Model {}
+
+impl<M> PartialOrd for Id<M>
+where
+    M: Model,
+{
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<M> Ord for Id<M>
+where
+    M: Model,
+{
+    /// The lowest `f` comes first, with ties going to the highest `g`, which is likely closer
+    /// to the goal, then to the node closest to the line between the start and the goal when
+    /// the optimizer measures it, and then to the oldest node so that the order is always the
+    /// same
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.f
+            .cmp(&other.f)
+            .then_with(|| self.g.cmp(&other.g))
+            .then_with(|| other.tie.cmp(&self.tie))
+            .then_with(|| other.id.cmp(&self.id))
+    }
+}
+
+impl<M> Debug for Id<M>
+where
+    M: Model,
+    M::Cost: Debug,
 {
     fn fmt(&self, fmt: &mut Formatter) -> Result<(), std::fmt::Error> {
-        fmt.debug_struct("AStar")
-            .field("counter", &self.id_counter)
-            .field("next", &self.queue.peek())
-            .field("queue", &self.queue)
-            .field("grid", &self.grid)
-            .field("parent_map", &self.parent_map)
+        fmt.debug_struct("Id")
+            .field("g", &self.g)
+            .field("f", &self.f)
+            .field("id", &self.id)
+            .field("depth", &self.depth)
+            .field("tie", &self.tie)
+            .finish()
+    }
+}
+
+/// Nodes stored for planning
+///
+/// The node which compares greatest is the most promising one to expand next.
+pub struct Node<M>
+where
+    M: Model,
+{
+    pub(super) id: Id<M>,
+    pub(super) state: M::State,
+    pub(super) control: M::Control,
+}
+
+impl<M> Clone for Node<M>
+where
+    M: Model,
+{
+    fn clone(&self) -> Self {
+        Node { id: self.id.clone(), state: self.state.clone(), control: self.control.clone() }
+    }
+}
+
+impl<M> PartialEq for Node<M>
+where
+    M: Model,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl<M> Eq for Node<M> where M: Model {}
+
+impl<M> PartialOrd for Node<M>
+where
+    M: Model,
+{
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.id.partial_cmp(&other.id)
+    }
+}
+
+impl<M> Ord for Node<M>
+where
+    M: Model,
+{
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.id.cmp(&other.id)
+    }
+}
+
+impl<M> Debug for Node<M>
+where
+    M: Model,
+    M::Cost: Debug,
+    M::State: Debug,
+    M::Control: Debug,
+{
+    fn fmt(&self, fmt: &mut Formatter) -> Result<(), std::fmt::Error> {
+        fmt.debug_struct("Node")
+            .field("id", &self.id.id)
+            .field("g", &self.id.g)
+            .field("f", &self.id.f)
+            .field("state", &self.state)
+            .field("control", &self.control)
             .finish()
     }
-}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::actor::{Actor, Heuristic, TurnOptimal, WalkSampler};
+    use crate::map::Tile;
+    use crate::path::graph::{GraphModel, GraphSampler, Vertex};
+    use crate::path::grid::{Cell, GridModel, GridSampler, Step};
+    use crate::path::jps::Connectivity;
+    use crate::path::testing::*;
+    use crate::Position;
+    use std::collections::hash_map::DefaultHasher;
+    use std::collections::HashSet;
+    use std::hash::BuildHasherDefault;
+    use std::time::Instant;
+
+    fn node(id: usize, f: usize, g: usize, x: i64) -> Node<GridModel> {
+        Node { id: Id::new(id, f, g), state: Cell::new(x, 0), control: Step { dx: 1, dy: 0 } }
+    }
+
+    /// Hashes every key the same, so that lookups always fall back to `Eq`
+    #[derive(Clone, Default)]
+    struct Colliding;
+
+    impl Hasher for Colliding {
+        fn finish(&self) -> u64 {
+            0
+        }
+
+        fn write(&mut self, _bytes: &[u8]) {}
+    }
+
+    impl BuildHasher for Colliding {
+        type Hasher = Colliding;
+
+        fn build_hasher(&self) -> Colliding {
+            Colliding
+        }
+    }
+
+    #[test]
+    fn nodes_with_equal_costs_keep_their_own_parents() {
+        let mut astar: AStar<GridModel, Colliding> = AStar::with_hasher(Colliding);
+        let (start, detour) = (node(0, 8, 0, 0), node(1, 10, 2, 1));
+        // Both ends tie on every cost, but were reached from different parents
+        let (direct, indirect) = (node(2, 14, 4, 2), node(3, 14, 4, 3));
+        astar.parent_map.insert(detour.id.clone(), start.clone());
+        astar.parent_map.insert(direct.id.clone(), start.clone());
+        astar.parent_map.insert(indirect.id.clone(), detour.clone());
+        assert_eq!(astar.parent_map.len(), 3);
+
+        let grid = GridModel::new(10, 10);
+        let states = |t: Trajectory<GridModel>| {
+            t.trajectory.into_iter().map(|(cell, _)| cell.x).collect::<Vec<_>>()
+        };
+        assert_eq!(states(astar.unwind_trajectory(&grid, direct)), vec![0, 2]);
+        assert_eq!(states(astar.unwind_trajectory(&grid, indirect)), vec![0, 1, 3]);
+    }
+
+    #[test]
+    fn a_budget_of_exactly_the_expansions_needed_reaches_the_goal() {
+        let mut grid = GridModel::new(10, 10);
+        let (start, goal) = (Cell::new(1, 1), Cell::new(6, 3));
+        let mut astar = AStar::new();
+        assert!(astar.optimize(&mut grid, &start, &goal, &mut GridSampler).is_final());
+        let needed = astar.stats().expanded;
+
+        astar.clear();
+        let result =
+            astar.optimize_with_budget(&mut grid, &start, &goal, &mut GridSampler, needed);
+        assert!(result.is_final());
+        assert_eq!(astar.stats().expanded, needed);
+
+        astar.clear();
+        match astar.optimize_with_budget(
+            &mut grid,
+            &start,
+            &goal,
+            &mut GridSampler,
+            needed - 1,
+        ) {
+            PathResult::Err(PathFindingErr::BudgetExhausted(budget)) => {
+                assert_eq!(budget, needed - 1)
+            }
+            _ => panic!("the budget runs out one expansion short of the goal"),
+        }
+    }
+
+    #[test]
+    fn every_step_follows_from_the_one_before() {
+        let mut map = room(12, 12);
+        map[(5, 5)] = Tile::WALL;
+        let mut model = TurnOptimal::new(map);
+        let (start, goal) = (walker(1, 1), walker(10, 9));
+        let mut sampler = WalkSampler::new();
+        let t = expect_final(AStar::new().optimize(&mut model, &start, &goal, &mut sampler));
+        assert_walkable(&t, &start);
+    }
+
+    #[test]
+    fn each_step_expands_a_position_not_expanded_before() {
+        let mut model = TurnOptimal::new(room(12, 12));
+        let (start, goal) = (walker(1, 1), walker(10, 9));
+        let (mut astar, mut sampler) = (AStar::new(), WalkSampler::new());
+        let mut expanded = HashSet::new();
+        while let PathResult::Intermediate(t) =
+            astar.next_trajectory(&mut model, &start, &goal, &mut sampler)
+        {
+            let (last, _) = t.trajectory.last().unwrap();
+            assert!(expanded.insert(last.pos.clone()), "{:?} expanded twice", last.pos);
+        }
+        assert!(!expanded.is_empty());
+    }
+
+    #[test]
+    fn weighting_bounds_the_cost_and_expands_fewer_nodes() {
+        let mut map = room(30, 30);
+        for y in 5..25 {
+            map[(15, y)] = Tile::WALL;
+        }
+        let mut model = TurnOptimal::new(map);
+        model.set_heuristic(Heuristic::Diagonal);
+        let (start, goal) = (walker(3, 15), walker(27, 14));
+        let (mut optimal, mut weighted) = (AStar::new(), AStar::with_weight(2.0));
+        let mut sampler = WalkSampler::new();
+        let best = expect_final(optimal.optimize(&mut model, &start, &goal, &mut sampler));
+        let fast = expect_final(weighted.optimize(&mut model, &start, &goal, &mut sampler));
+        assert!(fast.cost as f64 <= 2.0 * best.cost as f64);
+        assert!(weighted.closed_count() < optimal.closed_count());
+    }
+
+    #[test]
+    fn stats_count_the_search_until_cleared() {
+        let mut model = TurnOptimal::new(room(12, 12));
+        let (start, goal) = (walker(1, 1), walker(10, 9));
+        let mut astar = AStar::new();
+        astar.optimize(&mut model, &start, &goal, &mut WalkSampler::new());
+
+        let stats = astar.stats();
+        assert!(stats.expanded > 0);
+        assert!(stats.generated >= stats.expanded);
+        assert!(stats.max_queue_len > 0);
+
+        astar.clear();
+        assert_eq!(astar.stats(), &Stats::default());
+    }
+
+    /// A square room whose goal, 8 tiles in from the far corner, is walled off from the rest
+    fn walled_off(size: u32) -> (TurnOptimal, Actor) {
+        let mut map = room(size, size);
+        let (near, far) = (size - 10, size - 6);
+        for i in near..=far {
+            map[(i, near)] = Tile::WALL;
+            map[(i, far)] = Tile::WALL;
+            map[(near, i)] = Tile::WALL;
+            map[(far, i)] = Tile::WALL;
+        }
+        (TurnOptimal::new(map), walker(size - 8, size - 8))
+    }
+
+    #[test]
+    fn a_search_stops_when_its_budget_runs_out() {
+        let (mut model, goal) = walled_off(30);
+        let start = walker(2, 2);
+        let (mut astar, mut sampler) = (AStar::new(), WalkSampler::new());
+        match astar.optimize_with_budget(&mut model, &start, &goal, &mut sampler, 50) {
+            PathResult::Err(PathFindingErr::BudgetExhausted(50)) => {}
+            _ => panic!("the budget should run out first"),
+        }
+        assert_eq!(astar.stats().expanded, 50);
+        assert!(astar.partial_trajectory(&model).is_some());
+    }
+
+    #[test]
+    fn a_search_stops_when_its_deadline_passes() {
+        let (mut model, goal) = walled_off(100);
+        let start = walker(2, 2);
+        let (mut astar, mut sampler) = (AStar::new(), WalkSampler::new());
+        astar.set_clock_interval(16);
+        match astar.optimize_until(&mut model, &start, &goal, &mut sampler, Instant::now()) {
+            PathResult::Err(PathFindingErr::TimedOut) => {}
+            _ => panic!("the deadline has already passed"),
+        }
+        assert_eq!(astar.stats().expanded, 16);
+        assert!(!astar.partial_trajectory(&model).unwrap().trajectory.is_empty());
+    }
+
+    #[test]
+    fn iterating_yields_intermediate_trajectories_then_one_result() {
+        let mut model = TurnOptimal::new(room(12, 12));
+        let (start, goal) = (walker(1, 1), walker(10, 9));
+        let (mut astar, mut sampler) = (AStar::new(), WalkSampler::new());
+        let steps: Vec<_> = astar.iter(&mut model, &start, &goal, &mut sampler).collect();
+
+        let (last, rest) = steps.split_last().unwrap();
+        assert!(rest.iter().all(|step| matches!(step, PathResult::Intermediate(_))));
+        assert!(last.is_final());
+    }
+
+    #[test]
+    fn a_search_to_a_region_ends_inside_it() {
+        let mut model = TurnOptimal::new(room(20, 20));
+        // Any cell in the column x = 15, between y = 5 and y = 8
+        let in_region = |a: &Actor| a.pos.x == 15 && a.pos.y >= 5 && a.pos.y <= 8;
+        let estimate = |a: &Actor| 2 * (15 - a.pos.x.min(15)) as usize;
+
+        let (mut astar, mut sampler) = (AStar::new(), WalkSampler::new());
+        let start = walker(2, 12);
+        let t = astar.optimize_to(&mut model, &start, in_region, estimate, &mut sampler);
+        assert!(in_region(&expect_final(t).trajectory.last().unwrap().0));
+    }
+
+    #[test]
+    fn a_search_for_several_goals_reaches_the_cheapest() {
+        let mut model = TurnOptimal::new(room(20, 20));
+        let (mut astar, mut sampler) = (AStar::new(), WalkSampler::new());
+        let goals = [walker(2, 2), walker(17, 12), walker(2, 17)];
+        let t = expect_final(astar.optimize_multi(
+            &mut model,
+            &walker(10, 10),
+            &goals,
+            &mut sampler,
+        ));
+        assert_eq!(t.trajectory.last().unwrap().0.pos.x, 16);
+
+        // Already next to one of the goals
+        astar.clear();
+        let t = expect_final(astar.optimize_multi(
+            &mut model,
+            &walker(3, 2),
+            &goals,
+            &mut sampler,
+        ));
+        assert_eq!(t.trajectory.len(), 1);
+    }
+
+    #[test]
+    fn a_search_for_no_goals_is_unreachable() {
+        let mut model = TurnOptimal::new(room(20, 20));
+        let (mut astar, mut sampler) = (AStar::new(), WalkSampler::new());
+        match astar.optimize_multi(&mut model, &walker(10, 10), &[], &mut sampler) {
+            PathResult::Err(PathFindingErr::Unreachable) => {}
+            _ => panic!("there is nothing to reach"),
+        }
+    }
+
+    #[test]
+    fn the_best_effort_gets_as_close_as_the_walls_allow() {
+        let (mut model, goal) = walled_off(30);
+        let start = walker(2, 2);
+        let mut astar = AStar::new();
+        match astar.optimize(&mut model, &start, &goal, &mut WalkSampler::new()) {
+            PathResult::Err(PathFindingErr::Unreachable) => {}
+            _ => panic!("the goal is walled off"),
+        }
+
+        // The closest the search got was just outside the corner of the walls
+        let closest = astar.best_effort_trajectory(&model).unwrap();
+        let (last, _) = closest.trajectory.last().unwrap();
+        assert_eq!(closest.trajectory.first().unwrap().0.pos, start.pos);
+        assert!(last.pos.dist(goal.pos.clone()) < 4.0);
+    }
+
+    /// The trajectory to a goal in the corner of the walled room, and the goal moved from there
+    fn trajectory_to_a_moving_goal(
+        model: &mut TurnOptimal,
+    ) -> (Trajectory<TurnOptimal>, Actor) {
+        let (start, old_goal) = (walker(3, 15), walker(20, 25));
+        let mut sampler = WalkSampler::new();
+        let previous = AStar::new().optimize(model, &start, &old_goal, &mut sampler);
+        (expect_final(previous), walker(22, 25))
+    }
+
+    #[test]
+    fn seeding_from_a_trajectory_finds_the_same_cost_with_fewer_expansions() {
+        let mut model = TurnOptimal::new(walled_room());
+        model.set_heuristic(Heuristic::Diagonal);
+        let (previous, goal) = trajectory_to_a_moving_goal(&mut model);
+        let (start, mut sampler) = (walker(3, 15), WalkSampler::new());
+
+        let mut astar = AStar::new();
+        let cold = expect_final(astar.optimize(&mut model, &start, &goal, &mut sampler));
+        let cold_expanded = astar.stats().expanded;
+
+        astar.seed_from_trajectory(&mut model, &previous, &goal).unwrap();
+        let warm = expect_final(astar.optimize(&mut model, &start, &goal, &mut sampler));
+        assert!(astar.stats().expanded < cold_expanded);
+        assert_eq!(cold.cost, warm.cost);
+    }
+
+    #[test]
+    fn seeding_from_a_blocked_trajectory_is_discontinuous() {
+        let mut map = walled_room();
+        let mut model = TurnOptimal::new(map.clone());
+        model.set_heuristic(Heuristic::Diagonal);
+        let (previous, goal) = trajectory_to_a_moving_goal(&mut model);
+
+        // A wall now blocks the old trajectory
+        let (blocked, _) = &previous.trajectory[5];
+        map[(blocked.pos.x, blocked.pos.y)] = Tile::WALL;
+        let mut model = TurnOptimal::new(map);
+        assert_eq!(
+            AStar::new().seed_from_trajectory(&mut model, &previous, &goal),
+            Err(PathFindingErr::Discontinuous)
+        );
+    }
+
+    #[test]
+    fn any_hasher_finds_the_same_trajectory() {
+        let mut model = TurnOptimal::new(divided_room());
+        let (start, goal) = (walker(3, 10), walker(17, 9));
+        let mut sampler = WalkSampler::new();
+        let mut fnv = AStar::new();
+        let mut sip = AStar::with_hasher(BuildHasherDefault::<DefaultHasher>::default());
+
+        let a = expect_final(fnv.optimize(&mut model, &start, &goal, &mut sampler));
+        let b = expect_final(sip.optimize(&mut model, &start, &goal, &mut sampler));
+        assert_same_trajectory(&a, &b);
+    }
+
+    #[test]
+    fn reserving_room_finds_the_same_trajectory() {
+        let mut model = TurnOptimal::new(room(40, 40));
+        let (start, goal) = (walker(2, 3), walker(37, 35));
+        let mut sampler = WalkSampler::new();
+        let mut reserved = AStar::with_capacity(40 * 40);
+        let mut growing = AStar::new();
+
+        let a = expect_final(reserved.optimize(&mut model, &start, &goal, &mut sampler));
+        let b = expect_final(growing.optimize(&mut model, &start, &goal, &mut sampler));
+        assert_same_trajectory(&a, &b);
+    }
+
+    #[test]
+    fn clearing_keeps_the_allocations_until_they_are_shrunk() {
+        let mut model = TurnOptimal::new(room(30, 30));
+        let (start, goal) = (walker(2, 2), walker(27, 25));
+        let (mut astar, mut sampler) = (AStar::new(), WalkSampler::new());
+        astar.optimize(&mut model, &start, &goal, &mut sampler);
+
+        astar.clear();
+        let capacity = astar.capacity();
+        assert!(capacity > 0);
+        astar.optimize(&mut model, &start, &goal, &mut sampler);
+        assert_eq!(astar.capacity(), capacity);
+
+        astar.clear();
+        astar.shrink_to_fit();
+        assert_eq!(astar.capacity(), 0);
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn parallel_searches_match_serial_ones() {
+        let mut model = TurnOptimal::new(walled_room());
+        let (start, goal) = (walker(3, 15), walker(27, 14));
+        let mut sampler = WalkSampler::new();
+        let (mut serial, mut parallel) = (AStar::new(), AStar::new());
+
+        let a = expect_final(serial.optimize(&mut model, &start, &goal, &mut sampler));
+        let b =
+            expect_final(parallel.optimize_parallel(&mut model, &start, &goal, &mut sampler));
+        assert_same_trajectory(&a, &b);
+        let controls = |t: &Trajectory<TurnOptimal>| {
+            t.trajectory.iter().map(|(_, control)| control.clone()).collect::<Vec<_>>()
+        };
+        assert_eq!(controls(&a), controls(&b));
+        assert_eq!(serial.stats(), parallel.stats());
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn parallel_searches_reject_the_queries_serial_ones_do() {
+        let mut model = TurnOptimal::new(walled_room());
+        let (walled, goal) = (walker(15, 10), walker(27, 14));
+        let mut sampler = WalkSampler::new();
+        let (mut serial, mut parallel) = (AStar::new(), AStar::new());
+        for result in [
+            serial.optimize(&mut model, &walled, &goal, &mut sampler),
+            parallel.optimize_parallel(&mut model, &walled, &goal, &mut sampler),
+        ] {
+            match result {
+                PathResult::Err(PathFindingErr::InvalidStart) => {}
+                _ => panic!("the start is inside the wall"),
+            }
+        }
+
+        // As is resuming a search for another start
+        parallel.clear();
+        parallel.optimize_with_budget(&mut model, &walker(3, 15), &goal, &mut sampler, 5);
+        match parallel.optimize_parallel(&mut model, &walker(3, 16), &goal, &mut sampler) {
+            PathResult::Err(PathFindingErr::InvalidQuery) => {}
+            _ => panic!("the search was begun from another start"),
+        }
+    }
+
+    /// Points on a line which only lead forward, where every step costs far too much for a `u8`
+    struct Expensive;
+
+    impl Model for Expensive {
+        type State = Point;
+        type Control = ();
+        type Cost = u8;
+
+        fn cost(&self, _: &Point, _: &(), _: &Point) -> u8 {
+            100
+        }
+
+        fn init(&mut self, _: &Point) {}
+
+        fn converge(&self, current: &Point, goal: &Point) -> bool {
+            current.0 == goal.0
+        }
+
+        fn integrate(&self, previous: &Point, _: &()) -> Option<Point> {
+            Some(Point(previous.0 + 1))
+        }
+    }
+
+    impl HeuristicModel for Expensive {
+        fn heuristic(&self, _: &Point, _: &Point) -> u8 {
+            0
+        }
+    }
+
+    struct Forward;
+
+    impl Sampler<Expensive> for Forward {
+        fn sample(&mut self, _: &Expensive, _: &Point) -> &[()] {
+            &[()]
+        }
+    }
+
+    #[test]
+    fn costs_which_overflow_stop_the_search() {
+        let mut astar = AStar::new();
+        match astar.optimize(&mut Expensive, &Point(0), &Point(10), &mut Forward) {
+            PathResult::Err(PathFindingErr::CostOverflow) => {}
+            _ => panic!("ten steps cost more than a u8 can hold"),
+        }
+    }
+
+    #[test]
+    fn positions_are_closed_at_most_once() {
+        let mut model = TurnOptimal::new(room(12, 12));
+        let (start, goal) = (walker(1, 1), walker(10, 9));
+        let mut astar = AStar::new();
+        astar.optimize(&mut model, &start, &goal, &mut WalkSampler::new());
+
+        // Each position is expanded at most once, no matter how often it was queued
+        assert!(astar.closed_count() > 0);
+        assert!(astar.closed_count() <= astar.inspect_discovered().count() + 1);
+    }
 
-impl<M> Default for AStar<M>
-where
-    M: HeuristicModel,
-    M::Cost: Copy,
-{
-    fn default() -> Self {
-        Self::new()
+    #[test]
+    fn the_same_query_returns_the_same_trajectory() {
+        // An open map has many trajectories of the same cost
+        let mut model = TurnOptimal::new(room(20, 20));
+        let (start, goal) = (walker(2, 2), walker(17, 12));
+        let mut sampler = WalkSampler::new();
+        let first =
+            expect_final(AStar::new().optimize(&mut model, &start, &goal, &mut sampler));
+        let second =
+            expect_final(AStar::new().optimize(&mut model, &start, &goal, &mut sampler));
+        assert_eq!(format!("{:?}", first), format!("{:?}", second));
     }
-}
 
-/// The Id which identifies a particular node and allows for comparisons
-struct Id<M>
-where
-    M: Model,
-{
-    /// Simple integer ID which must be unique
-    id: usize,
-    /// Estimated cost including the heuristic
-    f: Reverse<M::Cost>,
-    /// Cost to arrive at this node following the parents
-    g: M::Cost,
-}
+    #[test]
+    fn a_cost_limit_admits_exactly_the_optimal_cost() {
+        let mut model = TurnOptimal::new(divided_room());
+        model.set_heuristic(Heuristic::Chebyshev);
+        let (start, goal) = (walker(3, 10), walker(17, 9));
+        let mut astar = AStar::new();
+        let mut sampler = WalkSampler::new();
+        let cost = expect_final(astar.optimize(&mut model, &start, &goal, &mut sampler)).cost;
 
-impl<M> Id<M>
-where
-    M: Model,
-{
-    pub fn new(id: usize, f: M::Cost, g: M::Cost) -> Self {
-        Id { id, f: Reverse(f), g }
+        // Exactly enough to reach the goal
+        astar.clear();
+        let t = astar.optimize_within(&mut model, &start, &goal, &mut sampler, cost);
+        assert_eq!(expect_final(t).cost, cost);
+
+        // Just short of it
+        astar.clear();
+        match astar.optimize_within(&mut model, &start, &goal, &mut sampler, cost - 1) {
+            PathResult::Err(PathFindingErr::CostLimitExceeded) => {}
+            _ => panic!("every trajectory costs more than the limit"),
+        }
     }
 
-    #[inline(always)]
-    pub fn g(&self) -> M::Cost {
-        self.g.clone()
+    #[test]
+    #[should_panic]
+    fn consistency_checks_catch_a_heuristic_which_overestimates_a_step() {
+        // A grid whose heuristic counts every step to the goal ten times over
+        let mut model = TestGrid::new(10, 10);
+        model.inflation = 10;
+        let mut astar = AStar::new();
+        astar.set_consistency_checks(true);
+        astar.optimize(&mut model, &Cell::new(1, 1), &Cell::new(8, 8), &mut GridSampler);
     }
-}
 
-impl<M> Clone for Id<M>
-where
-    M: Model,
-{
-    fn clone(&self) -> Self {
-        Id { id: self.id, f: self.f.clone(), g: self.g.clone() }
+    #[test]
+    fn an_inconsistent_heuristic_goes_unchecked_by_default() {
+        let mut model = TestGrid::new(10, 10);
+        model.inflation = 10;
+        let (start, goal) = (Cell::new(1, 1), Cell::new(8, 8));
+        assert!(AStar::new().optimize(&mut model, &start, &goal, &mut GridSampler).is_final());
     }
-}
 
-impl<M> Hash for Id<M>
-where
-    M: Model,
-{
-    fn hash<H: Hasher>(&self, state: &mut H) {
-        self.id.hash(state);
+    #[test]
+    fn reopening_recovers_the_optimum_under_an_inconsistent_heuristic() {
+        // The start 0 leads to 1 and 2, which both lead to 3 and on to the goal 4
+        let edges = vec![
+            (0, vec![(1, 1), (2, 1)]),
+            (1, vec![(3, 1)]),
+            (2, vec![(3, 3)]),
+            (3, vec![(4, 3)]),
+        ];
+        // Admissible, but the estimate of 1 drops by more than the cost of its step to 3
+        let estimate = |v: &u8, _: &u8| if *v == 1 { 4 } else { 0 };
+        let mut graph = GraphModel::with_heuristic(edges.into_iter().collect(), estimate);
+        let (start, goal) = (Vertex(0), Vertex(4));
+
+        let mut astar = AStar::new();
+        let t =
+            expect_final(astar.optimize(&mut graph, &start, &goal, &mut GraphSampler::new()));
+        assert_eq!(t.cost, 7);
+
+        astar.clear();
+        astar.set_reopen_closed(true);
+        let t =
+            expect_final(astar.optimize(&mut graph, &start, &goal, &mut GraphSampler::new()));
+        assert_eq!(t.cost, 5);
+        assert_eq!(astar.stats().reopened, 1);
     }
-}
 
-impl<M> PartialEq for Id<M>
-where
-    M: Model,
-{
-    fn eq(&self, other: &Self) -> bool {
-        self.f == other.f
+    #[test]
+    fn the_costs_to_come_include_the_start_and_the_goal() {
+        let mut map = room(12, 12);
+        for y in 2..10 {
+            map[(6, y)] = Tile::WALL;
+        }
+        let mut model = TurnOptimal::new(map);
+        let (start, goal) = (walker(2, 5), walker(10, 5));
+        let mut astar = AStar::new();
+        let t =
+            expect_final(astar.optimize(&mut model, &start, &goal, &mut WalkSampler::new()));
+
+        let reached = t.trajectory.last().unwrap().0.grid_position();
+        let costs: Vec<_> = astar.cost_to_come().collect();
+        assert!(costs.contains(&(&reached, &t.cost)));
+        assert!(costs.contains(&(&start.grid_position(), &0)));
     }
-}
 
-impl<M> Eq for Id<M> where M: Model {}
+    #[test]
+    fn the_builder_configures_the_same_optimizer_as_the_setters() {
+        let mut model = TurnOptimal::new(walled_room());
+        let (start, goal) = (walker(3, 15), walker(27, 14));
+        let mut sampler = WalkSampler::new();
+        let mut built = AStarBuilder::new()
+            .weight(1.5)
+            .reopen_closed(true)
+            .capacity(30 * 30)
+            .hasher(BuildHasherDefault::<DefaultHasher>::default())
+            .build();
 
-impl<M> PartialOrd for Id<M>
-where
-    M: Model,
-{
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(self.f.cmp(&other.f))
+        let mut manual = AStar::with_hasher(BuildHasherDefault::<DefaultHasher>::default());
+        manual.set_weight(1.5);
+        manual.set_reopen_closed(true);
+
+        let a = expect_final(built.optimize(&mut model, &start, &goal, &mut sampler));
+        let b = expect_final(manual.optimize(&mut model, &start, &goal, &mut sampler));
+        assert_eq!(a.cost, b.cost);
+        assert_eq!(built.stats(), manual.stats());
+        assert!(built.capacity() >= 30 * 30);
+
+        // The defaults are the same as a new optimizer
+        let mut default: AStar<TurnOptimal> = Default::default();
+        let mut new = AStar::new();
+        default.optimize(&mut model, &start, &goal, &mut sampler);
+        new.optimize(&mut model, &start, &goal, &mut sampler);
+        assert_eq!(default.stats(), new.stats());
     }
-}
 
-impl<M> Ord for Id<M>
-where
-    M: Model,
-{
-    fn cmp(&self, other: &Self) -> Ordering {
-        self.f.cmp(&other.f)
+    #[test]
+    fn the_memory_report_counts_what_the_search_holds() {
+        let mut model = TurnOptimal::new(room(20, 20));
+        let (start, goal) = (walker(2, 2), walker(17, 15));
+        let mut astar = AStar::new();
+        astar.optimize(&mut model, &start, &goal, &mut WalkSampler::new());
+
+        let report = astar.memory_report();
+        assert_eq!(report.queue_len, astar.inspect_queue().count());
+        assert_eq!(report.grid_len, astar.inspect_discovered().count());
+        assert_eq!(report.closed_len, astar.closed_count());
+        // Every discovered position other than the start was reached from a parent
+        assert!(report.parent_map_len >= report.grid_len - 1);
+        assert!(report.bytes > 0);
+
+        astar.clear();
+        assert_eq!(astar.memory_report(), MemoryReport::default());
     }
-}
 
-impl<M> Debug for Id<M>
-where
-    M: Model,
-    M::Cost: Debug,
-{
-    fn fmt(&self, fmt: &mut Formatter) -> Result<(), std::fmt::Error> {
-        fmt.debug_struct("Id")
-            .field("g", &self.g)
-            .field("f", &self.f)
-            .field("id", &self.id)
-            .finish()
+    #[test]
+    fn trajectories_lead_to_every_discovered_position() {
+        let mut map = room(20, 20);
+        for y in 1..15 {
+            map[(10, y)] = Tile::WALL;
+        }
+        let mut model = TurnOptimal::new(map);
+        let (start, goal) = (walker(2, 2), walker(17, 3));
+        let mut astar = AStar::new();
+        let found =
+            expect_final(astar.optimize(&mut model, &start, &goal, &mut WalkSampler::new()));
+
+        // The goal, which the search returns as soon as it is reached without expanding it
+        let reached = found.trajectory.last().unwrap().0.grid_position();
+        let to_goal = astar.trajectory_to(&model, &reached).unwrap();
+        assert_eq!(to_goal.cost, found.cost);
+        assert_eq!(to_goal.trajectory.len(), found.trajectory.len());
+
+        // Every position the search reached, on its way to the goal or not
+        let discovered: Vec<_> = astar.cost_to_come().map(|(p, g)| (p.clone(), *g)).collect();
+        assert!(discovered.len() > 50);
+        for (position, cost) in discovered.iter().step_by(7) {
+            let trajectory = astar.trajectory_to(&model, position).unwrap();
+            assert_eq!(trajectory.trajectory[0].0.pos, start.pos);
+            assert_eq!(trajectory.trajectory.last().unwrap().0.grid_position(), *position);
+            assert_eq!(trajectory.cost, *cost);
+        }
+
+        // The far corner is nowhere near the way to the goal
+        assert!(astar.trajectory_to(&model, &Position::new(2, 18)).is_none());
     }
-}
 
-/// Nodes stored for planning
-struct Node<M>
-where
-    M: Model,
-{
-    id: Id<M>,
-    state: M::State,
-    control: M::Control,
-}
+    #[test]
+    fn a_search_only_carries_on_from_the_start_it_began_with() {
+        let mut model = TurnOptimal::new(room(12, 12));
+        let (start, goal) = (walker(1, 1), walker(10, 9));
+        let mut astar = AStar::new();
+        let mut sampler = WalkSampler::new();
+        for _ in 0..5 {
+            match astar.next_trajectory(&mut model, &start, &goal, &mut sampler) {
+                PathResult::Intermediate(_) => {}
+                _ => panic!("the goal is further than five expansions away"),
+            }
+        }
 
-impl<M> Clone for Node<M>
-where
-    M: Model,
-{
-    fn clone(&self) -> Self {
-        Node { id: self.id.clone(), state: self.state.clone(), control: self.control.clone() }
+        let elsewhere = walker(5, 5);
+        match astar.next_trajectory(&mut model, &elsewhere, &goal, &mut sampler) {
+            PathResult::Err(PathFindingErr::InvalidQuery) => {}
+            _ => panic!("the search was begun from another start"),
+        }
+
+        // The search carries on as before with the start it was begun with
+        let t = expect_final(astar.optimize(&mut model, &start, &goal, &mut sampler));
+        assert_eq!(t.trajectory[0].0.pos, start.pos);
+
+        astar.clear();
+        let t = expect_final(astar.optimize(&mut model, &elsewhere, &goal, &mut sampler));
+        assert_eq!(t.trajectory[0].0.pos, elsewhere.pos);
     }
-}
 
-impl<M> PartialEq for Node<M>
-where
-    M: Model,
-{
-    fn eq(&self, other: &Self) -> bool {
-        self.id == other.id
+    #[test]
+    fn the_hook_sees_the_nodes_in_the_order_stepping_expands_them() {
+        let mut map = room(12, 12);
+        for y in 2..10 {
+            map[(6, y)] = Tile::WALL;
+        }
+        let mut model = TurnOptimal::new(map);
+        let (start, goal) = (walker(2, 5), walker(10, 6));
+        let mut sampler = WalkSampler::new();
+
+        let mut hooked = Vec::new();
+        let mut astar = AStar::new();
+        let result =
+            astar.optimize_with_hook(&mut model, &start, &goal, &mut sampler, |s, g| {
+                hooked.push((s.pos.clone(), *g))
+            });
+        assert!(matches!(result, PathResult::Final(_)));
+        assert_eq!(hooked.len(), astar.stats().expanded);
+
+        // Stepping through the same search expands the same nodes in the same order
+        let mut stepped = Vec::new();
+        let mut astar = AStar::new();
+        while let PathResult::Intermediate(t) =
+            astar.next_trajectory(&mut model, &start, &goal, &mut sampler)
+        {
+            stepped.push((t.trajectory.last().unwrap().0.pos.clone(), t.cost));
+        }
+        assert_eq!(hooked, stepped);
     }
-}
 
-impl<M> Eq for Node<M> where M: Model {}
+    #[test]
+    fn draining_hands_out_every_discovered_position_once() {
+        let mut model = TurnOptimal::new(room(20, 20));
+        let (start, goal) = (walker(2, 2), walker(17, 15));
+        let mut astar = AStar::new();
+        let mut sampler = WalkSampler::new();
+        let mut drawn = HashSet::new();
+        for _ in 0..5 {
+            for _ in 0..3 {
+                astar.next_trajectory(&mut model, &start, &goal, &mut sampler);
+            }
 
-impl<M> PartialOrd for Node<M>
-where
-    M: Model,
-{
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        self.id.partial_cmp(&other.id)
+            let new: Vec<_> = astar.drain_discovered().collect();
+            assert!(!new.is_empty());
+            for position in new {
+                assert!(drawn.insert(position), "discovered twice");
+            }
+            // Everything discovered so far has been handed out exactly once
+            assert_eq!(drawn, astar.inspect_discovered().cloned().collect());
+            assert_eq!(astar.drain_discovered().count(), 0);
+        }
     }
-}
 
-impl<M> Ord for Node<M>
-where
-    M: Model,
-{
-    fn cmp(&self, other: &Self) -> Ordering {
-        self.id.cmp(&other.id)
+    #[test]
+    fn costs_are_charged_in_the_direction_of_travel() {
+        // Every step up the map costs six times the same step down it
+        let mut hill = TestGrid::new(10, 10);
+        hill.weight = |current, next| if next.y > current.y { 6 } else { 1 };
+        hill.grid.set_connectivity(Connectivity::Four);
+
+        let (bottom, top) = (Cell::new(2, 1), Cell::new(4, 7));
+        for &(start, goal) in &[(bottom, top), (top, bottom)] {
+            let t = expect_final(AStar::new().optimize(
+                &mut hill,
+                &start,
+                &goal,
+                &mut GridSampler,
+            ));
+            let travelled = t.trajectory.windows(2).map(|pair| {
+                let ((from, _), (to, step)) = (&pair[0], &pair[1]);
+                hill.cost(from, step, to)
+            });
+            assert_eq!(t.cost, travelled.sum::<usize>());
+        }
     }
-}
 
-impl<M> Debug for Node<M>
-where
-    M: Model,
-    M::Cost: Debug,
-    M::State: Debug,
-    M::Control: Debug,
-{
-    fn fmt(&self, fmt: &mut Formatter) -> Result<(), std::fmt::Error> {
-        fmt.debug_struct("Node")
-            .field("id", &self.id.id)
-            .field("g", &self.id.g)
-            .field("f", &self.id.f)
-            .field("state", &self.state)
-            .field("control", &self.control)
-            .finish()
+    /// Keeps every item in order, with the greatest at the end
+    #[derive(Debug)]
+    struct SortedVec<T>(Vec<T>);
+
+    impl<T: Ord> PriorityQueue<T> for SortedVec<T> {
+        fn push(&mut self, item: T) {
+            let at = self.0.binary_search(&item).unwrap_or_else(|at| at);
+            self.0.insert(at, item);
+        }
+
+        fn pop(&mut self) -> Option<T> {
+            self.0.pop()
+        }
+
+        fn peek(&self) -> Option<&T> {
+            self.0.last()
+        }
+
+        fn len(&self) -> usize {
+            self.0.len()
+        }
+
+        fn clear(&mut self) {
+            self.0.clear()
+        }
+
+        fn iter(&self) -> Box<dyn Iterator<Item = &T> + '_> {
+            Box::new(self.0.iter())
+        }
+    }
+
+    #[test]
+    fn every_queue_finds_the_same_trajectory_in_as_many_expansions() {
+        let mut model = TurnOptimal::new(divided_room());
+        let (start, goal) = (walker(3, 10), walker(17, 9));
+        let mut sampler = WalkSampler::new();
+        let mut heap = AStar::new();
+        let mut sorted = AStar::with_queue(SortedVec(Vec::new()));
+
+        let a = expect_final(heap.optimize(&mut model, &start, &goal, &mut sampler));
+        let b = expect_final(sorted.optimize(&mut model, &start, &goal, &mut sampler));
+        assert_same_trajectory(&a, &b);
+        assert_eq!(
+            a.trajectory.iter().map(|(_, m)| m.clone()).collect::<Vec<_>>(),
+            b.trajectory.iter().map(|(_, m)| m.clone()).collect::<Vec<_>>()
+        );
+        assert_eq!(heap.stats(), sorted.stats());
+    }
+
+    #[test]
+    fn a_goal_tolerance_ends_the_search_close_to_the_goal() {
+        let mut model = Plane { size: 10.0, reach: 0.0 };
+        let (start, goal) = (Spot([1.0, 1.0]), Spot([8.3, 6.7]));
+        let mut astar = AStar::new();
+        match astar.optimize(&mut model, &start, &goal, &mut Headings) {
+            PathResult::Err(PathFindingErr::Unreachable) => {}
+            _ => panic!("no state lands exactly on the goal"),
+        }
+
+        astar.clear();
+        astar.set_goal_tolerance(Some(0.75));
+        let t = expect_final(astar.optimize(&mut model, &start, &goal, &mut Headings));
+        let end = t.states().last().unwrap();
+        assert!(end.0 != goal.0 && distance(end.0, goal.0) <= 0.75);
+    }
+
+    #[test]
+    fn reachable_costs_as_much_as_the_trajectory_without_any_parents() {
+        let mut model = TurnOptimal::new(divided_room());
+        let (start, goal) = (walker(3, 10), walker(17, 9));
+        let mut sampler = WalkSampler::new();
+        let mut planner = AStar::new();
+        let mut checker = AStar::new();
+
+        let cost = checker.reachable(&mut model, &start, &goal, &mut sampler);
+        let trajectory =
+            expect_final(planner.optimize(&mut model, &start, &goal, &mut sampler));
+        assert_eq!(cost, Ok(trajectory.cost));
+
+        // The same search, without a parent for any of its nodes
+        assert_eq!(checker.stats(), planner.stats());
+        assert_eq!(checker.memory_report().parent_map_len, 0);
+        assert!(planner.memory_report().parent_map_len > 0);
+        assert!(checker.memory_report().bytes < planner.memory_report().bytes);
+        assert!(checker.partial_trajectory(&model).is_none());
+
+        // Planning with it again starts over
+        let again = expect_final(checker.optimize(&mut model, &start, &goal, &mut sampler));
+        assert_eq!(again.len(), trajectory.len());
+
+        let walled_in = walker(0, 0);
+        assert_eq!(
+            checker.reachable(&mut model, &start, &walled_in, &mut sampler),
+            Err(PathFindingErr::InvalidGoal),
+        );
+    }
+
+    #[test]
+    fn optimize_many_reaches_every_goal_as_cheaply_as_alone() {
+        let mut model = TurnOptimal::new(divided_room());
+        // A* alone only finds the cheapest trajectories with an admissible heuristic
+        model.set_heuristic(Heuristic::Chebyshev);
+
+        let mut sampler = WalkSampler::new();
+        let start = walker(3, 10);
+        let goals = [walker(17, 9), walker(2, 2), walker(12, 17), walker(10, 10)];
+
+        let mut astar = AStar::new();
+        let results = astar.optimize_many(&mut model, &start, &goals, &mut sampler);
+        assert_eq!(results.len(), goals.len());
+        for (result, goal) in results.iter().zip(&goals[..3]) {
+            let alone =
+                expect_final(AStar::new().optimize(&mut model, &start, goal, &mut sampler));
+            let shared = match result {
+                PathResult::Final(t) => t,
+                _ => panic!("the goal is reachable"),
+            };
+            assert_eq!(shared.cost, alone.cost);
+            assert_eq!(shared.states().next().unwrap().pos, start.pos);
+        }
+
+        // The last goal is inside the wall
+        match &results[3] {
+            PathResult::Err(PathFindingErr::InvalidGoal) => {}
+            _ => panic!("the goal is a wall"),
+        }
+    }
+
+    #[test]
+    fn a_dynamic_weight_expands_fewer_nodes_within_the_same_bound() {
+        let mut model = TurnOptimal::new(walled_room());
+        model.set_heuristic(Heuristic::Diagonal);
+        let (start, goal) = (walker(3, 15), walker(27, 14));
+
+        // The same bound of twice the optimal cost
+        let mut optimal = AStar::new();
+        let mut weighted = AStar::with_weight(2.0);
+        let mut dynamic = AStar::new();
+        dynamic.set_dynamic_weight(1.0, 30);
+
+        let mut sampler = WalkSampler::new();
+        let mut cost = |astar: &mut AStar<_>| {
+            expect_final(astar.optimize(&mut model, &start, &goal, &mut sampler)).cost
+        };
+        let best = cost(&mut optimal);
+        assert!(cost(&mut weighted) <= 2 * best);
+        assert!(cost(&mut dynamic) <= 2 * best);
+
+        let expanded = |astar: &AStar<_>| astar.stats().expanded;
+        assert!(expanded(&weighted) < expanded(&optimal));
+        assert!(expanded(&dynamic) < expanded(&weighted));
+    }
+
+    #[test]
+    fn a_generous_successor_limit_generates_fewer_nodes_for_the_same_cost() {
+        // A grid where every vertex leaps to the 48 others within three steps in each axis
+        let mut edges = HashMap::new();
+        for (x, y) in (0..20i64).flat_map(|x| (0..20i64).map(move |y| (x, y))) {
+            let mut leaps = Vec::new();
+            for (dx, dy) in (-3..=3i64).flat_map(|dx| (-3..=3i64).map(move |dy| (dx, dy))) {
+                let (nx, ny) = (x + dx, y + dy);
+                if (dx, dy) != (0, 0) && (0..20).contains(&nx) && (0..20).contains(&ny) {
+                    leaps.push(((nx, ny), (4 + dx.abs() + dy.abs()) as u64));
+                }
+            }
+            edges.insert((x, y), leaps);
+        }
+        // No leap covers more than six steps, for at most ten
+        let heuristic = |&(x, y): &(i64, i64), &(gx, gy): &(i64, i64)| {
+            let steps = ((x - gx).abs() + (y - gy).abs()) as u64;
+            (steps * 10).div_ceil(6)
+        };
+        let mut graph = GraphModel::with_heuristic(edges, heuristic);
+        let (start, goal) = (Vertex((1, 2)), Vertex((18, 16)));
+
+        let mut every = AStar::new();
+        let mut few = AStar::new();
+        few.set_max_successors(Some(8));
+        let mut sampler = GraphSampler::new();
+        let a = expect_final(every.optimize(&mut graph, &start, &goal, &mut sampler));
+        let b = expect_final(few.optimize(&mut graph, &start, &goal, &mut sampler));
+        assert_eq!(a.cost, b.cost);
+        assert!(few.stats().generated * 2 < every.stats().generated);
+    }
+
+    #[test]
+    fn a_reset_search_can_be_stepped_through_another_query() {
+        let mut model = TurnOptimal::new(divided_room());
+        model.set_heuristic(Heuristic::Chebyshev);
+        let mut sampler = WalkSampler::new();
+        let mut astar = AStar::new();
+
+        let queries = [(walker(3, 10), walker(17, 9)), (walker(15, 2), walker(4, 16))];
+        for (start, goal) in &queries {
+            astar.reset(&model, start, goal);
+            let stepped = loop {
+                match astar.next_trajectory(&mut model, start, goal, &mut sampler) {
+                    PathResult::Intermediate(_) => continue,
+                    PathResult::Final(t) => break t,
+                    PathResult::Err(e) => panic!("the goal is reachable: {:?}", e),
+                }
+            };
+
+            assert_eq!(stepped.trajectory[0].0.pos, start.pos);
+            let t = expect_final(AStar::new().optimize(&mut model, start, goal, &mut sampler));
+            assert_eq!(stepped.cost, t.cost);
+        }
+
+        // Without a reset, the search for the second query would be resumed for the first
+        let (start, goal) = &queries[0];
+        match astar.next_trajectory(&mut model, start, goal, &mut sampler) {
+            PathResult::Err(PathFindingErr::InvalidQuery) => {}
+            _ => panic!("the search was begun for another query"),
+        }
+    }
+
+    /// Either stand still or step right
+    struct StandOrStep;
+
+    impl Sampler<TestGrid> for StandOrStep {
+        fn sample(&mut self, _: &TestGrid, _: &Cell) -> &[Step] {
+            &[Step { dx: 0, dy: 0 }, Step { dx: 1, dy: 0 }]
+        }
+    }
+
+    #[test]
+    fn progress_checks_catch_a_step_which_stands_still_for_free() {
+        let mut model = TestGrid::new(20, 1);
+        model.weight = |current, next| (current != next) as usize;
+        let (start, goal) = (Cell::new(0, 0), Cell::new(5, 0));
+
+        let mut astar = AStar::new();
+        astar.set_progress_checks(true);
+        match astar.optimize(&mut model, &start, &goal, &mut StandOrStep) {
+            PathResult::Err(PathFindingErr::NonProgressingEdge) => {}
+            _ => panic!("standing still makes no progress"),
+        }
+
+        astar.clear();
+        astar.set_progress_checks(false);
+        let t = expect_final(astar.optimize(&mut model, &start, &goal, &mut StandOrStep));
+        assert_eq!(t.cost, 10);
+    }
+
+    #[test]
+    fn peek_next_shows_the_node_the_next_step_expands() {
+        let mut model = TurnOptimal::new(divided_room());
+        model.set_heuristic(Heuristic::Chebyshev);
+        let (start, goal) = (walker(3, 10), walker(17, 9));
+        let mut sampler = WalkSampler::new();
+        let mut astar = AStar::new();
+        astar.reset(&model, &start, &goal);
+
+        loop {
+            let upcoming = astar.peek_next(&model).map(|(state, _, _)| state.pos.clone());
+            match astar.next_trajectory(&mut model, &start, &goal, &mut sampler) {
+                PathResult::Intermediate(t) => {
+                    let expanded = &t.trajectory.last().unwrap().0;
+                    assert_eq!(upcoming.as_ref(), Some(&expanded.pos));
+                }
+                PathResult::Final(_) => break,
+                PathResult::Err(e) => panic!("the goal is reachable: {:?}", e),
+            }
+        }
+    }
+
+    #[test]
+    fn incumbent_pruning_spares_nodes_without_losing_the_optimum() {
+        let mut model = TurnOptimal::new(walled_room());
+        model.set_heuristic(Heuristic::Chebyshev);
+        let (start, goal) = (walker(3, 15), walker(27, 14));
+        let mut sampler = WalkSampler::new();
+
+        let mut plain = AStar::new();
+        let mut pruned = AStar::new();
+        pruned.set_incumbent_pruning(true);
+        let mut proven = AStar::with_weight(2.0);
+        proven.set_incumbent_pruning(true);
+        proven.set_reopen_closed(true);
+
+        let mut cost = |astar: &mut AStar<_>| {
+            expect_final(astar.optimize(&mut model, &start, &goal, &mut sampler)).cost
+        };
+        let optimal = cost(&mut plain);
+        assert_eq!(cost(&mut pruned), optimal);
+        assert_eq!(pruned.incumbent_cost(), Some(&optimal));
+        assert!(pruned.stats().expanded < plain.stats().expanded);
+        assert!(pruned.stats().generated < plain.stats().generated);
+
+        // Weighted, but proven optimal before the search ends
+        assert_eq!(cost(&mut proven), optimal);
+    }
+
+    #[test]
+    fn a_search_handle_advances_to_the_same_trajectory_over_several_frames() {
+        let mut model = TurnOptimal::new(divided_room());
+        let (start, goal) = (walker(3, 10), walker(17, 9));
+        let mut sampler = WalkSampler::new();
+
+        let mut handle = SearchHandle::begin(&model, &start, &goal);
+        let mut frames = 0;
+        let advanced = loop {
+            frames += 1;
+            match handle.advance(&mut model, &mut sampler, 5) {
+                PathResult::Intermediate(t) => assert_eq!(t.trajectory[0].0.pos, start.pos),
+                result => break expect_final(result),
+            }
+        };
+        assert!(handle.is_finished());
+        assert!(frames > 1);
+
+        let optimized =
+            expect_final(AStar::new().optimize(&mut model, &start, &goal, &mut sampler));
+        assert_same_trajectory(&advanced, &optimized);
+
+        // Once finished, the handle keeps returning what it finished with
+        let expanded = handle.optimizer().stats().expanded;
+        assert!(expanded <= frames * 5);
+        assert!(handle.advance(&mut model, &mut sampler, 5).is_final());
+        assert_eq!(handle.optimizer().stats().expanded, expanded);
+    }
+
+    #[test]
+    fn straight_line_ties_keep_the_search_from_wandering_between_pillars() {
+        // A field of pillars, every third cell along each axis
+        let mut grid = GridModel::new(30, 30);
+        grid.set_connectivity(Connectivity::Four);
+        for (x, y) in (0..30).flat_map(|x| (0..30).map(move |y| (x, y))) {
+            grid.set_blocked(x, y, x % 3 == 0 && y % 3 == 0);
+        }
+        let (start, goal) = (Cell::new(1, 28), Cell::new(28, 3));
+
+        let mut plain = AStar::new();
+        let mut straight = AStar::new();
+        straight.set_straight_line_ties(true);
+        let mut cost = |astar: &mut AStar<_>| {
+            expect_final(astar.optimize(&mut grid, &start, &goal, &mut GridSampler)).cost
+        };
+        assert_eq!(cost(&mut plain), cost(&mut straight));
+        assert!(straight.stats().expanded < plain.stats().expanded);
+    }
+
+    #[test]
+    fn optimize_all_finds_every_way_around_a_wall() {
+        let rows = [".......", "S#####G", "......."];
+        let blocked = rows.iter().flat_map(|row| row.chars().map(|c| c == '#')).collect();
+        let mut grid = GridModel::with_occupancy(7, 3, blocked);
+        grid.set_connectivity(Connectivity::Four);
+        let (start, goal) = (Cell::new(0, 1), Cell::new(6, 1));
+
+        // The way over the wall and the way under it are equally long
+        let mut astar = AStar::new();
+        let paths =
+            astar.optimize_all(&mut grid, &start, &goal, &mut GridSampler, 10).unwrap();
+        assert_eq!(paths.len(), 2);
+        assert!(paths.iter().all(|t| t.cost == 16 && t.len() == 9));
+        assert!(paths.iter().any(|t| t.states().all(|cell| cell.y <= 1)));
+        assert!(paths.iter().any(|t| t.states().all(|cell| cell.y >= 1)));
+    }
+
+    #[test]
+    fn optimize_all_keeps_as_many_distinct_trajectories_as_asked() {
+        // Across an open grid there are 252 of them
+        let mut open = GridModel::new(6, 6);
+        open.set_connectivity(Connectivity::Four);
+        let (start, goal) = (Cell::new(0, 0), Cell::new(5, 5));
+        let mut astar = AStar::new();
+        let paths =
+            astar.optimize_all(&mut open, &start, &goal, &mut GridSampler, 100).unwrap();
+        assert_eq!(paths.len(), 100);
+        for (i, a) in paths.iter().enumerate() {
+            assert_eq!(a.cost, 20);
+            assert!(paths[..i].iter().all(|b| !a.states().eq(b.states())));
+        }
+    }
+
+    #[test]
+    fn a_finished_optimal_search_stays_unreachable() {
+        let (mut grid, start, goal) = (shut_in(), Cell::new(2, 2), Cell::new(4, 4));
+        let mut optimal = OptimalAStar::new();
+        let mut steps = 0;
+        loop {
+            match optimal.next_trajectory(&mut grid, &start, &goal, &mut GridSampler) {
+                PathResult::Intermediate(_) => steps += 1,
+                PathResult::Err(err) => {
+                    assert_eq!(err, PathFindingErr::Unreachable);
+                    break;
+                }
+                PathResult::Final(_) => panic!("the start is shut in"),
+            }
+            assert!(steps < 100, "the search should have finished");
+        }
+        match optimal.next_trajectory(&mut grid, &start, &goal, &mut GridSampler) {
+            PathResult::Err(PathFindingErr::Unreachable) => {}
+            _ => panic!("the search has finished"),
+        }
+    }
+
+    #[test]
+    fn contains_every_discovered_position_and_nothing_else() {
+        let mut model = TurnOptimal::new(divided_room());
+        let (start, goal) = (walker(3, 10), walker(17, 9));
+        let mut astar = AStar::new();
+        let mut sampler = WalkSampler::new();
+        for _ in 0..20 {
+            astar.next_trajectory(&mut model, &start, &goal, &mut sampler);
+        }
+
+        let discovered: Vec<_> = astar.inspect_discovered().cloned().collect();
+        assert!(discovered.iter().all(|position| astar.contains(position)));
+        assert!(astar.contains(&start.grid_position()));
+        // Beyond the wall, far from anything expanded so far
+        assert!(!astar.contains(&goal.grid_position()));
+        assert!(!astar.contains(&Position::new(10, 10)));
+    }
+
+    #[test]
+    fn best_costs_agree_with_the_paths_back_to_the_start() {
+        let mut model = TurnOptimal::new(divided_room());
+        let (start, goal) = (walker(3, 10), walker(17, 9));
+        let mut astar = AStar::new();
+        let mut sampler = WalkSampler::new();
+        let trajectory = expect_final(astar.optimize(&mut model, &start, &goal, &mut sampler));
+
+        let reached = trajectory.trajectory.last().unwrap().0.grid_position();
+        assert_eq!(astar.best_cost_to(&reached), Some(&trajectory.cost));
+        assert_eq!(astar.best_cost_to(&start.grid_position()), Some(&0));
+        let discovered: Vec<_> = astar.inspect_discovered().cloned().collect();
+        for position in discovered.iter().step_by(5) {
+            let path = astar.trajectory_to(&model, position).unwrap();
+            assert_eq!(astar.best_cost_to(position), Some(&path.cost));
+        }
+        assert_eq!(astar.best_cost_to(&Position::new(10, 10)), None);
+    }
+
+    #[test]
+    fn bounds_keep_the_search_inside_a_box() {
+        let mut model = TurnOptimal::new(divided_room());
+        let start = walker(3, 10);
+        let (min, max) = (Position::new(1, 5), Position::new(8, 15));
+        let mut sampler = WalkSampler::new();
+
+        let mut astar = AStar::new();
+        astar.set_bounds(min.clone(), max.clone());
+        let inside = walker(7, 6);
+        let t = expect_final(astar.optimize(&mut model, &start, &inside, &mut sampler));
+        assert!(model.converge(&t.trajectory.last().unwrap().0, &inside));
+        assert!(t.states().all(|actor| actor.pos.within(&min, &max)));
+        assert!(astar.inspect_discovered().all(|position| position.within(&min, &max)));
+
+        // Around the wall is out of bounds
+        let beyond = walker(17, 9);
+        astar.clear();
+        match astar.optimize(&mut model, &start, &beyond, &mut sampler) {
+            PathResult::Err(PathFindingErr::Unreachable) => {}
+            _ => panic!("the goal is outside the box"),
+        }
+
+        astar.remove_bounds();
+        astar.clear();
+        assert!(astar.optimize(&mut model, &start, &beyond, &mut sampler).is_final());
     }
 }
@@ -0,0 +1,296 @@
+use std::fmt::{Debug, Formatter};
+
+use fnv::{FnvHashMap, FnvHashSet};
+use std::collections::BinaryHeap;
+
+use super::astar::{Id, Node};
+use super::*;
+
+/// Beam search, which only keeps the most promising nodes of every round
+///
+/// Every node in the queue is expanded in a round, and of all of their successors just the
+/// `width` nodes with the lowest estimated cost are kept for the next round.  Each state is
+/// only ever queued once, so memory and time per round are bounded by the width rather than by
+/// the size of the map.
+///
+/// The search is not complete: once the beam has dropped the only way around an obstacle it
+/// can run out of nodes, returning `Unreachable` even though a trajectory exists.  The
+/// trajectories which are found are not necessarily optimal either.
+///
+/// ```
+/// # use game_lib::path::grid::{Cell, GridModel, GridSampler};
+/// # use game_lib::path::{beam::BeamSearch, Optimizer};
+/// let (mut beam, mut grid) = (BeamSearch::new(4), GridModel::new(30, 11));
+/// let (start, goal) = (Cell::new(5, 5), Cell::new(27, 5));
+/// assert!(beam.optimize(&mut grid, &start, &goal, &mut GridSampler).is_final());
+/// ```
+pub struct BeamSearch<M>
+where
+    M: HeuristicModel,
+    M::Cost: Copy,
+{
+    width: usize,
+    queue: BinaryHeap<Node<M>>,
+    parent_map: FnvHashMap<Id<M>, Node<M>>,
+    discovered: FnvHashSet<<<M as Model>::State as State>::Position>,
+    id_counter: usize,
+    stats: Stats,
+}
+
+impl<M> BeamSearch<M>
+where
+    M: HeuristicModel,
+    M::Cost: Copy,
+{
+    /// Create a beam search which keeps at most `width` nodes between rounds
+    ///
+    /// A width of zero is treated as one.
+    pub fn new(width: usize) -> Self {
+        BeamSearch {
+            width: width.max(1),
+            queue: BinaryHeap::new(),
+            parent_map: FnvHashMap::default(),
+            discovered: FnvHashSet::default(),
+            id_counter: 0,
+            stats: Stats::default(),
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn clear(&mut self) {
+        self.queue.clear();
+        self.parent_map.clear();
+        self.discovered.clear();
+        self.stats = Stats::default();
+    }
+
+    /// Counters describing the work done since the optimizer was created or last cleared
+    pub fn stats(&self) -> &Stats {
+        &self.stats
+    }
+
+    pub fn inspect_queue(&self) -> impl Iterator<Item = (&M::State, &M::Control)> {
+        self.queue.iter().map(|node| (&node.state, &node.control))
+    }
+
+    pub fn inspect_discovered(
+        &self,
+    ) -> impl Iterator<Item = &<<M as Model>::State as State>::Position> {
+        self.discovered.iter()
+    }
+
+    /// Expand every node of the beam, returning a node which has converged if there is one
+    ///
+    /// The successors replace the beam, keeping only the best `width` of them.
+    fn round<S>(&mut self, model: &mut M, goal: &M::State, sampler: &mut S) -> Option<Node<M>>
+    where
+        S: Sampler<M>,
+    {
+        let mut successors = BinaryHeap::new();
+
+        while let Some(current) = self.queue.pop() {
+            if model.converge(&current.state, goal) {
+                return Some(current);
+            }
+            self.stats.expanded += 1;
+
+            for control in sampler.sample(model, &current.state) {
+                if let Some(child_state) = model.integrate(&current.state, control) {
+                    if !self.discovered.insert(child_state.grid_position()) {
+                        continue;
+                    }
+                    self.id_counter += 1;
+                    self.stats.generated += 1;
+
+                    let cost =
+                        current.id.g() + model.cost(&current.state, control, &child_state);
+                    let heuristic = model.heuristic(&child_state, goal);
+
+                    let child = Node::<M> {
+                        id: Id::new(self.id_counter, cost + heuristic, cost),
+                        state: child_state,
+                        control: control.clone(),
+                    };
+
+                    self.parent_map.insert(child.id.clone(), current.clone());
+                    successors.push(child);
+                }
+            }
+        }
+
+        self.stats.max_queue_len = self.stats.max_queue_len.max(successors.len());
+        while self.queue.len() < self.width {
+            match successors.pop() {
+                Some(node) => self.queue.push(node),
+                None => break,
+            }
+        }
+
+        None
+    }
+
+    /// Follow the parents from the goal node up to the start node
+    fn unwind_trajectory(&self, mut current: Node<M>) -> Trajectory<M> {
+        let cost = current.id.g();
+        let mut result = Vec::new();
+        result.push((current.state.clone(), current.control.clone()));
+
+        while let Some(p) = self.parent_map.get(&current.id) {
+            current = (*p).clone();
+            result.push((current.state.clone(), current.control.clone()));
+        }
+
+        result.reverse();
+
+        Trajectory { cost, trajectory: result, expansions: self.stats.expanded }
+    }
+
+    fn seed(&mut self, model: &M, start: &M::State, goal: &M::State) {
+        let start_id = Id::new(0, model.heuristic(start, goal), Default::default());
+        self.discovered.insert(start.grid_position());
+        self.queue.push(Node {
+            id: start_id,
+            state: start.clone(),
+            control: Default::default(),
+        });
+    }
+}
+
+impl<M, S> Optimizer<M, S> for BeamSearch<M>
+where
+    M: HeuristicModel,
+    M::Cost: Copy,
+    S: Sampler<M>,
+{
+    /// Run a single round, returning the trajectory to the best node left in the beam
+    fn next_trajectory(
+        &mut self,
+        model: &mut M,
+        start: &M::State,
+        goal: &M::State,
+        sampler: &mut S,
+    ) -> PathResult<M> {
+        use PathFindingErr::*;
+        use PathResult::*;
+
+        if let Some(invalid) = invalid_endpoint(model, start, goal) {
+            return Err(invalid);
+        }
+
+        if self.parent_map.is_empty() && self.queue.is_empty() {
+            self.seed(model, start, goal);
+        }
+
+        if let Some(reached) = self.round(model, goal, sampler) {
+            return Final(self.unwind_trajectory(reached));
+        }
+
+        match self.queue.peek() {
+            Some(best) => Intermediate(self.unwind_trajectory(best.clone())),
+            None => Err(Unreachable),
+        }
+    }
+
+    fn optimize(
+        &mut self,
+        model: &mut M,
+        start: &M::State,
+        goal: &M::State,
+        sampler: &mut S,
+    ) -> PathResult<M> {
+        use PathFindingErr::*;
+        use PathResult::*;
+
+        if let Some(invalid) = invalid_endpoint(model, start, goal) {
+            return Err(invalid);
+        }
+
+        if model.converge(start, goal) {
+            return Final(Trajectory {
+                cost: Default::default(),
+                trajectory: vec![(start.clone(), Default::default())],
+                expansions: 0,
+            });
+        }
+
+        if self.parent_map.is_empty() && self.queue.is_empty() {
+            self.seed(model, start, goal);
+        }
+
+        while !self.queue.is_empty() {
+            if let Some(reached) = self.round(model, goal, sampler) {
+                return Final(self.unwind_trajectory(reached));
+            }
+        }
+
+        Err(Unreachable)
+    }
+}
+
+impl<M> Debug for BeamSearch<M>
+where
+    M: HeuristicModel,
+    M::State: Debug,
+    M::Control: Debug,
+    M::Cost: Debug + Copy,
+{
+    fn fmt(&self, fmt: &mut Formatter) -> Result<(), std::fmt::Error> {
+        fmt.debug_struct("BeamSearch")
+            .field("width", &self.width)
+            .field("counter", &self.id_counter)
+            .field("next", &self.queue.peek())
+            .field("queue", &self.queue)
+            .field("discovered", &self.discovered)
+            .field("stats", &self.stats)
+            .field("parent_map", &self.parent_map)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::actor::{Heuristic, TurnOptimal, WalkSampler};
+    use crate::map::{Map, Tile};
+    use crate::path::testing::*;
+
+    /// A corridor leads straight at the goal but is a dead end, the way there goes around
+    fn dead_end() -> TurnOptimal {
+        let mut map = Map::new(30, 11);
+        for x in 1..21 {
+            map[(x, 5)] = Tile::FLOOR;
+        }
+        for y in 5..10 {
+            map[(1, y)] = Tile::FLOOR;
+            map[(27, y)] = Tile::FLOOR;
+        }
+        for x in 1..28 {
+            map[(x, 9)] = Tile::FLOOR;
+        }
+        let mut model = TurnOptimal::new(map);
+        model.set_heuristic(Heuristic::Chebyshev);
+        model
+    }
+
+    #[test]
+    fn a_narrow_beam_follows_the_dead_end() {
+        let (mut model, start, goal) = (dead_end(), walker(5, 5), walker(27, 5));
+        let mut narrow = BeamSearch::new(1);
+        match narrow.optimize(&mut model, &start, &goal, &mut WalkSampler::new()) {
+            PathResult::Err(_) => {}
+            _ => panic!("a single node follows the dead end"),
+        }
+    }
+
+    #[test]
+    fn a_wide_beam_keeps_the_way_around() {
+        let (mut model, start, goal) = (dead_end(), walker(5, 5), walker(27, 5));
+        let mut wide = BeamSearch::new(4);
+        let t =
+            expect_final(wide.optimize(&mut model, &start, &goal, &mut WalkSampler::new()));
+        assert_eq!(t.trajectory.last().unwrap().0.pos.x, 27);
+    }
+}
@@ -0,0 +1,528 @@
+use std::fmt::{Debug, Formatter};
+
+use fnv::{FnvHashMap, FnvHashSet};
+use std::collections::hash_map::Entry;
+use std::collections::BinaryHeap;
+
+use super::astar::{Id, Node};
+use super::*;
+
+/// Models whose controls can be undone, so trajectories can be searched for from the goal
+pub trait ReversibleModel: HeuristicModel {
+    /// Find the state which `control` moves to `state`
+    ///
+    /// This is the inverse of [`Model::integrate`]: whenever `integrate_reverse(state,
+    /// control)` returns `Some(previous)`, `integrate(previous, control)` should reach the
+    /// grid position of `state`.  Return `None` if no such state exists.
+    ///
+    /// [`Model::integrate`]: ../trait.Model.html#tymethod.integrate
+    fn integrate_reverse(
+        &self,
+        state: &Self::State,
+        control: &Self::Control,
+    ) -> Option<Self::State>;
+
+    /// Every state which converges on the `goal`, which the backward search starts from
+    ///
+    /// Only the goal itself by default, which is exact when `converge` tests for equality.
+    fn goal_states(&self, goal: &Self::State) -> Vec<Self::State> {
+        vec![goal.clone()]
+    }
+}
+
+/// One of the two search trees of a [`BiAStar`]
+///
+/// [`BiAStar`]: struct.BiAStar.html
+struct Tree<M>
+where
+    M: HeuristicModel,
+    M::Cost: Copy,
+{
+    queue: BinaryHeap<Node<M>>,
+    parent_map: FnvHashMap<Id<M>, Node<M>>,
+    /// The cheapest node found for every position
+    grid: FnvHashMap<<<M as Model>::State as State>::Position, Node<M>>,
+    closed: FnvHashSet<<<M as Model>::State as State>::Position>,
+}
+
+impl<M> Tree<M>
+where
+    M: HeuristicModel,
+    M::Cost: Copy,
+{
+    fn new() -> Self {
+        Tree {
+            queue: BinaryHeap::new(),
+            parent_map: FnvHashMap::default(),
+            grid: FnvHashMap::default(),
+            closed: FnvHashSet::default(),
+        }
+    }
+
+    fn clear(&mut self) {
+        self.queue.clear();
+        self.parent_map.clear();
+        self.grid.clear();
+        self.closed.clear();
+    }
+
+    /// The lowest estimate of any queued node
+    fn lowest_estimate(&self) -> Option<M::Cost> {
+        self.queue.peek().map(|node| node.id.f.0)
+    }
+
+    fn seed(&mut self, root: Node<M>) {
+        self.grid.insert(root.state.grid_position(), root.clone());
+        self.queue.push(root);
+    }
+
+    /// Queue a child unless a cheaper node was already found for its position
+    fn discover(&mut self, child: Node<M>, parent: &Node<M>) -> bool {
+        match self.grid.entry(child.state.grid_position()) {
+            Entry::Occupied(mut best) => {
+                if best.get().id.g <= child.id.g {
+                    return false;
+                }
+                best.insert(child.clone());
+            }
+            Entry::Vacant(empty) => {
+                empty.insert(child.clone());
+            }
+        }
+
+        self.parent_map.insert(child.id.clone(), parent.clone());
+        self.queue.push(child);
+        true
+    }
+
+    /// Pop the next node which has not been expanded yet, discarding stale entries
+    fn pop(&mut self) -> Option<Node<M>> {
+        while let Some(node) = self.queue.pop() {
+            let position = node.state.grid_position();
+            let stale = self.grid.get(&position).is_some_and(|best| best.id.g < node.id.g);
+
+            if !stale && self.closed.insert(position) {
+                return Some(node);
+            }
+        }
+
+        None
+    }
+}
+
+/// Bidirectional A*, which searches from the start and from the goal until the two meet
+///
+/// One tree grows forwards from the start with [`Model::integrate`], estimating the cost to
+/// the goal, and the other grows backwards from the goal with
+/// [`ReversibleModel::integrate_reverse`], estimating the cost from the start with
+/// `heuristic(start, state)`.  The smaller of the two queues is expanded next.  Whenever a
+/// position is reached by both trees the combined cost is kept if it is the cheapest so far,
+/// and the search ends once neither queue can lead to anything cheaper.  This pays off when
+/// the goal is hard to get to, for example when it lies behind an obstacle facing the start:
+/// rather than [`AStar`] searching all the way along the obstacle, the backward tree finds
+/// its way out and meets the forward tree where it comes around.
+///
+/// The backward tree starts from every one of the [`ReversibleModel::goal_states`].  The
+/// backward half of the trajectory is integrated forwards again from the meeting state, and
+/// the trajectory ends at the first state which converges, as it does for the other
+/// optimizers.  The backward tree samples the controls of the state it is expanding, so the
+/// sampler should offer the same controls everywhere.
+///
+/// ```
+/// # use game_lib::path::grid::{Cell, GridModel, GridSampler};
+/// # use game_lib::path::{bidirectional::BiAStar, Optimizer};
+/// let (mut bi, mut grid) = (BiAStar::new(), GridModel::new(80, 40));
+/// let (start, goal) = (Cell::new(3, 20), Cell::new(41, 20));
+/// assert!(bi.optimize(&mut grid, &start, &goal, &mut GridSampler).is_final());
+/// ```
+///
+/// [`AStar`]: ../astar/struct.AStar.html
+/// [`Model::integrate`]: ../trait.Model.html#tymethod.integrate
+/// [`ReversibleModel::goal_states`]: trait.ReversibleModel.html#method.goal_states
+/// [`ReversibleModel::integrate_reverse`]: trait.ReversibleModel.html#tymethod.integrate_reverse
+pub struct BiAStar<M>
+where
+    M: ReversibleModel,
+    M::Cost: Copy,
+{
+    forward: Tree<M>,
+    backward: Tree<M>,
+    /// The cheapest meeting found so far, as the forward node and the backward node
+    best: Option<(M::Cost, Node<M>, Node<M>)>,
+    id_counter: usize,
+    stats: Stats,
+}
+
+impl<M> BiAStar<M>
+where
+    M: ReversibleModel,
+    M::Cost: Copy,
+{
+    /// Create a new bidirectional A* optimizer
+    pub fn new() -> Self {
+        BiAStar {
+            forward: Tree::new(),
+            backward: Tree::new(),
+            best: None,
+            id_counter: 0,
+            stats: Stats::default(),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.forward.clear();
+        self.backward.clear();
+        self.best = None;
+        self.stats = Stats::default();
+    }
+
+    /// Counters describing the work done by both trees together
+    pub fn stats(&self) -> &Stats {
+        &self.stats
+    }
+
+    pub fn inspect_queue(&self) -> impl Iterator<Item = (&M::State, &M::Control)> {
+        self.forward
+            .queue
+            .iter()
+            .chain(self.backward.queue.iter())
+            .map(|node| (&node.state, &node.control))
+    }
+
+    pub fn inspect_discovered(
+        &self,
+    ) -> impl Iterator<Item = &<<M as Model>::State as State>::Position> {
+        self.forward.grid.keys().chain(self.backward.grid.keys())
+    }
+
+    /// Whether the search is over: either tree ran out of nodes, or neither queue holds a
+    /// node which could improve on the best meeting
+    fn is_finished(&self) -> bool {
+        match (self.forward.lowest_estimate(), self.backward.lowest_estimate()) {
+            (Some(forward), Some(backward)) => {
+                self.best.as_ref().is_some_and(|(cost, _, _)| *cost <= forward.max(backward))
+            }
+            _ => true,
+        }
+    }
+
+    /// Expand the next node of the smaller queue
+    fn step<S>(&mut self, model: &M, start: &M::State, goal: &M::State, sampler: &mut S)
+    where
+        S: Sampler<M>,
+    {
+        let forwards = self.forward.queue.len() <= self.backward.queue.len();
+        let current = match if forwards { self.forward.pop() } else { self.backward.pop() } {
+            Some(current) => current,
+            None => return,
+        };
+        self.stats.expanded += 1;
+
+        for control in sampler.sample(model, &current.state) {
+            let (child_state, step_cost) = if forwards {
+                match model.integrate(&current.state, control) {
+                    Some(next) => {
+                        let cost = model.cost(&current.state, control, &next);
+                        (next, cost)
+                    }
+                    None => continue,
+                }
+            } else {
+                match model.integrate_reverse(&current.state, control) {
+                    Some(previous) => {
+                        let cost = model.cost(&previous, control, &current.state);
+                        (previous, cost)
+                    }
+                    None => continue,
+                }
+            };
+            self.id_counter += 1;
+            self.stats.generated += 1;
+
+            let cost = current.id.g() + step_cost;
+            let heuristic = if forwards {
+                model.heuristic(&child_state, goal)
+            } else {
+                model.heuristic(start, &child_state)
+            };
+
+            let child = Node::<M> {
+                id: Id::new(self.id_counter, cost + heuristic, cost),
+                state: child_state,
+                control: control.clone(),
+            };
+
+            let (tree, other) = if forwards {
+                (&mut self.forward, &self.backward)
+            } else {
+                (&mut self.backward, &self.forward)
+            };
+
+            let position = child.state.grid_position();
+            if !tree.discover(child.clone(), &current) {
+                continue;
+            }
+
+            if let Some(meeting) = other.grid.get(&position) {
+                let total = cost + meeting.id.g();
+                if self.best.as_ref().is_none_or(|(best, _, _)| total < *best) {
+                    self.best = Some(if forwards {
+                        (total, child, meeting.clone())
+                    } else {
+                        (total, meeting.clone(), child)
+                    });
+                }
+            }
+        }
+
+        let queued = self.forward.queue.len() + self.backward.queue.len();
+        self.stats.max_queue_len = self.stats.max_queue_len.max(queued);
+    }
+
+    /// Follow the parents of the forward tree from a node up to the start node
+    fn unwind(&self, mut current: Node<M>) -> Vec<(M::State, M::Control)> {
+        let mut result = Vec::new();
+        result.push((current.state.clone(), current.control.clone()));
+
+        while let Some(p) = self.forward.parent_map.get(&current.id) {
+            current = (*p).clone();
+            result.push((current.state.clone(), current.control.clone()));
+        }
+
+        result.reverse();
+        result
+    }
+
+    /// Join the trajectory to the forward node with the trajectory from the backward node
+    ///
+    /// Fails with `PathFindingErr::Discontinuous` when `integrate` rejects one of the controls
+    /// of the backward tree, which `integrate_reverse` should never have allowed.
+    fn stitch(
+        &self,
+        model: &M,
+        goal: &M::State,
+        forward: Node<M>,
+        backward: Node<M>,
+    ) -> PathResult<M> {
+        let mut result = self.unwind(forward);
+
+        // Replay the controls of the backward tree from the meeting state
+        let mut current = backward;
+        while let Some(p) = self.backward.parent_map.get(&current.id) {
+            let state = &result[result.len() - 1].0;
+            match model.integrate(state, &current.control) {
+                Some(next) => result.push((next, current.control.clone())),
+                None => return PathResult::Err(PathFindingErr::Discontinuous),
+            }
+            current = (*p).clone();
+        }
+
+        let end = result
+            .iter()
+            .position(|(state, _)| model.converge(state, goal))
+            .unwrap_or(result.len() - 1);
+        result.truncate(end + 1);
+
+        let mut cost = M::Cost::default();
+        for pair in result.windows(2) {
+            let ((from, _), (to, control)) = (&pair[0], &pair[1]);
+            cost = cost + model.cost(from, control, to);
+        }
+
+        PathResult::Final(Trajectory {
+            cost,
+            trajectory: result,
+            expansions: self.stats.expanded,
+        })
+    }
+
+    fn seed(&mut self, model: &M, start: &M::State, goal: &M::State) {
+        let heuristic = model.heuristic(start, goal);
+        self.forward.seed(Node {
+            id: Id::new(0, heuristic, Default::default()),
+            state: start.clone(),
+            control: Default::default(),
+        });
+
+        for state in model.goal_states(goal) {
+            self.id_counter += 1;
+            self.backward.seed(Node {
+                id: Id::new(
+                    self.id_counter,
+                    model.heuristic(start, &state),
+                    Default::default(),
+                ),
+                state,
+                control: Default::default(),
+            });
+        }
+    }
+
+    fn is_seeded(&self) -> bool {
+        !(self.forward.grid.is_empty() && self.backward.grid.is_empty())
+    }
+}
+
+impl<M, S> Optimizer<M, S> for BiAStar<M>
+where
+    M: ReversibleModel,
+    M::Cost: Copy,
+    S: Sampler<M>,
+{
+    /// Expand a single node, returning the trajectory to the most promising node of the
+    /// forward tree until the search is finished
+    fn next_trajectory(
+        &mut self,
+        model: &mut M,
+        start: &M::State,
+        goal: &M::State,
+        sampler: &mut S,
+    ) -> PathResult<M> {
+        use PathFindingErr::*;
+        use PathResult::*;
+
+        if let Some(invalid) = invalid_endpoint(model, start, goal) {
+            return Err(invalid);
+        }
+
+        if !self.is_seeded() {
+            self.seed(model, start, goal);
+        }
+
+        if !self.is_finished() {
+            self.step(model, start, goal, sampler);
+        }
+
+        if self.is_finished() {
+            return match self.best.clone() {
+                Some((_, forward, backward)) => self.stitch(model, goal, forward, backward),
+                None => Err(Unreachable),
+            };
+        }
+
+        match self.forward.queue.peek() {
+            Some(next) => Intermediate(Trajectory {
+                cost: next.id.g(),
+                trajectory: self.unwind(next.clone()),
+                expansions: self.stats.expanded,
+            }),
+            None => Err(Unreachable),
+        }
+    }
+
+    /// Search from both ends until the trees meet, and join them into a trajectory
+    ///
+    /// The backward half of the trajectory is replayed with `integrate` from where the trees
+    /// meet.  A model whose `integrate_reverse` allows steps which `integrate` does not
+    /// cannot be joined up, and the search fails with `PathFindingErr::Discontinuous` rather
+    /// than return a trajectory which stops short of the goal.
+    fn optimize(
+        &mut self,
+        model: &mut M,
+        start: &M::State,
+        goal: &M::State,
+        sampler: &mut S,
+    ) -> PathResult<M> {
+        use PathFindingErr::*;
+        use PathResult::*;
+
+        if let Some(invalid) = invalid_endpoint(model, start, goal) {
+            return Err(invalid);
+        }
+
+        if model.converge(start, goal) {
+            return Final(Trajectory {
+                cost: Default::default(),
+                trajectory: vec![(start.clone(), Default::default())],
+                expansions: 0,
+            });
+        }
+
+        if !self.is_seeded() {
+            self.seed(model, start, goal);
+        }
+
+        while !self.is_finished() {
+            self.step(model, start, goal, sampler);
+        }
+
+        match self.best.clone() {
+            Some((_, forward, backward)) => self.stitch(model, goal, forward, backward),
+            None => Err(Unreachable),
+        }
+    }
+}
+
+impl<M> Debug for BiAStar<M>
+where
+    M: ReversibleModel,
+    M::State: Debug,
+    M::Control: Debug,
+    M::Cost: Debug + Copy,
+{
+    fn fmt(&self, fmt: &mut Formatter) -> Result<(), std::fmt::Error> {
+        fmt.debug_struct("BiAStar")
+            .field("counter", &self.id_counter)
+            .field("best", &self.best)
+            .field("forward_queue", &self.forward.queue)
+            .field("backward_queue", &self.backward.queue)
+            .field("stats", &self.stats)
+            .finish()
+    }
+}
+
+impl<M> Default for BiAStar<M>
+where
+    M: ReversibleModel,
+    M::Cost: Copy,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::actor::{Heuristic, TurnOptimal, WalkSampler};
+    use crate::map::Tile;
+    use crate::path::astar::AStar;
+    use crate::path::grid::{Cell, GridSampler};
+    use crate::path::testing::*;
+
+    #[test]
+    fn reaches_a_goal_behind_a_wall_with_fewer_expansions_than_astar() {
+        // The goal is at the end of a tunnel which opens away from the start, behind a long wall
+        let mut map = room(80, 40);
+        for y in 8..33 {
+            map[(40, y)] = Tile::WALL;
+        }
+        for x in 40..53 {
+            map[(x, 19)] = Tile::WALL;
+            map[(x, 21)] = Tile::WALL;
+        }
+        let mut model = TurnOptimal::new(map);
+        model.set_heuristic(Heuristic::Diagonal);
+        let (start, goal) = (walker(3, 20), walker(41, 20));
+
+        let mut sampler = WalkSampler::new();
+        let (mut astar, mut bi) = (AStar::new(), BiAStar::new());
+        let a = expect_final(astar.optimize(&mut model, &start, &goal, &mut sampler));
+        let b = expect_final(bi.optimize(&mut model, &start, &goal, &mut sampler));
+        assert_eq!(a.cost, b.cost);
+        assert_eq!(a.trajectory.last().unwrap().0.pos, b.trajectory.last().unwrap().0.pos);
+        assert!(bi.stats().expanded * 3 < astar.stats().expanded);
+    }
+
+    #[test]
+    fn trees_which_cannot_be_joined_are_discontinuous() {
+        // A ledge at `x = 6` which can only be crossed backwards
+        let mut ledge = TestGrid::new(10, 5);
+        ledge.allow = |cell, next| cell.x >= 6 || next.x < 6;
+        let (start, goal) = (Cell::new(1, 2), Cell::new(8, 2));
+        match BiAStar::new().optimize(&mut ledge, &start, &goal, &mut GridSampler) {
+            PathResult::Err(PathFindingErr::Discontinuous) => {}
+            _ => panic!("the trees meet on the near side of the ledge"),
+        }
+    }
+}
@@ -0,0 +1,501 @@
+use std::fmt::{Debug, Formatter};
+
+use fnv::{FnvHashMap, FnvHashSet};
+use std::collections::hash_map::Entry;
+use std::collections::VecDeque;
+
+use super::astar::{Id, Node};
+use super::*;
+
+/// Priority queue with one bucket for every integer priority, lowest priority first
+///
+/// Pushing and popping take constant time as long as priorities stay within a small window
+/// above the lowest queued priority, which is the case for A* with integer costs: no node is
+/// queued more than a couple of edge costs above the node being expanded.  Priorities below
+/// the lowest one queued so far are allowed, but grow the window.
+///
+/// Values with equal priorities are popped most recently pushed first.
+///
+/// ```
+/// # use game_lib::path::bucket::BucketQueue;
+/// let mut queue = BucketQueue::new();
+/// queue.push(4, 'a');
+/// queue.push(2, 'b');
+/// queue.push(4, 'c');
+///
+/// assert_eq!(queue.pop(), Some((2, 'b')));
+/// assert_eq!(queue.pop(), Some((4, 'c')));
+/// assert_eq!(queue.pop(), Some((4, 'a')));
+/// assert_eq!(queue.pop(), None);
+/// ```
+pub struct BucketQueue<V> {
+    buckets: VecDeque<Vec<V>>,
+    /// Priority of the first bucket
+    base: usize,
+    len: usize,
+}
+
+impl<V> BucketQueue<V> {
+    pub fn new() -> Self {
+        Self::with_buckets(0)
+    }
+
+    /// Create a queue which spans `buckets` priorities before it has to grow
+    pub fn with_buckets(buckets: usize) -> Self {
+        BucketQueue { buckets: (0..buckets).map(|_| Vec::new()).collect(), base: 0, len: 0 }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Empty every bucket, keeping their allocations
+    pub fn clear(&mut self) {
+        for bucket in self.buckets.iter_mut() {
+            bucket.clear();
+        }
+        self.base = 0;
+        self.len = 0;
+    }
+
+    /// Iterate over the queued values in no particular order
+    pub fn iter(&self) -> impl Iterator<Item = &V> {
+        self.buckets.iter().flatten()
+    }
+
+    /// The lowest priority in the queue and the value which would be popped next
+    pub fn peek(&self) -> Option<(usize, &V)> {
+        let base = self.base;
+        self.buckets
+            .iter()
+            .enumerate()
+            .find_map(|(i, bucket)| bucket.last().map(|v| (base + i, v)))
+    }
+
+    /// Queue a value with the given priority
+    ///
+    /// An empty queue moves its window to begin at `priority`, so that the first value pushed
+    /// never needs buckets for the priorities below it, wherever it lands.
+    ///
+    /// ```
+    /// # use game_lib::path::bucket::BucketQueue;
+    /// let mut queue = BucketQueue::with_buckets(4);
+    /// queue.push(usize::MAX - 1, 'a');
+    /// queue.push(usize::MAX, 'b');
+    /// assert_eq!(queue.pop(), Some((usize::MAX - 1, 'a')));
+    /// assert_eq!(queue.pop(), Some((usize::MAX, 'b')));
+    ///
+    /// // Emptied, the queue can begin again far below
+    /// queue.push(3, 'c');
+    /// assert_eq!(queue.peek(), Some((3, &'c')));
+    /// ```
+    pub fn push(&mut self, priority: usize, value: V) {
+        if self.len == 0 {
+            // Every bucket is empty, so the window can start anywhere
+            self.base = priority;
+        }
+        while priority < self.base {
+            self.buckets.push_front(Vec::new());
+            self.base -= 1;
+        }
+
+        let index = priority - self.base;
+        while self.buckets.len() <= index {
+            self.buckets.push_back(Vec::new());
+        }
+
+        self.buckets[index].push(value);
+        self.len += 1;
+    }
+
+    /// Remove a value with the lowest priority
+    pub fn pop(&mut self) -> Option<(usize, V)> {
+        if self.len == 0 {
+            return None;
+        }
+
+        loop {
+            if let Some(value) = self.buckets.front_mut().and_then(Vec::pop) {
+                self.len -= 1;
+                return Some((self.base, value));
+            }
+
+            // Recycle the empty bucket as the next highest priority
+            let empty = self.buckets.pop_front().unwrap_or_default();
+            self.buckets.push_back(empty);
+            self.base += 1;
+        }
+    }
+}
+
+impl<V> Debug for BucketQueue<V>
+where
+    V: Debug,
+{
+    fn fmt(&self, fmt: &mut Formatter) -> Result<(), std::fmt::Error> {
+        fmt.debug_struct("BucketQueue")
+            .field("base", &self.base)
+            .field("len", &self.len)
+            .field("buckets", &self.buckets)
+            .finish()
+    }
+}
+
+impl<V> Default for BucketQueue<V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Models with integer costs which know the most expensive step they can take
+pub trait BoundedCostModel: HeuristicModel
+where
+    Self::Cost: IntegerCost,
+{
+    /// The highest cost of any single step from one state to the next
+    fn max_edge_cost(&self) -> Self::Cost;
+}
+
+/// A* optimizer which keeps its queue in a [`BucketQueue`]
+///
+/// Produces trajectories of the same cost as [`AStar`], but avoids the comparisons of a
+/// binary heap, which pays off on large grids where A* spends most of its time in the queue.
+/// Only models with [`IntegerCost`]s can be planned for.
+///
+/// ```
+/// # use game_lib::path::grid::{Cell, GridModel, GridSampler};
+/// # use game_lib::path::{bucket::BucketAStar, Optimizer};
+/// let mut grid = GridModel::new(30, 30);
+/// let mut buckets = BucketAStar::for_model(&grid);
+/// let (start, goal) = (Cell::new(3, 15), Cell::new(27, 14));
+/// assert!(buckets.optimize(&mut grid, &start, &goal, &mut GridSampler).is_final());
+/// ```
+///
+/// [`AStar`]: ../astar/struct.AStar.html
+/// [`BucketQueue`]: struct.BucketQueue.html
+/// [`IntegerCost`]: ../trait.IntegerCost.html
+pub struct BucketAStar<M>
+where
+    M: HeuristicModel,
+    M::Cost: IntegerCost,
+{
+    queue: BucketQueue<Node<M>>,
+    parent_map: FnvHashMap<Id<M>, Node<M>>,
+    grid: FnvHashMap<<<M as Model>::State as State>::Position, Id<M>>,
+    closed: FnvHashSet<<<M as Model>::State as State>::Position>,
+    id_counter: usize,
+    stats: Stats,
+    /// Whether the start was queued, so that an empty queue means the search has finished
+    seeded: bool,
+}
+
+impl<M> BucketAStar<M>
+where
+    M: HeuristicModel,
+    M::Cost: IntegerCost,
+{
+    /// Create a new bucket queue A* optimizer
+    pub fn new() -> Self {
+        Self::with_buckets(0)
+    }
+
+    /// Create an optimizer whose queue is sized for the most expensive step of the `model`
+    pub fn for_model(model: &M) -> Self
+    where
+        M: BoundedCostModel,
+    {
+        // Children are queued at most two steps above the node being expanded: one for the
+        // step itself, and one for how much the heuristic can grow over that step
+        Self::with_buckets(2 * model.max_edge_cost().index() + 1)
+    }
+
+    fn with_buckets(buckets: usize) -> Self {
+        BucketAStar {
+            queue: BucketQueue::with_buckets(buckets),
+            parent_map: FnvHashMap::default(),
+            grid: FnvHashMap::default(),
+            closed: FnvHashSet::default(),
+            id_counter: 0,
+            stats: Stats::default(),
+            seeded: false,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.queue.clear();
+        self.parent_map.clear();
+        self.grid.clear();
+        self.closed.clear();
+        self.stats = Stats::default();
+        self.seeded = false;
+    }
+
+    /// Counters describing the work done since the optimizer was created or last cleared
+    pub fn stats(&self) -> &Stats {
+        &self.stats
+    }
+
+    pub fn inspect_queue(&self) -> impl Iterator<Item = (&M::State, &M::Control)> {
+        self.queue.iter().map(|node| (&node.state, &node.control))
+    }
+
+    pub fn inspect_discovered(
+        &self,
+    ) -> impl Iterator<Item = &<<M as Model>::State as State>::Position> {
+        self.grid.keys()
+    }
+
+    #[inline(always)]
+    fn step<S>(
+        &mut self,
+        current: &Node<M>,
+        model: &mut M,
+        goal: &M::State,
+        sampler: &mut S,
+    ) -> bool
+    where
+        S: Sampler<M>,
+    {
+        if model.converge(&current.state, goal) {
+            return true;
+        }
+
+        if !self.closed.insert(current.state.grid_position()) {
+            return false;
+        }
+        self.stats.expanded += 1;
+
+        for control in sampler.sample(model, &current.state) {
+            if let Some(child_state) = model.integrate(&current.state, control) {
+                self.id_counter += 1;
+                self.stats.generated += 1;
+
+                let cost = current.id.g() + model.cost(&current.state, control, &child_state);
+                let heuristic = model.heuristic(&child_state, goal);
+
+                let child = Node::<M> {
+                    id: Id::new(self.id_counter, cost + heuristic, cost),
+                    state: child_state,
+                    control: control.clone(),
+                };
+
+                match self.grid.entry(child.state.grid_position()) {
+                    Entry::Occupied(mut best) => {
+                        let best = best.get_mut();
+                        if best.g <= child.id.g {
+                            continue;
+                        } else {
+                            *best = child.id.clone();
+                        }
+                    }
+                    Entry::Vacant(empty) => {
+                        empty.insert(child.id.clone());
+                    }
+                }
+
+                self.parent_map.insert(child.id.clone(), current.clone());
+                self.queue.push(child.id.f.0.index(), child);
+                self.stats.max_queue_len = self.stats.max_queue_len.max(self.queue.len());
+            }
+        }
+
+        false
+    }
+
+    /// Pop the next node from the queue, discarding stale entries along the way
+    fn pop(&mut self) -> Option<Node<M>> {
+        while let Some((_, node)) = self.queue.pop() {
+            let stale = self
+                .grid
+                .get(&node.state.grid_position())
+                .is_some_and(|best| best.g < node.id.g);
+
+            if !stale {
+                return Some(node);
+            }
+        }
+
+        None
+    }
+
+    /// Follow the parents from the goal node up to the start node
+    fn unwind_trajectory(&self, mut current: Node<M>) -> Trajectory<M> {
+        let cost = current.id.g();
+        let mut result = Vec::new();
+        result.push((current.state.clone(), current.control.clone()));
+
+        while let Some(p) = self.parent_map.get(&current.id) {
+            current = (*p).clone();
+            result.push((current.state.clone(), current.control.clone()));
+        }
+
+        result.reverse();
+
+        Trajectory { cost, trajectory: result, expansions: self.stats.expanded }
+    }
+
+    fn seed(&mut self, model: &M, start: &M::State, goal: &M::State) {
+        self.seeded = true;
+        let heuristic = model.heuristic(start, goal);
+        self.queue.push(
+            heuristic.index(),
+            Node {
+                id: Id::new(0, heuristic, Default::default()),
+                state: start.clone(),
+                control: Default::default(),
+            },
+        );
+    }
+}
+
+impl<M, S> Optimizer<M, S> for BucketAStar<M>
+where
+    M: HeuristicModel,
+    M::Cost: IntegerCost,
+    S: Sampler<M>,
+{
+    /// Expand the next node in the queue
+    ///
+    /// The start is queued by the first call only, so once the queue runs out the search has
+    /// finished and every later call stops with `PathFindingErr::Unreachable` as well, rather
+    /// than beginning the search again.
+    fn next_trajectory(
+        &mut self,
+        model: &mut M,
+        start: &M::State,
+        goal: &M::State,
+        sampler: &mut S,
+    ) -> PathResult<M> {
+        use PathFindingErr::*;
+        use PathResult::*;
+
+        if let Some(invalid) = invalid_endpoint(model, start, goal) {
+            return Err(invalid);
+        }
+
+        if !self.seeded {
+            self.seed(model, start, goal);
+        }
+
+        if let Some(current) = self.pop() {
+            if self.step(&current, model, goal, sampler) {
+                Final(self.unwind_trajectory(current))
+            } else {
+                Intermediate(self.unwind_trajectory(current))
+            }
+        } else {
+            Err(Unreachable)
+        }
+    }
+
+    fn optimize(
+        &mut self,
+        model: &mut M,
+        start: &M::State,
+        goal: &M::State,
+        sampler: &mut S,
+    ) -> PathResult<M> {
+        use PathFindingErr::*;
+        use PathResult::*;
+
+        if let Some(invalid) = invalid_endpoint(model, start, goal) {
+            return Err(invalid);
+        }
+
+        if model.converge(start, goal) {
+            return Final(Trajectory {
+                cost: Default::default(),
+                trajectory: vec![(start.clone(), Default::default())],
+                expansions: 0,
+            });
+        }
+
+        if !self.seeded {
+            self.seed(model, start, goal);
+        }
+
+        while let Some(current) = self.pop() {
+            if self.step(&current, model, goal, sampler) {
+                return Final(self.unwind_trajectory(current));
+            }
+        }
+
+        Err(Unreachable)
+    }
+}
+
+impl<M> Debug for BucketAStar<M>
+where
+    M: HeuristicModel,
+    M::State: Debug,
+    M::Control: Debug,
+    M::Cost: Debug + IntegerCost,
+{
+    fn fmt(&self, fmt: &mut Formatter) -> Result<(), std::fmt::Error> {
+        fmt.debug_struct("BucketAStar")
+            .field("counter", &self.id_counter)
+            .field("next", &self.queue.peek())
+            .field("queue", &self.queue)
+            .field("grid", &self.grid)
+            .field("closed", &self.closed)
+            .field("stats", &self.stats)
+            .field("parent_map", &self.parent_map)
+            .finish()
+    }
+}
+
+impl<M> Default for BucketAStar<M>
+where
+    M: HeuristicModel,
+    M::Cost: IntegerCost,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::actor::{Heuristic, TurnOptimal, WalkSampler};
+    use crate::path::astar::AStar;
+    use crate::path::grid::{Cell, GridSampler};
+    use crate::path::testing::*;
+
+    #[test]
+    fn finds_the_same_cost_as_astar_between_the_same_ends() {
+        let mut model = TurnOptimal::new(walled_room());
+        model.set_heuristic(Heuristic::Chebyshev);
+        let (start, goal) = (walker(3, 15), walker(27, 14));
+
+        let mut sampler = WalkSampler::new();
+        let (mut heap, mut buckets) = (AStar::new(), BucketAStar::for_model(&model));
+        let a = expect_final(heap.optimize(&mut model, &start, &goal, &mut sampler));
+        let b = expect_final(buckets.optimize(&mut model, &start, &goal, &mut sampler));
+        assert_eq!(a.cost, b.cost);
+        let (a, b) = (&a.trajectory, &b.trajectory);
+        assert_eq!(a.first().unwrap().0.pos, b.first().unwrap().0.pos);
+        assert_eq!(a.last().unwrap().0.pos, b.last().unwrap().0.pos);
+    }
+
+    #[test]
+    fn a_finished_search_stays_unreachable() {
+        let (mut grid, start, goal) = (shut_in(), Cell::new(2, 2), Cell::new(4, 4));
+        let mut bucket = BucketAStar::for_model(&grid);
+        match bucket.next_trajectory(&mut grid, &start, &goal, &mut GridSampler) {
+            PathResult::Intermediate(t) => assert_eq!(t.trajectory.len(), 1),
+            _ => panic!("the start is expanded first"),
+        }
+        for _ in 0..3 {
+            match bucket.next_trajectory(&mut grid, &start, &goal, &mut GridSampler) {
+                PathResult::Err(PathFindingErr::Unreachable) => {}
+                _ => panic!("the search has finished"),
+            }
+        }
+        assert_eq!(bucket.stats().expanded, 1);
+    }
+}
@@ -0,0 +1,248 @@
+use std::cell::RefCell;
+use std::fmt::{Debug, Formatter};
+use std::hash::Hash;
+
+use fnv::FnvHashMap;
+
+use super::*;
+
+type Position<M> = <<M as Model>::State as State>::Position;
+type CostKey<M> = (Position<M>, <M as Model>::Control, Position<M>);
+type HeuristicKey<M> = (Position<M>, Position<M>);
+
+/// Remembers every cost and heuristic a model has worked out, for models where they are
+/// expensive
+///
+/// A cost is keyed by the position of the state it starts from, the control, and the position
+/// of the state it ends in, and a heuristic by the positions of the state and the goal.  The
+/// model must give the same answer whenever the keys are the same, which is always the case
+/// when its positions hold the whole state.
+///
+/// Every distinct key stays in memory until the cache is cleared, so this trades a map entry
+/// for each evaluation against the time to repeat it.  That is only worth it when evaluations
+/// take much longer than a hash map lookup, or for repeated searches of the same region.
+///
+/// Samplers of the model are used through a [`CachingSampler`].
+///
+/// ```
+/// # use game_lib::path::astar::AStar;
+/// # use game_lib::path::caching::{CachingModel, CachingSampler};
+/// # use game_lib::path::grid::{Cell, GridModel, GridSampler};
+/// # use game_lib::path::Optimizer;
+/// let mut cached = CachingModel::new(GridModel::new(20, 20));
+/// let mut sampler = CachingSampler::new(GridSampler);
+/// let (start, goal) = (Cell::new(2, 3), Cell::new(17, 12));
+/// assert!(AStar::new().optimize(&mut cached, &start, &goal, &mut sampler).is_final());
+/// assert!(cached.cached().0 > 0);
+/// ```
+pub struct CachingModel<M>
+where
+    M: HeuristicModel,
+    M::Control: Hash + Eq,
+{
+    model: M,
+    costs: RefCell<FnvHashMap<CostKey<M>, M::Cost>>,
+    heuristics: RefCell<FnvHashMap<HeuristicKey<M>, M::Cost>>,
+}
+
+impl<M> CachingModel<M>
+where
+    M: HeuristicModel,
+    M::Control: Hash + Eq,
+{
+    pub fn new(model: M) -> Self {
+        CachingModel {
+            model,
+            costs: RefCell::new(FnvHashMap::default()),
+            heuristics: RefCell::new(FnvHashMap::default()),
+        }
+    }
+
+    pub fn model(&self) -> &M {
+        &self.model
+    }
+
+    /// Change the underlying model, forgetting everything cached from it
+    pub fn model_mut(&mut self) -> &mut M {
+        self.clear();
+        &mut self.model
+    }
+
+    pub fn into_inner(self) -> M {
+        self.model
+    }
+
+    /// Forget every cost and heuristic which has been cached
+    pub fn clear(&mut self) {
+        self.costs.get_mut().clear();
+        self.heuristics.get_mut().clear();
+    }
+
+    /// The number of costs and of heuristics which have been cached
+    pub fn cached(&self) -> (usize, usize) {
+        (self.costs.borrow().len(), self.heuristics.borrow().len())
+    }
+}
+
+impl<M> Model for CachingModel<M>
+where
+    M: HeuristicModel,
+    M::Control: Hash + Eq,
+{
+    type State = M::State;
+    type Control = M::Control;
+    type Cost = M::Cost;
+
+    fn cost(&self, current: &M::State, control: &M::Control, next: &M::State) -> M::Cost {
+        let key = (current.grid_position(), control.clone(), next.grid_position());
+        self.costs
+            .borrow_mut()
+            .entry(key)
+            .or_insert_with(|| self.model.cost(current, control, next))
+            .clone()
+    }
+
+    fn init(&mut self, start: &M::State) {
+        self.model.init(start)
+    }
+
+    fn converge(&self, current: &M::State, goal: &M::State) -> bool {
+        self.model.converge(current, goal)
+    }
+
+    fn integrate(&self, previous: &M::State, control: &M::Control) -> Option<M::State> {
+        self.model.integrate(previous, control)
+    }
+
+    fn is_valid(&self, state: &M::State) -> bool {
+        self.model.is_valid(state)
+    }
+}
+
+impl<M> HeuristicModel for CachingModel<M>
+where
+    M: HeuristicModel,
+    M::Control: Hash + Eq,
+{
+    fn heuristic(&self, current: &M::State, goal: &M::State) -> M::Cost {
+        let key = (current.grid_position(), goal.grid_position());
+        self.heuristics
+            .borrow_mut()
+            .entry(key)
+            .or_insert_with(|| self.model.heuristic(current, goal))
+            .clone()
+    }
+}
+
+impl<M> Debug for CachingModel<M>
+where
+    M: HeuristicModel + Debug,
+    M::Control: Hash + Eq,
+{
+    fn fmt(&self, fmt: &mut Formatter) -> Result<(), std::fmt::Error> {
+        let (costs, heuristics) = self.cached();
+        fmt.debug_struct("CachingModel")
+            .field("model", &self.model)
+            .field("costs", &costs)
+            .field("heuristics", &heuristics)
+            .finish()
+    }
+}
+
+/// Samples the controls of a sampler for the model inside a [`CachingModel`]
+#[derive(Clone, Debug, Default)]
+pub struct CachingSampler<S>(S);
+
+impl<S> CachingSampler<S> {
+    pub fn new(sampler: S) -> Self {
+        CachingSampler(sampler)
+    }
+
+    pub fn into_inner(self) -> S {
+        self.0
+    }
+}
+
+impl<M, S> Sampler<CachingModel<M>> for CachingSampler<S>
+where
+    M: HeuristicModel,
+    M::Control: Hash + Eq,
+    S: Sampler<M>,
+{
+    #[inline]
+    fn sample(&mut self, model: &CachingModel<M>, current: &M::State) -> &[M::Control] {
+        self.0.sample(&model.model, current)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::path::astar::AStar;
+    use crate::path::grid::{Cell, GridModel, GridSampler, Step};
+    use crate::path::testing::*;
+    use std::cell::Cell as Counter;
+
+    /// A model which counts how often its cost and heuristic are evaluated
+    struct Counting {
+        grid: GridModel,
+        calls: Counter<usize>,
+    }
+
+    impl Model for Counting {
+        type State = Cell;
+        type Control = Step;
+        type Cost = usize;
+
+        fn cost(&self, current: &Cell, step: &Step, next: &Cell) -> usize {
+            self.calls.set(self.calls.get() + 1);
+            self.grid.cost(current, step, next)
+        }
+
+        fn init(&mut self, _: &Cell) {}
+
+        fn converge(&self, current: &Cell, goal: &Cell) -> bool {
+            current == goal
+        }
+
+        fn integrate(&self, previous: &Cell, step: &Step) -> Option<Cell> {
+            self.grid.integrate(previous, step)
+        }
+    }
+
+    impl HeuristicModel for Counting {
+        fn heuristic(&self, current: &Cell, goal: &Cell) -> usize {
+            self.calls.set(self.calls.get() + 1);
+            self.grid.heuristic(current, goal)
+        }
+    }
+
+    impl Sampler<Counting> for GridSampler {
+        fn sample(&mut self, model: &Counting, current: &Cell) -> &[Step] {
+            Sampler::<GridModel>::sample(self, &model.grid, current)
+        }
+    }
+
+    #[test]
+    fn cached_searches_evaluate_less_and_find_the_same_cost() {
+        let mut model = Counting { grid: GridModel::new(20, 20), calls: Counter::new(0) };
+        let (start, goal) = (Cell::new(2, 3), Cell::new(17, 12));
+        let uncached =
+            expect_final(AStar::new().optimize(&mut model, &start, &goal, &mut GridSampler));
+        let calls = model.calls.replace(0);
+
+        // Positions are reached from several neighbours, so their heuristic is asked for again
+        let mut cached = CachingModel::new(model);
+        let mut sampler = CachingSampler::new(GridSampler);
+        let mut search = |cached: &mut CachingModel<Counting>| {
+            let t = expect_final(AStar::new().optimize(cached, &start, &goal, &mut sampler));
+            assert_eq!(t.cost, uncached.cost);
+            cached.model().calls.get()
+        };
+        let first = search(&mut cached);
+        assert!(first < calls);
+
+        // The second search finds everything it needs in the cache
+        assert_eq!(search(&mut cached), first);
+    }
+}
@@ -0,0 +1,327 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+use super::astar::AStar;
+use super::spacetime::SpaceTimeModel;
+use super::*;
+
+/// Something one agent is not allowed to do while it plans around the others
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Constraint<P> {
+    /// Being at the place at the time step
+    Vertex(P, u32),
+    /// Stepping from the first place at the time step to the second at the next time step
+    Edge(P, P, u32),
+}
+
+/// A model which forbids whatever the constraints of one agent do
+struct Constrained<'a, M>
+where
+    M: SpaceTimeModel,
+{
+    model: &'a mut M,
+    constraints: &'a [Constraint<M::Place>],
+}
+
+impl<'a, M> Model for Constrained<'a, M>
+where
+    M: SpaceTimeModel,
+{
+    type State = M::State;
+    type Control = M::Control;
+    type Cost = M::Cost;
+
+    fn cost(&self, current: &M::State, control: &M::Control, next: &M::State) -> M::Cost {
+        self.model.cost(current, control, next)
+    }
+
+    fn init(&mut self, start: &M::State) {
+        self.model.init(start)
+    }
+
+    /// The agent only stops at the goal when it is never in the way there afterwards
+    fn converge(&self, current: &M::State, goal: &M::State) -> bool {
+        if !self.model.converge(current, goal) {
+            return false;
+        }
+
+        let (place, time) = (self.model.place(current), self.model.time(current));
+        !self.constraints.iter().any(|constraint| match constraint {
+            Constraint::Vertex(p, t) => *p == place && *t >= time,
+            Constraint::Edge(..) => false,
+        })
+    }
+
+    fn integrate(&self, previous: &M::State, control: &M::Control) -> Option<M::State> {
+        let next = self.model.integrate(previous, control)?;
+
+        let (from, to) = (self.model.place(previous), self.model.place(&next));
+        let (leaves, arrives) = (self.model.time(previous), self.model.time(&next));
+        let forbidden = self.constraints.iter().any(|constraint| match constraint {
+            Constraint::Vertex(p, t) => *p == to && *t == arrives,
+            Constraint::Edge(a, b, t) => *a == from && *b == to && *t == leaves,
+        });
+
+        if forbidden {
+            None
+        } else {
+            Some(next)
+        }
+    }
+
+    fn is_valid(&self, state: &M::State) -> bool {
+        self.model.is_valid(state)
+    }
+}
+
+impl<'a, M> HeuristicModel for Constrained<'a, M>
+where
+    M: SpaceTimeModel + HeuristicModel,
+{
+    fn heuristic(&self, current: &M::State, goal: &M::State) -> M::Cost {
+        self.model.heuristic(current, goal)
+    }
+
+    fn heuristic_opt(&self, current: &M::State, goal: &M::State) -> Option<M::Cost> {
+        self.model.heuristic_opt(current, goal)
+    }
+}
+
+/// Hands the controls of the agents' sampler to the constrained model
+struct ConstrainedSampler<'s, S>(&'s mut S);
+
+impl<'a, 's, M, S> Sampler<Constrained<'a, M>> for ConstrainedSampler<'s, S>
+where
+    M: SpaceTimeModel,
+    S: Sampler<M>,
+{
+    #[inline]
+    fn sample(&mut self, model: &Constrained<'a, M>, current: &M::State) -> &[M::Control] {
+        self.0.sample(model.model, current)
+    }
+}
+
+/// A node of the constraint tree, holding a trajectory for every agent
+struct Node<M>
+where
+    M: SpaceTimeModel,
+{
+    constraints: Vec<Vec<Constraint<M::Place>>>,
+    trajectories: Vec<Trajectory<M>>,
+}
+
+/// Plan a trajectory for one agent with space-time A*, under its constraints
+fn plan<M, S>(
+    model: &mut M,
+    sampler: &mut S,
+    start: &M::State,
+    goal: &M::State,
+    constraints: &[Constraint<M::Place>],
+) -> Option<Trajectory<M>>
+where
+    M: SpaceTimeModel + HeuristicModel,
+    M::Cost: radix_heap::Radix + Copy + Weighted,
+    S: Sampler<M>,
+{
+    let mut constrained = Constrained { model, constraints };
+    match AStar::new().optimize(
+        &mut constrained,
+        start,
+        goal,
+        &mut ConstrainedSampler(sampler),
+    ) {
+        PathResult::Final(t) => Some(Trajectory {
+            cost: t.cost,
+            trajectory: t.trajectory,
+            expansions: t.expansions,
+        }),
+        _ => None,
+    }
+}
+
+/// The place of an agent at `index` steps after the start, where it stays at its goal after
+/// its trajectory ends
+fn place_at<M>(model: &M, trajectory: &Trajectory<M>, index: usize) -> M::Place
+where
+    M: SpaceTimeModel,
+{
+    let index = index.min(trajectory.trajectory.len() - 1);
+    model.place(&trajectory.trajectory[index].0)
+}
+
+/// The first time two agents meet, with the constraint which keeps each of them away
+fn first_conflict<M>(
+    model: &M,
+    trajectories: &[Trajectory<M>],
+    start_time: u32,
+) -> Option<[(usize, Constraint<M::Place>); 2]>
+where
+    M: SpaceTimeModel,
+{
+    let steps = trajectories.iter().map(|t| t.trajectory.len()).max().unwrap_or(0);
+    for index in 0..steps {
+        let time = start_time + index as u32;
+        for i in 0..trajectories.len() {
+            for j in i + 1..trajectories.len() {
+                let (a, b) = (
+                    place_at(model, &trajectories[i], index),
+                    place_at(model, &trajectories[j], index),
+                );
+                if a == b {
+                    return Some([
+                        (i, Constraint::Vertex(a, time)),
+                        (j, Constraint::Vertex(b, time)),
+                    ]);
+                }
+
+                let next_a = place_at(model, &trajectories[i], index + 1);
+                let next_b = place_at(model, &trajectories[j], index + 1);
+                if a == next_b && b == next_a {
+                    return Some([
+                        (i, Constraint::Edge(a, next_a, time)),
+                        (j, Constraint::Edge(b, next_b, time)),
+                    ]);
+                }
+            }
+        }
+    }
+
+    None
+}
+
+/// Plan trajectories for several agents which never run into each other, with Conflict-Based
+/// Search
+///
+/// Agent `i` travels from `starts[i]` to `goals[i]`, and stays on its goal once it gets
+/// there.  Two agents conflict when they are in the same place at the same time step, or when
+/// they swap places during the same step.  The search keeps a tree of constraints on the
+/// agents, and replans a single agent with space-time A* under its constraints each time a
+/// conflict is resolved, expanding the tree in order of the total cost of the trajectories.
+/// The total cost of the solution is the lowest possible when the model's heuristic is
+/// admissible.
+///
+/// Every agent starts at the time step of `starts[0]`, and every control advances time by a
+/// single step.  The tree grows exponentially with the number of conflicts, so the model
+/// should bound time, as the horizon of a [`SpaceTimeGrid`] does, for the search to give up
+/// when the agents cannot all reach their goals.
+///
+/// [`SpaceTimeGrid`]: ../spacetime/struct.SpaceTimeGrid.html
+///
+/// # Panics
+///
+/// When there is not a goal for every start.
+///
+/// ```
+/// # use game_lib::path::cbs::solve_mapf;
+/// # use game_lib::path::grid::{GridModel, GridSampler};
+/// # use game_lib::path::spacetime::{SpaceTimeGrid, TimedCell, WaitSampler};
+/// let mut model = SpaceTimeGrid::new(GridModel::new(7, 3), 30);
+/// // Two agents swapping ends of the grid
+/// let starts = [TimedCell::new(0, 1, 0), TimedCell::new(6, 1, 0)];
+/// let goals = [TimedCell::new(6, 1, 0), TimedCell::new(0, 1, 0)];
+/// let mut sampler = WaitSampler::new(GridSampler);
+/// assert_eq!(solve_mapf(&mut model, &starts, &goals, &mut sampler).unwrap().len(), 2);
+/// ```
+pub fn solve_mapf<M, S>(
+    model: &mut M,
+    starts: &[M::State],
+    goals: &[M::State],
+    sampler: &mut S,
+) -> Result<Vec<Trajectory<M>>, PathFindingErr>
+where
+    M: SpaceTimeModel + HeuristicModel,
+    M::Cost: radix_heap::Radix + Copy + Weighted,
+    S: Sampler<M>,
+{
+    assert_eq!(starts.len(), goals.len(), "every agent needs a start and a goal");
+    let start_time = match starts.first() {
+        Some(start) => model.time(start),
+        None => return Ok(Vec::new()),
+    };
+
+    let mut trajectories = Vec::with_capacity(starts.len());
+    for (start, goal) in starts.iter().zip(goals) {
+        trajectories
+            .push(plan(model, sampler, start, goal, &[]).ok_or(PathFindingErr::Unreachable)?);
+    }
+
+    // Nodes are kept until they are expanded, and ties go to the node which was made first
+    let total = |trajectories: &[Trajectory<M>]| {
+        trajectories.iter().fold(M::Cost::default(), |total, t| total + t.cost)
+    };
+    let mut queue = BinaryHeap::new();
+    queue.push((Reverse(total(&trajectories)), Reverse(0)));
+    let mut nodes =
+        vec![Some(Node { constraints: vec![Vec::new(); starts.len()], trajectories })];
+
+    while let Some((_, Reverse(id))) = queue.pop() {
+        let node = nodes[id].take().expect("every node is expanded once");
+        let conflict = match first_conflict(model, &node.trajectories, start_time) {
+            Some(conflict) => conflict,
+            None => return Ok(node.trajectories),
+        };
+
+        for (agent, constraint) in conflict.iter().cloned() {
+            let mut constraints = node.constraints.clone();
+            constraints[agent].push(constraint);
+
+            let replanned =
+                match plan(model, sampler, &starts[agent], &goals[agent], &constraints[agent])
+                {
+                    Some(trajectory) => trajectory,
+                    None => continue,
+                };
+            let mut trajectories: Vec<_> = node
+                .trajectories
+                .iter()
+                .map(|t| Trajectory {
+                    cost: t.cost,
+                    trajectory: t.trajectory.clone(),
+                    expansions: t.expansions,
+                })
+                .collect();
+            trajectories[agent] = replanned;
+
+            queue.push((Reverse(total(&trajectories)), Reverse(nodes.len())));
+            nodes.push(Some(Node { constraints, trajectories }));
+        }
+    }
+
+    Err(PathFindingErr::Unreachable)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::path::grid::{GridModel, GridSampler};
+    use crate::path::spacetime::{SpaceTimeGrid, TimedCell, WaitSampler};
+
+    #[test]
+    fn agents_swapping_ends_of_a_corridor_never_meet() {
+        // A corridor with a single passing place above its middle
+        let mut grid = GridModel::new(7, 3);
+        for x in 0..7 {
+            grid.set_blocked(x, 0, x != 3);
+            grid.set_blocked(x, 2, true);
+        }
+        let mut model = SpaceTimeGrid::new(grid, 30);
+
+        // The agents swap ends of the corridor
+        let starts = [TimedCell::new(0, 1, 0), TimedCell::new(6, 1, 0)];
+        let goals = [TimedCell::new(6, 1, 0), TimedCell::new(0, 1, 0)];
+        let mut sampler = WaitSampler::new(GridSampler);
+        let trajectories = solve_mapf(&mut model, &starts, &goals, &mut sampler).unwrap();
+
+        let at = |i: usize, t: usize| {
+            let trajectory = &trajectories[i].trajectory;
+            trajectory[t.min(trajectory.len() - 1)].0.cell
+        };
+        for (trajectory, goal) in trajectories.iter().zip(&goals) {
+            assert_eq!(trajectory.trajectory.last().unwrap().0.cell, goal.cell);
+        }
+        for t in 0..30 {
+            assert_ne!(at(0, t), at(1, t));
+            assert!(at(0, t) != at(1, t + 1) || at(1, t) != at(0, t + 1));
+        }
+    }
+}
@@ -0,0 +1,244 @@
+use std::f32::consts::SQRT_2;
+
+use super::bidirectional::ReversibleModel;
+use super::grid::{Cell, GridModel, GridSampler, Step};
+use super::*;
+
+/// The distance from every cell of a grid to the closest blocked cell
+///
+/// Distances are in cells, with a diagonal step counting as the square root of two, and the
+/// cells off the edge of the grid count as blocked.  They are found with a chamfer distance
+/// transform, which sweeps the grid twice, so they are the octile distances to the closest
+/// blocked cell: exact along the axes and the diagonals, and within a few percent of the
+/// distance in a straight line elsewhere.  Blocked cells are at a distance of zero.
+///
+/// ```
+/// # use game_lib::path::clearance::ClearanceField;
+/// # use game_lib::path::grid::GridModel;
+/// let mut grid = GridModel::new(9, 9);
+/// grid.set_blocked(4, 4, true);
+/// let field = ClearanceField::new(&grid);
+///
+/// assert_eq!(field.distance(4, 4), 0.0);
+/// assert_eq!(field.distance(4, 3), 1.0);
+/// assert_eq!(field.distance(3, 3), std::f32::consts::SQRT_2);
+/// // The edges of the map are just as much in the way
+/// assert_eq!(field.distance(0, 4), 1.0);
+/// assert_eq!(field.distance(-1, 4), 0.0);
+/// ```
+#[derive(Clone, Debug)]
+pub struct ClearanceField {
+    width: usize,
+    height: usize,
+    distances: Vec<f32>,
+}
+
+impl ClearanceField {
+    pub fn new(grid: &GridModel) -> Self {
+        let (width, height) = (grid.width(), grid.height());
+        let mut field = ClearanceField { width, height, distances: vec![0.0; width * height] };
+
+        for y in 0..height as i64 {
+            for x in 0..width as i64 {
+                if !grid.is_blocked(x, y) {
+                    field.distances[y as usize * width + x as usize] = f32::INFINITY;
+                }
+            }
+        }
+
+        // Each sweep carries the distances on from the neighbours it has already passed
+        let forward = [(-1, -1, SQRT_2), (0, -1, 1.0), (1, -1, SQRT_2), (-1, 0, 1.0)];
+        for y in 0..height as i64 {
+            for x in 0..width as i64 {
+                field.relax(x, y, &forward);
+            }
+        }
+        let backward = [(1, 1, SQRT_2), (0, 1, 1.0), (-1, 1, SQRT_2), (1, 0, 1.0)];
+        for y in (0..height as i64).rev() {
+            for x in (0..width as i64).rev() {
+                field.relax(x, y, &backward);
+            }
+        }
+
+        field
+    }
+
+    /// The distance from the cell at `(x, y)` to the closest blocked cell, which is zero for
+    /// every cell off the grid
+    pub fn distance(&self, x: i64, y: i64) -> f32 {
+        if x < 0 || y < 0 || x as u64 >= self.width as u64 || y as u64 >= self.height as u64 {
+            return 0.0;
+        }
+
+        self.distances[y as usize * self.width + x as usize]
+    }
+
+    fn relax(&mut self, x: i64, y: i64, neighbours: &[(i64, i64, f32)]) {
+        let closest = neighbours
+            .iter()
+            .map(|&(dx, dy, step)| self.distance(x + dx, y + dy) + step)
+            .fold(f32::INFINITY, f32::min);
+
+        let distance = &mut self.distances[y as usize * self.width + x as usize];
+        *distance = distance.min(closest);
+    }
+}
+
+/// A [`GridModel`] which charges extra for stepping close to blocked cells
+///
+/// Stepping into a cell closer than the inflation radius to a blocked cell adds a penalty on
+/// top of the cost of the step on the plain grid, which grows linearly from nothing at the
+/// radius up to the penalty weight times the radius next to the obstacle.  Trajectories then
+/// keep to the middle of corridors, and swing wide around corners, wherever the margin is
+/// worth the extra distance.  The heuristic is the plain grid's, which the penalties only
+/// ever add to, so it stays admissible.
+///
+/// The clearance is computed once, from the grid the model was made with.
+///
+/// ```
+/// # use game_lib::path::clearance::ClearanceModel;
+/// # use game_lib::path::grid::GridModel;
+/// let mut grid = GridModel::new(20, 20);
+/// grid.set_blocked(10, 10, true);
+/// // Penalties within three cells of the obstacle, growing up to four times three next to it
+/// let model = ClearanceModel::new(grid, 3.0, 4.0);
+/// assert!(model.penalty_at(10, 11) > model.penalty_at(10, 12));
+/// assert_eq!(model.penalty_at(10, 15), 0);
+/// ```
+#[derive(Clone, Debug)]
+pub struct ClearanceModel {
+    grid: GridModel,
+    field: ClearanceField,
+    inflation_radius: f32,
+    penalty: f32,
+}
+
+impl ClearanceModel {
+    /// Penalize the cells of `grid` closer than `inflation_radius` to a blocked cell, by up to
+    /// `penalty` for each cell of the radius they are inside of it
+    pub fn new(grid: GridModel, inflation_radius: f32, penalty: f32) -> Self {
+        let field = ClearanceField::new(&grid);
+        ClearanceModel { grid, field, inflation_radius, penalty }
+    }
+
+    pub fn grid(&self) -> &GridModel {
+        &self.grid
+    }
+
+    pub fn field(&self) -> &ClearanceField {
+        &self.field
+    }
+
+    pub fn into_inner(self) -> GridModel {
+        self.grid
+    }
+
+    pub fn inflation_radius(&self) -> f32 {
+        self.inflation_radius
+    }
+
+    pub fn set_inflation_radius(&mut self, radius: f32) {
+        self.inflation_radius = radius;
+    }
+
+    pub fn penalty(&self) -> f32 {
+        self.penalty
+    }
+
+    pub fn set_penalty(&mut self, penalty: f32) {
+        self.penalty = penalty;
+    }
+
+    /// The distance from the cell at `(x, y)` to the closest blocked cell
+    pub fn clearance(&self, x: i64, y: i64) -> f32 {
+        self.field.distance(x, y)
+    }
+
+    /// What stepping into the cell at `(x, y)` costs on top of the step itself
+    pub fn penalty_at(&self, x: i64, y: i64) -> usize {
+        let inside = self.inflation_radius - self.clearance(x, y);
+        if inside > 0.0 {
+            (self.penalty * inside).ceil() as usize
+        } else {
+            0
+        }
+    }
+}
+
+impl Model for ClearanceModel {
+    type State = Cell;
+    type Control = Step;
+    type Cost = usize;
+
+    fn cost(&self, current: &Cell, step: &Step, next: &Cell) -> usize {
+        self.grid.cost(current, step, next) + self.penalty_at(next.x, next.y)
+    }
+
+    /// Nothing to do on initialization
+    fn init(&mut self, _: &Cell) {}
+
+    fn converge(&self, current: &Cell, goal: &Cell) -> bool {
+        current == goal
+    }
+
+    fn integrate(&self, previous: &Cell, step: &Step) -> Option<Cell> {
+        self.grid.integrate(previous, step)
+    }
+
+    fn is_valid(&self, cell: &Cell) -> bool {
+        self.grid.is_valid(cell)
+    }
+}
+
+impl HeuristicModel for ClearanceModel {
+    fn heuristic(&self, current: &Cell, goal: &Cell) -> usize {
+        self.grid.heuristic(current, goal)
+    }
+}
+
+impl ReversibleModel for ClearanceModel {
+    fn integrate_reverse(&self, state: &Cell, step: &Step) -> Option<Cell> {
+        self.grid.integrate_reverse(state, step)
+    }
+}
+
+impl Sampler<ClearanceModel> for GridSampler {
+    #[inline]
+    fn sample(&mut self, model: &ClearanceModel, current: &Cell) -> &[Step] {
+        Sampler::<GridModel>::sample(self, &model.grid, current)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::path::astar::AStar;
+    use crate::path::testing::expect_final;
+
+    #[test]
+    fn trajectories_keep_away_from_the_corner_they_turn() {
+        // A wide corridor which turns a corner
+        let occupancy = (0..30 * 30).map(|i| {
+            let (x, y) = (i % 30, i / 30);
+            let across = (1..21).contains(&x) && (1..8).contains(&y);
+            let down = (14..21).contains(&x) && (1..28).contains(&y);
+            !across && !down
+        });
+        let mut grid = GridModel::with_occupancy(30, 30, occupancy.collect());
+        let (start, goal) = (Cell::new(2, 4), Cell::new(17, 26));
+
+        let mut model = ClearanceModel::new(grid.clone(), 3.0, 4.0);
+        let plain =
+            expect_final(AStar::new().optimize(&mut grid, &start, &goal, &mut GridSampler));
+        let clear =
+            expect_final(AStar::new().optimize(&mut model, &start, &goal, &mut GridSampler));
+
+        // The shortest path cuts the corner, keeping away from it is worth a few more steps
+        let clearance = |cells: Vec<&Cell>| {
+            let distances = cells.into_iter().map(|c| model.clearance(c.x, c.y));
+            distances.fold(f32::INFINITY, f32::min)
+        };
+        assert!(clearance(clear.states().collect()) > clearance(plain.states().collect()));
+        assert!(clear.len() >= plain.len());
+    }
+}
@@ -0,0 +1,156 @@
+use std::fmt::Debug;
+use std::hash::Hash;
+
+use super::*;
+
+/// The cell of a grid which a continuous position falls in
+///
+/// Each coordinate is divided by the size of the cells and rounded down, so that every
+/// position within the same cell gets the same key.
+///
+/// ```
+/// # use game_lib::path::continuous::QuantizedPosition;
+/// let a = QuantizedPosition::new([0.1, 2.4], 0.5);
+/// assert_eq!(a, QuantizedPosition([0, 4]));
+/// assert_eq!(a, QuantizedPosition::new([0.45, 2.05], 0.5));
+/// assert_ne!(a, QuantizedPosition::new([-0.1, 2.4], 0.5));
+/// assert_eq!(a.center(0.5), [0.25, 2.25]);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct QuantizedPosition<const N: usize>(pub [i64; N]);
+
+impl<const N: usize> QuantizedPosition<N> {
+    pub fn new(coordinates: [f64; N], cell_size: f64) -> Self {
+        QuantizedPosition(coordinates.map(|c| (c / cell_size).floor() as i64))
+    }
+
+    /// The middle of the cell
+    pub fn center(&self, cell_size: f64) -> [f64; N] {
+        self.0.map(|c| (c as f64 + 0.5) * cell_size)
+    }
+}
+
+/// Continuous coordinates which can be bucketed into the cells of a grid
+pub trait Coordinates {
+    type Quantized: Eq + Hash + Clone + Debug;
+
+    fn quantize(&self, cell_size: f64) -> Self::Quantized;
+
+    /// The coordinates a fraction `t` of the way from these towards `other`
+    fn lerp(&self, other: &Self, t: f64) -> Self;
+}
+
+impl<const N: usize> Coordinates for [f64; N] {
+    type Quantized = QuantizedPosition<N>;
+
+    fn quantize(&self, cell_size: f64) -> QuantizedPosition<N> {
+        QuantizedPosition::new(*self, cell_size)
+    }
+
+    fn lerp(&self, other: &Self, t: f64) -> Self {
+        let mut result = *self;
+        for (a, b) in result.iter_mut().zip(other) {
+            *a += (b - *a) * t;
+        }
+
+        result
+    }
+}
+
+/// States with continuous coordinates, whose positions are the cells they fall in
+///
+/// Floating point coordinates cannot be hashed, and two states a rounding error apart would
+/// never be the same position anyway, so a search over them keeps finding new states and
+/// never runs out of them.  Every type of this trait is a [`State`] whose position is the
+/// [`QuantizedPosition`] of its coordinates, and the optimizers treat all of the states in one
+/// cell as the same state: only the first to be reached is expanded.
+///
+/// The size of the cells trades precision for time.  Small cells keep states which are
+/// genuinely different apart, and the trajectories found approach the continuous optimum, but
+/// the search expands many more of them.  Large cells make the search quick, but the state
+/// which is kept for a cell stands in for every other one in it, so a cheaper trajectory
+/// through another point of the cell can be missed, and the result is only optimal among the
+/// states which were kept.  A cell size around the distance a single control moves is a
+/// reasonable start.  The cell size must be positive, and the same for every state.
+///
+/// ```
+/// # use game_lib::path::continuous::ContinuousState;
+/// # use game_lib::path::State;
+/// /// A point in the plane
+/// #[derive(Clone, Debug)]
+/// struct Point([f64; 2]);
+///
+/// impl ContinuousState for Point {
+///     type Coordinates = [f64; 2];
+///
+///     fn coordinates(&self) -> [f64; 2] {
+///         self.0
+///     }
+///
+///     fn cell_size(&self) -> f64 {
+///         0.5
+///     }
+///
+///     fn with_coordinates(&self, coordinates: [f64; 2]) -> Self {
+///         Point(coordinates)
+///     }
+/// }
+///
+/// // Points close together share a position
+/// assert_eq!(Point([1.1, 2.2]).grid_position(), Point([1.3, 2.4]).grid_position());
+/// assert_ne!(Point([1.1, 2.2]).grid_position(), Point([1.6, 2.2]).grid_position());
+/// ```
+pub trait ContinuousState {
+    type Coordinates: Coordinates;
+
+    fn coordinates(&self) -> Self::Coordinates;
+
+    /// The width of the cells the coordinates are bucketed into
+    fn cell_size(&self) -> f64;
+
+    /// This state moved to `coordinates`, with anything else about it kept
+    fn with_coordinates(&self, coordinates: Self::Coordinates) -> Self;
+}
+
+impl<S> State for S
+where
+    S: ContinuousState,
+{
+    type Position = <S::Coordinates as Coordinates>::Quantized;
+
+    fn grid_position(&self) -> Self::Position {
+        self.coordinates().quantize(self.cell_size())
+    }
+
+    fn interpolate(&self, other: &Self, t: f64) -> Self {
+        self.with_coordinates(self.coordinates().lerp(&other.coordinates(), t))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::path::astar::AStar;
+    use crate::path::testing::{expect_final, Headings, Plane, Spot};
+
+    #[test]
+    fn searches_of_the_plane_reach_a_goal_inside_it() {
+        let mut model = Plane { size: 10.0, reach: 1.0 };
+        let (start, goal) = (Spot([1.0, 1.0]), Spot([8.0, 6.0]));
+        let t = expect_final(AStar::new().optimize(&mut model, &start, &goal, &mut Headings));
+        assert!(t.len() > 5);
+    }
+
+    #[test]
+    fn searches_of_the_plane_run_out_of_cells() {
+        // Running out of cells to visit is what ends the search for a goal outside the square
+        let mut model = Plane { size: 10.0, reach: 1.0 };
+        let mut astar = AStar::new();
+        match astar.optimize(&mut model, &Spot([1.0, 1.0]), &Spot([30.0, 30.0]), &mut Headings)
+        {
+            PathResult::Err(PathFindingErr::Unreachable) => {}
+            _ => panic!("the goal is outside the square"),
+        }
+        assert!(astar.stats().expanded <= 20 * 20);
+    }
+}
@@ -0,0 +1,180 @@
+use super::astar::PriorityQueue;
+
+/// Priority queue kept as a heap in which every item has up to `D` children, greatest first
+///
+/// A wider heap is shallower, so pushing an item, which sifts it up towards the root, compares
+/// it with fewer parents than in a binary heap.  Popping compares more children at each level
+/// but visits fewer levels, and the children sit next to each other in memory.  A* pushes
+/// far more nodes than it ever pops, which is where a 4-ary or 8-ary heap tends to beat the
+/// standard `BinaryHeap`; use one as the open list of [`AStar::with_queue`].
+///
+/// [`AStar::with_queue`]: ../astar/struct.AStar.html#method.with_queue
+///
+/// ```
+/// # use game_lib::path::astar::PriorityQueue;
+/// # use game_lib::path::dary::DaryHeap;
+/// let mut heap: DaryHeap<_, 4> = DaryHeap::new();
+/// heap.push(3);
+/// heap.push(7);
+/// assert_eq!(heap.pop(), Some(7));
+/// ```
+#[derive(Clone, Debug)]
+pub struct DaryHeap<T, const D: usize> {
+    items: Vec<T>,
+}
+
+impl<T, const D: usize> DaryHeap<T, D>
+where
+    T: Ord,
+{
+    /// # Panics
+    ///
+    /// When `D` is less than two, as every item needs room for more than one child.
+    pub fn new() -> Self {
+        Self::with_capacity(0)
+    }
+
+    /// Create an empty heap with room for at least `capacity` items
+    ///
+    /// # Panics
+    ///
+    /// When `D` is less than two.
+    pub fn with_capacity(capacity: usize) -> Self {
+        assert!(D >= 2, "a d-ary heap needs at least two children for each item");
+        DaryHeap { items: Vec::with_capacity(capacity) }
+    }
+
+    /// Move the item at `index` up until its parent is at least as great
+    fn sift_up(&mut self, mut index: usize) {
+        while index > 0 {
+            let parent = (index - 1) / D;
+            if self.items[index] <= self.items[parent] {
+                break;
+            }
+            self.items.swap(index, parent);
+            index = parent;
+        }
+    }
+
+    /// Move the item at `index` down until none of its children are greater
+    fn sift_down(&mut self, mut index: usize) {
+        loop {
+            let first = index * D + 1;
+            if first >= self.items.len() {
+                break;
+            }
+
+            let children = first..(first + D).min(self.items.len());
+            let greatest = children
+                .reduce(|a, b| if self.items[b] > self.items[a] { b } else { a })
+                .expect("there is at least one child");
+            if self.items[greatest] <= self.items[index] {
+                break;
+            }
+            self.items.swap(index, greatest);
+            index = greatest;
+        }
+    }
+}
+
+impl<T, const D: usize> PriorityQueue<T> for DaryHeap<T, D>
+where
+    T: Ord,
+{
+    fn push(&mut self, item: T) {
+        self.items.push(item);
+        self.sift_up(self.items.len() - 1);
+    }
+
+    fn pop(&mut self) -> Option<T> {
+        if self.items.is_empty() {
+            return None;
+        }
+
+        let greatest = self.items.swap_remove(0);
+        self.sift_down(0);
+        Some(greatest)
+    }
+
+    #[inline]
+    fn peek(&self) -> Option<&T> {
+        self.items.first()
+    }
+
+    fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    fn clear(&mut self) {
+        self.items.clear()
+    }
+
+    fn iter(&self) -> Box<dyn Iterator<Item = &T> + '_> {
+        Box::new(self.items.iter())
+    }
+
+    fn reserve(&mut self, additional: usize) {
+        self.items.reserve(additional)
+    }
+
+    fn shrink_to_fit(&mut self) {
+        self.items.shrink_to_fit()
+    }
+
+    fn capacity(&self) -> usize {
+        self.items.capacity()
+    }
+}
+
+impl<T, const D: usize> Default for DaryHeap<T, D>
+where
+    T: Ord,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::actor::{TurnOptimal, WalkSampler};
+    use crate::path::astar::AStar;
+    use crate::path::testing::{assert_same_trajectory, divided_room, expect_final, walker};
+    use crate::path::Optimizer;
+    use std::collections::BinaryHeap;
+
+    #[test]
+    fn pops_in_the_same_order_as_a_binary_heap() {
+        let mut heap: DaryHeap<_, 4> = DaryHeap::new();
+        let mut binary = BinaryHeap::new();
+        for i in 0..200u32 {
+            let item = i.wrapping_mul(2_654_435_761) % 97;
+            heap.push(item);
+            binary.push(item);
+        }
+
+        assert_eq!(heap.peek(), binary.peek());
+        while let Some(item) = binary.pop() {
+            assert_eq!(heap.pop(), Some(item));
+        }
+        assert!(heap.is_empty());
+    }
+
+    #[test]
+    fn finds_the_same_trajectory_as_a_binary_heap() {
+        let mut model = TurnOptimal::new(divided_room());
+        let (start, goal) = (walker(3, 10), walker(17, 9));
+        let mut sampler = WalkSampler::new();
+        let mut binary = AStar::new();
+        let mut quaternary = AStar::with_queue(DaryHeap::<_, 4>::new());
+
+        let a = expect_final(binary.optimize(&mut model, &start, &goal, &mut sampler));
+        let b = expect_final(quaternary.optimize(&mut model, &start, &goal, &mut sampler));
+        assert_same_trajectory(&a, &b);
+        for ((_, x), (_, y)) in a.trajectory.iter().zip(&b.trajectory) {
+            assert_eq!(x, y);
+        }
+        assert_eq!(binary.stats(), quaternary.stats());
+    }
+}
@@ -1,12 +1,34 @@
-use super::{Model, Optimizer, PathFindingErr, PathResult, Sampler, State, Trajectory};
+use super::bidirectional::ReversibleModel;
+use super::{
+    invalid_endpoint, Model, Optimizer, PathFindingErr, PathResult, Sampler, State, Trajectory,
+};
 use fnv::FnvHashMap;
 use radix_heap::{Radix, RadixHeapMap};
 
 use std::cmp::{PartialEq, Reverse};
 use std::collections::hash_map::Entry;
+use std::collections::HashMap;
 use std::fmt::{self, Debug, Formatter};
 use std::hash::{self, Hash};
 
+/// Uniform-cost search which orders nodes by their cost-to-come alone
+///
+/// Since no heuristic is consulted, the model only has to implement [`Model`], and the
+/// returned trajectory is always optimal.  It finds the same cost as [`AStar`] does with an
+/// admissible heuristic, at the price of expanding more nodes:
+///
+/// ```
+/// # use game_lib::path::grid::{Cell, GridModel, GridSampler};
+/// # use game_lib::path::{astar::AStar, dijkstra::Dijkstra, Optimizer};
+/// let mut grid = GridModel::new(16, 16);
+/// let (start, goal) = (Cell::new(2, 3), Cell::new(13, 2));
+/// let dijkstra = Dijkstra::new().optimize(&mut grid, &start, &goal, &mut GridSampler);
+/// let astar = AStar::new().optimize(&mut grid, &start, &goal, &mut GridSampler);
+/// assert_eq!(dijkstra.trajectory().map(|t| t.cost), astar.trajectory().map(|t| t.cost));
+/// ```
+///
+/// [`Model`]: ../trait.Model.html
+/// [`AStar`]: ../astar/struct.AStar.html
 pub struct Dijkstra<M>
 where
     M: Model,
@@ -15,7 +37,12 @@ where
     queue: RadixHeapMap<Reverse<M::Cost>, Node<M>>,
     grid: FnvHashMap<<<M as Model>::State as State>::Position, Id<M>>,
     parent_map: FnvHashMap<Id<M>, Node<M>>,
+    /// The node each position was expanded with by `expand_to`, which holds the control
+    /// leading from it towards the goal
+    settled: FnvHashMap<<<M as Model>::State as State>::Position, Node<M>>,
     id_counter: usize,
+    /// Whether the start was queued, so that an empty queue means the search has finished
+    seeded: bool,
 }
 
 impl<M> Default for Dijkstra<M>
@@ -24,12 +51,7 @@ where
     M::Cost: Radix + Copy,
 {
     fn default() -> Self {
-        Dijkstra {
-            queue: Default::default(),
-            grid: Default::default(),
-            parent_map: Default::default(),
-            id_counter: 0,
-        }
+        Self::new()
     }
 }
 
@@ -38,10 +60,24 @@ where
     M: Model,
     M::Cost: Radix + Copy,
 {
+    /// Create a new Dijkstra optimizer
+    pub fn new() -> Self {
+        Dijkstra {
+            queue: Default::default(),
+            grid: Default::default(),
+            parent_map: Default::default(),
+            settled: Default::default(),
+            id_counter: 0,
+            seeded: false,
+        }
+    }
+
     pub fn clear(&mut self) {
         self.queue.clear();
         self.parent_map.clear();
         self.grid.clear();
+        self.settled.clear();
+        self.seeded = false;
     }
 
     pub fn inspect_queue(&self) -> impl Iterator<Item = (&M::State, &M::Control)> {
@@ -105,7 +141,22 @@ where
         false
     }
 
+    /// Queue the start node to begin a new search
+    fn seed(&mut self, start: &M::State) {
+        self.seeded = true;
+        let start_id = Id::new(0, Default::default());
+        self.queue.push(
+            Default::default(),
+            Node { id: start_id, state: start.clone(), control: Default::default() },
+        );
+    }
+
+    /// Follow the parents from the goal node up to the start node
+    ///
+    /// The cost of the trajectory is the cost-to-come of the last node, so it does not need to
+    /// be accumulated again.
     fn unwind_trajectory(&self, mut current: Node<M>) -> Trajectory<M> {
+        let cost = current.id.g.0;
         let mut result = Vec::new();
         result.push((current.state.clone(), current.control.clone()));
 
@@ -114,7 +165,105 @@ where
             result.push((current.state.clone(), current.control.clone()));
         }
 
-        Trajectory { cost: current.id.g.0, trajectory: result }
+        result.reverse();
+
+        Trajectory { cost, trajectory: result, expansions: 0 }
+    }
+}
+
+impl<M> Dijkstra<M>
+where
+    M: ReversibleModel,
+    M::Cost: Radix + Copy,
+{
+    /// Expand every state from which the `goal` can be reached, searching backwards from it
+    ///
+    /// The search starts from every one of the model's `goal_states` and follows each
+    /// control in reverse, until nothing is left to expand.  Any previous search is cleared,
+    /// and so should this one be before planning forwards again.  Afterwards every discovered
+    /// position knows the cheapest way to the goal, which [`flow_field`](#method.flow_field)
+    /// collects.
+    pub fn expand_to<S>(&mut self, model: &M, goal: &M::State, sampler: &mut S)
+    where
+        S: Sampler<M>,
+    {
+        self.clear();
+        for state in model.goal_states(goal) {
+            self.id_counter += 1;
+            let id = Id::new(self.id_counter, Default::default());
+            self.grid.insert(state.grid_position(), id.clone());
+            self.queue.push(id.g, Node { id, state, control: Default::default() });
+        }
+
+        while let Some((_, current)) = self.queue.pop() {
+            let position = current.state.grid_position();
+            if self.grid.get(&position).is_some_and(|best| *best != current.id) {
+                continue;
+            }
+
+            for control in sampler.sample(model, &current.state) {
+                if let Some(previous) = model.integrate_reverse(&current.state, control) {
+                    let cost = current.id.g.0 + model.cost(&previous, control, &current.state);
+
+                    self.id_counter += 1;
+                    let child = Node::<M> {
+                        id: Id::new(self.id_counter, cost),
+                        state: previous,
+                        control: control.clone(),
+                    };
+
+                    match self.grid.entry(child.state.grid_position()) {
+                        Entry::Occupied(mut best) => {
+                            let best = best.get_mut();
+                            if best.g.0 <= child.id.g.0 {
+                                continue;
+                            } else {
+                                *best = child.id.clone();
+                            }
+                        }
+                        Entry::Vacant(empty) => {
+                            empty.insert(child.id.clone());
+                        }
+                    }
+
+                    self.parent_map.insert(child.id.clone(), current.clone());
+                    self.queue.push(child.id.g, child);
+                }
+            }
+
+            self.settled.insert(position, current);
+        }
+    }
+
+    /// The control which leads each position discovered by [`expand_to`] towards the goal,
+    /// along the cheapest trajectory from it
+    ///
+    /// Following the field from any of its positions is the same as following an optimal
+    /// trajectory to the goal, so a single search serves every agent heading for the same
+    /// goal, wherever each of them starts.  The goal's own positions are left out, as there
+    /// is nowhere left to go, and so are controls which the model no longer allows, as when
+    /// the map has changed since the search.
+    ///
+    /// [`expand_to`]: #method.expand_to
+    ///
+    /// ```
+    /// # use game_lib::path::dijkstra::Dijkstra;
+    /// # use game_lib::path::grid::{Cell, GridModel, GridSampler, Step};
+    /// let (grid, goal) = (GridModel::new(12, 12), Cell::new(9, 2));
+    /// let mut dijkstra = Dijkstra::new();
+    /// dijkstra.expand_to(&grid, &goal, &mut GridSampler);
+    /// assert_eq!(dijkstra.flow_field(&grid)[&Cell::new(7, 2)], Step { dx: 1, dy: 0 });
+    /// ```
+    pub fn flow_field(
+        &self,
+        model: &M,
+    ) -> HashMap<<<M as Model>::State as State>::Position, M::Control> {
+        self.settled
+            .iter()
+            .filter(|(_, node)| self.parent_map.contains_key(&node.id))
+            .filter(|(_, node)| model.integrate(&node.state, &node.control).is_some())
+            .map(|(position, node)| (position.clone(), node.control.clone()))
+            .collect()
     }
 }
 
@@ -134,19 +283,20 @@ where
         use PathFindingErr::*;
         use PathResult::*;
 
+        if let Some(invalid) = invalid_endpoint(model, start, goal) {
+            return Err(invalid);
+        }
+
         if model.converge(start, goal) {
             return Final(Trajectory {
                 cost: Default::default(),
                 trajectory: vec![(start.clone(), Default::default())],
+                expansions: 0,
             });
         }
 
-        if self.queue.top().is_none() {
-            let start_id = Id::new(0, Default::default());
-            self.queue.push(
-                Default::default(),
-                Node { id: start_id, state: start.clone(), control: Default::default() },
-            );
+        if !self.seeded {
+            self.seed(start);
         }
 
         while let Some((_, current)) = self.queue.pop() {
@@ -158,6 +308,11 @@ where
         Err(Unreachable)
     }
 
+    /// Expand the next node in the queue
+    ///
+    /// The start is queued by the first call only, so once the queue runs out the search has
+    /// finished and every later call stops with `PathFindingErr::Unreachable` as well, rather
+    /// than beginning the search again.
     fn next_trajectory(
         &mut self,
         model: &mut M,
@@ -168,12 +323,12 @@ where
         use PathFindingErr::*;
         use PathResult::*;
 
-        if self.parent_map.is_empty() && self.queue.is_empty() {
-            let start_id = Id::new(0, Default::default());
-            self.queue.push(
-                Default::default(),
-                Node { id: start_id, state: start.clone(), control: Default::default() },
-            );
+        if let Some(invalid) = invalid_endpoint(model, start, goal) {
+            return Err(invalid);
+        }
+
+        if !self.seeded {
+            self.seed(start);
         }
 
         if let Some((_, current)) = self.queue.pop() {
@@ -277,3 +432,79 @@ where
             .finish()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::actor::{Heuristic, TurnOptimal, WalkSampler};
+    use crate::map::Tile;
+    use crate::path::astar::AStar;
+    use crate::path::grid::{Cell, GridModel, GridSampler};
+    use crate::path::testing::*;
+
+    #[test]
+    fn finds_the_same_cost_as_astar() {
+        let mut map = room(16, 16);
+        for y in 1..12 {
+            map[(7, y)] = Tile::WALL;
+        }
+        let mut model = TurnOptimal::new(map);
+        model.set_heuristic(Heuristic::Chebyshev);
+        let (start, goal) = (walker(2, 3), walker(13, 2));
+        let mut sampler = WalkSampler::new();
+        let d =
+            expect_final(Dijkstra::new().optimize(&mut model, &start, &goal, &mut sampler));
+        let a = expect_final(AStar::new().optimize(&mut model, &start, &goal, &mut sampler));
+        assert_eq!(d.cost, a.cost);
+        assert_walkable(&d, &start);
+    }
+
+    #[test]
+    fn following_the_flow_field_takes_an_optimal_trajectory() {
+        let mut grid = GridModel::new(12, 12);
+        // A wall with a gap at the bottom, between the goal and most of the grid
+        for y in 0..10 {
+            grid.set_blocked(6, y, true);
+        }
+        let goal = Cell::new(9, 2);
+
+        let mut dijkstra = Dijkstra::new();
+        dijkstra.expand_to(&grid, &goal, &mut GridSampler);
+        let field = dijkstra.flow_field(&grid);
+        assert_eq!(field.len(), 12 * 12 - 10 - 1);
+
+        for &start in &[Cell::new(1, 1), Cell::new(3, 8), Cell::new(11, 11), Cell::new(5, 0)] {
+            let (mut cell, mut cost) = (start, 0);
+            while cell != goal {
+                let step = field[&cell];
+                let next = grid.integrate(&cell, &step).unwrap();
+                cost += grid.cost(&cell, &step, &next);
+                cell = next;
+            }
+            // Every agent following the field takes an optimal trajectory to the goal
+            let t = expect_final(AStar::new().optimize(
+                &mut grid,
+                &start,
+                &goal,
+                &mut GridSampler,
+            ));
+            assert_eq!(cost, t.cost);
+        }
+    }
+
+    #[test]
+    fn a_finished_search_stays_unreachable() {
+        let (mut grid, start, goal) = (shut_in(), Cell::new(2, 2), Cell::new(4, 4));
+        let mut dijkstra = Dijkstra::new();
+        match dijkstra.next_trajectory(&mut grid, &start, &goal, &mut GridSampler) {
+            PathResult::Intermediate(t) => assert_eq!(t.trajectory.len(), 1),
+            _ => panic!("the start is expanded first"),
+        }
+        for _ in 0..3 {
+            match dijkstra.next_trajectory(&mut grid, &start, &goal, &mut GridSampler) {
+                PathResult::Err(PathFindingErr::Unreachable) => {}
+                _ => panic!("the search has finished"),
+            }
+        }
+    }
+}
@@ -0,0 +1,581 @@
+use std::cmp::{Ordering, Reverse};
+use std::fmt::{Debug, Formatter};
+
+use fnv::FnvHashMap;
+use std::collections::BinaryHeap;
+
+use super::bidirectional::ReversibleModel;
+use super::*;
+
+/// A cost which may be infinite, for positions which cannot reach the goal
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Dist<C> {
+    Finite(C),
+    Infinite,
+}
+
+impl<C: Cost + Copy> Dist<C> {
+    fn plus(self, other: Dist<C>) -> Dist<C> {
+        match (self, other) {
+            (Dist::Finite(a), Dist::Finite(b)) => Dist::Finite(a + b),
+            _ => Dist::Infinite,
+        }
+    }
+}
+
+type Key<C> = (Dist<C>, Dist<C>);
+
+/// A position in the queue, ordered by its key and then by when it was queued
+struct Entry<C, P> {
+    key: Key<C>,
+    id: usize,
+    position: P,
+}
+
+impl<C: Ord, P> PartialEq for Entry<C, P> {
+    fn eq(&self, other: &Self) -> bool {
+        self.key == other.key && self.id == other.id
+    }
+}
+
+impl<C: Ord, P> Eq for Entry<C, P> {}
+
+impl<C: Ord, P> PartialOrd for Entry<C, P> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<C: Ord, P> Ord for Entry<C, P> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key.cmp(&other.key).then(self.id.cmp(&other.id))
+    }
+}
+
+type PositionOf<M> = <<M as Model>::State as State>::Position;
+
+/// A successor along with the control and the cost of reaching it
+type Successor<M> = (<M as Model>::State, <M as Model>::Control, Dist<<M as Model>::Cost>);
+
+/// D* Lite, which repairs its trajectory when the cost of moving around the map changes
+///
+/// The search runs backwards from the [`goal_states`] towards the start, keeping for every
+/// position both its cost to the goal and a one-step lookahead of that cost.  After costs are
+/// changed with [`update_edge`], only the positions whose costs to the goal depend on the
+/// change are expanded again by [`replan`], so when the map changes near the actor replanning
+/// takes a fraction of the work of a new search.  The start may move between replans.
+///
+/// Step costs come from the model unless they are overridden with `update_edge`.  The
+/// predecessors of a state are found with [`ReversibleModel::integrate_reverse`], using the
+/// controls the sampler offers for that state.
+///
+/// ```
+/// # use game_lib::path::grid::{Cell, GridModel, GridSampler};
+/// # use game_lib::path::{dstar::DStarLite, Optimizer};
+/// let (mut dstar, mut grid) = (DStarLite::new(), GridModel::new(40, 30));
+/// let (start, goal) = (Cell::new(5, 5), Cell::new(35, 5));
+/// dstar.optimize(&mut grid, &start, &goal, &mut GridSampler);
+/// // Something turns up in the way
+/// dstar.update_edge(Cell::new(6, 5), None);
+/// assert!(dstar.replan(&mut grid, &start, &mut GridSampler).is_final());
+/// ```
+///
+/// [`goal_states`]: ../bidirectional/trait.ReversibleModel.html#method.goal_states
+/// [`replan`]: #method.replan
+/// [`update_edge`]: #method.update_edge
+/// [`ReversibleModel::integrate_reverse`]: ../bidirectional/trait.ReversibleModel.html#tymethod.integrate_reverse
+pub struct DStarLite<M>
+where
+    M: ReversibleModel,
+    M::Cost: Copy,
+{
+    goal: Option<M::State>,
+    /// The start of the last replan, which the keys in the queue are relative to
+    last_start: Option<M::State>,
+    /// How much the heuristic of the queued keys has shrunk as the start moved
+    km: M::Cost,
+    states: FnvHashMap<PositionOf<M>, M::State>,
+    g: FnvHashMap<PositionOf<M>, M::Cost>,
+    rhs: FnvHashMap<PositionOf<M>, M::Cost>,
+    queue: BinaryHeap<Reverse<Entry<M::Cost, PositionOf<M>>>>,
+    /// The id of the current queue entry of every queued position
+    queued: FnvHashMap<PositionOf<M>, usize>,
+    overrides: FnvHashMap<PositionOf<M>, Option<M::Cost>>,
+    changed: Vec<PositionOf<M>>,
+    id_counter: usize,
+    stats: Stats,
+}
+
+impl<M> DStarLite<M>
+where
+    M: ReversibleModel,
+    M::Cost: Copy,
+{
+    /// Create a new D* Lite optimizer
+    pub fn new() -> Self {
+        DStarLite {
+            goal: None,
+            last_start: None,
+            km: Default::default(),
+            states: FnvHashMap::default(),
+            g: FnvHashMap::default(),
+            rhs: FnvHashMap::default(),
+            queue: BinaryHeap::new(),
+            queued: FnvHashMap::default(),
+            overrides: FnvHashMap::default(),
+            changed: Vec::new(),
+            id_counter: 0,
+            stats: Stats::default(),
+        }
+    }
+
+    /// Forget the goal, the costs and the overrides
+    pub fn clear(&mut self) {
+        self.goal = None;
+        self.last_start = None;
+        self.km = Default::default();
+        self.states.clear();
+        self.g.clear();
+        self.rhs.clear();
+        self.queue.clear();
+        self.queued.clear();
+        self.overrides.clear();
+        self.changed.clear();
+        self.stats = Stats::default();
+    }
+
+    /// Counters describing the work done since the optimizer was created or last cleared
+    pub fn stats(&self) -> &Stats {
+        &self.stats
+    }
+
+    pub fn inspect_discovered(&self) -> impl Iterator<Item = &PositionOf<M>> {
+        self.states.keys()
+    }
+
+    /// Change the cost of every step into `position`, or block it with `None`
+    ///
+    /// The change takes effect on the next call to [`replan`](#method.replan).
+    pub fn update_edge(&mut self, position: PositionOf<M>, cost: Option<M::Cost>) {
+        self.overrides.insert(position.clone(), cost);
+        self.changed.push(position);
+    }
+
+    /// Repair the costs affected by any changes and the movement of the start, then follow
+    /// them from `start` to the goal of the last call to `optimize`
+    ///
+    /// Returns `Unreachable` if nothing has been planned yet.
+    pub fn replan<S>(
+        &mut self,
+        model: &mut M,
+        start: &M::State,
+        sampler: &mut S,
+    ) -> PathResult<M>
+    where
+        S: Sampler<M>,
+    {
+        use PathFindingErr::*;
+        use PathResult::*;
+
+        let goal = match &self.goal {
+            Some(goal) => goal.clone(),
+            None => return Err(Unreachable),
+        };
+
+        if let Some(last) = self.last_start.replace(start.clone()) {
+            self.km = self.km + model.heuristic(&last, start);
+        }
+        self.states.entry(start.grid_position()).or_insert_with(|| start.clone());
+
+        for position in std::mem::take(&mut self.changed) {
+            if let Some(state) = self.states.get(&position).cloned() {
+                for (previous, _) in self.predecessors(model, sampler, &state) {
+                    self.update_vertex(model, sampler, start, &goal, &previous);
+                }
+            }
+        }
+
+        self.compute(model, sampler, start, &goal);
+        self.extract(model, sampler, start, &goal)
+    }
+
+    fn g(&self, position: &PositionOf<M>) -> Dist<M::Cost> {
+        self.g.get(position).map_or(Dist::Infinite, |&g| Dist::Finite(g))
+    }
+
+    fn rhs(&self, position: &PositionOf<M>) -> Dist<M::Cost> {
+        self.rhs.get(position).map_or(Dist::Infinite, |&rhs| Dist::Finite(rhs))
+    }
+
+    fn set(
+        map: &mut FnvHashMap<PositionOf<M>, M::Cost>,
+        position: PositionOf<M>,
+        value: Dist<M::Cost>,
+    ) {
+        match value {
+            Dist::Finite(value) => map.insert(position, value),
+            Dist::Infinite => map.remove(&position),
+        };
+    }
+
+    /// The cost of a step, unless it has been overridden
+    fn step_cost(
+        &self,
+        model: &M,
+        from: &M::State,
+        control: &M::Control,
+        to: &M::State,
+    ) -> Dist<M::Cost> {
+        match self.overrides.get(&to.grid_position()) {
+            Some(Some(cost)) => Dist::Finite(*cost),
+            Some(None) => Dist::Infinite,
+            None => Dist::Finite(model.cost(from, control, to)),
+        }
+    }
+
+    /// The states reachable from `state`, with the control and the cost of reaching them
+    fn successors<S>(
+        &mut self,
+        model: &M,
+        sampler: &mut S,
+        state: &M::State,
+    ) -> Vec<Successor<M>>
+    where
+        S: Sampler<M>,
+    {
+        let controls = sampler.sample(model, state);
+        let mut successors = Vec::with_capacity(controls.len());
+        for control in controls {
+            if let Some(next) = model.integrate(state, control) {
+                self.stats.generated += 1;
+                self.states.entry(next.grid_position()).or_insert_with(|| next.clone());
+                let cost = self.step_cost(model, state, control, &next);
+                successors.push((next, control.clone(), cost));
+            }
+        }
+        successors
+    }
+
+    /// The states which reach `state` in one step, with the cost of that step
+    fn predecessors<S>(
+        &mut self,
+        model: &M,
+        sampler: &mut S,
+        state: &M::State,
+    ) -> Vec<(M::State, Dist<M::Cost>)>
+    where
+        S: Sampler<M>,
+    {
+        let controls = sampler.sample(model, state);
+        let mut predecessors = Vec::with_capacity(controls.len());
+        for control in controls {
+            if let Some(previous) = model.integrate_reverse(state, control) {
+                self.stats.generated += 1;
+                self.states
+                    .entry(previous.grid_position())
+                    .or_insert_with(|| previous.clone());
+                let cost = self.step_cost(model, &previous, control, state);
+                predecessors.push((previous, cost));
+            }
+        }
+        predecessors
+    }
+
+    fn key(&self, model: &M, start: &M::State, state: &M::State) -> Key<M::Cost> {
+        let position = state.grid_position();
+        match self.g(&position).min(self.rhs(&position)) {
+            Dist::Finite(best) => {
+                let estimate = best + model.heuristic(start, state) + self.km;
+                (Dist::Finite(estimate), Dist::Finite(best))
+            }
+            Dist::Infinite => (Dist::Infinite, Dist::Infinite),
+        }
+    }
+
+    fn push(&mut self, position: PositionOf<M>, key: Key<M::Cost>) {
+        self.id_counter += 1;
+        self.queued.insert(position.clone(), self.id_counter);
+        self.queue.push(Reverse(Entry { key, id: self.id_counter, position }));
+        self.stats.max_queue_len = self.stats.max_queue_len.max(self.queued.len());
+    }
+
+    /// Discard queue entries which have been superseded or removed
+    fn discard_stale(&mut self) {
+        while let Some(Reverse(top)) = self.queue.peek() {
+            if self.queued.get(&top.position) == Some(&top.id) {
+                return;
+            }
+            self.queue.pop();
+        }
+    }
+
+    /// Recalculate the lookahead of a state and queue it if it is inconsistent
+    fn update_vertex<S>(
+        &mut self,
+        model: &M,
+        sampler: &mut S,
+        start: &M::State,
+        goal: &M::State,
+        state: &M::State,
+    ) where
+        S: Sampler<M>,
+    {
+        let position = state.grid_position();
+        if !model.converge(state, goal) {
+            let mut best = Dist::Infinite;
+            for (next, _, cost) in self.successors(model, sampler, state) {
+                best = best.min(cost.plus(self.g(&next.grid_position())));
+            }
+            Self::set(&mut self.rhs, position.clone(), best);
+        }
+
+        self.queued.remove(&position);
+        if self.g(&position) != self.rhs(&position) {
+            let key = self.key(model, start, state);
+            self.push(position, key);
+        }
+    }
+
+    /// Expand inconsistent states until the cost from the start is known
+    fn compute<S>(&mut self, model: &M, sampler: &mut S, start: &M::State, goal: &M::State)
+    where
+        S: Sampler<M>,
+    {
+        let start_position = start.grid_position();
+
+        loop {
+            self.discard_stale();
+            let top = match self.queue.peek() {
+                Some(Reverse(top)) => (top.key, top.position.clone()),
+                None => return,
+            };
+
+            let consistent = self.g(&start_position) == self.rhs(&start_position);
+            if top.0 >= self.key(model, start, start) && consistent {
+                return;
+            }
+
+            self.queue.pop();
+            self.queued.remove(&top.1);
+            let state = match self.states.get(&top.1) {
+                Some(state) => state.clone(),
+                None => continue,
+            };
+
+            let key = self.key(model, start, &state);
+            if top.0 < key {
+                self.push(top.1, key);
+                continue;
+            }
+            self.stats.expanded += 1;
+
+            let position = top.1;
+            if self.g(&position) > self.rhs(&position) {
+                let rhs = self.rhs(&position);
+                Self::set(&mut self.g, position, rhs);
+            } else {
+                Self::set(&mut self.g, position, Dist::Infinite);
+                self.update_vertex(model, sampler, start, goal, &state);
+            }
+
+            for (previous, _) in self.predecessors(model, sampler, &state) {
+                self.update_vertex(model, sampler, start, goal, &previous);
+            }
+        }
+    }
+
+    /// Follow the cheapest successors from the start to the goal
+    fn extract<S>(
+        &mut self,
+        model: &M,
+        sampler: &mut S,
+        start: &M::State,
+        goal: &M::State,
+    ) -> PathResult<M>
+    where
+        S: Sampler<M>,
+    {
+        use PathFindingErr::*;
+        use PathResult::*;
+
+        let mut state = start.clone();
+        let mut cost = M::Cost::default();
+        let mut trajectory = vec![(start.clone(), Default::default())];
+
+        // Every step lowers the cost to the goal, so no trajectory visits more states
+        for _ in 0..=self.states.len() {
+            if model.converge(&state, goal) {
+                return Final(Trajectory {
+                    cost,
+                    trajectory,
+                    expansions: self.stats.expanded,
+                });
+            }
+
+            let mut best = None;
+            for (next, control, step) in self.successors(model, sampler, &state) {
+                let total = step.plus(self.g(&next.grid_position()));
+                if total != Dist::Infinite
+                    && best.as_ref().is_none_or(|(_, _, _, b)| total < *b)
+                {
+                    best = Some((next, control, step, total));
+                }
+            }
+
+            match best {
+                Some((next, control, Dist::Finite(step), _)) => {
+                    cost = cost + step;
+                    trajectory.push((next.clone(), control));
+                    state = next;
+                }
+                _ => return Err(Unreachable),
+            }
+        }
+
+        Err(Unreachable)
+    }
+
+    /// Start over with a new goal, keeping the overrides
+    fn seed(&mut self, model: &M, start: &M::State, goal: &M::State) {
+        self.goal = Some(goal.clone());
+        self.last_start = None;
+        self.km = Default::default();
+        self.states.clear();
+        self.g.clear();
+        self.rhs.clear();
+        self.queue.clear();
+        self.queued.clear();
+        self.changed.clear();
+
+        for state in model.goal_states(goal) {
+            let position = state.grid_position();
+            self.rhs.insert(position.clone(), Default::default());
+            let key = self.key(model, start, &state);
+            self.states.insert(position.clone(), state);
+            self.push(position, key);
+        }
+    }
+}
+
+impl<M, S> Optimizer<M, S> for DStarLite<M>
+where
+    M: ReversibleModel,
+    M::Cost: Copy,
+    S: Sampler<M>,
+{
+    /// D* Lite repairs all of its costs at once, so this is the same as `optimize`
+    fn next_trajectory(
+        &mut self,
+        model: &mut M,
+        start: &M::State,
+        goal: &M::State,
+        sampler: &mut S,
+    ) -> PathResult<M> {
+        self.optimize(model, start, goal, sampler)
+    }
+
+    /// Plan from `start` to `goal`, reusing the costs of the last plan if the goal is the same
+    fn optimize(
+        &mut self,
+        model: &mut M,
+        start: &M::State,
+        goal: &M::State,
+        sampler: &mut S,
+    ) -> PathResult<M> {
+        use PathResult::*;
+
+        if let Some(invalid) = invalid_endpoint(model, start, goal) {
+            return Err(invalid);
+        }
+
+        if model.converge(start, goal) {
+            return Final(Trajectory {
+                cost: Default::default(),
+                trajectory: vec![(start.clone(), Default::default())],
+                expansions: 0,
+            });
+        }
+
+        let same_goal = self
+            .goal
+            .as_ref()
+            .is_some_and(|last| last.grid_position() == goal.grid_position());
+        if !same_goal {
+            self.seed(model, start, goal);
+        }
+
+        self.replan(model, start, sampler)
+    }
+}
+
+impl<M> Debug for DStarLite<M>
+where
+    M: ReversibleModel,
+    M::State: Debug,
+    M::Control: Debug,
+    M::Cost: Debug + Copy,
+{
+    fn fmt(&self, fmt: &mut Formatter) -> Result<(), std::fmt::Error> {
+        fmt.debug_struct("DStarLite")
+            .field("goal", &self.goal)
+            .field("last_start", &self.last_start)
+            .field("km", &self.km)
+            .field("g", &self.g)
+            .field("rhs", &self.rhs)
+            .field("overrides", &self.overrides)
+            .field("stats", &self.stats)
+            .finish()
+    }
+}
+
+impl<M> Default for DStarLite<M>
+where
+    M: ReversibleModel,
+    M::Cost: Copy,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::actor::{Heuristic, TurnOptimal, WalkSampler};
+    use crate::map::Tile;
+    use crate::path::astar::AStar;
+    use crate::path::testing::*;
+
+    #[test]
+    fn replanning_around_a_change_finds_the_new_cost_with_less_work() {
+        let mut map = room(40, 30);
+        for y in 1..20 {
+            map[(20, y)] = Tile::WALL;
+        }
+        let mut model = TurnOptimal::new(map.clone());
+        model.set_heuristic(Heuristic::Chebyshev);
+        let (start, goal) = (walker(5, 5), walker(35, 5));
+
+        let mut sampler = WalkSampler::new();
+        let mut dstar = DStarLite::new();
+        let planned = expect_final(dstar.optimize(&mut model, &start, &goal, &mut sampler));
+
+        // A few steps along, the actor finds something in the way
+        let moved = planned.trajectory[3].0.clone();
+        let blocked = planned.trajectory[4].0.pos.clone();
+        dstar.update_edge(blocked.clone(), None);
+        let expanded = dstar.stats().expanded;
+        let repaired = expect_final(dstar.replan(&mut model, &moved, &mut sampler));
+        assert!(repaired.trajectory.iter().all(|(a, _)| a.pos != blocked));
+        let repair = dstar.stats().expanded - expanded;
+
+        // Planning from scratch on the changed map finds the same cost, with much more work
+        map[(blocked.x, blocked.y)] = Tile::WALL;
+        let mut changed = TurnOptimal::new(map);
+        changed.set_heuristic(Heuristic::Chebyshev);
+        let mut astar = AStar::new();
+        let t = expect_final(astar.optimize(&mut changed, &moved, &goal, &mut sampler));
+        assert_eq!(t.cost, repaired.cost);
+        assert!(repair * 10 < astar.stats().expanded);
+    }
+}
@@ -0,0 +1,130 @@
+use std::io::{self, Write};
+
+use super::*;
+
+/// Write the states of a trajectory as CSV, one row of `x,y` coordinates for each state
+///
+/// `project` maps each state to the coordinates written for it, which is where a model's
+/// states are turned into whatever frame the plotting tool or GIS expects.  The rows follow a
+/// header naming the columns, starting with the index of the state along the trajectory.
+///
+/// ```
+/// # use game_lib::path::astar::AStar;
+/// # use game_lib::path::export::to_csv;
+/// # use game_lib::path::grid::{Cell, GridModel, GridSampler};
+/// # use game_lib::path::Optimizer;
+/// let (mut grid, start, goal) = (GridModel::new(5, 5), Cell::new(0, 0), Cell::new(2, 0));
+/// let result = AStar::new().optimize(&mut grid, &start, &goal, &mut GridSampler);
+/// let mut buffer = Vec::new();
+/// to_csv(&result.into_trajectory().unwrap(), &mut buffer, |c: &Cell| (c.x as f64, c.y as f64))
+///     .unwrap();
+/// ```
+pub fn to_csv<M, W, P>(trajectory: &Trajectory<M>, mut writer: W, project: P) -> io::Result<()>
+where
+    M: Model,
+    W: Write,
+    P: Fn(&M::State) -> (f64, f64),
+{
+    writeln!(writer, "index,x,y")?;
+    for (index, state) in trajectory.states().enumerate() {
+        let (x, y) = project(state);
+        writeln!(writer, "{},{},{}", index, x, y)?;
+    }
+
+    Ok(())
+}
+
+/// Write a trajectory as a GeoJSON feature, with the states along a `LineString`
+///
+/// `project` maps each state to the coordinates of its point, longitude before latitude for
+/// tools which expect geographic coordinates.  The cost of the trajectory is left out of the
+/// feature's properties, since nothing says it can be written as a JSON value.  JSON has no
+/// way to write infinite or missing numbers, so coordinates which are not finite fail with
+/// `io::ErrorKind::InvalidData` before anything is written.
+///
+/// ```
+/// # use game_lib::path::astar::AStar;
+/// # use game_lib::path::export::to_geojson;
+/// # use game_lib::path::grid::{Cell, GridModel, GridSampler};
+/// # use game_lib::path::Optimizer;
+/// let (mut grid, start, goal) = (GridModel::new(5, 5), Cell::new(0, 0), Cell::new(2, 2));
+/// let result = AStar::new().optimize(&mut grid, &start, &goal, &mut GridSampler);
+/// let mut buffer = Vec::new();
+/// to_geojson(&result.into_trajectory().unwrap(), &mut buffer, |c: &Cell| (c.x as f64, c.y as f64))
+///     .unwrap();
+/// ```
+pub fn to_geojson<M, W, P>(
+    trajectory: &Trajectory<M>,
+    mut writer: W,
+    project: P,
+) -> io::Result<()>
+where
+    M: Model,
+    W: Write,
+    P: Fn(&M::State) -> (f64, f64),
+{
+    let points: Vec<_> = trajectory.states().map(project).collect();
+    if points.iter().any(|(x, y)| !x.is_finite() || !y.is_finite()) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "GeoJSON coordinates must be finite",
+        ));
+    }
+
+    write!(writer, r#"{{"type":"Feature","geometry":{{"type":"LineString","coordinates":["#)?;
+    for (index, (x, y)) in points.into_iter().enumerate() {
+        let separator = if index == 0 { "" } else { "," };
+        write!(writer, "{}[{},{}]", separator, x, y)?;
+    }
+    writeln!(writer, r#"]}},"properties":{{}}}}"#)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::path::astar::AStar;
+    use crate::path::grid::{Cell, GridModel, GridSampler};
+    use crate::path::testing::expect_final;
+
+    fn trajectory_to(goal: Cell) -> Trajectory<GridModel> {
+        let mut grid = GridModel::new(5, 5);
+        expect_final(AStar::new().optimize(
+            &mut grid,
+            &Cell::new(0, 0),
+            &goal,
+            &mut GridSampler,
+        ))
+    }
+
+    #[test]
+    fn csv_rows_follow_the_header() {
+        let trajectory = trajectory_to(Cell::new(2, 0));
+        let mut buffer = Vec::new();
+        to_csv(&trajectory, &mut buffer, |c: &Cell| (c.x as f64 + 0.5, c.y as f64)).unwrap();
+        assert_eq!(
+            String::from_utf8(buffer).unwrap(),
+            "index,x,y\n0,0.5,0\n1,1.5,0\n2,2.5,0\n"
+        );
+    }
+
+    #[test]
+    fn geojson_holds_the_states_along_a_line_string() {
+        let trajectory = trajectory_to(Cell::new(2, 2));
+        let mut buffer = Vec::new();
+        to_geojson(&trajectory, &mut buffer, |c: &Cell| (c.x as f64, c.y as f64)).unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&buffer).unwrap();
+        assert_eq!(json["type"], "Feature");
+        assert_eq!(json["geometry"]["type"], "LineString");
+        assert_eq!(
+            json["geometry"]["coordinates"],
+            serde_json::json!([[0, 0], [1, 1], [2, 2]])
+        );
+    }
+
+    #[test]
+    fn geojson_refuses_coordinates_which_are_not_finite() {
+        let trajectory = trajectory_to(Cell::new(2, 2));
+        let nowhere = to_geojson(&trajectory, Vec::new(), |_: &Cell| (f64::NAN, 0.0));
+        assert_eq!(nowhere.unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+}
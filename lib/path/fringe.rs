@@ -0,0 +1,379 @@
+use std::fmt::{Debug, Formatter};
+use std::mem;
+
+use fnv::FnvHashMap;
+use std::collections::hash_map::Entry;
+
+use super::astar::{Id, Node};
+use super::*;
+
+/// Outcome of a single iteration over the fringe
+enum Pass<M>
+where
+    M: Model,
+{
+    /// The goal was reached by the node
+    Found(Node<M>),
+    /// Some nodes were left for the next iteration, the last of them expanded being this one
+    Exceeded(Option<Node<M>>),
+    /// Every node reachable from the start was expanded without reaching the goal
+    Exhausted,
+}
+
+/// Fringe Search, which visits the nodes of A* without keeping them in a priority queue
+///
+/// The fringe is split into a list of nodes to visit now and a list of nodes to visit later.
+/// Each iteration visits the nodes of the first list in turn, expanding those whose estimated
+/// cost is within the threshold and putting the successors at the front of the list, so that
+/// they are visited in the same iteration.  A node estimated to cost more is moved to the
+/// second list instead, and once the first list runs out the threshold is raised to the
+/// lowest estimate that was moved and the lists change places.
+///
+/// Like IDA*, which this grew out of, the threshold only decides _whether_ a node is expanded
+/// during an iteration, never in which order, so there is no queue to keep sorted; unlike
+/// IDA*, the cheapest cost of each position is cached and nothing is expanded twice on the
+/// same trajectory.  On uniform grids, with many nodes sharing the same estimate, this tends
+/// to beat the heap of A*.  With an admissible heuristic the trajectory is optimal.
+///
+/// ```
+/// # use game_lib::path::fringe::FringeSearch;
+/// # use game_lib::path::grid::{Cell, GridModel, GridSampler};
+/// # use game_lib::path::Optimizer;
+/// let (mut grid, start, goal) = (GridModel::new(20, 20), Cell::new(3, 10), Cell::new(17, 9));
+/// let result = FringeSearch::new().optimize(&mut grid, &start, &goal, &mut GridSampler);
+/// assert_eq!(result.trajectory().map(|t| t.cost), Some(29));
+/// ```
+pub struct FringeSearch<M>
+where
+    M: HeuristicModel,
+    M::Cost: Copy,
+{
+    /// The nodes to visit during this iteration, the next of them last
+    now: Vec<Node<M>>,
+    /// The nodes estimated to cost more than the threshold, to visit during the next one
+    later: Vec<Node<M>>,
+    parent_map: FnvHashMap<Id<M>, Node<M>>,
+    /// The cheapest node found at each position; any other node there is stale
+    grid: FnvHashMap<<<M as Model>::State as State>::Position, Id<M>>,
+    threshold: Option<M::Cost>,
+    id_counter: usize,
+    stats: Stats,
+}
+
+impl<M> FringeSearch<M>
+where
+    M: HeuristicModel,
+    M::Cost: Copy,
+{
+    /// Create a new Fringe Search optimizer
+    pub fn new() -> Self {
+        FringeSearch {
+            now: Vec::new(),
+            later: Vec::new(),
+            parent_map: FnvHashMap::default(),
+            grid: FnvHashMap::default(),
+            threshold: None,
+            id_counter: 0,
+            stats: Stats::default(),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.now.clear();
+        self.later.clear();
+        self.parent_map.clear();
+        self.grid.clear();
+        self.threshold = None;
+        self.id_counter = 0;
+        self.stats = Stats::default();
+    }
+
+    /// Counters describing the work done since the optimizer was created or last cleared
+    ///
+    /// The fringe is counted as the queue.
+    pub fn stats(&self) -> &Stats {
+        &self.stats
+    }
+
+    /// The estimated cost above which nodes wait for the next iteration
+    pub fn threshold(&self) -> Option<M::Cost> {
+        self.threshold
+    }
+
+    pub fn inspect_queue(&self) -> impl Iterator<Item = (&M::State, &M::Control)> {
+        self.now.iter().chain(&self.later).map(|node| (&node.state, &node.control))
+    }
+
+    pub fn inspect_discovered(
+        &self,
+    ) -> impl Iterator<Item = &<<M as Model>::State as State>::Position> {
+        self.grid.keys()
+    }
+
+    #[inline(always)]
+    fn is_stale(&self, node: &Node<M>) -> bool {
+        self.grid.get(&node.state.grid_position()).is_some_and(|best| *best != node.id)
+    }
+
+    /// Visit every node of the fringe once, with the threshold set by the last iteration
+    fn pass<S>(&mut self, model: &M, goal: &M::State, sampler: &mut S) -> Pass<M>
+    where
+        S: Sampler<M>,
+    {
+        let threshold = match self.threshold {
+            Some(threshold) => threshold,
+            None => return Pass::Exhausted,
+        };
+
+        let mut lowest: Option<M::Cost> = None;
+        let mut last = None;
+        while let Some(current) = self.now.pop() {
+            if self.is_stale(&current) {
+                continue;
+            }
+
+            let estimate = current.id.f.0;
+            if estimate > threshold {
+                if lowest.is_none_or(|lowest| estimate < lowest) {
+                    lowest = Some(estimate);
+                }
+                self.later.push(current);
+                continue;
+            }
+
+            if model.converge(&current.state, goal) {
+                return Pass::Found(current);
+            }
+            self.expand(&current, model, goal, sampler);
+            last = Some(current);
+        }
+
+        // The nodes waiting for later are visited in the order they were moved
+        mem::swap(&mut self.now, &mut self.later);
+        self.now.reverse();
+        self.threshold = lowest;
+        match lowest {
+            Some(_) => Pass::Exceeded(last),
+            None => Pass::Exhausted,
+        }
+    }
+
+    /// Put the successors of `current` at the front of the fringe, unless their positions
+    /// were already reached more cheaply
+    #[inline(always)]
+    fn expand<S>(&mut self, current: &Node<M>, model: &M, goal: &M::State, sampler: &mut S)
+    where
+        S: Sampler<M>,
+    {
+        self.stats.expanded += 1;
+
+        for control in sampler.sample(model, &current.state) {
+            if let Some(child_state) = model.integrate(&current.state, control) {
+                self.id_counter += 1;
+                self.stats.generated += 1;
+
+                let cost = current.id.g() + model.cost(&current.state, control, &child_state);
+                let heuristic = model.heuristic(&child_state, goal);
+                let child = Node::<M> {
+                    id: Id::new(self.id_counter, cost + heuristic, cost),
+                    state: child_state,
+                    control: control.clone(),
+                };
+
+                match self.grid.entry(child.state.grid_position()) {
+                    Entry::Occupied(mut best) => {
+                        let best = best.get_mut();
+                        if best.g <= child.id.g {
+                            continue;
+                        } else {
+                            *best = child.id.clone();
+                        }
+                    }
+                    Entry::Vacant(empty) => {
+                        empty.insert(child.id.clone());
+                    }
+                }
+
+                self.parent_map.insert(child.id.clone(), current.clone());
+                self.now.push(child);
+            }
+        }
+
+        let fringe = self.now.len() + self.later.len();
+        self.stats.max_queue_len = self.stats.max_queue_len.max(fringe);
+    }
+
+    /// Follow the parents from the goal node up to the start node
+    fn unwind_trajectory(&self, mut current: Node<M>) -> Trajectory<M> {
+        let cost = current.id.g();
+        let mut result = Vec::new();
+        result.push((current.state.clone(), current.control.clone()));
+
+        while let Some(p) = self.parent_map.get(&current.id) {
+            current = (*p).clone();
+            result.push((current.state.clone(), current.control.clone()));
+        }
+
+        result.reverse();
+
+        Trajectory { cost, trajectory: result, expansions: self.stats.expanded }
+    }
+
+    fn seed(&mut self, model: &M, start: &M::State, goal: &M::State) {
+        let heuristic = model.heuristic(start, goal);
+        let start_id = Id::new(0, heuristic, Default::default());
+        self.grid.insert(start.grid_position(), start_id.clone());
+        self.now.push(Node {
+            id: start_id,
+            state: start.clone(),
+            control: Default::default(),
+        });
+        self.threshold = Some(heuristic);
+    }
+}
+
+impl<M, S> Optimizer<M, S> for FringeSearch<M>
+where
+    M: HeuristicModel,
+    M::Cost: Copy,
+    S: Sampler<M>,
+{
+    /// Run one iteration over the fringe
+    ///
+    /// An intermediate result holds the trajectory to the last node the iteration expanded.
+    /// An iteration which put every node off until the next one expanded nothing, and stops
+    /// with `PathFindingErr::Pending`; `Unreachable` means there is nothing left to expand.
+    fn next_trajectory(
+        &mut self,
+        model: &mut M,
+        start: &M::State,
+        goal: &M::State,
+        sampler: &mut S,
+    ) -> PathResult<M> {
+        use PathFindingErr::*;
+        use PathResult::*;
+
+        if let Some(invalid) = invalid_endpoint(model, start, goal) {
+            return Err(invalid);
+        }
+
+        // A search which ran out of nodes is finished, rather than to be begun again
+        if self.stats.expanded == 0 && self.now.is_empty() && self.later.is_empty() {
+            self.seed(model, start, goal);
+        }
+
+        match self.pass(model, goal, sampler) {
+            Pass::Found(node) => Final(self.unwind_trajectory(node)),
+            Pass::Exceeded(Some(node)) => Intermediate(self.unwind_trajectory(node)),
+            // Every node left was put off, so there is no trajectory to show for the iteration
+            Pass::Exceeded(None) => Err(Pending),
+            Pass::Exhausted => Err(Unreachable),
+        }
+    }
+
+    fn optimize(
+        &mut self,
+        model: &mut M,
+        start: &M::State,
+        goal: &M::State,
+        sampler: &mut S,
+    ) -> PathResult<M> {
+        use PathFindingErr::*;
+        use PathResult::*;
+
+        if let Some(invalid) = invalid_endpoint(model, start, goal) {
+            return Err(invalid);
+        }
+
+        if model.converge(start, goal) {
+            return Final(Trajectory {
+                cost: Default::default(),
+                trajectory: vec![(start.clone(), Default::default())],
+                expansions: 0,
+            });
+        }
+
+        if self.now.is_empty() && self.later.is_empty() {
+            self.seed(model, start, goal);
+        }
+
+        loop {
+            match self.pass(model, goal, sampler) {
+                Pass::Found(node) => return Final(self.unwind_trajectory(node)),
+                Pass::Exceeded(_) => {}
+                Pass::Exhausted => return Err(Unreachable),
+            }
+        }
+    }
+}
+
+impl<M> Debug for FringeSearch<M>
+where
+    M: HeuristicModel,
+    M::State: Debug,
+    M::Control: Debug,
+    M::Cost: Debug + Copy,
+{
+    fn fmt(&self, fmt: &mut Formatter) -> Result<(), std::fmt::Error> {
+        fmt.debug_struct("FringeSearch")
+            .field("threshold", &self.threshold)
+            .field("stats", &self.stats)
+            .field("now", &self.now)
+            .field("later", &self.later)
+            .finish()
+    }
+}
+
+impl<M> Default for FringeSearch<M>
+where
+    M: HeuristicModel,
+    M::Cost: Copy,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::actor::{Heuristic, TurnOptimal, WalkSampler};
+    use crate::map::Tile;
+    use crate::path::astar::AStar;
+    use crate::path::testing::{expect_final, room, walker, walled_room};
+
+    #[test]
+    fn finds_trajectories_as_cheap_as_astar() {
+        let mut model = TurnOptimal::new(walled_room());
+        model.set_heuristic(Heuristic::Chebyshev);
+        let (start, goal) = (walker(3, 15), walker(27, 14));
+
+        let mut sampler = WalkSampler::new();
+        let a = expect_final(AStar::new().optimize(&mut model, &start, &goal, &mut sampler));
+        let f = expect_final(FringeSearch::new().optimize(
+            &mut model,
+            &start,
+            &goal,
+            &mut sampler,
+        ));
+        assert_eq!(a.cost, f.cost);
+        assert_eq!(f.trajectory[0].0.pos, start.pos);
+        assert!(model.converge(&f.trajectory.last().unwrap().0, &goal));
+    }
+
+    #[test]
+    fn fails_once_the_fringe_runs_out() {
+        // The wall cuts the goal off from the other side
+        let mut map = room(30, 30);
+        for y in 1..29 {
+            map[(15, y)] = Tile::WALL;
+        }
+        let mut model = TurnOptimal::new(map);
+        let (start, goal) = (walker(3, 15), walker(27, 14));
+        match FringeSearch::new().optimize(&mut model, &start, &goal, &mut WalkSampler::new())
+        {
+            PathResult::Err(_) => {}
+            _ => panic!("the goal is walled off"),
+        }
+    }
+}
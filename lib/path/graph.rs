@@ -0,0 +1,229 @@
+use std::collections::HashMap;
+use std::fmt::{self, Debug, Formatter};
+
+use super::*;
+
+/// A vertex of a [`GraphModel`]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Vertex<N>(pub N);
+
+impl<N> State for Vertex<N>
+where
+    N: Eq + Hash + Clone + Debug,
+{
+    type Position = N;
+
+    fn grid_position(&self) -> N {
+        self.0.clone()
+    }
+
+    /// Vertices have nothing in between them, so this is whichever of the two is closer
+    fn interpolate(&self, other: &Self, t: f64) -> Self {
+        if t < 0.5 {
+            self.clone()
+        } else {
+            other.clone()
+        }
+    }
+}
+
+/// A weighted directed graph given as the edges leaving each of its vertices
+///
+/// The controls are indices into the edges leaving a vertex, which [`GraphSampler`] samples,
+/// and the cost of a trajectory is the sum of the weights of its edges.  The heuristic is
+/// zero unless one is given with [`with_heuristic`], which leaves `AStar` to search like
+/// Dijkstra's algorithm; a heuristic must never overestimate the cost to the goal for the
+/// trajectories to be shortest.
+///
+/// Vertices which are only ever the end of an edge are added with no edges of their own.
+///
+/// [`with_heuristic`]: #method.with_heuristic
+///
+/// ```
+/// # use game_lib::path::astar::AStar;
+/// # use game_lib::path::graph::{GraphModel, GraphSampler, Vertex};
+/// # use game_lib::path::Optimizer;
+/// let edges = vec![('a', vec![('b', 4), ('c', 1)]), ('c', vec![('b', 2)])];
+/// let mut graph = GraphModel::new(edges.into_iter().collect());
+/// let (a, b) = (Vertex('a'), Vertex('b'));
+/// let result = AStar::new().optimize(&mut graph, &a, &b, &mut GraphSampler::new());
+/// assert_eq!(result.trajectory().map(|t| t.cost), Some(3));
+/// ```
+pub struct GraphModel<N, C, H = fn(&N, &N) -> C> {
+    edges: HashMap<N, Vec<(N, C)>>,
+    heuristic: H,
+}
+
+impl<N, C> GraphModel<N, C>
+where
+    N: Eq + Hash + Clone + Debug,
+    C: Cost + Clone + Debug,
+{
+    pub fn new(edges: HashMap<N, Vec<(N, C)>>) -> Self {
+        Self::with_heuristic(edges, |_, _| Default::default())
+    }
+}
+
+impl<N, C, H> GraphModel<N, C, H>
+where
+    N: Eq + Hash + Clone + Debug,
+    C: Cost + Clone + Debug,
+    H: Fn(&N, &N) -> C,
+{
+    /// Search the graph with `heuristic` estimating the cost from a vertex to the goal
+    pub fn with_heuristic(mut edges: HashMap<N, Vec<(N, C)>>, heuristic: H) -> Self {
+        let ends: Vec<_> = edges.values().flatten().map(|(end, _)| end.clone()).collect();
+        for end in ends {
+            edges.entry(end).or_default();
+        }
+
+        GraphModel { edges, heuristic }
+    }
+
+    /// The edges leaving `vertex`, with their weights
+    pub fn edges(&self, vertex: &N) -> &[(N, C)] {
+        self.edges.get(vertex).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn vertices(&self) -> impl Iterator<Item = &N> {
+        self.edges.keys()
+    }
+}
+
+impl<N, C, H> Model for GraphModel<N, C, H>
+where
+    N: Eq + Hash + Clone + Debug,
+    C: Cost + Clone + Debug,
+    H: Fn(&N, &N) -> C,
+{
+    type State = Vertex<N>;
+    type Control = usize;
+    type Cost = C;
+
+    fn cost(&self, current: &Vertex<N>, edge: &usize, _: &Vertex<N>) -> C {
+        self.edges(&current.0)[*edge].1.clone()
+    }
+
+    /// Nothing to do on initialization
+    fn init(&mut self, _: &Vertex<N>) {}
+
+    fn converge(&self, current: &Vertex<N>, goal: &Vertex<N>) -> bool {
+        current == goal
+    }
+
+    fn integrate(&self, previous: &Vertex<N>, edge: &usize) -> Option<Vertex<N>> {
+        self.edges(&previous.0).get(*edge).map(|(end, _)| Vertex(end.clone()))
+    }
+
+    fn is_valid(&self, vertex: &Vertex<N>) -> bool {
+        self.edges.contains_key(&vertex.0)
+    }
+}
+
+impl<N, C, H> HeuristicModel for GraphModel<N, C, H>
+where
+    N: Eq + Hash + Clone + Debug,
+    C: Cost + Clone + Debug,
+    H: Fn(&N, &N) -> C,
+{
+    fn heuristic(&self, current: &Vertex<N>, goal: &Vertex<N>) -> C {
+        (self.heuristic)(&current.0, &goal.0)
+    }
+}
+
+impl<N, C, H> Debug for GraphModel<N, C, H>
+where
+    N: Debug,
+    C: Debug,
+{
+    fn fmt(&self, fmt: &mut Formatter) -> Result<(), fmt::Error> {
+        fmt.debug_struct("GraphModel").field("edges", &self.edges).finish()
+    }
+}
+
+/// Samples every edge leaving a vertex of a [`GraphModel`]
+#[derive(Clone, Debug, Default)]
+pub struct GraphSampler {
+    edges: Vec<usize>,
+}
+
+impl GraphSampler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<N, C, H> Sampler<GraphModel<N, C, H>> for GraphSampler
+where
+    N: Eq + Hash + Clone + Debug,
+    C: Cost + Clone + Debug,
+    H: Fn(&N, &N) -> C,
+{
+    fn sample(&mut self, model: &GraphModel<N, C, H>, current: &Vertex<N>) -> &[usize] {
+        let degree = model.edges(&current.0).len();
+        let known = self.edges.len();
+        if degree > known {
+            self.edges.extend(known..degree);
+        }
+
+        &self.edges[..degree]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::path::astar::AStar;
+    use crate::path::dijkstra::Dijkstra;
+    use crate::path::testing::expect_final;
+
+    fn edges() -> HashMap<char, Vec<(char, usize)>> {
+        vec![
+            ('a', vec![('b', 4), ('c', 2)]),
+            ('b', vec![('d', 5)]),
+            ('c', vec![('b', 1), ('d', 8), ('e', 10), ('f', 3)]),
+            ('d', vec![('e', 2)]),
+            ('e', vec![('a', 1)]),
+        ]
+        .into_iter()
+        .collect()
+    }
+
+    #[test]
+    fn searches_find_the_shortest_walk_along_the_edges() {
+        let mut graph = GraphModel::new(edges());
+        let (a, e) = (Vertex('a'), Vertex('e'));
+
+        // Worked out by hand: a-c-b-d-e costs 2 + 1 + 5 + 2, beating a-b-d-e at 11 and a-c-e
+        // at 12
+        let by_hand = vec!['a', 'c', 'b', 'd', 'e'];
+        let mut sampler = GraphSampler::new();
+        let t = expect_final(AStar::new().optimize(&mut graph, &a, &e, &mut sampler));
+        assert_eq!(t.cost, 10);
+        assert_eq!(t.states().map(|v| v.0).collect::<Vec<_>>(), by_hand);
+
+        let t = expect_final(Dijkstra::new().optimize(&mut graph, &a, &e, &mut sampler));
+        assert_eq!(t.cost, 10);
+    }
+
+    #[test]
+    fn an_admissible_heuristic_keeps_the_walk_shortest() {
+        // Every edge costs at least one, so one is never too much for the vertices short of it
+        let one_short = |v: &char, goal: &char| (v != goal) as usize;
+        let mut informed = GraphModel::with_heuristic(edges(), one_short);
+        let (a, e) = (Vertex('a'), Vertex('e'));
+        let mut sampler = GraphSampler::new();
+        let t = expect_final(AStar::new().optimize(&mut informed, &a, &e, &mut sampler));
+        assert_eq!(t.cost, 10);
+    }
+
+    #[test]
+    fn edges_only_go_one_way() {
+        // None leave f
+        let (mut graph, mut sampler) = (GraphModel::new(edges()), GraphSampler::new());
+        match AStar::new().optimize(&mut graph, &Vertex('f'), &Vertex('a'), &mut sampler) {
+            PathResult::Err(PathFindingErr::Unreachable) => {}
+            _ => panic!("nothing leaves f"),
+        }
+    }
+}
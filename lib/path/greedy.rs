@@ -0,0 +1,285 @@
+use std::fmt::{Debug, Formatter};
+
+use fnv::{FnvHashMap, FnvHashSet};
+use std::collections::hash_map::Entry;
+use std::collections::BinaryHeap;
+
+use super::astar::{Id, Node};
+use super::*;
+
+/// Greedy best-first search which orders nodes by the heuristic alone
+///
+/// The node which appears closest to the goal is always expanded next, ignoring how expensive
+/// it was to reach.  This usually finds _a_ trajectory with far fewer expansions than A*, but
+/// makes no promise that the trajectory is optimal.
+///
+/// The reported [`Trajectory::cost`] is still the true accumulated cost of the trajectory,
+/// even though that cost plays no part in the ordering of the search.
+///
+/// ```
+/// # use game_lib::path::grid::{Cell, GridModel, GridSampler};
+/// # use game_lib::path::{greedy::GreedyBestFirst, Optimizer};
+/// let mut grid = GridModel::new(20, 20);
+/// let (start, goal) = (Cell::new(1, 1), Cell::new(18, 12));
+/// let mut greedy = GreedyBestFirst::new();
+/// assert!(greedy.optimize(&mut grid, &start, &goal, &mut GridSampler).is_final());
+/// ```
+///
+/// [`Trajectory::cost`]: ../struct.Trajectory.html#structfield.cost
+pub struct GreedyBestFirst<M>
+where
+    M: HeuristicModel,
+    M::Cost: Copy,
+{
+    queue: BinaryHeap<Node<M>>,
+    parent_map: FnvHashMap<Id<M>, Node<M>>,
+    grid: FnvHashMap<<<M as Model>::State as State>::Position, Id<M>>,
+    closed: FnvHashSet<<<M as Model>::State as State>::Position>,
+    id_counter: usize,
+}
+
+impl<M> GreedyBestFirst<M>
+where
+    M: HeuristicModel,
+    M::Cost: Copy,
+{
+    /// Create a new greedy best-first optimizer
+    pub fn new() -> Self {
+        GreedyBestFirst {
+            queue: BinaryHeap::new(),
+            parent_map: FnvHashMap::default(),
+            grid: FnvHashMap::default(),
+            closed: FnvHashSet::default(),
+            id_counter: 0,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.queue.clear();
+        self.parent_map.clear();
+        self.grid.clear();
+        self.closed.clear();
+    }
+
+    /// Number of grid positions which have been expanded and will not be expanded again
+    pub fn closed_count(&self) -> usize {
+        self.closed.len()
+    }
+
+    pub fn inspect_queue(&self) -> impl Iterator<Item = (&M::State, &M::Control)> {
+        self.queue.iter().map(|node| (&node.state, &node.control))
+    }
+
+    pub fn inspect_discovered(
+        &self,
+    ) -> impl Iterator<Item = &<<M as Model>::State as State>::Position> {
+        self.grid.keys()
+    }
+
+    #[inline(always)]
+    fn step<S>(
+        &mut self,
+        current: &Node<M>,
+        model: &mut M,
+        goal: &M::State,
+        sampler: &mut S,
+    ) -> bool
+    where
+        S: Sampler<M>,
+    {
+        if model.converge(&current.state, goal) {
+            return true;
+        }
+
+        if !self.closed.insert(current.state.grid_position()) {
+            return false;
+        }
+
+        for control in sampler.sample(model, &current.state) {
+            if let Some(child_state) = model.integrate(&current.state, control) {
+                self.id_counter += 1;
+
+                // The cost is tracked for the trajectory, but only the estimate orders nodes
+                let cost = current.id.g() + model.cost(&current.state, control, &child_state);
+                let heuristic = model.heuristic(&child_state, goal);
+
+                let child = Node::<M> {
+                    id: Id::new(self.id_counter, heuristic, cost),
+                    state: child_state,
+                    control: control.clone(),
+                };
+
+                match self.grid.entry(child.state.grid_position()) {
+                    Entry::Occupied(mut best) => {
+                        let best = best.get_mut();
+                        if best.g <= child.id.g {
+                            continue;
+                        } else {
+                            *best = child.id.clone();
+                        }
+                    }
+                    Entry::Vacant(empty) => {
+                        empty.insert(child.id.clone());
+                    }
+                }
+
+                self.parent_map.insert(child.id.clone(), current.clone());
+                self.queue.push(child);
+            }
+        }
+
+        false
+    }
+
+    /// Follow the parents from the goal node up to the start node
+    fn unwind_trajectory(&self, mut current: Node<M>) -> Trajectory<M> {
+        let cost = current.id.g();
+        let mut result = Vec::new();
+        result.push((current.state.clone(), current.control.clone()));
+
+        while let Some(p) = self.parent_map.get(&current.id) {
+            current = (*p).clone();
+            result.push((current.state.clone(), current.control.clone()));
+        }
+
+        result.reverse();
+
+        Trajectory { cost, trajectory: result, expansions: 0 }
+    }
+
+    fn seed(&mut self, model: &M, start: &M::State, goal: &M::State) {
+        let start_id = Id::new(0, model.heuristic(start, goal), Default::default());
+        self.queue.push(Node {
+            id: start_id,
+            state: start.clone(),
+            control: Default::default(),
+        });
+    }
+}
+
+impl<M, S> Optimizer<M, S> for GreedyBestFirst<M>
+where
+    M: HeuristicModel,
+    M::Cost: Copy,
+    S: Sampler<M>,
+{
+    fn next_trajectory(
+        &mut self,
+        model: &mut M,
+        start: &M::State,
+        goal: &M::State,
+        sampler: &mut S,
+    ) -> PathResult<M> {
+        use PathFindingErr::*;
+        use PathResult::*;
+
+        if let Some(invalid) = invalid_endpoint(model, start, goal) {
+            return Err(invalid);
+        }
+
+        if self.parent_map.is_empty() && self.queue.is_empty() {
+            self.seed(model, start, goal);
+        }
+
+        if let Some(current) = self.queue.pop() {
+            if self.step(&current, model, goal, sampler) {
+                Final(self.unwind_trajectory(current))
+            } else {
+                Intermediate(self.unwind_trajectory(current))
+            }
+        } else {
+            Err(Unreachable)
+        }
+    }
+
+    fn optimize(
+        &mut self,
+        model: &mut M,
+        start: &M::State,
+        goal: &M::State,
+        sampler: &mut S,
+    ) -> PathResult<M> {
+        use PathFindingErr::*;
+        use PathResult::*;
+
+        if let Some(invalid) = invalid_endpoint(model, start, goal) {
+            return Err(invalid);
+        }
+
+        if model.converge(start, goal) {
+            return Final(Trajectory {
+                cost: Default::default(),
+                trajectory: vec![(start.clone(), Default::default())],
+                expansions: 0,
+            });
+        }
+
+        if self.queue.is_empty() {
+            self.seed(model, start, goal);
+        }
+
+        while let Some(current) = self.queue.pop() {
+            if self.step(&current, model, goal, sampler) {
+                return Final(self.unwind_trajectory(current));
+            }
+        }
+
+        Err(Unreachable)
+    }
+}
+
+impl<M> Debug for GreedyBestFirst<M>
+where
+    M: HeuristicModel,
+    M::State: Debug,
+    M::Control: Debug,
+    M::Cost: Debug + Copy,
+{
+    fn fmt(&self, fmt: &mut Formatter) -> Result<(), std::fmt::Error> {
+        fmt.debug_struct("GreedyBestFirst")
+            .field("counter", &self.id_counter)
+            .field("next", &self.queue.peek())
+            .field("queue", &self.queue)
+            .field("grid", &self.grid)
+            .field("closed", &self.closed)
+            .field("parent_map", &self.parent_map)
+            .finish()
+    }
+}
+
+impl<M> Default for GreedyBestFirst<M>
+where
+    M: HeuristicModel,
+    M::Cost: Copy,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::actor::{Heuristic, TurnOptimal, WalkSampler};
+    use crate::map::Tile;
+    use crate::path::astar::AStar;
+    use crate::path::testing::*;
+
+    #[test]
+    fn expands_fewer_nodes_than_astar_for_a_costlier_trajectory() {
+        // A wall between the start and goal makes the heuristic misleading
+        let mut map = room(30, 30);
+        for y in 5..25 {
+            map[(15, y)] = Tile::WALL;
+        }
+        let mut model = TurnOptimal::new(map);
+        model.set_heuristic(Heuristic::Chebyshev);
+        let (start, goal) = (walker(3, 15), walker(27, 14));
+        let (mut astar, mut greedy) = (AStar::new(), GreedyBestFirst::new());
+        let mut sampler = WalkSampler::new();
+        let optimal = expect_final(astar.optimize(&mut model, &start, &goal, &mut sampler));
+        let fast = expect_final(greedy.optimize(&mut model, &start, &goal, &mut sampler));
+        assert!(fast.cost >= optimal.cost);
+        assert!(greedy.closed_count() < astar.closed_count());
+    }
+}
@@ -0,0 +1,597 @@
+use super::bidirectional::ReversibleModel;
+use super::bucket::BoundedCostModel;
+use super::heuristics::{chebyshev, manhattan, Coord2D};
+use super::jps::{self, Connectivity};
+use super::random::RandomModel;
+use super::spacetime::WaitableModel;
+use super::*;
+
+use rand::Rng;
+
+/// Cost of a step to an orthogonal neighbour
+const STRAIGHT: usize = 2;
+/// Default cost of a step to a diagonal neighbour, close to `STRAIGHT` times the square root
+/// of two
+const DIAGONAL: usize = 3;
+
+static FOUR_STEPS: [Step; 4] = [
+    Step { dx: 0, dy: -1 },
+    Step { dx: 1, dy: 0 },
+    Step { dx: 0, dy: 1 },
+    Step { dx: -1, dy: 0 },
+];
+
+static EIGHT_STEPS: [Step; 8] = [
+    Step { dx: 0, dy: -1 },
+    Step { dx: 1, dy: -1 },
+    Step { dx: 1, dy: 0 },
+    Step { dx: 1, dy: 1 },
+    Step { dx: 0, dy: 1 },
+    Step { dx: -1, dy: 1 },
+    Step { dx: -1, dy: 0 },
+    Step { dx: -1, dy: -1 },
+];
+
+/// A cell of a [`GridModel`]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Cell {
+    pub x: i64,
+    pub y: i64,
+}
+
+impl Cell {
+    pub fn new(x: i64, y: i64) -> Self {
+        Cell { x, y }
+    }
+}
+
+impl State for Cell {
+    type Position = Cell;
+
+    fn grid_position(&self) -> Cell {
+        *self
+    }
+
+    fn interpolate(&self, other: &Self, t: f64) -> Self {
+        Cell {
+            x: self.x + ((other.x - self.x) as f64 * t).round() as i64,
+            y: self.y + ((other.y - self.y) as f64 * t).round() as i64,
+        }
+    }
+}
+
+impl BoundedPosition for Cell {
+    fn within(&self, min: &Self, max: &Self) -> bool {
+        (min.x..=max.x).contains(&self.x) && (min.y..=max.y).contains(&self.y)
+    }
+}
+
+impl Coord2D for Cell {
+    fn x(&self) -> i64 {
+        self.x
+    }
+
+    fn y(&self) -> i64 {
+        self.y
+    }
+}
+
+/// A step from a cell to one of its neighbours, where each offset is -1, 0, or 1
+///
+/// The default step stays in place, and is the control of the first state of a trajectory.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Step {
+    pub dx: i64,
+    pub dy: i64,
+}
+
+/// A two dimensional grid of cells which are either open or blocked
+///
+/// This is a complete reference implementation of the planning traits, and a starting point
+/// for models of other grids.  Straight steps cost 2 and diagonal steps cost 3, unless
+/// [`diagonal_cost`](#method.diagonal_cost) says otherwise, and the heuristic is the
+/// Manhattan distance on a grid with four neighbours and the octile distance on a grid with
+/// eight, both of which are exact on an open grid.  A trajectory converges when it reaches
+/// the goal cell.
+///
+/// As with [`jps::GridModel`], stepping into a cell only depends on whether that cell is
+/// blocked, so diagonal steps may cut past blocked corners.
+///
+/// ```
+/// # use game_lib::path::astar::AStar;
+/// # use game_lib::path::grid::{Cell, GridModel, GridSampler};
+/// # use game_lib::path::Optimizer;
+/// let mut grid = GridModel::new(10, 10);
+/// // A wall down the middle with a gap at the bottom
+/// for y in 0..8 {
+///     grid.set_blocked(5, y, true);
+/// }
+/// let (start, goal) = (Cell::new(2, 2), Cell::new(8, 2));
+/// assert!(AStar::new().optimize(&mut grid, &start, &goal, &mut GridSampler).is_final());
+/// ```
+#[derive(Clone, Debug)]
+pub struct GridModel {
+    width: usize,
+    height: usize,
+    blocked: Vec<bool>,
+    connectivity: Connectivity,
+    /// What a step to a diagonal neighbour costs
+    diagonal: usize,
+}
+
+impl GridModel {
+    /// Create an open grid where every cell connects to its eight neighbours
+    pub fn new(width: usize, height: usize) -> Self {
+        Self::with_occupancy(width, height, vec![false; width * height])
+    }
+
+    /// Create a grid from a row-major occupancy map, where `true` marks a blocked cell
+    ///
+    /// # Panics
+    ///
+    /// When the occupancy map does not hold exactly `width * height` cells.
+    pub fn with_occupancy(width: usize, height: usize, blocked: Vec<bool>) -> Self {
+        assert_eq!(blocked.len(), width * height, "the occupancy map must cover the grid");
+
+        GridModel {
+            width,
+            height,
+            blocked,
+            connectivity: Connectivity::Eight,
+            diagonal: DIAGONAL,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn connectivity(&self) -> Connectivity {
+        self.connectivity
+    }
+
+    /// Choose whether diagonal neighbours can be reached in a single step
+    ///
+    /// ```
+    /// # use game_lib::path::astar::AStar;
+    /// # use game_lib::path::grid::{Cell, GridModel, GridSampler};
+    /// # use game_lib::path::jps::Connectivity;
+    /// # use game_lib::path::Optimizer;
+    /// let mut grid = GridModel::new(8, 8);
+    /// grid.set_connectivity(Connectivity::Four);
+    /// let (start, goal) = (Cell::new(1, 1), Cell::new(5, 4));
+    /// assert!(AStar::new().optimize(&mut grid, &start, &goal, &mut GridSampler).is_final());
+    /// ```
+    pub fn set_connectivity(&mut self, connectivity: Connectivity) {
+        self.connectivity = connectivity;
+    }
+
+    /// Choose whether a diagonal step costs more than a straight one
+    ///
+    /// With the correction enabled, a diagonal step costs `cost`, where the default of 3 is
+    /// close to the straight cost of 2 times the square root of two, so that a trajectory
+    /// takes as long as it looks.  With it disabled, `cost` is ignored and every step costs
+    /// the same, and since a diagonal then goes as far as a straight step for nothing, the
+    /// cheapest trajectories include ones which zigzag where a straight line would do.  The
+    /// octile heuristic follows the cost either way, so it stays exact on an open grid.
+    ///
+    /// # Panics
+    ///
+    /// When the correction is enabled with a cost below that of a straight step, or above
+    /// that of two of them, where the heuristic would overestimate.
+    ///
+    /// ```
+    /// # use game_lib::path::grid::{Cell, GridModel, Step};
+    /// # use game_lib::path::Model;
+    /// let mut grid = GridModel::new(12, 12);
+    /// let (cell, diagonal, next) = (Cell::new(2, 2), Step { dx: 1, dy: 1 }, Cell::new(3, 3));
+    /// assert_eq!(grid.cost(&cell, &diagonal, &next), 3);
+    /// grid.diagonal_cost(false, 0);
+    /// assert_eq!(grid.cost(&cell, &diagonal, &next), 2);
+    /// ```
+    pub fn diagonal_cost(&mut self, enabled: bool, cost: usize) {
+        if enabled {
+            assert!(
+                (STRAIGHT..=2 * STRAIGHT).contains(&cost),
+                "a diagonal step must cost between one and two straight steps"
+            );
+            self.diagonal = cost;
+        } else {
+            self.diagonal = STRAIGHT;
+        }
+    }
+
+    /// Whether the cell at `(x, y)` cannot be entered, which includes every cell off the grid
+    pub fn is_blocked(&self, x: i64, y: i64) -> bool {
+        self.index(x, y).is_none_or(|i| self.blocked[i])
+    }
+
+    /// Block or open the cell at `(x, y)`
+    ///
+    /// # Panics
+    ///
+    /// When the cell is not on the grid.
+    pub fn set_blocked(&mut self, x: i64, y: i64, blocked: bool) {
+        let i = self.index(x, y).expect("the cell must be on the grid");
+        self.blocked[i] = blocked;
+    }
+
+    fn index(&self, x: i64, y: i64) -> Option<usize> {
+        if x < 0 || y < 0 || x as u64 >= self.width as u64 || y as u64 >= self.height as u64 {
+            return None;
+        }
+
+        Some(y as usize * self.width + x as usize)
+    }
+}
+
+impl Model for GridModel {
+    type State = Cell;
+    type Control = Step;
+    type Cost = usize;
+
+    fn cost(&self, _current: &Cell, step: &Step, _next: &Cell) -> usize {
+        if step.dx != 0 && step.dy != 0 {
+            self.diagonal
+        } else {
+            STRAIGHT
+        }
+    }
+
+    /// Nothing to do on initialization
+    fn init(&mut self, _: &Cell) {}
+
+    fn converge(&self, current: &Cell, goal: &Cell) -> bool {
+        current == goal
+    }
+
+    fn integrate(&self, previous: &Cell, step: &Step) -> Option<Cell> {
+        let next = Cell::new(previous.x + step.dx, previous.y + step.dy);
+
+        if self.is_blocked(next.x, next.y) {
+            None
+        } else {
+            Some(next)
+        }
+    }
+
+    /// Cells on the grid which are not blocked
+    fn is_valid(&self, cell: &Cell) -> bool {
+        !self.is_blocked(cell.x, cell.y)
+    }
+}
+
+impl HeuristicModel for GridModel {
+    /// The cost of the cheapest trajectory to the goal if nothing were blocked
+    fn heuristic(&self, current: &Cell, goal: &Cell) -> usize {
+        let axis_steps = manhattan(current, goal) as usize;
+
+        match self.connectivity {
+            Connectivity::Four => STRAIGHT * axis_steps,
+            Connectivity::Eight => {
+                // Each diagonal step stands in for two steps along the axes
+                let steps = chebyshev(current, goal) as usize;
+                STRAIGHT * steps + (self.diagonal - STRAIGHT) * (axis_steps - steps)
+            }
+        }
+    }
+}
+
+impl BoundedCostModel for GridModel {
+    fn max_edge_cost(&self) -> usize {
+        match self.connectivity {
+            Connectivity::Four => STRAIGHT,
+            Connectivity::Eight => self.diagonal,
+        }
+    }
+}
+
+impl jps::GridModel for GridModel {
+    fn connectivity(&self) -> Connectivity {
+        self.connectivity
+    }
+
+    fn cell(&self, state: &Cell) -> (i64, i64) {
+        (state.x, state.y)
+    }
+
+    fn is_blocked(&self, x: i64, y: i64) -> bool {
+        GridModel::is_blocked(self, x, y)
+    }
+
+    fn control(&self, dx: i64, dy: i64) -> Step {
+        Step { dx, dy }
+    }
+}
+
+impl ReversibleModel for GridModel {
+    fn integrate_reverse(&self, state: &Cell, step: &Step) -> Option<Cell> {
+        let previous = Cell::new(state.x - step.dx, state.y - step.dy);
+
+        if self.is_blocked(previous.x, previous.y) {
+            None
+        } else {
+            Some(previous)
+        }
+    }
+}
+
+impl RandomModel for GridModel {
+    /// Any of the steps the connectivity allows, whether or not it is blocked
+    fn random_control<R: Rng + ?Sized>(&self, _: &Cell, rng: &mut R) -> Step {
+        match self.connectivity {
+            Connectivity::Four => FOUR_STEPS[rng.gen_range(0, FOUR_STEPS.len())],
+            Connectivity::Eight => EIGHT_STEPS[rng.gen_range(0, EIGHT_STEPS.len())],
+        }
+    }
+
+    /// The step towards the goal, moving along the longer axis on a grid with four neighbours
+    fn steer(&self, state: &Cell, goal: &Cell) -> Step {
+        let (dx, dy) = ((goal.x - state.x).signum(), (goal.y - state.y).signum());
+
+        match self.connectivity {
+            Connectivity::Eight => Step { dx, dy },
+            Connectivity::Four if (goal.x - state.x).abs() >= (goal.y - state.y).abs() => {
+                Step { dx, dy: 0 }
+            }
+            Connectivity::Four => Step { dx: 0, dy },
+        }
+    }
+}
+
+impl WaitableModel for GridModel {
+    fn wait(&self) -> Step {
+        Step::default()
+    }
+}
+
+/// Samples a step to every neighbour the grid's connectivity allows
+#[derive(Clone, Copy, Debug, Default)]
+pub struct GridSampler;
+
+impl Sampler<GridModel> for GridSampler {
+    #[inline]
+    fn sample(&mut self, model: &GridModel, _: &Cell) -> &[Step] {
+        match model.connectivity {
+            Connectivity::Four => &FOUR_STEPS,
+            Connectivity::Eight => &EIGHT_STEPS,
+        }
+    }
+}
+
+/// A [`GridModel`] where each cell has a weight which multiplies what it costs to cross
+///
+/// Weights model terrain such as roads, mud or hills without writing a model of its own.  A
+/// step costs what it would on the plain grid, times the average of the weights of the two
+/// cells it joins, rounded up.  The heuristic is the plain grid's heuristic times the lowest
+/// weight on the grid, which keeps it admissible however the weights are set, though it is
+/// best informed when the lowest weight is common, as with open ground around costly patches.
+///
+/// Every cell starts out with a weight of `1.0`.  Weights must be positive.
+///
+/// ```
+/// # use game_lib::path::astar::AStar;
+/// # use game_lib::path::grid::{Cell, GridModel, GridSampler, WeightedGridModel};
+/// # use game_lib::path::Optimizer;
+/// let mut model = WeightedGridModel::new(GridModel::new(20, 20));
+/// // A patch of swamp between the start and the goal
+/// model.set_weight(10, 5, 10.0);
+/// let (start, goal) = (Cell::new(5, 5), Cell::new(15, 5));
+/// assert!(AStar::new().optimize(&mut model, &start, &goal, &mut GridSampler).is_final());
+/// ```
+#[derive(Clone, Debug)]
+pub struct WeightedGridModel {
+    grid: GridModel,
+    weights: Vec<f32>,
+    /// The lowest of the weights, which scales the heuristic
+    lowest: f32,
+}
+
+impl WeightedGridModel {
+    /// Weigh every cell of the grid the same as on a plain grid
+    pub fn new(grid: GridModel) -> Self {
+        let weights = vec![1.0; grid.width * grid.height];
+        Self::with_weights(grid, weights)
+    }
+
+    /// Weigh the cells of a grid with a row-major weight map
+    ///
+    /// # Panics
+    ///
+    /// When the weight map does not hold exactly one weight for every cell of the grid, or
+    /// when any weight is not positive.
+    pub fn with_weights(grid: GridModel, weights: Vec<f32>) -> Self {
+        assert_eq!(weights.len(), grid.width * grid.height, "the weights must cover the grid");
+        assert!(weights.iter().all(|&w| w > 0.0), "weights must be positive");
+
+        let lowest = weights.iter().cloned().fold(f32::INFINITY, f32::min);
+        WeightedGridModel { grid, weights, lowest }
+    }
+
+    pub fn grid(&self) -> &GridModel {
+        &self.grid
+    }
+
+    /// Change which cells are blocked, or the connectivity of the grid
+    pub fn grid_mut(&mut self) -> &mut GridModel {
+        &mut self.grid
+    }
+
+    /// The weight of the cell at `(x, y)`, where cells off the grid weigh nothing
+    pub fn weight(&self, x: i64, y: i64) -> f32 {
+        self.grid.index(x, y).map_or(0.0, |i| self.weights[i])
+    }
+
+    /// Change the weight of the cell at `(x, y)`
+    ///
+    /// # Panics
+    ///
+    /// When the cell is not on the grid, or the weight is not positive.
+    pub fn set_weight(&mut self, x: i64, y: i64, weight: f32) {
+        assert!(weight > 0.0, "weights must be positive");
+        let i = self.grid.index(x, y).expect("the cell must be on the grid");
+        let previous = std::mem::replace(&mut self.weights[i], weight);
+
+        if weight < self.lowest {
+            self.lowest = weight;
+        } else if previous == self.lowest {
+            self.lowest = self.weights.iter().cloned().fold(f32::INFINITY, f32::min);
+        }
+    }
+}
+
+impl Model for WeightedGridModel {
+    type State = Cell;
+    type Control = Step;
+    type Cost = usize;
+
+    fn cost(&self, current: &Cell, step: &Step, next: &Cell) -> usize {
+        let weight = (self.weight(current.x, current.y) + self.weight(next.x, next.y)) / 2.0;
+        (self.grid.cost(current, step, next) as f32 * weight).ceil() as usize
+    }
+
+    /// Nothing to do on initialization
+    fn init(&mut self, _: &Cell) {}
+
+    fn converge(&self, current: &Cell, goal: &Cell) -> bool {
+        current == goal
+    }
+
+    fn integrate(&self, previous: &Cell, step: &Step) -> Option<Cell> {
+        self.grid.integrate(previous, step)
+    }
+
+    fn is_valid(&self, cell: &Cell) -> bool {
+        self.grid.is_valid(cell)
+    }
+}
+
+impl HeuristicModel for WeightedGridModel {
+    /// The plain grid's heuristic for a grid which weighs the lowest weight everywhere
+    fn heuristic(&self, current: &Cell, goal: &Cell) -> usize {
+        (self.grid.heuristic(current, goal) as f32 * self.lowest) as usize
+    }
+}
+
+impl BoundedCostModel for WeightedGridModel {
+    fn max_edge_cost(&self) -> usize {
+        let highest = self.weights.iter().cloned().fold(0.0, f32::max);
+        (self.grid.max_edge_cost() as f32 * highest).ceil() as usize
+    }
+}
+
+impl ReversibleModel for WeightedGridModel {
+    fn integrate_reverse(&self, state: &Cell, step: &Step) -> Option<Cell> {
+        self.grid.integrate_reverse(state, step)
+    }
+}
+
+impl WaitableModel for WeightedGridModel {
+    fn wait(&self) -> Step {
+        Step::default()
+    }
+}
+
+impl Sampler<WeightedGridModel> for GridSampler {
+    #[inline]
+    fn sample(&mut self, model: &WeightedGridModel, current: &Cell) -> &[Step] {
+        Sampler::<GridModel>::sample(self, &model.grid, current)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::path::astar::AStar;
+    use crate::path::testing::*;
+
+    #[test]
+    fn trajectories_go_around_blocked_cells() {
+        let mut grid = GridModel::new(10, 10);
+        // A wall down the middle with a gap at the bottom
+        for y in 0..8 {
+            grid.set_blocked(5, y, true);
+        }
+        let (start, goal) = (Cell::new(2, 2), Cell::new(8, 2));
+        let t =
+            expect_final(AStar::new().optimize(&mut grid, &start, &goal, &mut GridSampler));
+        assert_eq!(t.trajectory.last().unwrap().0, goal);
+        assert!(t.trajectory.iter().all(|(cell, _)| !grid.is_blocked(cell.x, cell.y)));
+        assert!(t.trajectory.iter().any(|(cell, _)| cell.y >= 8));
+    }
+
+    #[test]
+    fn four_neighbours_only_take_straight_steps() {
+        let mut grid = GridModel::new(8, 8);
+        grid.set_connectivity(Connectivity::Four);
+        let (start, goal) = (Cell::new(1, 1), Cell::new(5, 4));
+        let t =
+            expect_final(AStar::new().optimize(&mut grid, &start, &goal, &mut GridSampler));
+        assert_eq!(t.cost, 2 * 7);
+        assert!(t.trajectory.iter().all(|(_, step)| step.dx == 0 || step.dy == 0));
+    }
+
+    #[test]
+    fn weighted_trajectories_go_around_costly_cells() {
+        // A stripe of swamp down the middle of the map, which stops short of the bottom
+        let mut model = WeightedGridModel::new(GridModel::new(20, 20));
+        for (x, y) in (10..13).flat_map(|x| (0..16).map(move |y| (x, y))) {
+            model.set_weight(x, y, 10.0);
+        }
+        let (start, goal) = (Cell::new(5, 5), Cell::new(15, 5));
+
+        let t =
+            expect_final(AStar::new().optimize(&mut model, &start, &goal, &mut GridSampler));
+        // The way around the bottom of the stripe is much longer, but cheaper than wading
+        // across
+        let across: usize = (5..15)
+            .map(|x| {
+                model.cost(&Cell::new(x, 5), &Step { dx: 1, dy: 0 }, &Cell::new(x + 1, 5))
+            })
+            .sum();
+        assert!(t.states().all(|cell| model.weight(cell.x, cell.y) == 1.0));
+        assert!(t.len() > 11);
+        assert!(t.cost < across);
+
+        // Without the swamp the path is straight across
+        let mut plain = model.grid().clone();
+        let t =
+            expect_final(AStar::new().optimize(&mut plain, &start, &goal, &mut GridSampler));
+        assert_eq!(t.len(), 11);
+    }
+
+    #[test]
+    fn only_the_corrected_diagonal_cost_tells_a_zigzag_apart() {
+        let (start, goal) = (Cell::new(2, 2), Cell::new(8, 5));
+        // Four steps up and one down on the way across, which ends where the diagonal does
+        let zigzag = [1, 1, -1, 1, 0, 1].iter().map(|&dy| Step { dx: 1, dy });
+        let cost = |grid: &GridModel| {
+            let mut cell = start;
+            zigzag.clone().fold(0, |total, step| {
+                let next = grid.integrate(&cell, &step).unwrap();
+                let total = total + grid.cost(&cell, &step, &next);
+                cell = next;
+                total
+            })
+        };
+
+        let corrected = GridModel::new(12, 12);
+        let mut uniform = corrected.clone();
+        uniform.diagonal_cost(false, 0);
+
+        for grid in &mut [corrected.clone(), uniform.clone()] {
+            let mut astar = AStar::new();
+            // Panics if the heuristic ever overestimates a step
+            astar.set_consistency_checks(true);
+            let t = expect_final(astar.optimize(grid, &start, &goal, &mut GridSampler));
+            assert_eq!(t.cost, grid.heuristic(&start, &goal));
+        }
+
+        assert!(cost(&corrected) > corrected.heuristic(&start, &goal));
+        assert_eq!(cost(&uniform), uniform.heuristic(&start, &goal));
+    }
+}
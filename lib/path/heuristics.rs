@@ -0,0 +1,114 @@
+/// Positions on a two dimensional grid which the distance heuristics can measure between
+pub trait Coord2D {
+    fn x(&self) -> i64;
+    fn y(&self) -> i64;
+}
+
+impl Coord2D for (i64, i64) {
+    fn x(&self) -> i64 {
+        self.0
+    }
+
+    fn y(&self) -> i64 {
+        self.1
+    }
+}
+
+/// The distance along each axis between two positions
+fn offsets<A: Coord2D, B: Coord2D>(a: &A, b: &B) -> (u64, u64) {
+    ((a.x() - b.x()).unsigned_abs(), (a.y() - b.y()).unsigned_abs())
+}
+
+/// Number of orthogonal steps between two positions
+///
+/// Admissible when only the four orthogonal neighbours can be reached in a step, and every
+/// step costs at least one.  Overestimates as soon as diagonal steps are allowed.
+///
+/// ```
+/// # use game_lib::path::heuristics::{euclidean, manhattan};
+/// assert_eq!(manhattan(&(1, 2), &(4, 6)), 7);
+/// assert_eq!(manhattan(&(4, 6), &(1, 2)), 7);
+///
+/// // The straight line is never longer than the way along the axes
+/// for b in [(0, 0), (3, 0), (-2, 5), (7, 7)] {
+///     assert!(manhattan(&(1, 1), &b) as f64 >= euclidean(&(1, 1), &b));
+/// }
+/// ```
+pub fn manhattan<A: Coord2D, B: Coord2D>(a: &A, b: &B) -> u64 {
+    let (dx, dy) = offsets(a, b);
+    dx + dy
+}
+
+/// Straight line distance between two positions
+///
+/// Admissible for any set of steps as long as a step costs at least the distance it covers,
+/// which makes it the heuristic for any-angle planners and for grids where diagonal steps are
+/// allowed.  It is lower than the octile distance on such grids, so it expands more nodes.
+///
+/// ```
+/// # use game_lib::path::heuristics::euclidean;
+/// assert_eq!(euclidean(&(1, 2), &(4, 6)), 5.0);
+/// assert_eq!(euclidean(&(3, 3), &(3, 3)), 0.0);
+/// ```
+pub fn euclidean<A: Coord2D, B: Coord2D>(a: &A, b: &B) -> f64 {
+    let (dx, dy) = offsets(a, b);
+    (dx as f64).hypot(dy as f64)
+}
+
+/// Cost of the cheapest trajectory on an open grid where diagonal steps cost the square root
+/// of two and orthogonal steps cost one
+///
+/// Admissible when all eight neighbours can be reached in a step with those costs, or more.
+/// Exact on an open grid, so it is the most informed of these heuristics for such grids.
+///
+/// ```
+/// # use game_lib::path::heuristics::octile;
+/// assert_eq!(octile(&(0, 0), &(5, 0)), 5.0);
+/// assert!((octile(&(0, 0), &(3, 5)) - (2.0 + 3.0 * 2f64.sqrt())).abs() < 1e-9);
+/// ```
+pub fn octile<A: Coord2D, B: Coord2D>(a: &A, b: &B) -> f64 {
+    let (dx, dy) = offsets(a, b);
+    let (long, short) = (dx.max(dy) as f64, dx.min(dy) as f64);
+    long + (std::f64::consts::SQRT_2 - 1.0) * short
+}
+
+/// Number of steps between two positions when diagonal steps cost the same as orthogonal ones
+///
+/// Admissible when all eight neighbours can be reached in a step and every step costs at least
+/// one.  When diagonal steps are more expensive it is still admissible, but less informed than
+/// [`octile`].
+///
+/// ```
+/// # use game_lib::path::heuristics::chebyshev;
+/// assert_eq!(chebyshev(&(1, 2), &(4, 6)), 4);
+/// assert_eq!(chebyshev(&(-3, 0), &(3, 1)), 6);
+/// ```
+pub fn chebyshev<A: Coord2D, B: Coord2D>(a: &A, b: &B) -> u64 {
+    let (dx, dy) = offsets(a, b);
+    dx.max(dy)
+}
+
+/// Number of steps between two positions on a hex grid, which are given in axial coordinates
+///
+/// The `x` of a position is read as the axial column `q` and the `y` as the axial row `r`, so
+/// the third cube coordinate is `-q - r`.  A step to one of the six neighbours changes two of
+/// the three cube coordinates by one each, which makes the distance half the sum of the
+/// offsets along the cube axes.  Admissible when every step costs at least one, and exact on
+/// an open hex grid.
+///
+/// ```
+/// # use game_lib::path::heuristics::hexagonal;
+/// # use game_lib::path::hex::Hex;
+/// // Each neighbour is one step away
+/// for &(q, r) in &[(1, 0), (1, -1), (0, -1), (-1, 0), (-1, 1), (0, 1)] {
+///     assert_eq!(hexagonal(&(0, 0), &(q, r)), 1);
+/// }
+/// let cube = |q, r, s| Hex::from_cube(q, r, s);
+/// assert_eq!(hexagonal(&cube(0, 0, 0), &cube(3, -1, -2)), 3);
+/// assert_eq!(hexagonal(&cube(-2, 3, -1), &cube(1, -2, 1)), 5);
+/// assert_eq!(hexagonal(&cube(2, 2, -4), &cube(-2, -2, 4)), 8);
+/// ```
+pub fn hexagonal<A: Coord2D, B: Coord2D>(a: &A, b: &B) -> u64 {
+    let (dq, dr) = (a.x() - b.x(), a.y() - b.y());
+    (dq.unsigned_abs() + dr.unsigned_abs() + (dq + dr).unsigned_abs()) / 2
+}
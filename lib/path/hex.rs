@@ -0,0 +1,263 @@
+use super::bucket::BoundedCostModel;
+use super::heuristics::{hexagonal, Coord2D};
+use super::*;
+
+/// Every step to a neighbour, counterclockwise from the one along the `q` axis
+static SIX_STEPS: [Step; 6] = [
+    Step { dq: 1, dr: 0 },
+    Step { dq: 1, dr: -1 },
+    Step { dq: 0, dr: -1 },
+    Step { dq: -1, dr: 0 },
+    Step { dq: -1, dr: 1 },
+    Step { dq: 0, dr: 1 },
+];
+
+/// A hexagon of a [`HexModel`], in axial coordinates
+///
+/// The axial column `q` and row `r` are two of the three cube coordinates, the third of which
+/// is `s = -q - r`.  Cube coordinates always sum to zero, which is why two are enough.  Moving
+/// to a neighbour changes two of the three by one each, in opposite directions.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Hex {
+    pub q: i64,
+    pub r: i64,
+}
+
+impl Hex {
+    pub fn new(q: i64, r: i64) -> Self {
+        Hex { q, r }
+    }
+
+    /// The hexagon at the cube coordinates `(q, r, s)`
+    ///
+    /// # Panics
+    ///
+    /// When the coordinates do not sum to zero.
+    pub fn from_cube(q: i64, r: i64, s: i64) -> Self {
+        assert_eq!(q + r + s, 0, "cube coordinates must sum to zero");
+        Hex { q, r }
+    }
+
+    /// The third cube coordinate
+    pub fn s(&self) -> i64 {
+        -self.q - self.r
+    }
+}
+
+impl State for Hex {
+    type Position = Hex;
+
+    /// The axial coordinates
+    fn grid_position(&self) -> Hex {
+        *self
+    }
+
+    /// Rounds the point between the hexagons in cube coordinates, to the hexagon it falls in
+    fn interpolate(&self, other: &Self, t: f64) -> Self {
+        let lerp = |a: i64, b: i64| a as f64 + (b - a) as f64 * t;
+        let (q, r, s) =
+            (lerp(self.q, other.q), lerp(self.r, other.r), lerp(self.s(), other.s()));
+        let (mut rq, mut rr, rs) = (q.round(), r.round(), s.round());
+
+        // Rounding each may break the sum, so the one which moved furthest gives way
+        let (dq, dr, ds) = ((rq - q).abs(), (rr - r).abs(), (rs - s).abs());
+        if dq > dr && dq > ds {
+            rq = -rr - rs;
+        } else if dr > ds {
+            rr = -rq - rs;
+        }
+
+        Hex { q: rq as i64, r: rr as i64 }
+    }
+}
+
+/// The box between the corners in axial coordinates, a rhombus on the map
+impl BoundedPosition for Hex {
+    fn within(&self, min: &Self, max: &Self) -> bool {
+        (min.q..=max.q).contains(&self.q) && (min.r..=max.r).contains(&self.r)
+    }
+}
+
+impl Coord2D for Hex {
+    fn x(&self) -> i64 {
+        self.q
+    }
+
+    fn y(&self) -> i64 {
+        self.r
+    }
+}
+
+/// A step from a hexagon to one of its six neighbours, in axial offsets
+///
+/// The default step stays in place, and is the control of the first state of a trajectory.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Step {
+    pub dq: i64,
+    pub dr: i64,
+}
+
+/// A map of hexagons which are either open or blocked, where every step costs one
+///
+/// Hexagons are addressed in axial coordinates, as described for [`Hex`], with `q` growing
+/// towards the east and `r` towards the south east on a map with pointy tops, or towards the
+/// south on one with flat tops.  The map holds the hexagons whose `q` is below the width and
+/// `r` below the height, both of them from zero, which makes it a rhombus; larger maps can
+/// block the corners to cut it down to another shape.  The heuristic is the [`hexagonal`]
+/// distance, which is exact on an open map.  A trajectory converges when it reaches the goal.
+///
+/// [`hexagonal`]: ../heuristics/fn.hexagonal.html
+///
+/// ```
+/// # use game_lib::path::astar::AStar;
+/// # use game_lib::path::hex::{Hex, HexModel, HexSampler};
+/// # use game_lib::path::Optimizer;
+/// let (mut model, start, goal) = (HexModel::new(12, 12), Hex::new(3, 4), Hex::new(9, 4));
+/// let result = AStar::new().optimize(&mut model, &start, &goal, &mut HexSampler);
+/// assert_eq!(result.trajectory().map(|t| t.cost), Some(6));
+/// ```
+#[derive(Clone, Debug)]
+pub struct HexModel {
+    width: usize,
+    height: usize,
+    blocked: Vec<bool>,
+}
+
+impl HexModel {
+    /// Create an open map of `width` columns and `height` rows
+    pub fn new(width: usize, height: usize) -> Self {
+        Self::with_occupancy(width, height, vec![false; width * height])
+    }
+
+    /// Create a map from an occupancy map, where `true` marks a blocked hexagon
+    ///
+    /// The occupancy map is in row-major order, so the hexagon at `(q, r)` is found at
+    /// `r * width + q`.
+    ///
+    /// # Panics
+    ///
+    /// When the occupancy map does not hold exactly `width * height` hexagons.
+    pub fn with_occupancy(width: usize, height: usize, blocked: Vec<bool>) -> Self {
+        assert_eq!(blocked.len(), width * height, "the occupancy map must cover the map");
+
+        HexModel { width, height, blocked }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Whether the hexagon at `(q, r)` cannot be entered, which includes every hexagon off the
+    /// map
+    pub fn is_blocked(&self, q: i64, r: i64) -> bool {
+        self.index(q, r).is_none_or(|i| self.blocked[i])
+    }
+
+    /// Block or open the hexagon at `(q, r)`
+    ///
+    /// # Panics
+    ///
+    /// When the hexagon is not on the map.
+    pub fn set_blocked(&mut self, q: i64, r: i64, blocked: bool) {
+        let i = self.index(q, r).expect("the hexagon must be on the map");
+        self.blocked[i] = blocked;
+    }
+
+    fn index(&self, q: i64, r: i64) -> Option<usize> {
+        if q < 0 || r < 0 || q as u64 >= self.width as u64 || r as u64 >= self.height as u64 {
+            return None;
+        }
+
+        Some(r as usize * self.width + q as usize)
+    }
+}
+
+impl Model for HexModel {
+    type State = Hex;
+    type Control = Step;
+    type Cost = usize;
+
+    fn cost(&self, _current: &Hex, _step: &Step, _next: &Hex) -> usize {
+        1
+    }
+
+    /// Nothing to do on initialization
+    fn init(&mut self, _: &Hex) {}
+
+    fn converge(&self, current: &Hex, goal: &Hex) -> bool {
+        current == goal
+    }
+
+    fn integrate(&self, previous: &Hex, step: &Step) -> Option<Hex> {
+        let next = Hex::new(previous.q + step.dq, previous.r + step.dr);
+
+        if self.is_blocked(next.q, next.r) {
+            None
+        } else {
+            Some(next)
+        }
+    }
+
+    /// Hexagons on the map which are not blocked
+    fn is_valid(&self, hex: &Hex) -> bool {
+        !self.is_blocked(hex.q, hex.r)
+    }
+}
+
+impl HeuristicModel for HexModel {
+    /// The number of steps to the goal if nothing were blocked
+    fn heuristic(&self, current: &Hex, goal: &Hex) -> usize {
+        hexagonal(current, goal) as usize
+    }
+}
+
+impl BoundedCostModel for HexModel {
+    fn max_edge_cost(&self) -> usize {
+        1
+    }
+}
+
+/// Samples a step to each of the six neighbours
+#[derive(Clone, Copy, Debug, Default)]
+pub struct HexSampler;
+
+impl Sampler<HexModel> for HexSampler {
+    #[inline]
+    fn sample(&mut self, _: &HexModel, _: &Hex) -> &[Step] {
+        &SIX_STEPS
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::path::astar::AStar;
+    use crate::path::dijkstra::Dijkstra;
+    use crate::path::heuristics::hexagonal;
+    use crate::path::testing::expect_final;
+
+    #[test]
+    fn trajectories_step_between_neighbouring_open_hexagons() {
+        let mut model = HexModel::new(12, 12);
+        // A wall along the `r` axis with a gap at the far end
+        for r in 0..9 {
+            model.set_blocked(6, r, true);
+        }
+        let (start, goal) = (Hex::new(3, 4), Hex::new(9, 4));
+
+        let mut astar = AStar::new();
+        astar.set_consistency_checks(true);
+        let a = expect_final(astar.optimize(&mut model, &start, &goal, &mut HexSampler));
+        let d =
+            expect_final(Dijkstra::new().optimize(&mut model, &start, &goal, &mut HexSampler));
+        assert_eq!(a.cost, d.cost);
+        assert!(a.cost > model.heuristic(&start, &goal));
+        assert!(a.states().all(|hex| !model.is_blocked(hex.q, hex.r)));
+        assert!(a.states().zip(a.states().skip(1)).all(|(a, b)| hexagonal(a, b) == 1));
+        assert!(a.states().any(|hex| hex.q == 6 && hex.r >= 9));
+    }
+}
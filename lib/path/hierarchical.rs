@@ -0,0 +1,298 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::fmt::{Debug, Formatter};
+
+use fnv::FnvHashMap;
+
+use super::astar::AStar;
+use super::*;
+
+/// Models with a coarse abstraction of their positions, such as a grid downsampled into blocks
+///
+/// Every step between two states must be matched by the abstraction: either both states map
+/// to the same abstract position, or the abstract position of the second is one of the coarse
+/// successors of the first, at a coarse cost no higher than the step.  The cheapest coarse
+/// route is then never more expensive than the trajectory it abstracts, which makes it an
+/// admissible and consistent heuristic.  The coarse graph must be undirected, with the same
+/// cost both ways across an edge.
+pub trait HierarchicalModel: HeuristicModel {
+    /// A region of positions which is planned over as one
+    type Abstract: Eq + Hash + Clone + Debug;
+
+    /// The region `state` is in
+    fn abstract_position(&self, state: &Self::State) -> Self::Abstract;
+
+    /// The regions which can be stepped into from `position`, with the coarse cost of doing so
+    fn coarse_successors(
+        &self,
+        position: &Self::Abstract,
+    ) -> Vec<(Self::Abstract, Self::Cost)>;
+}
+
+/// The fine model, with the coarse costs to the goal added to its heuristic
+struct Refined<'a, M>
+where
+    M: HierarchicalModel,
+{
+    model: &'a mut M,
+    coarse: &'a FnvHashMap<M::Abstract, M::Cost>,
+}
+
+impl<'a, M> Refined<'a, M>
+where
+    M: HierarchicalModel,
+    M::Cost: Copy,
+{
+    /// Raise the model's `estimate` to the cheapest coarse route from `current` to the goal
+    fn refine(&self, current: &M::State, estimate: M::Cost) -> M::Cost {
+        match self.coarse.get(&self.model.abstract_position(current)) {
+            Some(&coarse) if coarse > estimate => coarse,
+            _ => estimate,
+        }
+    }
+}
+
+impl<'a, M> Model for Refined<'a, M>
+where
+    M: HierarchicalModel,
+{
+    type State = M::State;
+    type Control = M::Control;
+    type Cost = M::Cost;
+
+    fn cost(&self, current: &M::State, control: &M::Control, next: &M::State) -> M::Cost {
+        self.model.cost(current, control, next)
+    }
+
+    fn init(&mut self, start: &M::State) {
+        self.model.init(start)
+    }
+
+    fn converge(&self, current: &M::State, goal: &M::State) -> bool {
+        self.model.converge(current, goal)
+    }
+
+    fn integrate(&self, previous: &M::State, control: &M::Control) -> Option<M::State> {
+        self.model.integrate(previous, control)
+    }
+
+    fn is_valid(&self, state: &M::State) -> bool {
+        self.model.is_valid(state)
+    }
+}
+
+impl<'a, M> HeuristicModel for Refined<'a, M>
+where
+    M: HierarchicalModel,
+    M::Cost: Copy,
+{
+    /// The larger of the model's heuristic and the cheapest coarse route to the goal
+    fn heuristic(&self, current: &M::State, goal: &M::State) -> M::Cost {
+        let estimate = self.model.heuristic(current, goal);
+        self.refine(current, estimate)
+    }
+
+    fn heuristic_opt(&self, current: &M::State, goal: &M::State) -> Option<M::Cost> {
+        let estimate = self.model.heuristic_opt(current, goal)?;
+        Some(self.refine(current, estimate))
+    }
+}
+
+/// Hands the controls of the model's sampler to the refined model
+struct RefinedSampler<'s, S>(&'s mut S);
+
+impl<'a, 's, M, S> Sampler<Refined<'a, M>> for RefinedSampler<'s, S>
+where
+    M: HierarchicalModel,
+    S: Sampler<M>,
+{
+    #[inline]
+    fn sample(&mut self, model: &Refined<'a, M>, current: &M::State) -> &[M::Control] {
+        self.0.sample(model.model, current)
+    }
+}
+
+/// A* guided by a search of a coarse abstraction of the model
+///
+/// Each query first finds the cheapest coarse route from every region to the region of the
+/// goal, with a uniform-cost search of the coarse graph outward from the goal.  The fine
+/// search is then A* with the larger of the model's heuristic and the coarse cost as its
+/// heuristic, which steers it away from regions the abstraction already knows to be dead ends.
+pub struct HierarchicalAStar<M>
+where
+    M: HierarchicalModel,
+{
+    /// The cheapest coarse route from each region to the region of the goal
+    coarse: FnvHashMap<M::Abstract, M::Cost>,
+    stats: Stats,
+}
+
+impl<M> HierarchicalAStar<M>
+where
+    M: HierarchicalModel,
+    M::Cost: radix_heap::Radix + Copy + Weighted,
+{
+    pub fn new() -> Self {
+        HierarchicalAStar { coarse: FnvHashMap::default(), stats: Stats::default() }
+    }
+
+    pub fn clear(&mut self) {
+        self.coarse.clear();
+        self.stats = Stats::default();
+    }
+
+    /// What the fine search of the last query did
+    pub fn stats(&self) -> &Stats {
+        &self.stats
+    }
+
+    /// The coarse cost from each region to the goal of the last query
+    pub fn coarse_costs(&self) -> impl Iterator<Item = (&M::Abstract, &M::Cost)> {
+        self.coarse.iter()
+    }
+
+    /// Calculate an optimal trajectory, searching the abstraction before the model
+    pub fn optimize<S>(
+        &mut self,
+        model: &mut M,
+        start: &M::State,
+        goal: &M::State,
+        sampler: &mut S,
+    ) -> PathResult<M>
+    where
+        S: Sampler<M>,
+    {
+        use PathResult::*;
+
+        self.flood(model, model.abstract_position(goal));
+
+        let mut astar = AStar::new();
+        let mut refined = Refined { model, coarse: &self.coarse };
+        let result = astar.optimize(&mut refined, start, goal, &mut RefinedSampler(sampler));
+        self.stats = astar.stats().clone();
+
+        let unrefine = |t: Trajectory<Refined<M>>| Trajectory {
+            cost: t.cost,
+            trajectory: t.trajectory,
+            expansions: t.expansions,
+        };
+        match result {
+            Final(t) => Final(unrefine(t)),
+            Intermediate(t) => Intermediate(unrefine(t)),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Find the cheapest coarse route from every region to `goal`
+    fn flood(&mut self, model: &M, goal: M::Abstract) {
+        self.coarse.clear();
+        self.coarse.insert(goal.clone(), M::Cost::default());
+
+        // Regions are kept aside so that the queue only needs to order the costs
+        let mut regions = vec![goal];
+        let mut queue = BinaryHeap::new();
+        queue.push((Reverse(M::Cost::default()), 0));
+
+        while let Some((Reverse(cost), index)) = queue.pop() {
+            let region = regions[index].clone();
+            if self.coarse.get(&region).is_some_and(|&best| best < cost) {
+                continue;
+            }
+
+            // The coarse graph is undirected, so its successors lead back towards the goal
+            for (next, step) in model.coarse_successors(&region) {
+                let next_cost = cost + step;
+                if self.coarse.get(&next).is_none_or(|&best| next_cost < best) {
+                    self.coarse.insert(next.clone(), next_cost);
+                    queue.push((Reverse(next_cost), regions.len()));
+                    regions.push(next);
+                }
+            }
+        }
+    }
+}
+
+impl<M> Default for HierarchicalAStar<M>
+where
+    M: HierarchicalModel,
+    M::Cost: radix_heap::Radix + Copy + Weighted,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<M> Debug for HierarchicalAStar<M>
+where
+    M: HierarchicalModel,
+{
+    fn fmt(&self, fmt: &mut Formatter) -> Result<(), std::fmt::Error> {
+        fmt.debug_struct("HierarchicalAStar")
+            .field("coarse", &self.coarse.len())
+            .field("stats", &self.stats)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::path::grid::{Cell, GridModel, GridSampler};
+    use crate::path::jps::Connectivity;
+    use crate::path::testing::*;
+
+    const SIZE: i64 = 2;
+
+    /// Blocks of 2 by 2 cells, which neighbour each other wherever a step crosses between them
+    impl HierarchicalModel for TestGrid {
+        type Abstract = (i64, i64);
+
+        fn abstract_position(&self, cell: &Cell) -> (i64, i64) {
+            (cell.x.div_euclid(SIZE), cell.y.div_euclid(SIZE))
+        }
+
+        fn coarse_successors(&self, &(bx, by): &(i64, i64)) -> Vec<((i64, i64), usize)> {
+            // The cells of the block along its side facing `d`, or the whole side across it
+            let side = |d: i64, b: i64, i: i64| match d {
+                0 => b * SIZE + i,
+                _ => b * SIZE + (d + 1) / 2 * (SIZE - 1),
+            };
+            let crosses = |&(dx, dy): &(i64, i64)| {
+                (0..SIZE).any(|i| {
+                    let (x, y) = (side(dx, bx, i), side(dy, by, i));
+                    !self.grid.is_blocked(x, y) && !self.grid.is_blocked(x + dx, y + dy)
+                })
+            };
+
+            // A step between blocks costs at least one straight step
+            let steps = [(1, 0), (-1, 0), (0, 1), (0, -1)];
+            let steps = steps.iter().filter(|d| crosses(d));
+            steps.map(|&(dx, dy)| ((bx + dx, by + dy), 2)).collect()
+        }
+    }
+
+    #[test]
+    fn the_abstraction_steers_the_search_away_from_dead_ends() {
+        // A comb of dead ends leading up from the bottom of the map towards the goal at the
+        // top, which is only reached by going all the way around the right hand side
+        let occupancy = (0..40 * 40).map(|i| {
+            let (x, y) = (i % 40, i / 40);
+            let row = (1..39).contains(&x) && (y == 1 || y == 38);
+            let column = x == 38 && (1..39).contains(&y);
+            let tooth = (2..37).contains(&x) && x % 2 == 0 && (3..38).contains(&y);
+            !row && !column && !tooth
+        });
+        let mut model = TestGrid::new(40, 40);
+        model.grid = GridModel::with_occupancy(40, 40, occupancy.collect());
+        model.grid.set_connectivity(Connectivity::Four);
+        let (start, goal) = (Cell::new(1, 38), Cell::new(1, 1));
+
+        let mut manhattan = AStar::new();
+        let mut hierarchical = HierarchicalAStar::new();
+        let a = expect_final(manhattan.optimize(&mut model, &start, &goal, &mut GridSampler));
+        let b =
+            expect_final(hierarchical.optimize(&mut model, &start, &goal, &mut GridSampler));
+        assert_eq!(a.cost, b.cost);
+        assert!(hierarchical.stats().expanded < manhattan.stats().expanded);
+    }
+}
@@ -0,0 +1,268 @@
+use std::f64::consts::PI;
+use std::fmt::{Debug, Formatter};
+use std::marker::PhantomData;
+
+use super::astar::AStar;
+use super::*;
+
+/// Continuous states of a vehicle with a position in the plane and a heading
+pub trait Oriented {
+    fn x(&self) -> f64;
+    fn y(&self) -> f64;
+
+    /// The heading in radians, anticlockwise from the positive x axis
+    fn heading(&self) -> f64;
+}
+
+/// A state along with the grid it is binned into
+#[derive(Clone, Debug)]
+struct Binned<S> {
+    state: S,
+    cell_size: f64,
+    heading_bins: usize,
+}
+
+impl<S> State for Binned<S>
+where
+    S: Oriented + State,
+{
+    /// The cell of the position, and the bin of the heading
+    type Position = (i64, i64, usize);
+
+    fn grid_position(&self) -> (i64, i64, usize) {
+        let bins = self.heading_bins as f64;
+        let heading = self.state.heading().rem_euclid(2.0 * PI) / (2.0 * PI) * bins;
+        (
+            (self.state.x() / self.cell_size).floor() as i64,
+            (self.state.y() / self.cell_size).floor() as i64,
+            heading.floor() as usize % self.heading_bins,
+        )
+    }
+
+    fn interpolate(&self, other: &Self, t: f64) -> Self {
+        Binned { state: self.state.interpolate(&other.state, t), ..*self }
+    }
+}
+
+/// The model, planning over its binned states
+struct Hybrid<'a, M> {
+    model: &'a mut M,
+    cell_size: f64,
+    heading_bins: usize,
+}
+
+impl<'a, M> Hybrid<'a, M>
+where
+    M: HeuristicModel,
+    M::State: Oriented,
+{
+    fn bin(&self, state: M::State) -> Binned<M::State> {
+        Binned { state, cell_size: self.cell_size, heading_bins: self.heading_bins }
+    }
+}
+
+impl<'a, M> Model for Hybrid<'a, M>
+where
+    M: HeuristicModel,
+    M::State: Oriented,
+{
+    type State = Binned<M::State>;
+    type Control = M::Control;
+    type Cost = M::Cost;
+
+    fn cost(
+        &self,
+        current: &Self::State,
+        control: &M::Control,
+        next: &Self::State,
+    ) -> M::Cost {
+        self.model.cost(&current.state, control, &next.state)
+    }
+
+    fn init(&mut self, start: &Self::State) {
+        self.model.init(&start.state)
+    }
+
+    fn converge(&self, current: &Self::State, goal: &Self::State) -> bool {
+        self.model.converge(&current.state, &goal.state)
+    }
+
+    fn integrate(&self, previous: &Self::State, control: &M::Control) -> Option<Self::State> {
+        let next = self.model.integrate(&previous.state, control)?;
+        Some(self.bin(next))
+    }
+
+    fn is_valid(&self, state: &Self::State) -> bool {
+        self.model.is_valid(&state.state)
+    }
+}
+
+impl<'a, M> HeuristicModel for Hybrid<'a, M>
+where
+    M: HeuristicModel,
+    M::State: Oriented,
+{
+    fn heuristic(&self, current: &Self::State, goal: &Self::State) -> M::Cost {
+        self.model.heuristic(&current.state, &goal.state)
+    }
+
+    fn heuristic_opt(&self, current: &Self::State, goal: &Self::State) -> Option<M::Cost> {
+        self.model.heuristic_opt(&current.state, &goal.state)
+    }
+}
+
+/// Hands the controls of the model's sampler to the binned model
+struct HybridSampler<'s, S>(&'s mut S);
+
+impl<'a, 's, M, S> Sampler<Hybrid<'a, M>> for HybridSampler<'s, S>
+where
+    M: HeuristicModel,
+    M::State: Oriented,
+    S: Sampler<M>,
+{
+    #[inline]
+    fn sample(&mut self, model: &Hybrid<'a, M>, current: &Binned<M::State>) -> &[M::Control] {
+        self.0.sample(model.model, &current.state)
+    }
+}
+
+/// A* over continuous vehicle states, which only keeps one state for each cell and heading
+///
+/// The usual way to plan for car-like vehicles: every node holds the exact continuous state
+/// the controls integrate to, so the trajectory is one the vehicle can really drive, but the
+/// search treats states as the same when their positions fall in the same cell of a grid and
+/// their headings in the same bin.  Of those, only the cheapest found so far is kept, which
+/// bounds the search by the size of the grid instead of letting it wander through an endless
+/// continuum of almost identical states.
+///
+/// Finer cells and more heading bins find cheaper trajectories through tighter gaps, at the
+/// cost of expanding more nodes.  The trajectory is only optimal up to the discretization,
+/// as a state later pruned from a bin might have led somewhere cheaper.
+///
+/// ```
+/// # use game_lib::path::hybrid::HybridAStar;
+/// # use game_lib::path::lattice::LatticeSampler;
+/// # use game_lib::path::models::dubins::{DubinsCar, Pose, Turn};
+/// let mut car = DubinsCar::new(20.0, 12.0, 2.0, 1.0);
+/// let (start, goal) = (Pose::new(3.0, 6.0, 0.0), Pose::new(16.0, 6.0, 0.0));
+/// // Cells half a unit across, and headings in bins of five degrees
+/// let mut hybrid = HybridAStar::new(0.5, 72);
+/// let mut sampler = LatticeSampler::new(Turn::ALL.to_vec());
+/// assert!(hybrid.optimize(&mut car, &start, &goal, &mut sampler).is_final());
+/// ```
+pub struct HybridAStar<M>
+where
+    M: HeuristicModel,
+    M::State: Oriented,
+{
+    cell_size: f64,
+    heading_bins: usize,
+    stats: Stats,
+    _model: PhantomData<M>,
+}
+
+impl<M> HybridAStar<M>
+where
+    M: HeuristicModel,
+    M::State: Oriented,
+    M::Cost: radix_heap::Radix + Copy + Weighted,
+{
+    /// Bin positions into squares `cell_size` wide, and headings into `heading_bins` ranges
+    ///
+    /// # Panics
+    ///
+    /// When there are no heading bins.
+    pub fn new(cell_size: f64, heading_bins: usize) -> Self {
+        assert!(heading_bins > 0, "headings need at least one bin");
+        HybridAStar { cell_size, heading_bins, stats: Stats::default(), _model: PhantomData }
+    }
+
+    pub fn cell_size(&self) -> f64 {
+        self.cell_size
+    }
+
+    pub fn heading_bins(&self) -> usize {
+        self.heading_bins
+    }
+
+    /// What the last query did
+    pub fn stats(&self) -> &Stats {
+        &self.stats
+    }
+
+    /// Calculate a trajectory of continuous states, keeping one state for each bin
+    pub fn optimize<S>(
+        &mut self,
+        model: &mut M,
+        start: &M::State,
+        goal: &M::State,
+        sampler: &mut S,
+    ) -> PathResult<M>
+    where
+        S: Sampler<M>,
+    {
+        use PathResult::*;
+
+        let mut hybrid =
+            Hybrid { model, cell_size: self.cell_size, heading_bins: self.heading_bins };
+        let (start, goal) = (hybrid.bin(start.clone()), hybrid.bin(goal.clone()));
+
+        let mut astar = AStar::new();
+        let result = astar.optimize(&mut hybrid, &start, &goal, &mut HybridSampler(sampler));
+        self.stats = astar.stats().clone();
+
+        let unbin = |t: Trajectory<Hybrid<M>>| Trajectory {
+            cost: t.cost,
+            trajectory: t.trajectory.into_iter().map(|(s, c)| (s.state, c)).collect(),
+            expansions: t.expansions,
+        };
+        match result {
+            Final(t) => Final(unbin(t)),
+            Intermediate(t) => Intermediate(unbin(t)),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+impl<M> Debug for HybridAStar<M>
+where
+    M: HeuristicModel,
+    M::State: Oriented,
+{
+    fn fmt(&self, fmt: &mut Formatter) -> Result<(), std::fmt::Error> {
+        fmt.debug_struct("HybridAStar")
+            .field("cell_size", &self.cell_size)
+            .field("heading_bins", &self.heading_bins)
+            .field("stats", &self.stats)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::path::lattice::LatticeSampler;
+    use crate::path::models::dubins::{DubinsCar, Pose, Turn};
+    use crate::path::testing::expect_final;
+
+    #[test]
+    fn trajectories_are_driven_exactly_by_the_controls() {
+        let mut car = DubinsCar::new(20.0, 12.0, 2.0, 1.0);
+        car.add_obstacle(10.0, 6.0, 2.5);
+
+        let (start, goal) = (Pose::new(3.0, 6.0, 0.0), Pose::new(16.0, 8.0, PI));
+        let mut hybrid = HybridAStar::new(0.5, 72);
+        let mut sampler = LatticeSampler::new(Turn::ALL.to_vec());
+        let t = expect_final(hybrid.optimize(&mut car, &start, &goal, &mut sampler));
+        assert!(car.converge(t.states().last().unwrap(), &goal));
+        for pair in t.trajectory.windows(2) {
+            let ((from, _), (to, turn)) = (&pair[0], &pair[1]);
+            // The states are exactly where the controls drive, not the centres of cells
+            assert_eq!(car.integrate(from, turn), Some(*to));
+            // And they never turn tighter than the radius allows
+            let turned = (to.heading - from.heading).sin().abs();
+            assert!(turned <= (car.step() / car.radius()).sin() + 1e-9);
+        }
+        assert!(hybrid.stats().expanded > 0);
+    }
+}
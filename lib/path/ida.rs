@@ -0,0 +1,305 @@
+use std::fmt::{Debug, Formatter};
+
+use super::*;
+
+/// Outcome of a single depth-first pass bounded by a threshold
+enum Pass<C> {
+    /// The goal was reached at the given cost
+    Found(C),
+    /// The lowest estimate of the nodes which were cut off by the threshold
+    Exceeded(C),
+    /// Every trajectory from the start was followed without reaching the goal
+    Exhausted,
+}
+
+/// Iterative Deepening A*, which only keeps the trajectory being explored in memory
+///
+/// Each pass is a depth-first search which cuts off every node whose estimated cost exceeds
+/// the threshold, starting with the heuristic of the start state.  When a pass fails to reach
+/// the goal, the threshold is raised to the lowest estimate that was cut off and the search
+/// starts over.  Memory grows with the length of the trajectory instead of with the number of
+/// nodes discovered, at the price of expanding nodes again on every pass.  States are only
+/// checked against the trajectory leading to them, so the same state can be reached many
+/// times over different trajectories; this is best suited to small or open problems.
+///
+/// ```
+/// # use game_lib::path::grid::{Cell, GridModel, GridSampler};
+/// # use game_lib::path::{ida::IdaStar, Optimizer};
+/// let (mut ida, mut grid) = (IdaStar::new(), GridModel::new(10, 10));
+/// let (start, goal) = (Cell::new(2, 5), Cell::new(8, 4));
+/// assert!(ida.optimize(&mut grid, &start, &goal, &mut GridSampler).is_final());
+/// ```
+pub struct IdaStar<M>
+where
+    M: HeuristicModel,
+    M::Cost: Copy,
+{
+    /// The trajectory currently being explored, starting with the start state
+    path: Vec<(M::State, M::Control)>,
+    /// The trajectory to the node which set the threshold of the next pass
+    frontier: Vec<(M::State, M::Control)>,
+    threshold: Option<M::Cost>,
+    next_threshold: Option<M::Cost>,
+    /// The controls sampled at each depth of the path, kept to reuse their allocations
+    buffers: Vec<Vec<M::Control>>,
+    stats: Stats,
+}
+
+impl<M> IdaStar<M>
+where
+    M: HeuristicModel,
+    M::Cost: Copy,
+{
+    /// Create a new IDA* optimizer
+    pub fn new() -> Self {
+        IdaStar {
+            path: Vec::new(),
+            frontier: Vec::new(),
+            threshold: None,
+            next_threshold: None,
+            buffers: Vec::new(),
+            stats: Stats::default(),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.path.clear();
+        self.frontier.clear();
+        self.threshold = None;
+        self.next_threshold = None;
+        self.stats = Stats::default();
+    }
+
+    /// Counters describing the work done since the optimizer was created or last cleared
+    ///
+    /// Nodes are counted every time they are expanded, including on later passes.
+    pub fn stats(&self) -> &Stats {
+        &self.stats
+    }
+
+    /// The estimated cost above which the next pass cuts nodes off
+    pub fn threshold(&self) -> Option<M::Cost> {
+        self.threshold
+    }
+
+    /// Run a single pass from the start state with the current threshold
+    fn pass<S>(
+        &mut self,
+        model: &M,
+        start: &M::State,
+        goal: &M::State,
+        sampler: &mut S,
+    ) -> Pass<M::Cost>
+    where
+        S: Sampler<M>,
+    {
+        let threshold = *self.threshold.get_or_insert_with(|| model.heuristic(start, goal));
+
+        self.path.clear();
+        self.path.push((start.clone(), Default::default()));
+        self.next_threshold = None;
+
+        self.search(model, goal, sampler, Default::default(), threshold)
+    }
+
+    /// Depth-first search below the last state of the path
+    fn search<S>(
+        &mut self,
+        model: &M,
+        goal: &M::State,
+        sampler: &mut S,
+        cost: M::Cost,
+        threshold: M::Cost,
+    ) -> Pass<M::Cost>
+    where
+        S: Sampler<M>,
+    {
+        let current = match self.path.last() {
+            Some((state, _)) => state.clone(),
+            None => return Pass::Exhausted,
+        };
+
+        let estimate = cost + model.heuristic(&current, goal);
+        if estimate > threshold {
+            if self.next_threshold.is_none_or(|next| estimate < next) {
+                self.next_threshold = Some(estimate);
+                self.frontier = self.path.clone();
+            }
+            return Pass::Exceeded(estimate);
+        }
+
+        if model.converge(&current, goal) {
+            return Pass::Found(cost);
+        }
+        self.stats.expanded += 1;
+
+        // The controls are copied so the sampler is free to be used further down, into a buffer
+        // for each depth so that later passes do not allocate
+        let depth = self.path.len() - 1;
+        if self.buffers.len() <= depth {
+            self.buffers.resize_with(depth + 1, Vec::new);
+        }
+        let mut controls = std::mem::take(&mut self.buffers[depth]);
+        sampler.sample_into(model, &current, &mut controls);
+
+        let mut lowest = None;
+        let mut found = None;
+        for control in &controls {
+            if let Some(child) = model.integrate(&current, control) {
+                let position = child.grid_position();
+                if self.path.iter().any(|(state, _)| state.grid_position() == position) {
+                    continue;
+                }
+                self.stats.generated += 1;
+
+                let child_cost = cost + model.cost(&current, control, &child);
+                self.path.push((child, control.clone()));
+                match self.search(model, goal, sampler, child_cost, threshold) {
+                    Pass::Found(total) => {
+                        found = Some(total);
+                        break;
+                    }
+                    Pass::Exceeded(estimate) => {
+                        if lowest.is_none_or(|lowest| estimate < lowest) {
+                            lowest = Some(estimate);
+                        }
+                    }
+                    Pass::Exhausted => {}
+                }
+                self.path.pop();
+            }
+        }
+        self.buffers[depth] = controls;
+
+        match found {
+            Some(total) => Pass::Found(total),
+            None => lowest.map_or(Pass::Exhausted, Pass::Exceeded),
+        }
+    }
+
+    /// The trajectory along the path the last pass found to the goal
+    fn found(&self, cost: M::Cost) -> Trajectory<M> {
+        Trajectory { cost, trajectory: self.path.clone(), expansions: self.stats.expanded }
+    }
+}
+
+impl<M, S> Optimizer<M, S> for IdaStar<M>
+where
+    M: HeuristicModel,
+    M::Cost: Copy,
+    S: Sampler<M>,
+{
+    /// Run one pass with the current threshold
+    ///
+    /// An intermediate result holds the trajectory to the node which set the threshold of the
+    /// next pass, which is where that pass will push furthest.
+    fn next_trajectory(
+        &mut self,
+        model: &mut M,
+        start: &M::State,
+        goal: &M::State,
+        sampler: &mut S,
+    ) -> PathResult<M> {
+        use PathFindingErr::*;
+        use PathResult::*;
+
+        if let Some(invalid) = invalid_endpoint(model, start, goal) {
+            return Err(invalid);
+        }
+
+        match self.pass(model, start, goal, sampler) {
+            Pass::Found(cost) => Final(self.found(cost)),
+            Pass::Exceeded(next) => {
+                self.threshold = Some(next);
+                Intermediate(Trajectory {
+                    cost: next,
+                    trajectory: self.frontier.clone(),
+                    expansions: self.stats.expanded,
+                })
+            }
+            Pass::Exhausted => Err(Unreachable),
+        }
+    }
+
+    fn optimize(
+        &mut self,
+        model: &mut M,
+        start: &M::State,
+        goal: &M::State,
+        sampler: &mut S,
+    ) -> PathResult<M> {
+        use PathFindingErr::*;
+        use PathResult::*;
+
+        if let Some(invalid) = invalid_endpoint(model, start, goal) {
+            return Err(invalid);
+        }
+
+        if model.converge(start, goal) {
+            return Final(Trajectory {
+                cost: Default::default(),
+                trajectory: vec![(start.clone(), Default::default())],
+                expansions: 0,
+            });
+        }
+
+        loop {
+            match self.pass(model, start, goal, sampler) {
+                Pass::Found(cost) => return Final(self.found(cost)),
+                Pass::Exceeded(next) => self.threshold = Some(next),
+                Pass::Exhausted => return Err(Unreachable),
+            }
+        }
+    }
+}
+
+impl<M> Debug for IdaStar<M>
+where
+    M: HeuristicModel,
+    M::State: Debug,
+    M::Control: Debug,
+    M::Cost: Debug + Copy,
+{
+    fn fmt(&self, fmt: &mut Formatter) -> Result<(), std::fmt::Error> {
+        fmt.debug_struct("IdaStar")
+            .field("threshold", &self.threshold)
+            .field("stats", &self.stats)
+            .field("path", &self.path)
+            .finish()
+    }
+}
+
+impl<M> Default for IdaStar<M>
+where
+    M: HeuristicModel,
+    M::Cost: Copy,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::actor::{Heuristic, TurnOptimal, WalkSampler};
+    use crate::map::Tile;
+    use crate::path::astar::AStar;
+    use crate::path::testing::*;
+
+    #[test]
+    fn finds_the_same_cost_as_astar_around_a_wall() {
+        let mut map = room(10, 10);
+        for y in 2..8 {
+            map[(5, y)] = Tile::WALL;
+        }
+        let mut model = TurnOptimal::new(map);
+        model.set_heuristic(Heuristic::Chebyshev);
+        let (start, goal) = (walker(2, 5), walker(8, 4));
+
+        let mut sampler = WalkSampler::new();
+        let a = expect_final(AStar::new().optimize(&mut model, &start, &goal, &mut sampler));
+        let i = expect_final(IdaStar::new().optimize(&mut model, &start, &goal, &mut sampler));
+        assert_eq!(a.cost, i.cost);
+    }
+}
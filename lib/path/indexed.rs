@@ -0,0 +1,464 @@
+use std::fmt::{Debug, Formatter};
+
+use fnv::{FnvHashMap, FnvHashSet};
+use std::collections::hash_map::Entry;
+use std::hash::Hash;
+
+use super::astar::{Id, Node};
+use super::*;
+
+/// Binary max-heap which holds at most one value per key
+///
+/// Pushing a value for a key which is already queued keeps only the value with the highest
+/// priority, moving it in place rather than queueing a duplicate.  For a queue of nodes
+/// ordered by lowest cost, this is the decrease-key operation.
+///
+/// ```
+/// # use game_lib::path::indexed::IndexedHeap;
+/// let mut heap = IndexedHeap::new();
+/// heap.push('a', 1);
+/// heap.push('b', 5);
+/// heap.push('a', 7);
+/// heap.push('b', 2);
+///
+/// assert_eq!(heap.len(), 2);
+/// assert_eq!(heap.pop(), Some(('a', 7)));
+/// assert_eq!(heap.pop(), Some(('b', 5)));
+/// assert_eq!(heap.pop(), None);
+/// ```
+pub struct IndexedHeap<K, V>
+where
+    K: Hash + Eq + Clone,
+    V: Ord,
+{
+    heap: Vec<(K, V)>,
+    index: FnvHashMap<K, usize>,
+}
+
+impl<K, V> IndexedHeap<K, V>
+where
+    K: Hash + Eq + Clone,
+    V: Ord,
+{
+    pub fn new() -> Self {
+        IndexedHeap { heap: Vec::new(), index: FnvHashMap::default() }
+    }
+
+    pub fn len(&self) -> usize {
+        self.heap.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.heap.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.heap.clear();
+        self.index.clear();
+    }
+
+    pub fn contains(&self, key: &K) -> bool {
+        self.index.contains_key(key)
+    }
+
+    /// The value queued for `key`, if there is one
+    pub fn get(&self, key: &K) -> Option<&V> {
+        self.index.get(key).map(|&i| &self.heap[i].1)
+    }
+
+    /// The entry with the highest priority
+    pub fn peek(&self) -> Option<(&K, &V)> {
+        self.heap.first().map(|(k, v)| (k, v))
+    }
+
+    /// Iterate over the queued entries in no particular order
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
+        self.heap.iter().map(|(k, v)| (k, v))
+    }
+
+    /// Queue `value` for `key`, or raise the priority of the value already queued for `key`
+    ///
+    /// Returns `false`, leaving the heap untouched, when the queued value already has at
+    /// least the priority of `value`.
+    pub fn push(&mut self, key: K, value: V) -> bool {
+        match self.index.entry(key.clone()) {
+            Entry::Occupied(queued) => {
+                let i = *queued.get();
+                if self.heap[i].1 >= value {
+                    return false;
+                }
+                self.heap[i].1 = value;
+                self.sift_up(i);
+            }
+            Entry::Vacant(empty) => {
+                empty.insert(self.heap.len());
+                self.heap.push((key, value));
+                self.sift_up(self.heap.len() - 1);
+            }
+        }
+
+        true
+    }
+
+    /// Remove the entry with the highest priority
+    pub fn pop(&mut self) -> Option<(K, V)> {
+        if self.heap.is_empty() {
+            return None;
+        }
+
+        let last = self.heap.len() - 1;
+        self.swap(0, last);
+        let (key, value) = self.heap.pop()?;
+        self.index.remove(&key);
+
+        if !self.heap.is_empty() {
+            self.sift_down(0);
+        }
+
+        Some((key, value))
+    }
+
+    fn sift_up(&mut self, mut i: usize) {
+        while i > 0 {
+            let parent = (i - 1) / 2;
+            if self.heap[i].1 <= self.heap[parent].1 {
+                break;
+            }
+            self.swap(i, parent);
+            i = parent;
+        }
+    }
+
+    fn sift_down(&mut self, mut i: usize) {
+        loop {
+            let (left, right) = (2 * i + 1, 2 * i + 2);
+            let mut largest = i;
+            if left < self.heap.len() && self.heap[left].1 > self.heap[largest].1 {
+                largest = left;
+            }
+            if right < self.heap.len() && self.heap[right].1 > self.heap[largest].1 {
+                largest = right;
+            }
+            if largest == i {
+                break;
+            }
+            self.swap(i, largest);
+            i = largest;
+        }
+    }
+
+    /// Swap two entries, keeping the index up to date
+    fn swap(&mut self, a: usize, b: usize) {
+        self.heap.swap(a, b);
+        self.index.insert(self.heap[a].0.clone(), a);
+        self.index.insert(self.heap[b].0.clone(), b);
+    }
+}
+
+impl<K, V> Debug for IndexedHeap<K, V>
+where
+    K: Hash + Eq + Clone + Debug,
+    V: Ord + Debug,
+{
+    fn fmt(&self, fmt: &mut Formatter) -> Result<(), std::fmt::Error> {
+        fmt.debug_list().entries(self.heap.iter()).finish()
+    }
+}
+
+impl<K, V> Default for IndexedHeap<K, V>
+where
+    K: Hash + Eq + Clone,
+    V: Ord,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A* optimizer which keeps a single queue entry per grid position
+///
+/// [`AStar`] queues a duplicate whenever a cheaper path to a position is found and skips the
+/// stale entry later.  This optimizer instead updates the queued entry in place with an
+/// [`IndexedHeap`], so the queue never holds more than one node per position.  That keeps
+/// memory down on maps where positions are rediscovered often, at the price of maintaining
+/// the index on every push and pop.
+///
+/// ```
+/// # use game_lib::path::grid::{Cell, GridModel, GridSampler};
+/// # use game_lib::path::{indexed::IndexedAStar, Optimizer};
+/// let (mut indexed, mut grid) = (IndexedAStar::new(), GridModel::new(40, 40));
+/// let (start, goal) = (Cell::new(2, 20), Cell::new(37, 20));
+/// assert!(indexed.optimize(&mut grid, &start, &goal, &mut GridSampler).is_final());
+/// ```
+///
+/// [`AStar`]: ../astar/struct.AStar.html
+/// [`IndexedHeap`]: struct.IndexedHeap.html
+pub struct IndexedAStar<M>
+where
+    M: HeuristicModel,
+    M::Cost: Copy,
+{
+    queue: IndexedHeap<<<M as Model>::State as State>::Position, Node<M>>,
+    parent_map: FnvHashMap<Id<M>, Node<M>>,
+    grid: FnvHashMap<<<M as Model>::State as State>::Position, Id<M>>,
+    closed: FnvHashSet<<<M as Model>::State as State>::Position>,
+    id_counter: usize,
+    stats: Stats,
+}
+
+impl<M> IndexedAStar<M>
+where
+    M: HeuristicModel,
+    M::Cost: Copy,
+{
+    /// Create a new indexed A* optimizer
+    pub fn new() -> Self {
+        IndexedAStar {
+            queue: IndexedHeap::new(),
+            parent_map: FnvHashMap::default(),
+            grid: FnvHashMap::default(),
+            closed: FnvHashSet::default(),
+            id_counter: 0,
+            stats: Stats::default(),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.queue.clear();
+        self.parent_map.clear();
+        self.grid.clear();
+        self.closed.clear();
+        self.stats = Stats::default();
+    }
+
+    /// Counters describing the work done since the optimizer was created or last cleared
+    pub fn stats(&self) -> &Stats {
+        &self.stats
+    }
+
+    pub fn inspect_queue(&self) -> impl Iterator<Item = (&M::State, &M::Control)> {
+        self.queue.iter().map(|(_, node)| (&node.state, &node.control))
+    }
+
+    pub fn inspect_discovered(
+        &self,
+    ) -> impl Iterator<Item = &<<M as Model>::State as State>::Position> {
+        self.grid.keys()
+    }
+
+    #[inline(always)]
+    fn step<S>(
+        &mut self,
+        current: &Node<M>,
+        model: &mut M,
+        goal: &M::State,
+        sampler: &mut S,
+    ) -> bool
+    where
+        S: Sampler<M>,
+    {
+        if model.converge(&current.state, goal) {
+            return true;
+        }
+
+        self.closed.insert(current.state.grid_position());
+        self.stats.expanded += 1;
+
+        for control in sampler.sample(model, &current.state) {
+            if let Some(child_state) = model.integrate(&current.state, control) {
+                self.id_counter += 1;
+                self.stats.generated += 1;
+
+                let position = child_state.grid_position();
+                if self.closed.contains(&position) {
+                    continue;
+                }
+
+                let cost = current.id.g() + model.cost(&current.state, control, &child_state);
+                let heuristic = model.heuristic(&child_state, goal);
+
+                let child = Node::<M> {
+                    id: Id::new(self.id_counter, cost + heuristic, cost),
+                    state: child_state,
+                    control: control.clone(),
+                };
+
+                match self.grid.entry(position.clone()) {
+                    Entry::Occupied(mut best) => {
+                        let best = best.get_mut();
+                        if best.g <= child.id.g {
+                            continue;
+                        } else {
+                            *best = child.id.clone();
+                        }
+                    }
+                    Entry::Vacant(empty) => {
+                        empty.insert(child.id.clone());
+                    }
+                }
+
+                self.parent_map.insert(child.id.clone(), current.clone());
+                self.queue.push(position, child);
+                self.stats.max_queue_len = self.stats.max_queue_len.max(self.queue.len());
+            }
+        }
+
+        false
+    }
+
+    /// Follow the parents from the goal node up to the start node
+    fn unwind_trajectory(&self, mut current: Node<M>) -> Trajectory<M> {
+        let cost = current.id.g();
+        let mut result = Vec::new();
+        result.push((current.state.clone(), current.control.clone()));
+
+        while let Some(p) = self.parent_map.get(&current.id) {
+            current = (*p).clone();
+            result.push((current.state.clone(), current.control.clone()));
+        }
+
+        result.reverse();
+
+        Trajectory { cost, trajectory: result, expansions: self.stats.expanded }
+    }
+
+    fn seed(&mut self, model: &M, start: &M::State, goal: &M::State) {
+        let start_id = Id::new(0, model.heuristic(start, goal), Default::default());
+        self.grid.insert(start.grid_position(), start_id.clone());
+        self.queue.push(
+            start.grid_position(),
+            Node { id: start_id, state: start.clone(), control: Default::default() },
+        );
+    }
+}
+
+impl<M, S> Optimizer<M, S> for IndexedAStar<M>
+where
+    M: HeuristicModel,
+    M::Cost: Copy,
+    S: Sampler<M>,
+{
+    fn next_trajectory(
+        &mut self,
+        model: &mut M,
+        start: &M::State,
+        goal: &M::State,
+        sampler: &mut S,
+    ) -> PathResult<M> {
+        use PathFindingErr::*;
+        use PathResult::*;
+
+        if let Some(invalid) = invalid_endpoint(model, start, goal) {
+            return Err(invalid);
+        }
+
+        if self.parent_map.is_empty() && self.queue.is_empty() {
+            self.seed(model, start, goal);
+        }
+
+        if let Some((_, current)) = self.queue.pop() {
+            if self.step(&current, model, goal, sampler) {
+                Final(self.unwind_trajectory(current))
+            } else {
+                Intermediate(self.unwind_trajectory(current))
+            }
+        } else {
+            Err(Unreachable)
+        }
+    }
+
+    fn optimize(
+        &mut self,
+        model: &mut M,
+        start: &M::State,
+        goal: &M::State,
+        sampler: &mut S,
+    ) -> PathResult<M> {
+        use PathFindingErr::*;
+        use PathResult::*;
+
+        if let Some(invalid) = invalid_endpoint(model, start, goal) {
+            return Err(invalid);
+        }
+
+        if model.converge(start, goal) {
+            return Final(Trajectory {
+                cost: Default::default(),
+                trajectory: vec![(start.clone(), Default::default())],
+                expansions: 0,
+            });
+        }
+
+        if self.parent_map.is_empty() && self.queue.is_empty() {
+            self.seed(model, start, goal);
+        }
+
+        while let Some((_, current)) = self.queue.pop() {
+            if self.step(&current, model, goal, sampler) {
+                return Final(self.unwind_trajectory(current));
+            }
+        }
+
+        Err(Unreachable)
+    }
+}
+
+impl<M> Debug for IndexedAStar<M>
+where
+    M: HeuristicModel,
+    M::State: Debug,
+    M::Control: Debug,
+    M::Cost: Debug + Copy,
+{
+    fn fmt(&self, fmt: &mut Formatter) -> Result<(), std::fmt::Error> {
+        fmt.debug_struct("IndexedAStar")
+            .field("counter", &self.id_counter)
+            .field("next", &self.queue.peek())
+            .field("queue", &self.queue)
+            .field("grid", &self.grid)
+            .field("closed", &self.closed)
+            .field("stats", &self.stats)
+            .field("parent_map", &self.parent_map)
+            .finish()
+    }
+}
+
+impl<M> Default for IndexedAStar<M>
+where
+    M: HeuristicModel,
+    M::Cost: Copy,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::actor::{Heuristic, TurnOptimal, WalkSampler};
+    use crate::map::Tile;
+    use crate::path::astar::AStar;
+    use crate::path::testing::*;
+
+    #[test]
+    fn queues_less_than_astar_for_the_same_cost() {
+        // A comb of walls, so that many positions are reached along several paths
+        let mut map = room(40, 40);
+        for x in (4..36).step_by(4) {
+            for y in 1..30 {
+                map[(x, y + (x % 8) / 4 * 8)] = Tile::WALL;
+            }
+        }
+        let mut model = TurnOptimal::new(map);
+        model.set_heuristic(Heuristic::Chebyshev);
+        let (start, goal) = (walker(2, 20), walker(37, 20));
+
+        let mut sampler = WalkSampler::new();
+        let (mut lazy, mut indexed) = (AStar::new(), IndexedAStar::new());
+        let a = expect_final(lazy.optimize(&mut model, &start, &goal, &mut sampler));
+        let b = expect_final(indexed.optimize(&mut model, &start, &goal, &mut sampler));
+        assert_eq!(a.cost, b.cost);
+        assert!(indexed.stats().max_queue_len < lazy.stats().max_queue_len);
+    }
+}
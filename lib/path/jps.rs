@@ -0,0 +1,494 @@
+use std::fmt::{Debug, Formatter};
+
+use fnv::{FnvHashMap, FnvHashSet};
+use std::collections::hash_map::Entry;
+use std::collections::BinaryHeap;
+
+use super::astar::{Id, Node};
+use super::*;
+
+/// How the cells of a grid connect to their neighbours
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Connectivity {
+    /// Only the four orthogonal neighbours can be reached in one step
+    Four,
+    /// The diagonal neighbours can be reached in one step as well
+    Eight,
+}
+
+/// Models of grids where every straight step costs the same, as does every diagonal step
+///
+/// Stepping into a cell only depends on whether that cell is blocked, so diagonal steps may
+/// cut past blocked corners.  Every cell outside of the grid must be reported as blocked.
+pub trait GridModel: HeuristicModel {
+    /// The steps which can be taken from one cell to the next
+    fn connectivity(&self) -> Connectivity;
+
+    /// The cell occupied by a state
+    fn cell(&self, state: &Self::State) -> (i64, i64);
+
+    /// Whether the cell at `(x, y)` cannot be entered
+    fn is_blocked(&self, x: i64, y: i64) -> bool;
+
+    /// The control which moves a state by `(dx, dy)` cells, each of which is -1, 0, or 1
+    fn control(&self, dx: i64, dy: i64) -> Self::Control;
+}
+
+/// Jump Point Search, which prunes the symmetric paths of uniform-cost grids
+///
+/// Rather than queueing every neighbour, each direction is followed in a straight or diagonal
+/// line until a cell is reached where the trajectory could have to turn, and only that cell is
+/// queued.  The optimal cost is the same as that found by [`AStar`], with far fewer nodes
+/// expanded.  The controls come from [`GridModel::control`], so the sampler is never used.
+///
+/// The trajectory contains every cell along the way, not just the jump points.
+///
+/// ```
+/// # use game_lib::path::grid::{Cell, GridModel, GridSampler};
+/// # use game_lib::path::{jps::JumpPointSearch, Optimizer};
+/// let (mut jps, mut grid) = (JumpPointSearch::new(), GridModel::new(40, 40));
+/// let (start, goal) = (Cell::new(2, 20), Cell::new(37, 20));
+/// let t = jps.optimize(&mut grid, &start, &goal, &mut GridSampler).into_trajectory().unwrap();
+/// // Every cell along the way is in the trajectory, not just the jump points
+/// assert_eq!(t.trajectory.len(), 36);
+/// ```
+///
+/// [`AStar`]: ../astar/struct.AStar.html
+/// [`GridModel::control`]: trait.GridModel.html#tymethod.control
+pub struct JumpPointSearch<M>
+where
+    M: GridModel,
+    M::Cost: Copy,
+{
+    queue: BinaryHeap<Node<M>>,
+    parent_map: FnvHashMap<Id<M>, Node<M>>,
+    grid: FnvHashMap<<<M as Model>::State as State>::Position, Id<M>>,
+    closed: FnvHashSet<<<M as Model>::State as State>::Position>,
+    id_counter: usize,
+    stats: Stats,
+}
+
+impl<M> JumpPointSearch<M>
+where
+    M: GridModel,
+    M::Cost: Copy,
+{
+    /// Create a new Jump Point Search optimizer
+    pub fn new() -> Self {
+        JumpPointSearch {
+            queue: BinaryHeap::new(),
+            parent_map: FnvHashMap::default(),
+            grid: FnvHashMap::default(),
+            closed: FnvHashSet::default(),
+            id_counter: 0,
+            stats: Stats::default(),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.queue.clear();
+        self.parent_map.clear();
+        self.grid.clear();
+        self.closed.clear();
+        self.stats = Stats::default();
+    }
+
+    /// Counters describing the work done since the optimizer was created or last cleared
+    pub fn stats(&self) -> &Stats {
+        &self.stats
+    }
+
+    pub fn inspect_queue(&self) -> impl Iterator<Item = (&M::State, &M::Control)> {
+        self.queue.iter().map(|node| (&node.state, &node.control))
+    }
+
+    pub fn inspect_discovered(
+        &self,
+    ) -> impl Iterator<Item = &<<M as Model>::State as State>::Position> {
+        self.grid.keys()
+    }
+
+    #[inline(always)]
+    fn step(&mut self, current: &Node<M>, model: &M, goal: &M::State) -> bool {
+        if model.converge(&current.state, goal) {
+            return true;
+        }
+
+        if !self.closed.insert(current.state.grid_position()) {
+            return false;
+        }
+        self.stats.expanded += 1;
+
+        let (x, y) = model.cell(&current.state);
+        let arrival = self.parent_map.get(&current.id).map(|parent| {
+            let (px, py) = model.cell(&parent.state);
+            ((x - px).signum(), (y - py).signum())
+        });
+
+        for direction in Self::directions(model, (x, y), arrival) {
+            if let Some((child_state, control, step_cost)) =
+                Self::jump(model, &current.state, direction, goal)
+            {
+                self.id_counter += 1;
+                self.stats.generated += 1;
+
+                let cost = current.id.g() + step_cost;
+                let heuristic = model.heuristic(&child_state, goal);
+
+                let child = Node::<M> {
+                    id: Id::new(self.id_counter, cost + heuristic, cost),
+                    state: child_state,
+                    control,
+                };
+
+                match self.grid.entry(child.state.grid_position()) {
+                    Entry::Occupied(mut best) => {
+                        let best = best.get_mut();
+                        if best.g <= child.id.g {
+                            continue;
+                        } else {
+                            *best = child.id.clone();
+                        }
+                    }
+                    Entry::Vacant(empty) => {
+                        empty.insert(child.id.clone());
+                    }
+                }
+
+                self.parent_map.insert(child.id.clone(), current.clone());
+                self.queue.push(child);
+                self.stats.max_queue_len = self.stats.max_queue_len.max(self.queue.len());
+            }
+        }
+
+        false
+    }
+
+    /// The directions worth following from a cell, given the direction it was arrived from
+    fn directions(
+        model: &M,
+        (x, y): (i64, i64),
+        arrival: Option<(i64, i64)>,
+    ) -> Vec<(i64, i64)> {
+        use Connectivity::*;
+
+        let blocked = |dx, dy| model.is_blocked(x + dx, y + dy);
+        let connectivity = model.connectivity();
+
+        match (connectivity, arrival) {
+            (Four, None) => vec![(1, 0), (-1, 0), (0, 1), (0, -1)],
+            (Eight, None) => {
+                let mut all = Vec::with_capacity(8);
+                for dx in -1..=1 {
+                    for dy in -1..=1 {
+                        if (dx, dy) != (0, 0) {
+                            all.push((dx, dy));
+                        }
+                    }
+                }
+                all
+            }
+            // Trajectories turn from vertical to horizontal freely, but only turn back where
+            // an obstacle prevented the turn from being taken earlier
+            (Four, Some((dx, 0))) => {
+                let mut natural = vec![(dx, 0)];
+                natural
+                    .extend([1, -1].iter().filter(|&&dy| blocked(-dx, dy)).map(|&dy| (0, dy)));
+                natural
+            }
+            (Four, Some((_, dy))) => vec![(0, dy), (1, 0), (-1, 0)],
+            (Eight, Some((dx, 0))) => {
+                let mut natural = vec![(dx, 0)];
+                natural
+                    .extend([1, -1].iter().filter(|&&dy| blocked(0, dy)).map(|&dy| (dx, dy)));
+                natural
+            }
+            (Eight, Some((0, dy))) => {
+                let mut natural = vec![(0, dy)];
+                natural
+                    .extend([1, -1].iter().filter(|&&dx| blocked(dx, 0)).map(|&dx| (dx, dy)));
+                natural
+            }
+            (Eight, Some((dx, dy))) => {
+                let mut natural = vec![(dx, 0), (0, dy), (dx, dy)];
+                if blocked(-dx, 0) {
+                    natural.push((-dx, dy));
+                }
+                if blocked(0, -dy) {
+                    natural.push((dx, -dy));
+                }
+                natural
+            }
+        }
+    }
+
+    /// Whether a cell reached by moving in a direction has a neighbour which can only be
+    /// reached optimally through the cell
+    fn is_forced(model: &M, (x, y): (i64, i64), (dx, dy): (i64, i64)) -> bool {
+        use Connectivity::*;
+
+        let blocked = |cx, cy| model.is_blocked(x + cx, y + cy);
+        let forced = |side: (i64, i64), ahead: (i64, i64)| {
+            blocked(side.0, side.1) && !blocked(ahead.0, ahead.1)
+        };
+
+        match (model.connectivity(), dx, dy) {
+            (Four, dx, 0) => forced((-dx, 1), (0, 1)) || forced((-dx, -1), (0, -1)),
+            (Four, _, _) => false,
+            (Eight, dx, 0) => forced((0, 1), (dx, 1)) || forced((0, -1), (dx, -1)),
+            (Eight, 0, dy) => forced((1, 0), (1, dy)) || forced((-1, 0), (-1, dy)),
+            (Eight, dx, dy) => forced((-dx, 0), (-dx, dy)) || forced((0, -dy), (dx, -dy)),
+        }
+    }
+
+    /// Take a single step in a direction, if the cell it leads to is open
+    fn advance(
+        model: &M,
+        state: &M::State,
+        (dx, dy): (i64, i64),
+    ) -> Option<(M::State, M::Control)> {
+        let (x, y) = model.cell(state);
+        if model.is_blocked(x + dx, y + dy) {
+            return None;
+        }
+
+        let control = model.control(dx, dy);
+        model.integrate(state, &control).map(|next| (next, control))
+    }
+
+    /// Follow a direction until the next jump point, returning it along with the control of
+    /// the last step and the cost of getting there
+    fn jump(
+        model: &M,
+        from: &M::State,
+        direction: (i64, i64),
+        goal: &M::State,
+    ) -> Option<(M::State, M::Control, M::Cost)> {
+        let (dx, dy) = direction;
+        let mut state = from.clone();
+        let mut cost = M::Cost::default();
+
+        loop {
+            let (next, control) = Self::advance(model, &state, direction)?;
+            cost = cost + model.cost(&state, &control, &next);
+            state = next;
+
+            let cell = model.cell(&state);
+            if model.converge(&state, goal) || Self::is_forced(model, cell, direction) {
+                return Some((state, control, cost));
+            }
+
+            // Stop wherever a turn leads to a jump point of its own
+            let turns: &[(i64, i64)] = match model.connectivity() {
+                Connectivity::Eight if dx != 0 && dy != 0 => &[(dx, 0), (0, dy)],
+                Connectivity::Four if dx == 0 => &[(1, 0), (-1, 0)],
+                _ => &[],
+            };
+            if turns.iter().any(|&turn| Self::jump(model, &state, turn, goal).is_some()) {
+                return Some((state, control, cost));
+            }
+        }
+    }
+
+    /// Pop the next node from the queue, discarding stale entries along the way
+    fn pop(&mut self) -> Option<Node<M>> {
+        while let Some(node) = self.queue.pop() {
+            let stale = self
+                .grid
+                .get(&node.state.grid_position())
+                .is_some_and(|best| best.g < node.id.g);
+
+            if !stale {
+                return Some(node);
+            }
+        }
+
+        None
+    }
+
+    /// Follow the parents from the goal node up to the start node, filling in the cells
+    /// between each pair of jump points
+    fn unwind_trajectory(&self, model: &M, mut current: Node<M>) -> Trajectory<M> {
+        let cost = current.id.g();
+        let mut jump_points = vec![(current.state.clone(), current.control.clone())];
+
+        while let Some(p) = self.parent_map.get(&current.id) {
+            current = (*p).clone();
+            jump_points.push((current.state.clone(), current.control.clone()));
+        }
+
+        jump_points.reverse();
+
+        let mut result = vec![jump_points[0].clone()];
+        for pair in jump_points.windows(2) {
+            let ((from, _), to) = (&pair[0], &pair[1]);
+            let ((fx, fy), (tx, ty)) = (model.cell(from), model.cell(&to.0));
+            let direction = ((tx - fx).signum(), (ty - fy).signum());
+
+            let mut state = from.clone();
+            for _ in 1..(tx - fx).abs().max((ty - fy).abs()) {
+                match Self::advance(model, &state, direction) {
+                    Some((next, control)) => {
+                        result.push((next.clone(), control));
+                        state = next;
+                    }
+                    None => break,
+                }
+            }
+            result.push(to.clone());
+        }
+
+        Trajectory { cost, trajectory: result, expansions: self.stats.expanded }
+    }
+
+    fn seed(&mut self, model: &M, start: &M::State, goal: &M::State) {
+        let start_id = Id::new(0, model.heuristic(start, goal), Default::default());
+        self.queue.push(Node {
+            id: start_id,
+            state: start.clone(),
+            control: Default::default(),
+        });
+    }
+}
+
+impl<M, S> Optimizer<M, S> for JumpPointSearch<M>
+where
+    M: GridModel,
+    M::Cost: Copy,
+    S: Sampler<M>,
+{
+    fn next_trajectory(
+        &mut self,
+        model: &mut M,
+        start: &M::State,
+        goal: &M::State,
+        _sampler: &mut S,
+    ) -> PathResult<M> {
+        use PathFindingErr::*;
+        use PathResult::*;
+
+        if let Some(invalid) = invalid_endpoint(model, start, goal) {
+            return Err(invalid);
+        }
+
+        if self.parent_map.is_empty() && self.queue.is_empty() {
+            self.seed(model, start, goal);
+        }
+
+        if let Some(current) = self.pop() {
+            if self.step(&current, model, goal) {
+                Final(self.unwind_trajectory(model, current))
+            } else {
+                Intermediate(self.unwind_trajectory(model, current))
+            }
+        } else {
+            Err(Unreachable)
+        }
+    }
+
+    fn optimize(
+        &mut self,
+        model: &mut M,
+        start: &M::State,
+        goal: &M::State,
+        _sampler: &mut S,
+    ) -> PathResult<M> {
+        use PathFindingErr::*;
+        use PathResult::*;
+
+        if let Some(invalid) = invalid_endpoint(model, start, goal) {
+            return Err(invalid);
+        }
+
+        if model.converge(start, goal) {
+            return Final(Trajectory {
+                cost: Default::default(),
+                trajectory: vec![(start.clone(), Default::default())],
+                expansions: 0,
+            });
+        }
+
+        if self.parent_map.is_empty() && self.queue.is_empty() {
+            self.seed(model, start, goal);
+        }
+
+        while let Some(current) = self.pop() {
+            if self.step(&current, model, goal) {
+                return Final(self.unwind_trajectory(model, current));
+            }
+        }
+
+        Err(Unreachable)
+    }
+}
+
+impl<M> Debug for JumpPointSearch<M>
+where
+    M: GridModel,
+    M::State: Debug,
+    M::Control: Debug,
+    M::Cost: Debug + Copy,
+{
+    fn fmt(&self, fmt: &mut Formatter) -> Result<(), std::fmt::Error> {
+        fmt.debug_struct("JumpPointSearch")
+            .field("counter", &self.id_counter)
+            .field("next", &self.queue.peek())
+            .field("queue", &self.queue)
+            .field("grid", &self.grid)
+            .field("closed", &self.closed)
+            .field("stats", &self.stats)
+            .field("parent_map", &self.parent_map)
+            .finish()
+    }
+}
+
+impl<M> Default for JumpPointSearch<M>
+where
+    M: GridModel,
+    M::Cost: Copy,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::actor::{Heuristic, TurnOptimal, WalkSampler};
+    use crate::map::Tile;
+    use crate::path::astar::AStar;
+    use crate::path::testing::*;
+
+    #[test]
+    fn matches_astar_with_far_fewer_expansions() {
+        let open = room(40, 40);
+        // Walls across the room with the gaps at alternating ends
+        let mut maze = open.clone();
+        for (i, x) in (6..39).step_by(6).enumerate() {
+            let gap = if i % 2 == 0 { 37 } else { 2 };
+            for y in (1..39).filter(|&y| y != gap) {
+                maze[(x, y)] = Tile::WALL;
+            }
+        }
+
+        let (start, goal) = (walker(2, 20), walker(37, 20));
+        for map in [open, maze] {
+            let mut model = TurnOptimal::new(map);
+            model.set_heuristic(Heuristic::Chebyshev);
+
+            let mut sampler = WalkSampler::new();
+            let (mut astar, mut jps) = (AStar::new(), JumpPointSearch::new());
+            let a = expect_final(astar.optimize(&mut model, &start, &goal, &mut sampler));
+            let j = expect_final(jps.optimize(&mut model, &start, &goal, &mut sampler));
+            assert_eq!(a.cost, j.cost);
+            // Every step of the trajectory moves to a neighbouring cell
+            assert!(j.trajectory.windows(2).all(|pair| {
+                let (a, b) = (&pair[0].0.pos, &pair[1].0.pos);
+                let (dx, dy) = (a.x as i64 - b.x as i64, a.y as i64 - b.y as i64);
+                dx.abs().max(dy.abs()) == 1
+            }));
+            assert!(jps.stats().expanded * 5 < astar.stats().expanded);
+        }
+    }
+}
@@ -0,0 +1,96 @@
+use std::fmt::{Debug, Formatter};
+
+use super::*;
+
+/// Samples the same precomputed set of motion primitives in every state
+///
+/// State lattices plan over a fixed set of feasible motions, computed ahead of time from what
+/// the vehicle can actually do, so that the search never has to know how the motions were
+/// found.  The model's `integrate` still decides whether a primitive can be taken from a
+/// particular state.
+///
+/// ```
+/// # use game_lib::path::astar::AStar;
+/// # use game_lib::path::grid::{Cell, GridModel, Step};
+/// # use game_lib::path::lattice::LatticeSampler;
+/// # use game_lib::path::Optimizer;
+/// // Only ever moving right, up, or diagonally between the two
+/// let primitives = vec![Step { dx: 1, dy: 0 }, Step { dx: 0, dy: 1 }, Step { dx: 1, dy: 1 }];
+/// let mut sampler = LatticeSampler::new(primitives);
+/// let (mut grid, start, goal) = (GridModel::new(12, 12), Cell::new(1, 1), Cell::new(10, 9));
+/// assert!(AStar::new().optimize(&mut grid, &start, &goal, &mut sampler).is_final());
+/// ```
+pub struct LatticeSampler<M>
+where
+    M: Model,
+{
+    primitives: Vec<M::Control>,
+}
+
+impl<M> LatticeSampler<M>
+where
+    M: Model,
+{
+    /// Create a sampler which offers every one of `primitives` in every state
+    pub fn new(primitives: Vec<M::Control>) -> Self {
+        LatticeSampler { primitives }
+    }
+
+    pub fn primitives(&self) -> &[M::Control] {
+        &self.primitives
+    }
+}
+
+impl<M> Sampler<M> for LatticeSampler<M>
+where
+    M: Model,
+{
+    #[inline]
+    fn sample(&mut self, _: &M, _: &M::State) -> &[M::Control] {
+        &self.primitives
+    }
+}
+
+impl<M> Clone for LatticeSampler<M>
+where
+    M: Model,
+{
+    fn clone(&self) -> Self {
+        LatticeSampler { primitives: self.primitives.clone() }
+    }
+}
+
+impl<M> Debug for LatticeSampler<M>
+where
+    M: Model,
+{
+    fn fmt(&self, fmt: &mut Formatter) -> Result<(), std::fmt::Error> {
+        fmt.debug_struct("LatticeSampler").field("primitives", &self.primitives).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::path::astar::AStar;
+    use crate::path::grid::{Cell, GridModel, Step};
+    use crate::path::testing::*;
+
+    #[test]
+    fn trajectories_only_take_the_primitives() {
+        let mut grid = GridModel::new(12, 12);
+        for x in 3..9 {
+            grid.set_blocked(x, 5, true);
+        }
+
+        // Only ever moving right, up, or diagonally between the two
+        let primitives =
+            vec![Step { dx: 1, dy: 0 }, Step { dx: 0, dy: 1 }, Step { dx: 1, dy: 1 }];
+        let mut sampler = LatticeSampler::new(primitives.clone());
+
+        let (start, goal) = (Cell::new(1, 1), Cell::new(10, 9));
+        let t = expect_final(AStar::new().optimize(&mut grid, &start, &goal, &mut sampler));
+        assert_eq!(t.trajectory.last().unwrap().0, goal);
+        assert!(t.trajectory[1..].iter().all(|(_, step)| primitives.contains(step)));
+    }
+}
@@ -25,12 +25,50 @@
 //!
 //! [`Model`]: /path/trait.Model.html
 
-use std::fmt::Debug;
+use std::error::Error;
+use std::fmt::{self, Debug, Display};
 use std::hash::Hash;
-use std::ops::Add;
+use std::ops::{Add, Index};
 
+pub mod alt;
+pub mod ara;
 pub mod astar;
+pub mod beam;
+pub mod bidirectional;
+pub mod bucket;
+pub mod caching;
+pub mod cbs;
+pub mod clearance;
+pub mod continuous;
+pub mod dary;
 pub mod dijkstra;
+pub mod dstar;
+pub mod export;
+pub mod fringe;
+pub mod graph;
+pub mod greedy;
+pub mod grid;
+pub mod heuristics;
+pub mod hex;
+pub mod hierarchical;
+pub mod hybrid;
+pub mod ida;
+pub mod indexed;
+pub mod jps;
+pub mod lattice;
+pub mod models;
+pub mod nearest;
+pub mod pairing;
+pub mod pareto;
+pub mod random;
+pub mod rrt;
+pub mod sma;
+pub mod smooth;
+pub mod spacetime;
+#[cfg(test)]
+mod testing;
+pub mod theta;
+pub mod voxel;
 
 /// Marker trait which is required for the type which a [`Model`] uses to represent costs.
 ///
@@ -41,23 +79,107 @@ pub trait Cost: Ord + Eq + Default + Add<Output = Self>
 where
     Self: Sized,
 {
+    /// Add two costs, returning `None` when the sum cannot be represented
+    ///
+    /// Costs which cannot overflow can rely on the default, which always adds.
+    fn checked_add(self, other: Self) -> Option<Self> {
+        Some(self + other)
+    }
+}
+
+macro_rules! impl_cost {
+    ($($num:ty),+) => {
+        $(
+            impl Cost for $num {
+                #[inline(always)]
+                fn checked_add(self, other: Self) -> Option<Self> {
+                    <$num>::checked_add(self, other)
+                }
+            }
+        )+
+    };
+}
+
+impl_cost!(usize, u8, u16, u32, u64, isize, i8, i16, i32, i64);
+
+/// Costs which can be scaled by a floating point weight
+///
+/// Used to inflate heuristic estimates, as in weighted A*.  The scaled value is truncated back
+/// into the cost type.
+pub trait Weighted: Cost {
+    fn scale(self, weight: f64) -> Self;
+
+    /// The cost as a floating point value, for measuring fractions of a cost
+    fn to_f64(self) -> f64;
 }
 
-impl Cost for usize {}
-impl Cost for u8 {}
-impl Cost for u16 {}
-impl Cost for u32 {}
-impl Cost for u64 {}
-impl Cost for isize {}
-impl Cost for i8 {}
-impl Cost for i16 {}
-impl Cost for i32 {}
-impl Cost for i64 {}
+macro_rules! impl_weighted {
+    ($($num:ty),+) => {
+        $(
+            #[allow(clippy::cast_lossless)]
+            impl Weighted for $num {
+                #[inline(always)]
+                fn scale(self, weight: f64) -> Self {
+                    (self as f64 * weight) as $num
+                }
+
+                #[inline(always)]
+                fn to_f64(self) -> f64 {
+                    self as f64
+                }
+            }
+        )+
+    };
+}
+
+impl_weighted!(usize, u8, u16, u32, u64, isize, i8, i16, i32, i64);
+
+/// Unsigned integer costs which can be used directly as an index
+///
+/// Allows costs to address the buckets of a [`BucketQueue`].
+///
+/// [`BucketQueue`]: bucket/struct.BucketQueue.html
+pub trait IntegerCost: Cost + Copy {
+    fn index(self) -> usize;
+}
+
+macro_rules! impl_integer_cost {
+    ($($num:ty),+) => {
+        $(
+            #[allow(clippy::cast_lossless)]
+            impl IntegerCost for $num {
+                #[inline(always)]
+                fn index(self) -> usize {
+                    self as usize
+                }
+            }
+        )+
+    };
+}
+
+impl_integer_cost!(usize, u8, u16, u32, u64);
 
 pub trait State {
-    type Position: Eq + Hash + Debug;
+    type Position: Eq + Hash + Clone + Debug;
 
     fn grid_position(&self) -> Self::Position;
+
+    /// The state a fraction `t` of the way from this state towards `other`
+    ///
+    /// `t` is between `0.0`, which is this state, and `1.0`, which is `other`.
+    fn interpolate(&self, other: &Self, t: f64) -> Self;
+}
+
+/// Positions which can be compared with the corners of a box, one axis at a time
+pub trait BoundedPosition {
+    /// Whether every coordinate lies between those of `min` and `max`, both included
+    fn within(&self, min: &Self, max: &Self) -> bool;
+}
+
+impl BoundedPosition for (i64, i64) {
+    fn within(&self, min: &Self, max: &Self) -> bool {
+        (min.0..=max.0).contains(&self.0) && (min.1..=max.1).contains(&self.1)
+    }
 }
 
 /// Interface which defines the problem
@@ -69,6 +191,9 @@ pub trait Model {
     type State: Debug + Clone + State;
 
     /// Actions which can be taken to effect the system
+    ///
+    /// The default control stands in for the control of the start of every trajectory, which
+    /// nothing led to, so it should be one that does nothing.
     type Control: Debug + Clone + Default;
 
     /// A measurement of the cost in the system
@@ -124,6 +249,23 @@ pub trait Model {
     /// [`State`]: #associatedtype.State
     fn converge(&self, current: &Self::State, goal: &Self::State) -> bool;
 
+    /// Convergence to within `tolerance` of the goal, for optimizers told to accept states
+    /// near it, see [`AStar::set_goal_tolerance`]
+    ///
+    /// What the tolerance measures is up to the model, typically the distance to the goal.
+    /// Continuous models rarely land exactly on a goal, so they are the ones to implement
+    /// this; the rest fall back to `converge`, which ignores the tolerance.
+    ///
+    /// [`AStar::set_goal_tolerance`]: astar/struct.AStar.html#method.set_goal_tolerance
+    fn converge_within(
+        &self,
+        current: &Self::State,
+        goal: &Self::State,
+        _tolerance: f64,
+    ) -> bool {
+        self.converge(current, goal)
+    }
+
     /// Generate a new current state from a control which is applied to a previous state
     ///
     /// Since States are not generated directly and expand from previous States, a function is
@@ -145,6 +287,57 @@ pub trait Model {
         previous: &Self::State,
         control: &Self::Control,
     ) -> Option<Self::State>;
+
+    /// Whether the system can be in `state` at all
+    ///
+    /// Optimizers check the start and goal with this before searching, so that a query which
+    /// begins or ends inside an obstacle fails with `PathFindingErr::InvalidStart` or
+    /// `InvalidGoal` instead of a search for a trajectory which cannot exist.  The default
+    /// accepts every state.
+    ///
+    /// ```
+    /// # use game_lib::path::astar::AStar;
+    /// # use game_lib::path::grid::{Cell, GridModel, GridSampler};
+    /// # use game_lib::path::{Optimizer, PathFindingErr, PathResult};
+    /// let (mut grid, open, blocked) = (GridModel::new(10, 10), Cell::new(5, 5), Cell::new(9, 9));
+    /// grid.set_blocked(blocked.x, blocked.y, true);
+    /// let result = AStar::new().optimize(&mut grid, &open, &blocked, &mut GridSampler);
+    /// assert!(matches!(result, PathResult::Err(PathFindingErr::InvalidGoal)));
+    /// ```
+    fn is_valid(&self, _state: &Self::State) -> bool {
+        true
+    }
+
+    /// Whether a straight connection between two states is free of obstacles
+    ///
+    /// Any-angle optimizers such as [`ThetaStar`] use this to skip over intermediate states,
+    /// which only makes sense when `cost` measures the distance between any two states, not
+    /// just neighbouring ones.  The default never allows a connection, so the trajectory
+    /// follows the controls one step at a time.
+    ///
+    /// [`ThetaStar`]: theta/struct.ThetaStar.html
+    fn line_of_sight(&self, _from: &Self::State, _to: &Self::State) -> bool {
+        false
+    }
+
+    /// The key under which states are merged while searching
+    ///
+    /// [`AStar`] keeps a single node for every key, the cheapest one found, and expands each
+    /// key once, so states which share a key are treated as the same state.  The default is
+    /// the grid position, which only merges identical positions.  Models whose states are
+    /// equivalent in some other way, such as the rotations of a symmetric puzzle, can map
+    /// them to one key instead and spare the search from exploring each of them in turn.  The
+    /// trajectories found still hold the states as they were reached, and positions passed to
+    /// the optimizer, such as the one for `trajectory_to`, are keys.
+    ///
+    /// States merged this way must be interchangeable: which of them is kept should not
+    /// change the cost of reaching the goal, which is also why `converge` has to accept every
+    /// state with the key of the goal.
+    ///
+    /// [`AStar`]: astar/struct.AStar.html
+    fn canonicalize(&self, state: &Self::State) -> <Self::State as State>::Position {
+        state.grid_position()
+    }
 }
 
 /// Heuristic Models are models which can estimate the cost to the goal
@@ -171,6 +364,17 @@ pub trait HeuristicModel: Model {
     /// Over-estimation breaks optimality guarantees. Furthermore the heuristic must never
     /// return a negative value.
     fn heuristic(&self, current: &Self::State, goal: &Self::State) -> Self::Cost;
+
+    /// Estimate of future costs, or `None` when the goal can never be reached from `current`
+    ///
+    /// A model which knows a state is a dead end, such as one on the wrong side of a one-way
+    /// barrier, can say so here, and `AStar` drops the state as soon as it is generated,
+    /// without queueing it or anything beyond it.  Only return `None` when the goal really is
+    /// unreachable, or the trajectories through the state are lost.  Defaults to the
+    /// [`heuristic`](#tymethod.heuristic), which treats every state as having a way out.
+    fn heuristic_opt(&self, current: &Self::State, goal: &Self::State) -> Option<Self::Cost> {
+        Some(self.heuristic(current, goal))
+    }
 }
 
 pub trait Sampler<M>
@@ -178,19 +382,86 @@ where
     M: Model,
 {
     fn sample(&mut self, model: &M, current: &M::State) -> &[M::Control];
+
+    /// Write the controls to try in `current` into `buffer`, replacing what it held
+    ///
+    /// Optimizers which still need the controls once they use the sampler again, like the
+    /// depth-first ones, copy them out with this method.  Reusing the same buffer from one
+    /// node to the next avoids allocating for every node.  The default copies the controls
+    /// returned by `sample`.
+    ///
+    /// ```
+    /// # use game_lib::path::grid::{Cell, GridModel, GridSampler};
+    /// # use game_lib::path::Sampler;
+    /// let (grid, mut buffer) = (GridModel::new(5, 5), Vec::new());
+    /// GridSampler.sample_into(&grid, &Cell::new(2, 2), &mut buffer);
+    /// assert_eq!(buffer.len(), 8);
+    /// ```
+    fn sample_into(&mut self, model: &M, current: &M::State, buffer: &mut Vec<M::Control>) {
+        buffer.clear();
+        buffer.extend_from_slice(self.sample(model, current));
+    }
 }
 
 /// The result of optimization: a trajectory from the start to goal
 ///
 /// A trajectory which carries the cost of its execution, and all of the steps as pairs of
 /// states and controls, who's types are determined by the Model.
+///
+/// The control of each state is the one which led to it from the state before.  The start
+/// has no state before it, so its control is always the default control, whichever
+/// optimizer planned the trajectory and however far it got, even when the start has already
+/// converged.  It is only there to fill the pair and must not be executed; iterate the
+/// [`steps`](#method.steps) to go through the controls without it.
+///
+/// With the `serde` feature enabled a trajectory can be serialized whenever the cost, state
+/// and control types of its model can be:
+///
+/// ```
+/// # #[cfg(feature = "serde")]
+/// # {
+/// # use game_lib::actor::TurnOptimal;
+/// # use game_lib::path::Trajectory;
+/// let trajectory = Trajectory::<TurnOptimal>::default();
+/// let json = serde_json::to_string(&trajectory).unwrap();
+/// let loaded: Trajectory<TurnOptimal> = serde_json::from_str(&json).unwrap();
+/// assert_eq!(loaded.cost, trajectory.cost);
+/// # }
+/// ```
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(
+        serialize = "M::Cost: serde::Serialize, M::State: serde::Serialize, \
+                     M::Control: serde::Serialize",
+        deserialize = "M::Cost: serde::Deserialize<'de>, M::State: serde::Deserialize<'de>, \
+                       M::Control: serde::Deserialize<'de>"
+    ))
+)]
 pub struct Trajectory<M>
 where
     M: Model,
 {
     pub cost: M::Cost,
     pub trajectory: Vec<(M::State, M::Control)>,
+    /// How many nodes the search had expanded when it returned the trajectory
+    ///
+    /// Counted since the optimizer was created or last cleared, the same as its `stats`, and
+    /// zero from optimizers which do not keep count.  It describes one run of a search rather
+    /// than the trajectory, so it is not serialized.
+    ///
+    /// ```
+    /// # use game_lib::path::astar::AStar;
+    /// # use game_lib::path::grid::{Cell, GridModel, GridSampler};
+    /// # use game_lib::path::Optimizer;
+    /// let (mut grid, start, goal) = (GridModel::new(20, 20), Cell::new(3, 10), Cell::new(17, 9));
+    /// let mut astar = AStar::new();
+    /// let result = astar.optimize(&mut grid, &start, &goal, &mut GridSampler);
+    /// assert_eq!(result.trajectory().unwrap().expansions, astar.stats().expanded);
+    /// ```
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub expansions: usize,
 }
 
 impl<M> Default for Trajectory<M>
@@ -198,17 +469,330 @@ where
     M: Model,
 {
     fn default() -> Self {
-        Trajectory { cost: Default::default(), trajectory: Vec::new() }
+        Trajectory { cost: Default::default(), trajectory: Vec::new(), expansions: 0 }
     }
 }
 
-/// Errors that result from
-#[derive(Debug, Clone, PartialEq)]
+/// Iterating a trajectory goes through its steps from the start to the end
+///
+/// ```
+/// # use game_lib::path::grid::{Cell, GridModel, GridSampler};
+/// # use game_lib::path::{astar::AStar, Optimizer};
+/// let (mut grid, start, goal) = (GridModel::new(10, 10), Cell::new(2, 2), Cell::new(7, 4));
+/// let result = AStar::new().optimize(&mut grid, &start, &goal, &mut GridSampler);
+/// let trajectory = result.into_trajectory().unwrap();
+/// for (cell, step) in &trajectory {
+///     println!("{:?} after {:?}", cell, step);
+/// }
+/// assert_eq!(trajectory.states().last(), Some(&goal));
+/// ```
+impl<M> Trajectory<M>
+where
+    M: Model,
+{
+    /// The number of steps, counting the start
+    pub fn len(&self) -> usize {
+        self.trajectory.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.trajectory.is_empty()
+    }
+
+    /// The states passed through, from the start to the end
+    pub fn states(&self) -> impl Iterator<Item = &M::State> {
+        self.trajectory.iter().map(|(state, _)| state)
+    }
+
+    /// The control reaching each state, the first being the default control of the start
+    pub fn controls(&self) -> impl Iterator<Item = &M::Control> {
+        self.trajectory.iter().map(|(_, control)| control)
+    }
+
+    /// Each move along the trajectory, as the state it leaves, the control it executes and
+    /// the state it arrives at
+    ///
+    /// The default control of the start is left out, so every control is one to execute.
+    ///
+    /// ```
+    /// # use game_lib::path::astar::AStar;
+    /// # use game_lib::path::grid::{Cell, GridModel, GridSampler};
+    /// # use game_lib::path::{Model, Optimizer};
+    /// let (mut grid, start, goal) = (GridModel::new(10, 10), Cell::new(1, 1), Cell::new(6, 3));
+    /// let result = AStar::new().optimize(&mut grid, &start, &goal, &mut GridSampler);
+    /// for (from, step, to) in result.into_trajectory().unwrap().steps() {
+    ///     assert_eq!(grid.integrate(from, step), Some(*to));
+    /// }
+    /// ```
+    pub fn steps(&self) -> impl Iterator<Item = (&M::State, &M::Control, &M::State)> {
+        self.trajectory.windows(2).map(|pair| (&pair[0].0, &pair[1].1, &pair[1].0))
+    }
+
+    /// The same states from the end back to the start, with the cost of going that way
+    ///
+    /// Each state is reached with the control of the step it was left by in this trajectory,
+    /// so this only makes sense for models where a control is the same both ways, such as
+    /// the backward half of a bidirectional search.  The cost is found again from the model.
+    pub fn reversed(&self, model: &M) -> Trajectory<M> {
+        let mut trajectory = Vec::with_capacity(self.trajectory.len());
+        let mut control = M::Control::default();
+        for (state, left_by) in self.trajectory.iter().rev() {
+            trajectory.push((state.clone(), std::mem::replace(&mut control, left_by.clone())));
+        }
+
+        let mut cost = M::Cost::default();
+        for pair in trajectory.windows(2) {
+            let ((from, _), (to, control)) = (&pair[0], &pair[1]);
+            cost = cost + model.cost(from, control, to);
+        }
+
+        Trajectory { cost, trajectory, expansions: self.expansions }
+    }
+
+    /// This trajectory followed by `other`, which must start at the position this one ends
+    ///
+    /// The state this ends with and `other` starts with is kept once, and the costs are added.
+    /// Fails with `PathFindingErr::Discontinuous` when the two positions differ, and with
+    /// `CostOverflow` when the sum of the costs does.  Joining an empty trajectory gives the
+    /// other one.  Both costs are already known, so `model` is only taken to match
+    /// [`reversed`](#method.reversed).
+    ///
+    /// ```
+    /// # use game_lib::path::grid::{Cell, GridModel, GridSampler};
+    /// # use game_lib::path::{astar::AStar, Optimizer};
+    /// let mut grid = GridModel::new(10, 10);
+    /// let (a, b, c) = (Cell::new(1, 1), Cell::new(3, 2), Cell::new(5, 3));
+    /// let first = AStar::new().optimize(&mut grid, &a, &b, &mut GridSampler).into_trajectory();
+    /// let second = AStar::new().optimize(&mut grid, &b, &c, &mut GridSampler).into_trajectory();
+    /// let joined = first.unwrap().concat(&second.unwrap(), &grid).unwrap();
+    /// assert_eq!(joined.states().last(), Some(&c));
+    /// ```
+    pub fn concat(
+        &self,
+        other: &Trajectory<M>,
+        _model: &M,
+    ) -> Result<Trajectory<M>, PathFindingErr> {
+        let (end, start) = match (self.trajectory.last(), other.trajectory.first()) {
+            (Some((end, _)), Some((start, _))) => (end, start),
+            (None, _) => return Ok(other.copy()),
+            (_, None) => return Ok(self.copy()),
+        };
+        if end.grid_position() != start.grid_position() {
+            return Err(PathFindingErr::Discontinuous);
+        }
+
+        let cost = self.cost.clone().checked_add(other.cost.clone());
+        let cost = cost.ok_or(PathFindingErr::CostOverflow)?;
+        let mut trajectory = self.trajectory.clone();
+        trajectory.extend_from_slice(&other.trajectory[1..]);
+
+        let expansions = self.expansions + other.expansions;
+        Ok(Trajectory { cost, trajectory, expansions })
+    }
+
+    /// A copy which does not need the model to be `Clone`, as the derived one does
+    fn copy(&self) -> Trajectory<M> {
+        Trajectory {
+            cost: self.cost.clone(),
+            trajectory: self.trajectory.clone(),
+            expansions: self.expansions,
+        }
+    }
+}
+
+impl<M> IntoIterator for Trajectory<M>
+where
+    M: Model,
+{
+    type Item = (M::State, M::Control);
+    type IntoIter = std::vec::IntoIter<(M::State, M::Control)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.trajectory.into_iter()
+    }
+}
+
+impl<'a, M> IntoIterator for &'a Trajectory<M>
+where
+    M: Model,
+{
+    type Item = &'a (M::State, M::Control);
+    type IntoIter = std::slice::Iter<'a, (M::State, M::Control)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.trajectory.iter()
+    }
+}
+
+impl<M> Index<usize> for Trajectory<M>
+where
+    M: Model,
+{
+    type Output = (M::State, M::Control);
+
+    fn index(&self, index: usize) -> &Self::Output {
+        &self.trajectory[index]
+    }
+}
+
+/// Counters which describe how much work a search has done
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Stats {
+    /// Nodes popped from the queue whose successors were generated
+    pub expanded: usize,
+    /// Successor states produced by sampling and integrating controls
+    pub generated: usize,
+    /// The largest number of nodes held in the queue at once
+    pub max_queue_len: usize,
+    /// Nodes which were expanded again after a cheaper path to them was found
+    pub reopened: usize,
+}
+
+/// The number of entries an optimizer is holding, and roughly how much memory they take
+///
+/// The byte count multiplies the size of each entry by the number of entries, so it leaves
+/// out any memory the states own on the heap, the spare capacity of the collections, and the
+/// bookkeeping of the hash maps.  It is meant for comparing workloads and sizing allocations,
+/// not as an exact measurement.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MemoryReport {
+    /// Nodes waiting in the queue
+    pub queue_len: usize,
+    /// Nodes whose parent is remembered in order to unwind trajectories
+    pub parent_map_len: usize,
+    /// Positions which have been discovered, along with their cheapest node
+    pub grid_len: usize,
+    /// Positions which have been expanded
+    pub closed_len: usize,
+    /// Estimated size of all of the entries together
+    pub bytes: usize,
+}
+
+/// Errors that result from path-finding
+///
+/// ```
+/// # use game_lib::path::PathFindingErr;
+/// # use std::error::Error;
+/// let err = PathFindingErr::Unreachable;
+/// assert_eq!(err.to_string(), "the goal cannot be reached from the start");
+///
+/// let boxed: Box<dyn Error> = Box::new(err);
+/// assert_eq!(boxed.to_string(), "the goal cannot be reached from the start");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum PathFindingErr {
     Unreachable,
     IterationLimit(usize),
+    /// The search expanded as many nodes as it was allowed to without reaching the goal
+    ///
+    /// Only the budget is carried, to keep the error independent of the model.  The search is
+    /// left as it stopped, so the trajectory to its most promising node comes from the
+    /// optimizer instead, as with `AStar::partial_trajectory`.
+    BudgetExhausted(usize),
+    /// The search ran past its deadline without reaching the goal
+    TimedOut,
+    /// The search was paused with nodes left to expand, before it could tell whether the
+    /// goal is reachable
+    Pending,
+    /// Accumulating the cost of a trajectory overflowed the model's cost type
+    CostOverflow,
+    /// Every trajectory to the goal costs more than the limit the search was given
+    CostLimitExceeded,
+    /// The trajectories being joined do not meet: one ends where the other does not start
+    Discontinuous,
+    /// A search was resumed with a different start or goal than it was begun with
+    InvalidQuery,
+    /// The model does not consider the start a valid state
+    InvalidStart,
+    /// The model does not consider the goal a valid state
+    InvalidGoal,
+    /// A control leads from a state back to its own position at no cost
+    NonProgressingEdge,
 }
 
+impl Display for PathFindingErr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PathFindingErr::Unreachable => {
+                write!(f, "the goal cannot be reached from the start")
+            }
+            PathFindingErr::IterationLimit(limit) => {
+                write!(f, "the search did not finish within {} iterations", limit)
+            }
+            PathFindingErr::BudgetExhausted(budget) => {
+                write!(f, "the search did not finish within {} expansions", budget)
+            }
+            PathFindingErr::TimedOut => {
+                write!(f, "the search did not finish before its deadline")
+            }
+            PathFindingErr::Pending => {
+                write!(f, "the search was paused before it finished")
+            }
+            PathFindingErr::CostOverflow => {
+                write!(f, "the cost of a trajectory overflowed the cost type")
+            }
+            PathFindingErr::CostLimitExceeded => {
+                write!(f, "no trajectory to the goal is within the cost limit")
+            }
+            PathFindingErr::Discontinuous => {
+                write!(f, "the trajectories do not end and start at the same position")
+            }
+            PathFindingErr::InvalidQuery => {
+                write!(f, "the search was begun for a different start or goal")
+            }
+            PathFindingErr::InvalidStart => {
+                write!(f, "the start is not a valid state")
+            }
+            PathFindingErr::InvalidGoal => {
+                write!(f, "the goal is not a valid state")
+            }
+            PathFindingErr::NonProgressingEdge => {
+                write!(f, "a control stays at the same position at no cost")
+            }
+        }
+    }
+}
+
+impl PathFindingErr {
+    /// Whether the search stopped before it finished, so that resuming it may still reach the
+    /// goal
+    ///
+    /// This holds for a search which was paused, ran out of its budget of expansions, or ran
+    /// past its deadline.  Every other error is final: in particular `Unreachable` is only
+    /// returned once a search has expanded everything it could reach.
+    ///
+    /// ```
+    /// # use game_lib::path::PathFindingErr;
+    /// assert!(PathFindingErr::Pending.is_pending());
+    /// assert!(!PathFindingErr::Unreachable.is_pending());
+    /// ```
+    pub fn is_pending(&self) -> bool {
+        matches!(
+            self,
+            PathFindingErr::Pending
+                | PathFindingErr::BudgetExhausted(_)
+                | PathFindingErr::TimedOut
+        )
+    }
+}
+
+/// The error for a query whose start or goal the model does not consider a valid state
+fn invalid_endpoint<M: Model>(
+    model: &M,
+    start: &M::State,
+    goal: &M::State,
+) -> Option<PathFindingErr> {
+    if !model.is_valid(start) {
+        Some(PathFindingErr::InvalidStart)
+    } else if !model.is_valid(goal) {
+        Some(PathFindingErr::InvalidGoal)
+    } else {
+        None
+    }
+}
+
+impl Error for PathFindingErr {}
+
 #[derive(Debug, Clone)]
 pub enum PathResult<M>
 where
@@ -219,6 +803,43 @@ where
     Err(PathFindingErr),
 }
 
+impl<M> PathResult<M>
+where
+    M: Model,
+{
+    /// Whether the search has finished with a trajectory to the goal
+    pub fn is_final(&self) -> bool {
+        matches!(self, PathResult::Final(_))
+    }
+
+    /// The trajectory carried by the result, whether final or intermediate
+    pub fn trajectory(&self) -> Option<&Trajectory<M>> {
+        match self {
+            PathResult::Final(t) | PathResult::Intermediate(t) => Some(t),
+            PathResult::Err(_) => None,
+        }
+    }
+
+    /// Convert into the carried trajectory, or the error which stopped the search
+    ///
+    /// Intermediate trajectories are returned as well as final ones: both are valid
+    /// trajectories from the start, and only an error leaves nothing to follow.  Check
+    /// [`is_final`](#method.is_final) first when only a complete path will do.
+    ///
+    /// ```
+    /// # use game_lib::actor::TurnOptimal;
+    /// # use game_lib::path::{PathFindingErr, PathResult};
+    /// let failed: PathResult<TurnOptimal> = PathResult::Err(PathFindingErr::Unreachable);
+    /// assert_eq!(failed.into_trajectory().unwrap_err(), PathFindingErr::Unreachable);
+    /// ```
+    pub fn into_trajectory(self) -> Result<Trajectory<M>, PathFindingErr> {
+        match self {
+            PathResult::Final(t) | PathResult::Intermediate(t) => Ok(t),
+            PathResult::Err(e) => Err(e),
+        }
+    }
+}
+
 /// A strategy to find a trajectory from the start state to the goal state
 pub trait Optimizer<M, S>
 where
@@ -248,13 +869,59 @@ where
     ) -> PathResult<M>;
 }
 
+/// What came of expanding a single node, see [`Explore`]
+#[derive(Debug)]
+pub enum ExploreStep<M>
+where
+    M: Model,
+{
+    /// A node was expanded and its successors queued
+    Expanded(M::State),
+    /// The goal was reached, ending the search with the trajectory to it
+    Goal(Trajectory<M>),
+    /// Nothing is left to expand, so the goal cannot be reached
+    Exhausted,
+    /// The search cannot carry on, as when the cost of a trajectory overflows
+    Failed(PathFindingErr),
+}
+
+/// A search which can be driven one expansion at a time
+///
+/// Where [`Optimizer`] hands back trajectories, this only says what each expansion did, which
+/// is all that tooling such as visualizers, loggers and budgeters needs to drive any planner
+/// the same way.  The search has to have been begun first, which is up to each planner.
+///
+/// ```
+/// # use game_lib::path::astar::AStar;
+/// # use game_lib::path::grid::{Cell, GridModel, GridSampler};
+/// # use game_lib::path::{Explore, ExploreStep};
+/// let (mut grid, start, goal) = (GridModel::new(10, 10), Cell::new(1, 1), Cell::new(8, 1));
+/// let mut astar = AStar::new();
+/// astar.begin(&grid, &start, &goal);
+/// let explorer: &mut dyn Explore<GridModel, GridSampler> = &mut astar;
+/// while let ExploreStep::Expanded(_) = explorer.expand_one(&mut grid, &goal, &mut GridSampler) {}
+/// ```
+pub trait Explore<M, S>
+where
+    M: Model,
+    S: Sampler<M>,
+{
+    /// Expand the next node of the search towards `goal`
+    fn expand_one(
+        &mut self,
+        model: &mut M,
+        goal: &M::State,
+        sampler: &mut S,
+    ) -> ExploreStep<M>;
+}
+
 use self::astar::{AStar, OptimalAStar};
 use self::dijkstra::Dijkstra;
 
 pub enum Algorithm<M>
 where
     M: HeuristicModel,
-    M::Cost: radix_heap::Radix + Copy,
+    M::Cost: radix_heap::Radix + Copy + Weighted,
 {
     AStar(AStar<M>),
     Dijkstra(Dijkstra<M>),
@@ -264,7 +931,7 @@ where
 impl<M, S> Optimizer<M, S> for Algorithm<M>
 where
     M: HeuristicModel,
-    M::Cost: radix_heap::Radix + Copy,
+    M::Cost: radix_heap::Radix + Copy + Weighted,
     S: Sampler<M>,
 {
     fn next_trajectory(
@@ -299,7 +966,7 @@ where
 impl<M> Algorithm<M>
 where
     M: HeuristicModel,
-    M::Cost: radix_heap::Radix + Copy,
+    M::Cost: radix_heap::Radix + Copy + Weighted,
 {
     pub fn new() -> Self {
         Algorithm::AStar(AStar::new())
@@ -358,9 +1025,441 @@ where
 impl<M> Default for Algorithm<M>
 where
     M: HeuristicModel,
-    M::Cost: radix_heap::Radix + Copy,
+    M::Cost: radix_heap::Radix + Copy + Weighted,
 {
     fn default() -> Self {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::astar::AStar;
+    use super::grid::{Cell, GridModel, GridSampler, Step};
+    use super::testing::{divided_room, expect_final, room, walker, TestGrid};
+    use super::*;
+    use crate::actor::{Actor, Movement, TurnOptimal, WalkSampler};
+    use crate::map::{Map, Tile};
+
+    fn trajectory() -> Trajectory<GridModel> {
+        Trajectory {
+            cost: 10,
+            trajectory: vec![
+                (Cell::new(0, 0), Step::default()),
+                (Cell::new(1, 0), Step { dx: 1, dy: 0 }),
+            ],
+            expansions: 1,
+        }
+    }
+
+    #[test]
+    fn final_results_carry_their_trajectory() {
+        let result = PathResult::Final(trajectory());
+        assert!(result.is_final());
+        assert_eq!(result.trajectory().map(|t| t.cost), Some(10));
+        assert_eq!(result.into_trajectory().map(|t| t.len()), Ok(2));
+    }
+
+    #[test]
+    fn intermediate_results_carry_their_trajectory_without_being_final() {
+        let result = PathResult::Intermediate(trajectory());
+        assert!(!result.is_final());
+        assert_eq!(result.trajectory().map(|t| t.cost), Some(10));
+        assert_eq!(result.into_trajectory().map(|t| t.len()), Ok(2));
+    }
+
+    #[test]
+    fn errors_carry_no_trajectory() {
+        let result: PathResult<GridModel> = PathResult::Err(PathFindingErr::Unreachable);
+        assert!(!result.is_final());
+        assert!(result.trajectory().is_none());
+        assert_eq!(
+            result.into_trajectory().map(|t| t.len()),
+            Err(PathFindingErr::Unreachable)
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn trajectories_survive_a_round_trip_through_json() {
+        use crate::actor::{Actor, Direction, Movement, TurnOptimal};
+
+        let trajectory: Trajectory<TurnOptimal> = Trajectory {
+            cost: 2,
+            trajectory: vec![
+                (Actor::new(3, 3, 0, 10), Movement::None),
+                (Actor::new(3, 4, 0, 10), Movement::Walk(Direction::North)),
+            ],
+            expansions: 0,
+        };
+
+        let json = serde_json::to_string(&trajectory).unwrap();
+        let loaded: Trajectory<TurnOptimal> = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded.cost, trajectory.cost);
+        assert_eq!(loaded.trajectory.len(), 2);
+        assert_eq!(loaded.trajectory[1].0.pos, trajectory.trajectory[1].0.pos);
+        assert_eq!(loaded.trajectory[1].1, trajectory.trajectory[1].1);
+    }
+
+    #[test]
+    fn sampling_into_a_buffer_replaces_what_it_held() {
+        let mut map = Map::new(5, 5);
+        map[(2, 2)] = Tile::FLOOR;
+        let model = TurnOptimal::new(map);
+        let state = walker(2, 2);
+        let mut sampler = WalkSampler::new();
+
+        let mut buffer = Vec::new();
+        for _ in 0..3 {
+            sampler.sample_into(&model, &state, &mut buffer);
+            assert_eq!(buffer, sampler.sample(&model, &state));
+        }
+    }
+
+    #[test]
+    fn iterating_a_trajectory_goes_from_the_start_to_the_end() {
+        let mut model = TurnOptimal::new(room(10, 10));
+        let (start, goal) = (walker(2, 2), walker(7, 4));
+        let mut sampler = WalkSampler::new();
+        let trajectory =
+            expect_final(AStar::new().optimize(&mut model, &start, &goal, &mut sampler));
+
+        assert!(!trajectory.is_empty());
+        assert_eq!(trajectory.states().count(), trajectory.len());
+        assert_eq!(trajectory.states().next().unwrap().pos, start.pos);
+        assert!(model.converge(trajectory.states().last().unwrap(), &goal));
+        assert!(trajectory.controls().skip(1).all(|c| *c != Movement::None));
+
+        for (i, (state, control)) in (&trajectory).into_iter().enumerate() {
+            assert_eq!(state.pos, trajectory[i].0.pos);
+            assert_eq!(*control, trajectory[i].1);
+        }
+        let len = trajectory.len();
+        let controls: Vec<_> = trajectory.controls().cloned().collect();
+        let steps: Vec<_> = trajectory.into_iter().map(|(_, control)| control).collect();
+        assert_eq!(steps.len(), len);
+        assert_eq!(steps, controls);
+    }
+
+    /// A trajectory through `cells` on `model`, each a step from the one before
+    fn path(model: &GridModel, cells: &[Cell]) -> Trajectory<GridModel> {
+        let mut trajectory = Trajectory::<GridModel>::default();
+        trajectory.trajectory.push((cells[0], Step::default()));
+        for pair in cells.windows(2) {
+            let step = Step { dx: pair[1].x - pair[0].x, dy: pair[1].y - pair[0].y };
+            trajectory.cost += model.cost(&pair[0], &step, &pair[1]);
+            trajectory.trajectory.push((pair[1], step));
+        }
+        trajectory
+    }
+
+    #[test]
+    fn joined_trajectories_keep_the_state_they_meet_at_once() {
+        let model = GridModel::new(10, 10);
+        let first = path(&model, &[Cell::new(1, 1), Cell::new(2, 2), Cell::new(3, 2)]);
+        let second = path(&model, &[Cell::new(3, 2), Cell::new(4, 2), Cell::new(5, 3)]);
+        let joined = first.concat(&second, &model).unwrap();
+        assert_eq!(joined.len(), 5);
+        assert_eq!(joined.cost, first.cost + second.cost);
+        assert_eq!(joined[2].0, Cell::new(3, 2));
+        assert_eq!(joined[3], second[1]);
+
+        // Back the way it came costs the same on a grid
+        let back = joined.reversed(&model);
+        assert_eq!(back.cost, joined.cost);
+        assert_eq!(back[0].0, Cell::new(5, 3));
+        assert_eq!(back.states().last(), Some(&Cell::new(1, 1)));
+    }
+
+    #[test]
+    fn joined_trajectories_have_to_meet() {
+        let model = GridModel::new(10, 10);
+        let first = path(&model, &[Cell::new(1, 1), Cell::new(2, 2), Cell::new(3, 2)]);
+        let apart = path(&model, &[Cell::new(4, 2), Cell::new(5, 3)]);
+        match first.concat(&apart, &model) {
+            Err(PathFindingErr::Discontinuous) => {}
+            _ => panic!("the trajectories do not meet"),
+        }
+    }
+
+    #[test]
+    fn queries_inside_obstacles_are_invalid() {
+        let mut grid = GridModel::new(10, 10);
+        grid.set_blocked(0, 0, true);
+        grid.set_blocked(9, 9, true);
+        let (open, blocked) = (Cell::new(5, 5), Cell::new(9, 9));
+
+        let mut astar = AStar::new();
+        match astar.optimize(&mut grid, &Cell::new(0, 0), &open, &mut GridSampler) {
+            PathResult::Err(PathFindingErr::InvalidStart) => {}
+            _ => panic!("the start is blocked"),
+        }
+        match astar.optimize(&mut grid, &open, &blocked, &mut GridSampler) {
+            PathResult::Err(PathFindingErr::InvalidGoal) => {}
+            _ => panic!("the goal is blocked"),
+        }
+        // Checked before stepping through the search just the same
+        match astar.next_trajectory(&mut grid, &open, &blocked, &mut GridSampler) {
+            PathResult::Err(PathFindingErr::InvalidGoal) => {}
+            _ => panic!("the goal is blocked"),
+        }
+    }
+
+    #[test]
+    fn exploring_one_expansion_at_a_time_reaches_the_goal() {
+        let mut grid = GridModel::new(10, 10);
+        for y in 0..8 {
+            grid.set_blocked(5, y, true);
+        }
+        let (start, goal) = (Cell::new(1, 1), Cell::new(8, 1));
+
+        let mut astar = AStar::new();
+        astar.begin(&grid, &start, &goal);
+        let explorer: &mut dyn Explore<GridModel, GridSampler> = &mut astar;
+
+        let mut expanded = 0;
+        let trajectory = loop {
+            match explorer.expand_one(&mut grid, &goal, &mut GridSampler) {
+                ExploreStep::Expanded(_) => expanded += 1,
+                ExploreStep::Goal(t) => break t,
+                step => panic!("the goal is reachable around the wall, not {:?}", step),
+            }
+        };
+
+        assert_eq!(trajectory.states().last(), Some(&goal));
+        assert_eq!(expanded, astar.stats().expanded);
+    }
+
+    #[test]
+    fn steps_leave_out_the_default_control_of_the_start() {
+        let mut grid = GridModel::new(10, 10);
+        let (start, goal) = (Cell::new(1, 1), Cell::new(6, 3));
+
+        let mut astar = AStar::new();
+        let first = astar.next_trajectory(&mut grid, &start, &goal, &mut GridSampler);
+        let planned = astar.optimize(&mut grid, &start, &goal, &mut GridSampler);
+        let converged = AStar::new().optimize(&mut grid, &goal, &goal, &mut GridSampler);
+        for result in [first, planned, converged] {
+            let t = result.into_trajectory().unwrap();
+            // The start always comes with the default control
+            assert_eq!(t.trajectory[0], (t.trajectory[0].0, Step::default()));
+
+            assert_eq!(t.steps().count(), t.len() - 1);
+            for (from, step, to) in t.steps() {
+                assert_ne!(*step, Step::default());
+                assert_eq!(grid.integrate(from, step), Some(*to));
+            }
+        }
+    }
+
+    #[test]
+    fn trajectories_count_the_expansions_of_their_search() {
+        let mut model = TurnOptimal::new(divided_room());
+        let (start, goal) = (walker(3, 10), walker(17, 9));
+        let mut sampler = WalkSampler::new();
+
+        let mut counted = 0;
+        let count = |_: &Actor, _: &usize| counted += 1;
+        let mut astar = AStar::new();
+        let t = expect_final(astar.optimize_with_hook(
+            &mut model,
+            &start,
+            &goal,
+            &mut sampler,
+            count,
+        ));
+        assert_eq!(t.expansions, counted);
+
+        // Each step of a search expands one more node
+        let mut astar = AStar::new();
+        for step in 1..=5 {
+            match astar.next_trajectory(&mut model, &start, &goal, &mut sampler) {
+                PathResult::Intermediate(t) => assert_eq!(t.expansions, step),
+                _ => panic!("the goal is further than five expansions away"),
+            }
+        }
+    }
+
+    /// Whether a cell is in the room of `trap`
+    fn trapped(cell: &Cell) -> bool {
+        (4..9).contains(&cell.x) && (12..19).contains(&cell.y)
+    }
+
+    /// A grid with a room which can be entered but never left, which the heuristic may know
+    /// about, and a wall between either side of the grid
+    fn trap(known: bool) -> TestGrid {
+        let mut model = TestGrid::new(20, 20);
+        for y in 3..20 {
+            model.grid.set_blocked(10, y, true);
+        }
+        model.allow = |previous, next| !trapped(previous) || trapped(next);
+        if known {
+            model.dead_end = |current, goal| trapped(current) && !trapped(goal);
+        }
+        model
+    }
+
+    #[test]
+    fn dead_ends_are_dropped_when_generated() {
+        let (start, goal) = (Cell::new(3, 10), Cell::new(17, 10));
+
+        let mut costs = Vec::new();
+        for &known in &[false, true] {
+            let mut model = trap(known);
+            let mut astar = AStar::new();
+            let t = expect_final(astar.optimize(&mut model, &start, &goal, &mut GridSampler));
+            costs.push(t.cost);
+            assert_eq!(astar.inspect_discovered().any(trapped), !known);
+        }
+        assert_eq!(costs[0], costs[1]);
+    }
+
+    #[test]
+    fn searches_from_a_dead_end_give_up_before_expanding() {
+        let mut model = trap(true);
+        let (inside, goal) = (Cell::new(6, 15), Cell::new(17, 10));
+        let mut astar = AStar::new();
+        match astar.next_trajectory(&mut model, &inside, &goal, &mut GridSampler) {
+            PathResult::Err(PathFindingErr::Unreachable) => {}
+            _ => panic!("the goal cannot be reached from the room"),
+        }
+        let reachable = astar.reachable(&mut model, &inside, &goal, &mut GridSampler);
+        assert_eq!(reachable, Err(PathFindingErr::Unreachable));
+        assert_eq!(astar.stats().expanded, 0);
+    }
+
+    #[test]
+    fn only_a_paused_search_is_pending() {
+        let mut map = room(30, 30);
+        let mut model = TurnOptimal::new(map.clone());
+        let (start, goal) = (walker(2, 2), walker(22, 22));
+        let mut sampler = WalkSampler::new();
+
+        // Paused on an open map, and resumed until it reaches the goal
+        let mut astar = AStar::new();
+        match astar.optimize_with_budget(&mut model, &start, &goal, &mut sampler, 10) {
+            PathResult::Err(err) => assert!(err.is_pending()),
+            _ => panic!("the budget should run out first"),
+        }
+        assert!(astar.optimize(&mut model, &start, &goal, &mut sampler).is_final());
+
+        // Paused the same way with the goal walled off, but only unreachable once finished
+        for i in 20..25 {
+            map[(i, 20)] = Tile::WALL;
+            map[(i, 24)] = Tile::WALL;
+            map[(20, i)] = Tile::WALL;
+            map[(24, i)] = Tile::WALL;
+        }
+        let mut model = TurnOptimal::new(map);
+        let mut astar = AStar::new();
+        match astar.optimize_with_budget(&mut model, &start, &goal, &mut sampler, 10) {
+            PathResult::Err(err) => assert!(err.is_pending()),
+            _ => panic!("the budget should run out first"),
+        }
+        match astar.optimize(&mut model, &start, &goal, &mut sampler) {
+            PathResult::Err(err) => {
+                assert_eq!(err, PathFindingErr::Unreachable);
+                assert!(!err.is_pending());
+            }
+            _ => panic!("the goal is walled off"),
+        }
+    }
+
+    /// A ring of beads where neighbours can trade places, and which can be turned freely
+    struct Necklace {
+        turnable: bool,
+    }
+
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    struct Beads([u8; 6]);
+
+    impl Beads {
+        /// The rotation which sorts first
+        fn smallest_turn(&self) -> [u8; 6] {
+            let turn = |i: usize| {
+                let mut beads = self.0;
+                beads.rotate_left(i);
+                beads
+            };
+            (0..6).map(turn).min().unwrap()
+        }
+    }
+
+    impl State for Beads {
+        type Position = [u8; 6];
+
+        fn grid_position(&self) -> [u8; 6] {
+            self.0
+        }
+
+        fn interpolate(&self, other: &Self, t: f64) -> Self {
+            if t < 0.5 {
+                *self
+            } else {
+                *other
+            }
+        }
+    }
+
+    impl Model for Necklace {
+        type State = Beads;
+        /// The bead to swap with the one after it
+        type Control = Option<usize>;
+        type Cost = usize;
+
+        fn cost(&self, _: &Beads, _: &Option<usize>, _: &Beads) -> usize {
+            1
+        }
+
+        fn init(&mut self, _: &Beads) {}
+
+        /// Any turn of the goal will do
+        fn converge(&self, current: &Beads, goal: &Beads) -> bool {
+            current.smallest_turn() == goal.smallest_turn()
+        }
+
+        fn integrate(&self, beads: &Beads, swap: &Option<usize>) -> Option<Beads> {
+            let mut next = *beads;
+            if let Some(i) = *swap {
+                next.0.swap(i, (i + 1) % 6);
+            }
+            Some(next)
+        }
+
+        fn canonicalize(&self, beads: &Beads) -> [u8; 6] {
+            if self.turnable {
+                beads.smallest_turn()
+            } else {
+                beads.0
+            }
+        }
+    }
+
+    impl HeuristicModel for Necklace {
+        fn heuristic(&self, _: &Beads, _: &Beads) -> usize {
+            0
+        }
+    }
+
+    struct Swaps;
+
+    impl Sampler<Necklace> for Swaps {
+        fn sample(&mut self, _: &Necklace, _: &Beads) -> &[Option<usize>] {
+            &[Some(0), Some(1), Some(2), Some(3), Some(4), Some(5)]
+        }
+    }
+
+    #[test]
+    fn merged_states_are_expanded_once_for_the_same_cost() {
+        let (start, goal) = (Beads([0, 1, 2, 3, 4, 5]), Beads([5, 4, 3, 2, 1, 0]));
+        let mut fixed = Necklace { turnable: false };
+        let mut turnable = Necklace { turnable: true };
+
+        let (mut naive, mut merged) = (AStar::new(), AStar::new());
+        let n = expect_final(naive.optimize(&mut fixed, &start, &goal, &mut Swaps));
+        let m = expect_final(merged.optimize(&mut turnable, &start, &goal, &mut Swaps));
+        assert_eq!(n.cost, m.cost);
+        assert!(turnable.converge(&m.trajectory.last().unwrap().0, &goal));
+        assert!(merged.stats().expanded * 3 < naive.stats().expanded);
+    }
+}
@@ -0,0 +1,402 @@
+use std::f64::consts::PI;
+
+use super::super::continuous::QuantizedPosition;
+use super::super::hybrid::Oriented;
+use super::super::*;
+
+/// The width of the cells which poses are bucketed into, in the same units as the positions
+pub const CELL_SIZE: f64 = 0.5;
+
+/// The number of ranges of heading which poses are bucketed into
+pub const HEADINGS: i64 = 16;
+
+/// An angle moved into the range from `0.0` up to two pi
+fn normalize(angle: f64) -> f64 {
+    angle.rem_euclid(2.0 * PI)
+}
+
+/// A position in the plane and the heading of the car there
+///
+/// The heading is in radians anticlockwise from the positive x axis.  The position of a pose
+/// for the optimizers is its cell of [`CELL_SIZE`] and its range of the [`HEADINGS`].
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Pose {
+    pub x: f64,
+    pub y: f64,
+    pub heading: f64,
+}
+
+impl Pose {
+    pub fn new(x: f64, y: f64, heading: f64) -> Self {
+        Pose { x, y, heading }
+    }
+
+    pub fn distance(&self, other: &Pose) -> f64 {
+        (self.x - other.x).hypot(self.y - other.y)
+    }
+}
+
+impl State for Pose {
+    type Position = QuantizedPosition<3>;
+
+    fn grid_position(&self) -> QuantizedPosition<3> {
+        let range = 2.0 * PI / HEADINGS as f64;
+        let heading = (normalize(self.heading) / range).round() as i64 % HEADINGS;
+        let [x, y] = QuantizedPosition::new([self.x, self.y], CELL_SIZE).0;
+        QuantizedPosition([x, y, heading])
+    }
+
+    /// Interpolate the position in a straight line, and the heading the shorter way round
+    fn interpolate(&self, other: &Self, t: f64) -> Self {
+        let turn = normalize(other.heading - self.heading + PI) - PI;
+        Pose {
+            x: self.x + (other.x - self.x) * t,
+            y: self.y + (other.y - self.y) * t,
+            heading: normalize(self.heading + turn * t),
+        }
+    }
+}
+
+/// Which way the car turns for the length of a step
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Turn {
+    Left,
+    #[default]
+    Straight,
+    Right,
+}
+
+impl Turn {
+    /// Every turn, for sampling with a [`LatticeSampler`]
+    ///
+    /// [`LatticeSampler`]: ../../lattice/struct.LatticeSampler.html
+    pub const ALL: [Turn; 3] = [Turn::Left, Turn::Straight, Turn::Right];
+}
+
+/// The kinds of shortest path for a Dubins car, by the turns of their three segments
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Word {
+    Lsl,
+    Rsr,
+    Lsr,
+    Rsl,
+    Rlr,
+    Lrl,
+}
+
+/// The shortest path between two poses for a car which can only drive forwards, with a
+/// bounded turning radius
+///
+/// Dubins showed that such a path is always made of three segments, each of them either a
+/// straight line or an arc of the tightest turn, in one of six combinations.  Each is worked
+/// out in closed form, and the shortest is kept.
+///
+/// ```
+/// # use game_lib::path::models::dubins::{DubinsPath, Pose};
+/// // Straight ahead
+/// let start = Pose::new(0.0, 0.0, 0.0);
+/// let path = DubinsPath::shortest(&start, &Pose::new(10.0, 0.0, 0.0), 1.0);
+/// assert!((path.length() - 10.0).abs() < 1e-9);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DubinsPath {
+    pub word: Word,
+    /// The length of each segment, in the units of the positions
+    pub segments: [f64; 3],
+}
+
+impl DubinsPath {
+    /// The shortest path from `start` to `goal` when the car turns no tighter than `radius`
+    pub fn shortest(start: &Pose, goal: &Pose, radius: f64) -> DubinsPath {
+        let (dx, dy) = (goal.x - start.x, goal.y - start.y);
+        let d = dx.hypot(dy) / radius;
+        let theta = normalize(dy.atan2(dx));
+        let a = normalize(start.heading - theta);
+        let b = normalize(goal.heading - theta);
+
+        let words = [Word::Lsl, Word::Rsr, Word::Lsr, Word::Rsl, Word::Rlr, Word::Lrl];
+        let (word, [t, p, q]) = words
+            .iter()
+            .filter_map(|&word| segments(word, a, b, d).map(|s| (word, s)))
+            .min_by(|(_, x), (_, y)| x.iter().sum::<f64>().total_cmp(&y.iter().sum()))
+            .expect("a Dubins path always exists");
+
+        DubinsPath { word, segments: [t * radius, p * radius, q * radius] }
+    }
+
+    pub fn length(&self) -> f64 {
+        self.segments.iter().sum()
+    }
+}
+
+/// The lengths of the segments of a word on the unit circle, where `a` and `b` are the start
+/// and goal heading relative to the line between them and `d` is the distance between them
+fn segments(word: Word, a: f64, b: f64, d: f64) -> Option<[f64; 3]> {
+    let (sa, ca, sb, cb) = (a.sin(), a.cos(), b.sin(), b.cos());
+    let cab = (a - b).cos();
+
+    match word {
+        Word::Lsl => {
+            let p2 = 2.0 + d * d - 2.0 * cab + 2.0 * d * (sa - sb);
+            let turn = (cb - ca).atan2(d + sa - sb);
+            (p2 >= 0.0).then(|| [normalize(turn - a), p2.sqrt(), normalize(b - turn)])
+        }
+        Word::Rsr => {
+            let p2 = 2.0 + d * d - 2.0 * cab + 2.0 * d * (sb - sa);
+            let turn = (ca - cb).atan2(d - sa + sb);
+            (p2 >= 0.0).then(|| [normalize(a - turn), p2.sqrt(), normalize(turn - b)])
+        }
+        Word::Lsr => {
+            let p2 = -2.0 + d * d + 2.0 * cab + 2.0 * d * (sa + sb);
+            (p2 >= 0.0).then(|| {
+                let p = p2.sqrt();
+                let turn = (-ca - cb).atan2(d + sa + sb) - (-2.0f64).atan2(p);
+                [normalize(turn - a), p, normalize(turn - b)]
+            })
+        }
+        Word::Rsl => {
+            let p2 = -2.0 + d * d + 2.0 * cab - 2.0 * d * (sa + sb);
+            (p2 >= 0.0).then(|| {
+                let p = p2.sqrt();
+                let turn = (ca + cb).atan2(d - sa - sb) - 2.0f64.atan2(p);
+                [normalize(a - turn), p, normalize(b - turn)]
+            })
+        }
+        Word::Rlr => {
+            let c = (6.0 - d * d + 2.0 * cab + 2.0 * d * (sa - sb)) / 8.0;
+            (c.abs() <= 1.0).then(|| {
+                let p = normalize(2.0 * PI - c.acos());
+                let t = normalize(a - (ca - cb).atan2(d - sa + sb) + p / 2.0);
+                [t, p, normalize(a - b - t + p)]
+            })
+        }
+        Word::Lrl => {
+            let c = (6.0 - d * d + 2.0 * cab + 2.0 * d * (sb - sa)) / 8.0;
+            (c.abs() <= 1.0).then(|| {
+                let p = normalize(2.0 * PI - c.acos());
+                let t = normalize(-a - (ca - cb).atan2(d + sa - sb) + p / 2.0);
+                [t, p, normalize(b - a - t + p)]
+            })
+        }
+    }
+}
+
+/// A car which drives forwards at a constant speed, and turns no tighter than a radius
+///
+/// Each control drives the same distance, whether in a straight line or around the tightest
+/// turn to either side, and costs that distance in thousandths.  The car moves within a
+/// rectangle from the origin, around any number of circular obstacles, and converges once it
+/// is within a tolerance of the position and heading of the goal.
+///
+/// The heuristic is the length of the [`DubinsPath`] to the goal, less the tolerance of the
+/// position.  It ignores the obstacles and the tolerance of the heading, which it can
+/// overestimate by a little for a goal it could reach at a slightly different heading, so the
+/// trajectories are close to but not always exactly the shortest for the controls.
+///
+/// ```
+/// # use game_lib::path::astar::AStar;
+/// # use game_lib::path::lattice::LatticeSampler;
+/// # use game_lib::path::models::dubins::{DubinsCar, Pose, Turn};
+/// # use game_lib::path::Optimizer;
+/// let mut car = DubinsCar::new(20.0, 12.0, 2.0, 1.0);
+/// car.add_obstacle(10.0, 6.0, 2.5);
+/// let (start, goal) = (Pose::new(3.0, 6.0, 0.0), Pose::new(16.0, 6.0, 0.0));
+/// let mut sampler = LatticeSampler::new(Turn::ALL.to_vec());
+/// assert!(AStar::new().optimize(&mut car, &start, &goal, &mut sampler).is_final());
+/// ```
+#[derive(Clone, Debug)]
+pub struct DubinsCar {
+    width: f64,
+    height: f64,
+    radius: f64,
+    step: f64,
+    /// The centre and radius of each obstacle
+    obstacles: Vec<(f64, f64, f64)>,
+    tolerance: f64,
+    heading_tolerance: f64,
+}
+
+impl DubinsCar {
+    /// A car in a `width` by `height` rectangle, which turns no tighter than `radius` and
+    /// drives `step` with every control
+    ///
+    /// The goal is reached within the length of a step of its position and within the range of
+    /// one of the [`HEADINGS`] of its heading, until the tolerances are changed.
+    pub fn new(width: f64, height: f64, radius: f64, step: f64) -> Self {
+        DubinsCar {
+            width,
+            height,
+            radius,
+            step,
+            obstacles: Vec::new(),
+            tolerance: step,
+            heading_tolerance: 2.0 * PI / HEADINGS as f64,
+        }
+    }
+
+    /// Keep the car further than `radius` from the point at `x`, `y`
+    pub fn add_obstacle(&mut self, x: f64, y: f64, radius: f64) {
+        self.obstacles.push((x, y, radius));
+    }
+
+    /// Change how close the car has to get to the position and the heading of the goal
+    pub fn set_tolerance(&mut self, distance: f64, heading: f64) {
+        self.tolerance = distance;
+        self.heading_tolerance = heading;
+    }
+
+    pub fn radius(&self) -> f64 {
+        self.radius
+    }
+
+    pub fn step(&self) -> f64 {
+        self.step
+    }
+
+    /// The length the heuristic estimates for the rest of the way from `current` to `goal`
+    pub fn heuristic_length(&self, current: &Pose, goal: &Pose) -> f64 {
+        let length = DubinsPath::shortest(current, goal, self.radius).length();
+        (length - self.tolerance).max(0.0)
+    }
+
+    /// Whether the car can be at `pose`
+    pub fn is_free(&self, pose: &Pose) -> bool {
+        let inside =
+            (0.0..self.width).contains(&pose.x) && (0.0..self.height).contains(&pose.y);
+        inside && self.obstacles.iter().all(|&(x, y, r)| (pose.x - x).hypot(pose.y - y) > r)
+    }
+
+    /// Where driving `length` with `turn` from `pose` ends
+    fn drive(&self, pose: &Pose, turn: Turn, length: f64) -> Pose {
+        let curvature = match turn {
+            Turn::Left => 1.0 / self.radius,
+            Turn::Straight => {
+                let (sin, cos) = pose.heading.sin_cos();
+                return Pose::new(pose.x + length * cos, pose.y + length * sin, pose.heading);
+            }
+            Turn::Right => -1.0 / self.radius,
+        };
+
+        let heading = pose.heading + length * curvature;
+        Pose {
+            x: pose.x + (heading.sin() - pose.heading.sin()) / curvature,
+            y: pose.y - (heading.cos() - pose.heading.cos()) / curvature,
+            heading: normalize(heading),
+        }
+    }
+}
+
+impl Model for DubinsCar {
+    type State = Pose;
+    type Control = Turn;
+    type Cost = usize;
+
+    fn cost(&self, _: &Pose, _: &Turn, _: &Pose) -> usize {
+        (self.step * 1000.0).round() as usize
+    }
+
+    fn init(&mut self, _: &Pose) {}
+
+    fn converge(&self, current: &Pose, goal: &Pose) -> bool {
+        let turn = normalize(goal.heading - current.heading + PI) - PI;
+        current.distance(goal) <= self.tolerance && turn.abs() <= self.heading_tolerance
+    }
+
+    /// Drive a step, checking for obstacles at a few points along the way
+    fn integrate(&self, previous: &Pose, turn: &Turn) -> Option<Pose> {
+        const CHECKS: u32 = 4;
+        let clear = (1..=CHECKS).all(|i| {
+            let along = self.step * f64::from(i) / f64::from(CHECKS);
+            self.is_free(&self.drive(previous, *turn, along))
+        });
+
+        if clear {
+            Some(self.drive(previous, *turn, self.step))
+        } else {
+            None
+        }
+    }
+
+    fn is_valid(&self, pose: &Pose) -> bool {
+        self.is_free(pose)
+    }
+}
+
+impl HeuristicModel for DubinsCar {
+    fn heuristic(&self, current: &Pose, goal: &Pose) -> usize {
+        (self.heuristic_length(current, goal) * 1000.0) as usize
+    }
+}
+
+impl Oriented for Pose {
+    fn x(&self) -> f64 {
+        self.x
+    }
+
+    fn y(&self) -> f64 {
+        self.y
+    }
+
+    fn heading(&self) -> f64 {
+        self.heading
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::path::astar::AStar;
+    use crate::path::lattice::LatticeSampler;
+    use crate::path::testing::expect_final;
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+
+    #[test]
+    fn the_shortest_paths_match_the_ones_worked_out_by_hand() {
+        let start = Pose::new(0.0, 0.0, 0.0);
+
+        // Straight ahead
+        let path = DubinsPath::shortest(&start, &Pose::new(10.0, 0.0, 0.0), 1.0);
+        assert!(close(path.length(), 10.0));
+
+        // Half a turn to the left, and to the right, of the tightest radius
+        let back = DubinsPath::shortest(&start, &Pose::new(0.0, 4.0, PI), 2.0);
+        assert!(close(back.length(), 2.0 * PI));
+        let back = DubinsPath::shortest(&start, &Pose::new(0.0, -4.0, PI), 2.0);
+        assert!(close(back.length(), 2.0 * PI));
+
+        // A quarter turn left then straight on, and a quarter turn right, straight on, then left
+        let path = DubinsPath::shortest(&start, &Pose::new(1.0, 6.0, PI / 2.0), 1.0);
+        assert!(close(path.segments[0], PI / 2.0) && close(path.segments[2], 0.0));
+        assert!(close(path.length(), PI / 2.0 + 5.0));
+        let path = DubinsPath::shortest(&start, &Pose::new(2.0, -5.0, 0.0), 1.0);
+        assert_eq!(path.word, Word::Rsl);
+        assert!(close(path.length(), PI + 3.0));
+
+        // Getting back to where it started facing the other way takes a loop
+        let turn = DubinsPath::shortest(&start, &Pose::new(0.0, 0.0, PI), 1.0);
+        assert!(turn.length() > PI);
+    }
+
+    #[test]
+    fn the_car_drives_around_the_obstacle() {
+        let mut car = DubinsCar::new(20.0, 12.0, 2.0, 1.0);
+        car.add_obstacle(10.0, 6.0, 2.5);
+
+        // Around the obstacle, and ending up facing back the way it came
+        let (start, goal) = (Pose::new(3.0, 6.0, 0.0), Pose::new(16.0, 8.0, PI));
+        let mut sampler = LatticeSampler::new(Turn::ALL.to_vec());
+        let t = expect_final(AStar::new().optimize(&mut car, &start, &goal, &mut sampler));
+        assert!(car.converge(t.states().last().unwrap(), &goal));
+        // Every step is a control the car can drive, and clear of the obstacle
+        for pair in t.trajectory.windows(2) {
+            let ((from, _), (to, turn)) = (&pair[0], &pair[1]);
+            let driven = car.integrate(from, turn).unwrap();
+            assert!(driven.distance(to) < 1e-9);
+            assert!(to.distance(&Pose::new(10.0, 6.0, 0.0)) > 2.5);
+        }
+        assert!(t.cost as f64 >= 1000.0 * car.heuristic_length(&start, &goal));
+    }
+}
@@ -0,0 +1,3 @@
+//! Complete models of common kinds of vehicles, to plan for as they are or to start from
+
+pub mod dubins;
@@ -0,0 +1,259 @@
+use std::fmt::{Debug, Formatter};
+
+/// States which are points in a space of a fixed number of coordinates
+///
+/// The distance must never be less than the difference along any one axis, which holds for
+/// the Euclidean distance of the default and for any other norm of the differences.
+pub trait Metric {
+    /// The number of coordinates of every point
+    const DIMENSIONS: usize;
+
+    /// The coordinate along `axis`, which is less than `DIMENSIONS`
+    fn coordinate(&self, axis: usize) -> f64;
+
+    fn distance(&self, other: &Self) -> f64 {
+        let squares = (0..Self::DIMENSIONS).map(|axis| {
+            let difference = self.coordinate(axis) - other.coordinate(axis);
+            difference * difference
+        });
+        squares.sum::<f64>().sqrt()
+    }
+}
+
+impl<const N: usize> Metric for [f64; N] {
+    const DIMENSIONS: usize = N;
+
+    #[inline]
+    fn coordinate(&self, axis: usize) -> f64 {
+        self[axis]
+    }
+}
+
+/// A point of the tree, splitting the space after it along one axis
+struct Node<S> {
+    state: S,
+    axis: usize,
+    /// The child on the side of lower coordinates along the axis
+    lower: Option<usize>,
+    /// The child on the side of equal or higher coordinates along the axis
+    upper: Option<usize>,
+}
+
+/// A kd-tree of states, for finding the states closest to a point
+///
+/// Sampling planners, like rapidly-exploring random trees and probabilistic roadmaps, grow a
+/// set of states and keep asking which of them is closest to a new sample.  The tree answers
+/// that without measuring the distance to every state, by skipping the parts of the space
+/// which are further along one axis than the closest state found so far.
+///
+/// States are inserted one at a time, splitting along each axis in turn as the tree gets
+/// deeper.  The tree is never rebalanced, which suits states that arrive in random order, but
+/// states inserted along a line make it degrade towards a linear scan.
+///
+/// ```
+/// # use game_lib::path::nearest::NearestNeighbor;
+/// let mut tree = NearestNeighbor::new();
+/// for &point in &[[1.0, 1.0], [4.0, 2.0], [8.0, 7.0]] {
+///     tree.insert(point);
+/// }
+/// assert_eq!(tree.nearest(&[5.0, 3.0]), Some(&[4.0, 2.0]));
+/// assert_eq!(tree.within(&[0.0, 0.0], 2.0), vec![&[1.0, 1.0]]);
+/// ```
+pub struct NearestNeighbor<S>
+where
+    S: Metric,
+{
+    nodes: Vec<Node<S>>,
+}
+
+impl<S> NearestNeighbor<S>
+where
+    S: Metric,
+{
+    pub fn new() -> Self {
+        NearestNeighbor { nodes: Vec::new() }
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.nodes.clear();
+    }
+
+    /// Every state in the order it was inserted
+    pub fn iter(&self) -> impl Iterator<Item = &S> {
+        self.nodes.iter().map(|node| &node.state)
+    }
+
+    /// The state at `index`, counting from zero in the order states were inserted
+    pub fn get(&self, index: usize) -> Option<&S> {
+        self.nodes.get(index).map(|node| &node.state)
+    }
+
+    /// Add a state to the tree, returning its index
+    pub fn insert(&mut self, state: S) -> usize {
+        let index = self.nodes.len();
+        let mut axis = 0;
+
+        if !self.nodes.is_empty() {
+            let mut current = 0;
+            loop {
+                let node = &mut self.nodes[current];
+                let child = if state.coordinate(node.axis) < node.state.coordinate(node.axis) {
+                    &mut node.lower
+                } else {
+                    &mut node.upper
+                };
+                axis = (node.axis + 1) % S::DIMENSIONS;
+
+                match *child {
+                    Some(next) => current = next,
+                    None => {
+                        *child = Some(index);
+                        break;
+                    }
+                }
+            }
+        }
+
+        self.nodes.push(Node { state, axis, lower: None, upper: None });
+        index
+    }
+
+    /// The state closest to `query`, or `None` when the tree is empty
+    pub fn nearest(&self, query: &S) -> Option<&S> {
+        self.nearest_index(query).map(|index| &self.nodes[index].state)
+    }
+
+    /// The index of the state closest to `query`, or `None` when the tree is empty
+    pub fn nearest_index(&self, query: &S) -> Option<usize> {
+        if self.nodes.is_empty() {
+            return None;
+        }
+
+        let mut best = (0, f64::INFINITY);
+        self.search_nearest(0, query, &mut best);
+        Some(best.0)
+    }
+
+    /// Every state no further than `radius` from `query`, in no particular order
+    pub fn within(&self, query: &S, radius: f64) -> Vec<&S> {
+        let indices = self.within_indices(query, radius).into_iter();
+        indices.map(|index| &self.nodes[index].state).collect()
+    }
+
+    /// The index of every state no further than `radius` from `query`, in no particular order
+    pub fn within_indices(&self, query: &S, radius: f64) -> Vec<usize> {
+        let mut found = Vec::new();
+        let mut stack = Vec::new();
+        if !self.nodes.is_empty() {
+            stack.push(0);
+        }
+
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+            if query.distance(&node.state) <= radius {
+                found.push(index);
+            }
+
+            let difference = query.coordinate(node.axis) - node.state.coordinate(node.axis);
+            if let Some(lower) = node.lower.filter(|_| difference < radius) {
+                stack.push(lower);
+            }
+            if let Some(upper) = node.upper.filter(|_| difference >= -radius) {
+                stack.push(upper);
+            }
+        }
+
+        found
+    }
+
+    fn search_nearest(&self, index: usize, query: &S, best: &mut (usize, f64)) {
+        let node = &self.nodes[index];
+        let distance = query.distance(&node.state);
+        if distance < best.1 {
+            *best = (index, distance);
+        }
+
+        // Search the side of the split the query is on first, as it most likely holds the
+        // closest state, and the other side only when the split is closer than that state
+        let difference = query.coordinate(node.axis) - node.state.coordinate(node.axis);
+        let (near, far) =
+            if difference < 0.0 { (node.lower, node.upper) } else { (node.upper, node.lower) };
+
+        if let Some(near) = near {
+            self.search_nearest(near, query, best);
+        }
+        if let Some(far) = far {
+            if difference.abs() < best.1 {
+                self.search_nearest(far, query, best);
+            }
+        }
+    }
+}
+
+impl<S> Default for NearestNeighbor<S>
+where
+    S: Metric,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S> Debug for NearestNeighbor<S>
+where
+    S: Metric,
+{
+    fn fmt(&self, fmt: &mut Formatter) -> Result<(), std::fmt::Error> {
+        fmt.debug_struct("NearestNeighbor").field("len", &self.nodes.len()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{Rng, SeedableRng};
+    use rand_xorshift::XorShiftRng;
+
+    #[test]
+    fn the_tree_answers_the_same_as_measuring_every_distance() {
+        let mut rng = XorShiftRng::seed_from_u64(3);
+        let mut point = || [rng.gen_range(0.0, 10.0), rng.gen_range(0.0, 10.0)];
+
+        let mut tree = NearestNeighbor::new();
+        let mut points = Vec::new();
+        for i in 0..300 {
+            let p = point();
+            assert_eq!(tree.insert(p), i);
+            points.push(p);
+        }
+        assert_eq!(tree.len(), points.len());
+
+        for _ in 0..50 {
+            let query = point();
+            let closest =
+                points.iter().map(|p| p.distance(&query)).fold(f64::INFINITY, f64::min);
+            assert_eq!(tree.nearest(&query).unwrap().distance(&query), closest);
+
+            let mut within: Vec<_> = tree.within(&query, 1.5);
+            let mut brute: Vec<_> =
+                points.iter().filter(|p| p.distance(&query) <= 1.5).collect();
+            let order = |a: &&[f64; 2], b: &&[f64; 2]| a.partial_cmp(b).unwrap();
+            within.sort_by(order);
+            brute.sort_by(order);
+            assert_eq!(within, brute);
+        }
+    }
+
+    #[test]
+    fn an_empty_tree_has_nothing_nearest() {
+        assert!(NearestNeighbor::<[f64; 2]>::new().nearest(&[0.0, 0.0]).is_none());
+    }
+}
@@ -0,0 +1,315 @@
+use super::astar::PriorityQueue;
+
+/// An item along with the links which place it in the heap
+#[derive(Clone, Debug)]
+struct Slot<T> {
+    /// Taken once the item is popped, so that its handle is never handed out again
+    item: Option<T>,
+    /// The first of the children
+    child: Option<usize>,
+    /// The next of the parent's children
+    next: Option<usize>,
+    /// The previous of the parent's children, or the parent for its first child
+    prev: Option<usize>,
+}
+
+/// Priority queue kept as a pairing heap, greatest first, which can raise an item in place
+///
+/// Pushing takes constant time, and so does [`decrease_key`], amortized: the item behind a
+/// handle is cut from its parent and melded back in at the root.  A* uses that to replace a
+/// queued node once it finds a cheaper way to its position, instead of queueing the cheaper
+/// node alongside it, so the open list never holds stale nodes and stays as small as the
+/// frontier of the search.  Popping does the work left over, pairing up the children of the
+/// root, in `O(log n)` amortized time.
+///
+/// Handles stay valid until the queue is cleared, and slots are not reused before then, so
+/// the memory held grows with the number of items pushed rather than the number queued.
+///
+/// [`decrease_key`]: ../astar/trait.PriorityQueue.html#method.decrease_key
+///
+/// ```
+/// # use game_lib::path::astar::PriorityQueue;
+/// # use game_lib::path::pairing::PairingHeap;
+/// let mut heap = PairingHeap::new();
+/// let low = heap.push_handle(1).unwrap();
+/// heap.push(5);
+/// heap.decrease_key(low, 9);
+/// assert_eq!(heap.pop(), Some(9));
+/// ```
+///
+/// A cheaper way to a position is not always a more promising one, as when the weight falls
+/// off with the depth of a node, or the model merges states with different estimates.  Then
+/// the node cannot be raised in place, and A* queues it alongside the old one instead.
+#[derive(Clone, Debug)]
+pub struct PairingHeap<T> {
+    slots: Vec<Slot<T>>,
+    root: Option<usize>,
+    len: usize,
+}
+
+impl<T> PairingHeap<T>
+where
+    T: Ord,
+{
+    pub fn new() -> Self {
+        PairingHeap { slots: Vec::new(), root: None, len: 0 }
+    }
+
+    #[inline]
+    fn item(&self, index: usize) -> &T {
+        self.slots[index].item.as_ref().expect("only queued items are linked into the heap")
+    }
+
+    /// Make the lesser of two heaps the first child of the greater, returning the greater
+    fn meld(&mut self, a: usize, b: usize) -> usize {
+        let (parent, child) = if self.item(b) > self.item(a) { (b, a) } else { (a, b) };
+
+        let first = self.slots[parent].child;
+        if let Some(first) = first {
+            self.slots[first].prev = Some(child);
+        }
+        self.slots[child].next = first;
+        self.slots[child].prev = Some(parent);
+        self.slots[parent].child = Some(child);
+
+        parent
+    }
+
+    /// Meld a list of siblings into a single heap, in pairs from the left and then from the
+    /// right, which is what keeps popping cheap
+    fn merge_pairs(&mut self, first: usize) -> usize {
+        let mut siblings = Vec::new();
+        let mut next = Some(first);
+        while let Some(sibling) = next {
+            next = self.slots[sibling].next.take();
+            self.slots[sibling].prev = None;
+            siblings.push(sibling);
+        }
+
+        let mut paired = Vec::with_capacity(siblings.len() / 2 + 1);
+        for pair in siblings.chunks(2) {
+            paired.push(match *pair {
+                [a, b] => self.meld(a, b),
+                _ => pair[0],
+            });
+        }
+
+        let mut root = paired.pop().expect("there is at least one sibling");
+        while let Some(heap) = paired.pop() {
+            root = self.meld(heap, root);
+        }
+
+        root
+    }
+}
+
+impl<T> PriorityQueue<T> for PairingHeap<T>
+where
+    T: Ord,
+{
+    fn push(&mut self, item: T) {
+        self.push_handle(item);
+    }
+
+    fn pop(&mut self) -> Option<T> {
+        let root = self.root?;
+        let item = self.slots[root].item.take();
+        self.len -= 1;
+        self.root = self.slots[root].child.take().map(|first| self.merge_pairs(first));
+
+        item
+    }
+
+    #[inline]
+    fn peek(&self) -> Option<&T> {
+        self.root.map(|root| self.item(root))
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Forget every item, along with every handle handed out so far
+    fn clear(&mut self) {
+        self.slots.clear();
+        self.root = None;
+        self.len = 0;
+    }
+
+    fn iter(&self) -> Box<dyn Iterator<Item = &T> + '_> {
+        Box::new(self.slots.iter().filter_map(|slot| slot.item.as_ref()))
+    }
+
+    fn reserve(&mut self, additional: usize) {
+        self.slots.reserve(additional)
+    }
+
+    fn shrink_to_fit(&mut self) {
+        self.slots.shrink_to_fit()
+    }
+
+    fn capacity(&self) -> usize {
+        self.slots.capacity()
+    }
+
+    fn push_handle(&mut self, item: T) -> Option<usize> {
+        let index = self.slots.len();
+        self.slots.push(Slot { item: Some(item), child: None, next: None, prev: None });
+        self.root = Some(match self.root {
+            Some(root) => self.meld(root, index),
+            None => index,
+        });
+        self.len += 1;
+
+        Some(index)
+    }
+
+    fn decrease_key(&mut self, handle: usize, item: T) -> Option<usize> {
+        if self.slots.get(handle).is_none_or(|slot| slot.item.is_none()) {
+            return self.push_handle(item);
+        }
+
+        debug_assert!(item >= *self.item(handle), "an item may only be raised in place");
+        self.slots[handle].item = Some(item);
+        if self.root == Some(handle) {
+            return Some(handle);
+        }
+
+        // Cut the item, along with its children, from its parent and meld it at the root
+        let prev = self.slots[handle].prev.take().expect("only the root has no parent");
+        let next = self.slots[handle].next.take();
+        if self.slots[prev].child == Some(handle) {
+            self.slots[prev].child = next;
+        } else {
+            self.slots[prev].next = next;
+        }
+        if let Some(next) = next {
+            self.slots[next].prev = Some(prev);
+        }
+
+        let root = self.root.expect("a queued item has a root above it");
+        self.root = Some(self.meld(root, handle));
+
+        Some(handle)
+    }
+}
+
+impl<T> Default for PairingHeap<T>
+where
+    T: Ord,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::actor::{TurnOptimal, WalkSampler};
+    use crate::path::astar::AStar;
+    use crate::path::grid::{Cell, GridModel, GridSampler, Step};
+    use crate::path::testing::{assert_same_trajectory, divided_room, expect_final, walker};
+    use crate::path::{HeuristicModel, Model, Optimizer, Sampler};
+
+    #[test]
+    fn raises_items_in_place() {
+        let mut heap = PairingHeap::new();
+        let items = [5, 1, 8, 3, 7];
+        let handles: Vec<_> = items.iter().map(|&i| heap.push_handle(i).unwrap()).collect();
+
+        // Raise the 1 above everything else, and the 3 to the middle
+        assert_eq!(heap.decrease_key(handles[1], 9), Some(handles[1]));
+        heap.decrease_key(handles[3], 6);
+        assert_eq!(heap.peek(), Some(&9));
+        assert_eq!(heap.len(), 5);
+
+        let popped: Vec<_> = std::iter::from_fn(|| heap.pop()).take(2).collect();
+        assert_eq!(popped, vec![9, 8]);
+
+        // The 8 is gone, so raising it queues the new item instead
+        let requeued = heap.decrease_key(handles[2], 10).unwrap();
+        assert_ne!(requeued, handles[2]);
+        let rest: Vec<_> = std::iter::from_fn(|| heap.pop()).collect();
+        assert_eq!(rest, vec![10, 7, 6, 5]);
+    }
+
+    #[test]
+    fn keeps_the_open_list_smaller_without_changing_the_search() {
+        let mut model = TurnOptimal::new(divided_room());
+        let (start, goal) = (walker(3, 10), walker(17, 9));
+        let mut sampler = WalkSampler::new();
+        let mut lazy = AStar::new();
+        let mut eager = AStar::with_queue(PairingHeap::new());
+
+        let a = expect_final(lazy.optimize(&mut model, &start, &goal, &mut sampler));
+        let b = expect_final(eager.optimize(&mut model, &start, &goal, &mut sampler));
+        assert_same_trajectory(&a, &b);
+        for ((_, x), (_, y)) in a.trajectory.iter().zip(&b.trajectory) {
+            assert_eq!(x, y);
+        }
+        assert_eq!(lazy.stats().expanded, eager.stats().expanded);
+        assert!(eager.stats().max_queue_len < lazy.stats().max_queue_len);
+    }
+
+    /// A grid which is the same above and below its middle row, where either half will do
+    struct Mirrored(GridModel);
+
+    impl Model for Mirrored {
+        type State = Cell;
+        type Control = Step;
+        type Cost = usize;
+
+        fn cost(&self, current: &Cell, step: &Step, next: &Cell) -> usize {
+            self.0.cost(current, step, next)
+        }
+
+        fn init(&mut self, start: &Cell) {
+            self.0.init(start)
+        }
+
+        fn converge(&self, current: &Cell, goal: &Cell) -> bool {
+            self.canonicalize(current) == self.canonicalize(goal)
+        }
+
+        fn integrate(&self, cell: &Cell, step: &Step) -> Option<Cell> {
+            self.0.integrate(cell, step)
+        }
+
+        fn canonicalize(&self, cell: &Cell) -> Cell {
+            let height = self.0.height() as i64;
+            Cell::new(cell.x, cell.y.min(height - 1 - cell.y))
+        }
+    }
+
+    impl HeuristicModel for Mirrored {
+        fn heuristic(&self, current: &Cell, goal: &Cell) -> usize {
+            self.0.heuristic(current, goal)
+        }
+    }
+
+    impl Sampler<Mirrored> for GridSampler {
+        fn sample(&mut self, mirrored: &Mirrored, cell: &Cell) -> &[Step] {
+            Sampler::<GridModel>::sample(self, &mirrored.0, cell)
+        }
+    }
+
+    #[test]
+    fn queues_nodes_which_cannot_be_raised_alongside_the_old_ones() {
+        let mut grid = GridModel::new(30, 30);
+        for y in 4..26 {
+            grid.set_blocked(15, y, true);
+        }
+        let (start, goal) = (Cell::new(2, 14), Cell::new(28, 20));
+
+        let mut dynamic = AStar::with_queue(PairingHeap::new());
+        dynamic.set_dynamic_weight(1.0, 10);
+        let d = expect_final(dynamic.optimize(&mut grid, &start, &goal, &mut GridSampler));
+        assert_eq!(d.trajectory.last().unwrap().0, goal);
+
+        let mut merged = AStar::with_queue(PairingHeap::new());
+        let mut mirrored = Mirrored(grid);
+        let m = expect_final(merged.optimize(&mut mirrored, &start, &goal, &mut GridSampler));
+        assert!(mirrored.converge(&m.trajectory.last().unwrap().0, &goal));
+    }
+}
@@ -0,0 +1,330 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::fmt::{Debug, Formatter};
+use std::ops::Add;
+
+use fnv::FnvHashMap;
+
+use super::*;
+
+/// Costs made up of several objectives, which are only partially ordered by dominance
+///
+/// The `Ord` the queue needs must be a linear extension of dominance: a cost which dominates
+/// another must also compare less than it.  Comparing the objectives lexicographically, in
+/// order of priority, is one such order.
+pub trait Dominance {
+    /// Whether this cost is no worse than `other` in every objective
+    fn weakly_dominates(&self, other: &Self) -> bool;
+
+    /// Whether this cost is no worse than `other` in every objective, and better in at least
+    /// one of them
+    fn dominates(&self, other: &Self) -> bool {
+        self.weakly_dominates(other) && !other.weakly_dominates(self)
+    }
+}
+
+/// A cost with `N` objectives, which are added separately and compared lexicographically
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Objectives<const N: usize>(pub [usize; N]);
+
+impl<const N: usize> Default for Objectives<N> {
+    fn default() -> Self {
+        Objectives([0; N])
+    }
+}
+
+impl<const N: usize> Add for Objectives<N> {
+    type Output = Self;
+
+    fn add(mut self, other: Self) -> Self {
+        for (a, b) in self.0.iter_mut().zip(&other.0) {
+            *a += b;
+        }
+
+        self
+    }
+}
+
+impl<const N: usize> Cost for Objectives<N> {
+    fn checked_add(mut self, other: Self) -> Option<Self> {
+        for (a, b) in self.0.iter_mut().zip(&other.0) {
+            *a = a.checked_add(*b)?;
+        }
+
+        Some(self)
+    }
+}
+
+impl<const N: usize> Dominance for Objectives<N> {
+    fn weakly_dominates(&self, other: &Self) -> bool {
+        self.0.iter().zip(&other.0).all(|(a, b)| a <= b)
+    }
+}
+
+/// A trajectory to a position, of which several may be kept for the same position
+struct Label<M>
+where
+    M: Model,
+{
+    state: M::State,
+    control: M::Control,
+    g: M::Cost,
+    parent: Option<usize>,
+    /// Whether a label for the same position has been found which dominates this one
+    dominated: bool,
+}
+
+/// Multi-objective A*, which finds every Pareto-optimal trajectory to the goal
+///
+/// Where A* keeps the single cheapest trajectory to each position, this keeps every trajectory
+/// whose cost is not dominated by another trajectory to the same position, and expands them in
+/// the order of the cost type.  A trajectory is dropped as soon as its estimated cost is
+/// dominated by a trajectory already found to the goal.  The result is a trajectory for every
+/// cost on the Pareto front, from which the caller picks the trade-off between objectives.
+///
+/// The heuristic must be admissible in every objective and zero at the goal.  The number of
+/// trajectories kept can grow far beyond the number of positions when the objectives conflict.
+pub struct ParetoAStar<M>
+where
+    M: HeuristicModel,
+    M::Cost: Dominance + Copy,
+{
+    labels: Vec<Label<M>>,
+    queue: BinaryHeap<(Reverse<M::Cost>, Reverse<usize>)>,
+    /// The labels of each position which have not been dominated
+    frontier: FnvHashMap<<<M as Model>::State as State>::Position, Vec<usize>>,
+    controls: Vec<M::Control>,
+    stats: Stats,
+}
+
+impl<M> ParetoAStar<M>
+where
+    M: HeuristicModel,
+    M::Cost: Dominance + Copy,
+{
+    pub fn new() -> Self {
+        ParetoAStar {
+            labels: Vec::new(),
+            queue: BinaryHeap::new(),
+            frontier: FnvHashMap::default(),
+            controls: Vec::new(),
+            stats: Stats::default(),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.labels.clear();
+        self.queue.clear();
+        self.frontier.clear();
+        self.stats = Stats::default();
+    }
+
+    pub fn stats(&self) -> &Stats {
+        &self.stats
+    }
+
+    /// Number of trajectories which have been kept, including those dominated since
+    pub fn labels(&self) -> usize {
+        self.labels.len()
+    }
+
+    /// Calculate a trajectory for every cost on the Pareto front, in the order of the costs
+    pub fn optimize<S>(
+        &mut self,
+        model: &mut M,
+        start: &M::State,
+        goal: &M::State,
+        sampler: &mut S,
+    ) -> Result<Vec<Trajectory<M>>, PathFindingErr>
+    where
+        S: Sampler<M>,
+    {
+        self.clear();
+        model.init(start);
+
+        self.frontier.insert(start.grid_position(), vec![0]);
+        self.labels.push(Label {
+            state: start.clone(),
+            control: Default::default(),
+            g: Default::default(),
+            parent: None,
+            dominated: false,
+        });
+        self.queue.push((Reverse(model.heuristic(start, goal)), Reverse(0)));
+
+        let mut solutions: Vec<usize> = Vec::new();
+        while let Some((Reverse(f), Reverse(id))) = self.queue.pop() {
+            let labels = &mut self.labels;
+            if labels[id].dominated
+                || solutions.iter().any(|&s| labels[s].g.weakly_dominates(&f))
+            {
+                continue;
+            }
+
+            // Nothing can be popped later with a cost which dominates this one
+            if model.converge(&labels[id].state, goal) {
+                solutions.push(id);
+                continue;
+            }
+            self.stats.expanded += 1;
+
+            let (state, g) = (labels[id].state.clone(), labels[id].g);
+            sampler.sample_into(model, &state, &mut self.controls);
+            for control in &self.controls {
+                let child = match model.integrate(&state, control) {
+                    Some(child) => child,
+                    None => continue,
+                };
+                self.stats.generated += 1;
+
+                let child_g = g.checked_add(model.cost(&state, control, &child));
+                let child_g = child_g.ok_or(PathFindingErr::CostOverflow)?;
+                let child_f = child_g.checked_add(model.heuristic(&child, goal));
+                let child_f = child_f.ok_or(PathFindingErr::CostOverflow)?;
+                if solutions.iter().any(|&s| labels[s].g.weakly_dominates(&child_f)) {
+                    continue;
+                }
+
+                let at = self.frontier.entry(child.grid_position()).or_default();
+                if at.iter().any(|&l| labels[l].g.weakly_dominates(&child_g)) {
+                    continue;
+                }
+                at.retain(|&l| {
+                    let dominated = child_g.dominates(&labels[l].g);
+                    labels[l].dominated = dominated;
+                    !dominated
+                });
+
+                at.push(labels.len());
+                self.queue.push((Reverse(child_f), Reverse(labels.len())));
+                labels.push(Label {
+                    state: child,
+                    control: control.clone(),
+                    g: child_g,
+                    parent: Some(id),
+                    dominated: false,
+                });
+                self.stats.max_queue_len = self.stats.max_queue_len.max(self.queue.len());
+            }
+        }
+
+        if solutions.is_empty() {
+            return Err(PathFindingErr::Unreachable);
+        }
+
+        Ok(solutions.into_iter().map(|id| self.unwind_trajectory(id)).collect())
+    }
+
+    /// Follow the parents from a label up to the start
+    fn unwind_trajectory(&self, mut id: usize) -> Trajectory<M> {
+        let cost = self.labels[id].g;
+
+        let mut trajectory = Vec::new();
+        loop {
+            let label = &self.labels[id];
+            trajectory.push((label.state.clone(), label.control.clone()));
+            match label.parent {
+                Some(parent) => id = parent,
+                None => break,
+            }
+        }
+        trajectory.reverse();
+
+        Trajectory { cost, trajectory, expansions: self.stats.expanded }
+    }
+}
+
+impl<M> Default for ParetoAStar<M>
+where
+    M: HeuristicModel,
+    M::Cost: Dominance + Copy,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<M> Debug for ParetoAStar<M>
+where
+    M: HeuristicModel,
+    M::Cost: Dominance + Copy,
+{
+    fn fmt(&self, fmt: &mut Formatter) -> Result<(), std::fmt::Error> {
+        fmt.debug_struct("ParetoAStar")
+            .field("labels", &self.labels.len())
+            .field("queue", &self.queue.len())
+            .field("frontier", &self.frontier.len())
+            .field("stats", &self.stats)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::path::grid::{Cell, GridModel, GridSampler, Step};
+
+    /// Costs are the distance and the danger, which is high in a band between the start and
+    /// the goal and fades towards the bottom of the map, where the band ends
+    struct Dangerous(GridModel);
+
+    impl Dangerous {
+        fn danger(&self, cell: &Cell) -> usize {
+            if (4..8).contains(&cell.x) && cell.y < 5 {
+                5 - cell.y as usize
+            } else {
+                0
+            }
+        }
+    }
+
+    impl Model for Dangerous {
+        type State = Cell;
+        type Control = Step;
+        type Cost = Objectives<2>;
+
+        fn cost(&self, current: &Cell, step: &Step, next: &Cell) -> Objectives<2> {
+            Objectives([self.0.cost(current, step, next), self.danger(next)])
+        }
+
+        fn init(&mut self, _: &Cell) {}
+
+        fn converge(&self, current: &Cell, goal: &Cell) -> bool {
+            current == goal
+        }
+
+        fn integrate(&self, previous: &Cell, step: &Step) -> Option<Cell> {
+            self.0.integrate(previous, step)
+        }
+    }
+
+    impl HeuristicModel for Dangerous {
+        fn heuristic(&self, current: &Cell, goal: &Cell) -> Objectives<2> {
+            Objectives([self.0.heuristic(current, goal), 0])
+        }
+    }
+
+    impl Sampler<Dangerous> for GridSampler {
+        fn sample(&mut self, model: &Dangerous, current: &Cell) -> &[Step] {
+            Sampler::<GridModel>::sample(self, &model.0, current)
+        }
+    }
+
+    #[test]
+    fn the_front_holds_every_trade_off_between_distance_and_danger() {
+        let mut model = Dangerous(GridModel::new(12, 7));
+        let (start, goal) = (Cell::new(1, 2), Cell::new(10, 2));
+
+        let mut pareto = ParetoAStar::new();
+        let front = pareto.optimize(&mut model, &start, &goal, &mut GridSampler).unwrap();
+
+        // The short and dangerous way, the long and safe way around, and ways in between
+        assert!(front.len() > 2);
+        assert!(front.iter().any(|t| t.cost.0[1] == 0));
+        assert!(front.iter().any(|t| t.cost.0[0] == 2 * 9));
+        for a in &front {
+            assert_eq!(a.trajectory.last().unwrap().0, goal);
+            assert!(front.iter().all(|b| !b.cost.dominates(&a.cost)));
+        }
+    }
+}
@@ -0,0 +1,168 @@
+use std::fmt::{Debug, Formatter};
+
+use rand::Rng;
+
+use super::*;
+
+/// Models whose controls can be drawn at random, for sampling-based planning
+pub trait RandomModel: Model {
+    /// A control drawn at random which can be applied in `state`
+    fn random_control<R: Rng + ?Sized>(
+        &self,
+        state: &Self::State,
+        rng: &mut R,
+    ) -> Self::Control;
+
+    /// The control which takes `state` most directly towards `goal`
+    fn steer(&self, state: &Self::State, goal: &Self::State) -> Self::Control;
+}
+
+/// Samples random controls, steering towards the goal some of the time
+///
+/// Every call to `sample` draws the same number of controls.  Each of them is the model's
+/// [`steer`](trait.RandomModel.html#tymethod.steer) control towards the goal with the
+/// probability given by the goal bias, and a
+/// [`random_control`](trait.RandomModel.html#tymethod.random_control) otherwise.  The random
+/// number generator is handed in, so seeding it makes the samples reproducible.
+///
+/// ```
+/// # use game_lib::path::grid::{Cell, GridModel};
+/// # use game_lib::path::random::RandomSampler;
+/// # use game_lib::path::Sampler;
+/// use rand::SeedableRng;
+/// use rand_xorshift::XorShiftRng;
+///
+/// let (model, start, goal) = (GridModel::new(20, 20), Cell::new(2, 2), Cell::new(17, 17));
+/// let mut sampler = RandomSampler::new(XorShiftRng::seed_from_u64(7), goal, 8);
+/// sampler.set_goal_bias(0.2);
+/// assert_eq!(sampler.sample(&model, &start).len(), 8);
+/// ```
+pub struct RandomSampler<M, R>
+where
+    M: RandomModel,
+    R: Rng,
+{
+    rng: R,
+    goal: M::State,
+    goal_bias: f64,
+    samples: usize,
+    controls: Vec<M::Control>,
+}
+
+impl<M, R> RandomSampler<M, R>
+where
+    M: RandomModel,
+    R: Rng,
+{
+    /// Create a sampler which draws `samples` controls at a time from `rng`
+    ///
+    /// The goal bias starts out at zero, so none of the controls are steered.
+    pub fn new(rng: R, goal: M::State, samples: usize) -> Self {
+        RandomSampler {
+            rng,
+            goal,
+            goal_bias: 0.0,
+            samples,
+            controls: Vec::with_capacity(samples),
+        }
+    }
+
+    /// Change the probability of steering each control towards the goal
+    ///
+    /// The probability is clamped between `0.0` and `1.0`.
+    pub fn set_goal_bias(&mut self, probability: f64) {
+        self.goal_bias = probability.clamp(0.0, 1.0);
+    }
+
+    /// Change the goal which controls are steered towards
+    pub fn set_goal(&mut self, goal: M::State) {
+        self.goal = goal;
+    }
+
+    pub fn goal_bias(&self) -> f64 {
+        self.goal_bias
+    }
+
+    pub fn samples(&self) -> usize {
+        self.samples
+    }
+}
+
+impl<M, R> Sampler<M> for RandomSampler<M, R>
+where
+    M: RandomModel,
+    R: Rng,
+{
+    fn sample(&mut self, model: &M, current: &M::State) -> &[M::Control] {
+        self.controls.clear();
+        for _ in 0..self.samples {
+            let control = if self.rng.gen_bool(self.goal_bias) {
+                model.steer(current, &self.goal)
+            } else {
+                model.random_control(current, &mut self.rng)
+            };
+            self.controls.push(control);
+        }
+
+        &self.controls
+    }
+}
+
+impl<M, R> Debug for RandomSampler<M, R>
+where
+    M: RandomModel,
+    R: Rng,
+{
+    fn fmt(&self, fmt: &mut Formatter) -> Result<(), std::fmt::Error> {
+        fmt.debug_struct("RandomSampler")
+            .field("goal", &self.goal)
+            .field("goal_bias", &self.goal_bias)
+            .field("samples", &self.samples)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::path::grid::{Cell, GridModel};
+    use rand::SeedableRng;
+    use rand_xorshift::XorShiftRng;
+
+    #[test]
+    fn the_same_seed_gives_the_same_samples() {
+        let (model, start, goal) =
+            (GridModel::new(20, 20), Cell::new(2, 2), Cell::new(17, 17));
+        let mut a = RandomSampler::new(XorShiftRng::seed_from_u64(7), goal, 8);
+        let mut b = RandomSampler::new(XorShiftRng::seed_from_u64(7), goal, 8);
+        for _ in 0..10 {
+            assert_eq!(a.sample(&model, &start), b.sample(&model, &start));
+        }
+    }
+
+    #[test]
+    fn a_goal_bias_reaches_the_goal_more_often() {
+        let (model, start, goal) =
+            (GridModel::new(20, 20), Cell::new(2, 2), Cell::new(17, 17));
+        // Wander for a while by following the first sample, and count how often the goal is
+        // reached
+        let reached = |bias| {
+            let mut sampler = RandomSampler::new(XorShiftRng::seed_from_u64(7), goal, 1);
+            sampler.set_goal_bias(bias);
+            (0..20)
+                .filter(|_| {
+                    let mut state = start;
+                    for _ in 0..60 {
+                        let control = sampler.sample(&model, &state)[0];
+                        state = model.integrate(&state, &control).unwrap_or(state);
+                        if model.converge(&state, &goal) {
+                            return true;
+                        }
+                    }
+                    false
+                })
+                .count()
+        };
+        assert!(reached(0.5) > reached(0.0));
+    }
+}
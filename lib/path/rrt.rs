@@ -0,0 +1,585 @@
+use std::fmt::{Debug, Formatter};
+
+use rand::{Rng, SeedableRng};
+use rand_xorshift::XorShiftRng;
+
+use super::nearest::{Metric, NearestNeighbor};
+use super::random::RandomModel;
+use super::*;
+
+/// Models whose states can be drawn at random from the whole space being planned in
+pub trait StateSpace: RandomModel
+where
+    Self::State: Metric,
+{
+    /// A state drawn at random, whether or not it is valid
+    fn random_state<R: Rng + ?Sized>(&self, rng: &mut R) -> Self::State;
+}
+
+/// A state of the tree, with what it took to reach it from its parent
+struct Branch<M>
+where
+    M: Model,
+{
+    control: M::Control,
+    parent: Option<usize>,
+    g: M::Cost,
+}
+
+/// Follow the parents from a state of a tree up to the start
+fn unwind_trajectory<M>(
+    tree: &NearestNeighbor<M::State>,
+    branches: &[Branch<M>],
+    mut index: usize,
+) -> Trajectory<M>
+where
+    M: Model,
+    M::State: Metric,
+{
+    let cost = branches[index].g.clone();
+
+    let mut trajectory = Vec::new();
+    loop {
+        let branch = &branches[index];
+        let state = tree.get(index).expect("every branch has a state");
+        trajectory.push((state.clone(), branch.control.clone()));
+        match branch.parent {
+            Some(parent) => index = parent,
+            None => break,
+        }
+    }
+    trajectory.reverse();
+
+    Trajectory { cost, trajectory, expansions: 0 }
+}
+
+/// A rapidly-exploring random tree, which plans by growing a tree of states towards samples
+///
+/// Each iteration draws a random state, or the goal with the probability given by the goal
+/// bias, and finds the state of the tree closest to it.  The tree is then extended from there
+/// by the model's [`steer`](../random/trait.RandomModel.html#tymethod.steer) control towards
+/// the sample, wherever `integrate` allows it.  The search stops once a new state converges
+/// on the goal, or with `PathFindingErr::IterationLimit` when none has after the given number
+/// of iterations.
+///
+/// The tree spreads quickly into the unexplored parts of the space, and it never needs a
+/// grid, so it suits continuous models with many dimensions or with dynamics which a lattice
+/// of controls approximates badly.  The trajectories are feasible but far from optimal.  The
+/// random number generator is seeded, so the same seed grows the same tree.
+pub struct Rrt<M>
+where
+    M: StateSpace,
+    M::State: Metric,
+{
+    rng: XorShiftRng,
+    goal_bias: f64,
+    max_iterations: usize,
+    tree: NearestNeighbor<M::State>,
+    branches: Vec<Branch<M>>,
+    stats: Stats,
+}
+
+impl<M> Rrt<M>
+where
+    M: StateSpace,
+    M::State: Metric,
+{
+    /// Create a tree seeded with `seed` which samples the goal with probability `goal_bias`
+    ///
+    /// The goal bias is clamped between `0.0` and `1.0`.
+    pub fn new(seed: u64, goal_bias: f64, max_iterations: usize) -> Self {
+        Rrt {
+            rng: XorShiftRng::seed_from_u64(seed),
+            goal_bias: goal_bias.clamp(0.0, 1.0),
+            max_iterations,
+            tree: NearestNeighbor::new(),
+            branches: Vec::new(),
+            stats: Stats::default(),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.tree.clear();
+        self.branches.clear();
+        self.stats = Stats::default();
+    }
+
+    /// Every state in the tree of the last query
+    pub fn tree(&self) -> &NearestNeighbor<M::State> {
+        &self.tree
+    }
+
+    /// What the last query did, where every iteration counts as an expansion
+    pub fn stats(&self) -> &Stats {
+        &self.stats
+    }
+
+    /// Grow a tree from `start` until it reaches `goal`
+    pub fn optimize(
+        &mut self,
+        model: &mut M,
+        start: &M::State,
+        goal: &M::State,
+    ) -> PathResult<M> {
+        use PathFindingErr::*;
+        use PathResult::*;
+
+        self.clear();
+        model.init(start);
+        self.tree.insert(start.clone());
+        self.branches.push(Branch {
+            control: Default::default(),
+            parent: None,
+            g: Default::default(),
+        });
+        if model.converge(start, goal) {
+            return Final(Trajectory {
+                expansions: self.stats.expanded,
+                ..unwind_trajectory(&self.tree, &self.branches, 0)
+            });
+        }
+
+        for _ in 0..self.max_iterations {
+            self.stats.expanded += 1;
+
+            let sample = if self.rng.gen_bool(self.goal_bias) {
+                goal.clone()
+            } else {
+                model.random_state(&mut self.rng)
+            };
+            let parent = self.tree.nearest_index(&sample).expect("the tree holds the start");
+            let from = self.tree.get(parent).expect("every branch has a state");
+
+            let control = model.steer(from, &sample);
+            let next = match model.integrate(from, &control) {
+                Some(next) => next,
+                None => continue,
+            };
+            self.stats.generated += 1;
+
+            let g =
+                self.branches[parent].g.clone().checked_add(model.cost(from, &control, &next));
+            let g = match g {
+                Some(g) => g,
+                None => return Err(CostOverflow),
+            };
+            let converged = model.converge(&next, goal);
+            let index = self.tree.insert(next);
+            self.branches.push(Branch { control, parent: Some(parent), g });
+            if converged {
+                return Final(Trajectory {
+                    expansions: self.stats.expanded,
+                    ..unwind_trajectory(&self.tree, &self.branches, index)
+                });
+            }
+        }
+
+        Err(IterationLimit(self.max_iterations))
+    }
+}
+
+impl<M> Debug for Rrt<M>
+where
+    M: StateSpace,
+    M::State: Metric,
+{
+    fn fmt(&self, fmt: &mut Formatter) -> Result<(), std::fmt::Error> {
+        fmt.debug_struct("Rrt")
+            .field("goal_bias", &self.goal_bias)
+            .field("max_iterations", &self.max_iterations)
+            .field("tree", &self.tree.len())
+            .field("stats", &self.stats)
+            .finish()
+    }
+}
+
+/// A rapidly-exploring random tree which rewires itself, so that its trajectories keep
+/// getting cheaper the longer it grows
+///
+/// The tree is extended towards samples as by [`Rrt`], but the new state is joined to
+/// whichever of the states within the neighbourhood radius reaches it most cheaply, and the
+/// other states of the neighbourhood are then rewired through the new state wherever that
+/// lowers their cost.  The search does not stop at the first state to converge on the goal:
+/// it runs every iteration, and returns the cheapest trajectory to any of the states which
+/// converge.  With enough iterations the trajectory approaches the optimum.
+///
+/// The radius shrinks as the tree grows, as `gamma * (ln(n) / n)^(1 / d)` for a tree of `n`
+/// states in `d` dimensions, up to a maximum radius.  Rewiring is only asymptotically optimal
+/// when `gamma` grows with the size of the space, roughly as its width.
+///
+/// Two states are connected by integrating the [`steer`] control from one towards the other,
+/// and the connection only counts when it lands at the grid position of the other state.  The
+/// model's steering must therefore be exact over the maximum radius, and its costs positive.
+///
+/// [`steer`]: ../random/trait.RandomModel.html#tymethod.steer
+pub struct RrtStar<M>
+where
+    M: StateSpace,
+    M::State: Metric,
+{
+    rng: XorShiftRng,
+    goal_bias: f64,
+    max_iterations: usize,
+    gamma: f64,
+    max_radius: f64,
+    tree: NearestNeighbor<M::State>,
+    branches: Vec<Branch<M>>,
+    /// The children of each state, which are updated when a parent gets cheaper
+    children: Vec<Vec<usize>>,
+    stats: Stats,
+}
+
+impl<M> RrtStar<M>
+where
+    M: StateSpace,
+    M::State: Metric,
+{
+    /// Create a tree seeded with `seed` which samples the goal with probability `goal_bias`
+    ///
+    /// The goal bias is clamped between `0.0` and `1.0`.  The radius starts out with a `gamma`
+    /// of `1.0` and no maximum.
+    pub fn new(seed: u64, goal_bias: f64, max_iterations: usize) -> Self {
+        RrtStar {
+            rng: XorShiftRng::seed_from_u64(seed),
+            goal_bias: goal_bias.clamp(0.0, 1.0),
+            max_iterations,
+            gamma: 1.0,
+            max_radius: f64::INFINITY,
+            tree: NearestNeighbor::new(),
+            branches: Vec::new(),
+            children: Vec::new(),
+            stats: Stats::default(),
+        }
+    }
+
+    /// Change how the neighbourhood radius shrinks as the tree grows
+    pub fn set_radius(&mut self, gamma: f64, max_radius: f64) {
+        self.gamma = gamma;
+        self.max_radius = max_radius;
+    }
+
+    /// The neighbourhood radius once the tree holds `states` states
+    pub fn radius(&self, states: usize) -> f64 {
+        let n = states.max(2) as f64;
+        let radius = self.gamma * (n.ln() / n).powf(1.0 / M::State::DIMENSIONS as f64);
+        radius.min(self.max_radius)
+    }
+
+    pub fn clear(&mut self) {
+        self.tree.clear();
+        self.branches.clear();
+        self.children.clear();
+        self.stats = Stats::default();
+    }
+
+    /// Every state in the tree of the last query
+    pub fn tree(&self) -> &NearestNeighbor<M::State> {
+        &self.tree
+    }
+
+    /// What the last query did, where every iteration counts as an expansion and every rewired
+    /// state as reopened
+    pub fn stats(&self) -> &Stats {
+        &self.stats
+    }
+
+    /// Grow a tree from `start` for every iteration, returning the cheapest trajectory to
+    /// `goal`
+    pub fn optimize(
+        &mut self,
+        model: &mut M,
+        start: &M::State,
+        goal: &M::State,
+    ) -> PathResult<M> {
+        use PathFindingErr::*;
+        use PathResult::*;
+
+        self.clear();
+        model.init(start);
+        self.tree.insert(start.clone());
+        self.branches.push(Branch {
+            control: Default::default(),
+            parent: None,
+            g: Default::default(),
+        });
+        self.children.push(Vec::new());
+
+        let mut goals = Vec::new();
+        if model.converge(start, goal) {
+            goals.push(0);
+        }
+
+        for _ in 0..self.max_iterations {
+            self.stats.expanded += 1;
+
+            let sample = if self.rng.gen_bool(self.goal_bias) {
+                goal.clone()
+            } else {
+                model.random_state(&mut self.rng)
+            };
+            let nearest = self.tree.nearest_index(&sample).expect("the tree holds the start");
+            let from = self.tree.get(nearest).expect("every branch has a state");
+
+            let mut control = model.steer(from, &sample);
+            let next = match model.integrate(from, &control) {
+                Some(next) => next,
+                None => continue,
+            };
+            self.stats.generated += 1;
+
+            let step = model.cost(from, &control, &next);
+            let mut g = match self.branches[nearest].g.clone().checked_add(step) {
+                Some(g) => g,
+                None => return Err(CostOverflow),
+            };
+
+            // Join the new state to whichever neighbour reaches it most cheaply
+            let mut parent = nearest;
+            let near = self.tree.within_indices(&next, self.radius(self.tree.len() + 1));
+            for &i in &near {
+                let state = self.tree.get(i).expect("every branch has a state");
+                if let Some((c, step)) = connect(model, state, &next) {
+                    match self.branches[i].g.clone().checked_add(step) {
+                        Some(through) if through < g => {
+                            parent = i;
+                            g = through;
+                            control = c;
+                        }
+                        _ => {}
+                    }
+                }
+            }
+
+            let converged = model.converge(&next, goal);
+            let index = self.tree.insert(next);
+            self.branches.push(Branch { control, parent: Some(parent), g: g.clone() });
+            self.children.push(Vec::new());
+            self.children[parent].push(index);
+            if converged {
+                goals.push(index);
+            }
+
+            // Then rewire the neighbours which the new state reaches more cheaply
+            for &i in near.iter().filter(|&&i| i != parent) {
+                let state = self.tree.get(index).expect("every branch has a state");
+                let neighbour = self.tree.get(i).expect("every branch has a state");
+                let (c, step) = match connect(model, state, neighbour) {
+                    Some(connection) => connection,
+                    None => continue,
+                };
+                let through = g.clone() + step;
+                if through < self.branches[i].g {
+                    self.rewire(model, i, index, c, through);
+                }
+            }
+        }
+
+        let best =
+            goals.into_iter().min_by(|&a, &b| self.branches[a].g.cmp(&self.branches[b].g));
+        match best {
+            Some(index) => Final(Trajectory {
+                expansions: self.stats.expanded,
+                ..unwind_trajectory(&self.tree, &self.branches, index)
+            }),
+            None => Err(IterationLimit(self.max_iterations)),
+        }
+    }
+
+    /// Move a state under a new parent, and pass the lower cost on to its descendants
+    fn rewire(
+        &mut self,
+        model: &M,
+        index: usize,
+        parent: usize,
+        control: M::Control,
+        g: M::Cost,
+    ) {
+        self.stats.reopened += 1;
+        if let Some(old) = self.branches[index].parent {
+            self.children[old].retain(|&child| child != index);
+        }
+        self.children[parent].push(index);
+        self.branches[index] = Branch { control, parent: Some(parent), g };
+
+        let mut stack = vec![index];
+        while let Some(current) = stack.pop() {
+            let state = self.tree.get(current).expect("every branch has a state");
+            for &child in &self.children[current] {
+                let next = self.tree.get(child).expect("every branch has a state");
+                let step = model.cost(state, &self.branches[child].control, next);
+                self.branches[child].g = self.branches[current].g.clone() + step;
+                stack.push(child);
+            }
+        }
+    }
+}
+
+/// The control which takes `from` to the position of `to`, and its cost, if steering gets
+/// there
+fn connect<M>(model: &M, from: &M::State, to: &M::State) -> Option<(M::Control, M::Cost)>
+where
+    M: StateSpace,
+    M::State: Metric,
+{
+    let control = model.steer(from, to);
+    let reached = model.integrate(from, &control)?;
+    if reached.grid_position() == to.grid_position() {
+        let cost = model.cost(from, &control, to);
+        Some((control, cost))
+    } else {
+        None
+    }
+}
+
+impl<M> Debug for RrtStar<M>
+where
+    M: StateSpace,
+    M::State: Metric,
+{
+    fn fmt(&self, fmt: &mut Formatter) -> Result<(), std::fmt::Error> {
+        fmt.debug_struct("RrtStar")
+            .field("goal_bias", &self.goal_bias)
+            .field("max_iterations", &self.max_iterations)
+            .field("gamma", &self.gamma)
+            .field("max_radius", &self.max_radius)
+            .field("tree", &self.tree.len())
+            .field("stats", &self.stats)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::path::continuous::{ContinuousState, Coordinates};
+
+    /// A point in the plane
+    #[derive(Clone, Debug)]
+    struct Point([f64; 2]);
+
+    impl ContinuousState for Point {
+        type Coordinates = [f64; 2];
+
+        fn coordinates(&self) -> [f64; 2] {
+            self.0
+        }
+
+        fn cell_size(&self) -> f64 {
+            0.01
+        }
+
+        fn with_coordinates(&self, coordinates: [f64; 2]) -> Self {
+            Point(coordinates)
+        }
+    }
+
+    impl Metric for Point {
+        const DIMENSIONS: usize = 2;
+
+        fn coordinate(&self, axis: usize) -> f64 {
+            self.0[axis]
+        }
+    }
+
+    /// Moving up to a unit length at a time across a square with a wall most of the way up
+    /// its middle, where the cost is in hundredths of the distance
+    struct Plane;
+
+    fn blocked([x, y]: [f64; 2]) -> bool {
+        let outside = !(0.0..10.0).contains(&x) || !(0.0..10.0).contains(&y);
+        outside || (4.5..5.5).contains(&x) && y < 7.0
+    }
+
+    fn collides(from: &Point, to: &Point) -> bool {
+        (0..=10).any(|i| blocked(from.0.lerp(&to.0, i as f64 / 10.0)))
+    }
+
+    impl Model for Plane {
+        type State = Point;
+        type Control = [f64; 2];
+        type Cost = u64;
+
+        fn cost(&self, current: &Point, _: &[f64; 2], next: &Point) -> u64 {
+            (current.distance(next) * 100.0).round() as u64
+        }
+
+        fn init(&mut self, _: &Point) {}
+
+        fn converge(&self, current: &Point, goal: &Point) -> bool {
+            current.distance(goal) < 0.5
+        }
+
+        fn integrate(&self, previous: &Point, &[dx, dy]: &[f64; 2]) -> Option<Point> {
+            let next = Point([previous.0[0] + dx, previous.0[1] + dy]);
+            if collides(previous, &next) {
+                None
+            } else {
+                Some(next)
+            }
+        }
+    }
+
+    impl RandomModel for Plane {
+        fn random_control<R: Rng + ?Sized>(&self, _: &Point, rng: &mut R) -> [f64; 2] {
+            [rng.gen_range(-1.0, 1.0), rng.gen_range(-1.0, 1.0)]
+        }
+
+        fn steer(&self, state: &Point, goal: &Point) -> [f64; 2] {
+            let scale = state.distance(goal).max(1.0);
+            [(goal.0[0] - state.0[0]) / scale, (goal.0[1] - state.0[1]) / scale]
+        }
+    }
+
+    impl StateSpace for Plane {
+        fn random_state<R: Rng + ?Sized>(&self, rng: &mut R) -> Point {
+            Point([rng.gen_range(0.0, 10.0), rng.gen_range(0.0, 10.0)])
+        }
+    }
+
+    #[test]
+    fn the_tree_grows_around_the_wall_without_colliding() {
+        let (start, goal) = (Point([2.0, 2.0]), Point([8.0, 2.0]));
+        let mut rrt = Rrt::new(11, 0.1, 5000);
+        match rrt.optimize(&mut Plane, &start, &goal) {
+            PathResult::Final(t) => {
+                assert!(Plane.converge(t.states().last().unwrap(), &goal));
+                for pair in t.trajectory.windows(2) {
+                    assert!(!collides(&pair[0].0, &pair[1].0));
+                }
+                // The wall is only passed above its top
+                assert!(t.states().any(|p| p.0[1] >= 7.0));
+            }
+            _ => panic!("the goal is reachable around the wall"),
+        }
+        assert!(rrt.stats().expanded <= 5000);
+    }
+
+    fn cost(result: PathResult<Plane>) -> u64 {
+        match result {
+            PathResult::Final(t) => t.cost,
+            _ => panic!("the goal is reachable around the wall"),
+        }
+    }
+
+    #[test]
+    fn rewiring_keeps_shortening_the_trajectory() {
+        let (start, goal) = (Point([2.0, 2.0]), Point([8.0, 2.0]));
+
+        // A plain tree returns the first trajectory it finds, however long it is allowed to
+        // grow
+        let rrt = |iterations| {
+            let mut rrt = Rrt::new(5, 0.1, iterations);
+            cost(rrt.optimize(&mut Plane, &start, &goal))
+        };
+        assert_eq!(rrt(2000), rrt(8000));
+
+        let rrt_star = |iterations| {
+            let mut rrt_star = RrtStar::new(5, 0.1, iterations);
+            rrt_star.set_radius(20.0, 1.0);
+            cost(rrt_star.optimize(&mut Plane, &start, &goal))
+        };
+        let (short, long) = (rrt_star(2000), rrt_star(8000));
+        assert!(long < short);
+        assert!(long < rrt(8000));
+    }
+}
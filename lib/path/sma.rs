@@ -0,0 +1,444 @@
+use std::cmp::Reverse;
+use std::collections::BTreeSet;
+use std::fmt::{Debug, Formatter};
+
+use fnv::FnvHashMap;
+
+use super::*;
+
+type PositionOf<M> = <<M as Model>::State as State>::Position;
+
+/// A node held in memory, along with the best estimate of the successors it has forgotten
+struct Record<M>
+where
+    M: Model,
+{
+    state: M::State,
+    control: M::Control,
+    parent: Option<usize>,
+    g: M::Cost,
+    f: M::Cost,
+    depth: usize,
+    /// Number of successors which are still held in memory
+    children: usize,
+    /// The lowest estimate of the successors which had to be dropped since the last expansion
+    forgotten: Option<M::Cost>,
+}
+
+/// Order of the leaves, lowest estimate first and the deepest node among equal estimates
+type Key<C> = (C, Reverse<usize>, usize);
+
+/// Simplified Memory-Bounded A*, which never holds more than a fixed number of nodes
+///
+/// The search behaves like A* until the node limit is reached.  From then on, whenever room is
+/// needed for a successor, the leaf with the highest estimate is dropped and its estimate is
+/// backed up into its parent, so that the parent is expanded again once everything more
+/// promising has been tried.  With enough memory for the whole search the trajectory is
+/// optimal; with less the search falls back on regenerating forgotten nodes, and fails with
+/// `Unreachable` only when even the trajectory to the goal does not fit within the limit.
+///
+/// Successors are checked against the trajectory leading to them and against the nodes in
+/// memory, so a position is only held twice when the second node reaches it more cheaply.
+///
+/// ```
+/// # use game_lib::path::grid::{Cell, GridModel, GridSampler};
+/// # use game_lib::path::{sma::SmaStar, Optimizer};
+/// let (mut grid, start, goal) = (GridModel::new(20, 20), Cell::new(3, 10), Cell::new(17, 9));
+/// let mut sma = SmaStar::new(60);
+/// assert!(sma.optimize(&mut grid, &start, &goal, &mut GridSampler).is_final());
+/// assert!(sma.retained() <= 60);
+/// ```
+pub struct SmaStar<M>
+where
+    M: HeuristicModel,
+    M::Cost: Copy,
+{
+    max_nodes: usize,
+    nodes: FnvHashMap<usize, Record<M>>,
+    /// The nodes in memory without any successors in memory
+    leaves: BTreeSet<Key<M::Cost>>,
+    positions: FnvHashMap<PositionOf<M>, usize>,
+    id_counter: usize,
+    stats: Stats,
+}
+
+impl<M> SmaStar<M>
+where
+    M: HeuristicModel,
+    M::Cost: Copy,
+{
+    /// Create an SMA* optimizer which holds at most `max_nodes` nodes
+    ///
+    /// A limit of zero is treated as one.
+    pub fn new(max_nodes: usize) -> Self {
+        SmaStar {
+            max_nodes: max_nodes.max(1),
+            nodes: FnvHashMap::default(),
+            leaves: BTreeSet::new(),
+            positions: FnvHashMap::default(),
+            id_counter: 0,
+            stats: Stats::default(),
+        }
+    }
+
+    pub fn max_nodes(&self) -> usize {
+        self.max_nodes
+    }
+
+    /// Number of nodes currently held in memory
+    pub fn retained(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn clear(&mut self) {
+        self.nodes.clear();
+        self.leaves.clear();
+        self.positions.clear();
+        self.stats = Stats::default();
+    }
+
+    /// Counters describing the work done since the optimizer was created or last cleared
+    ///
+    /// Nodes are counted every time they are expanded, including after being forgotten.
+    pub fn stats(&self) -> &Stats {
+        &self.stats
+    }
+
+    pub fn inspect_queue(&self) -> impl Iterator<Item = (&M::State, &M::Control)> {
+        self.leaves.iter().map(move |&(_, _, id)| {
+            let node = &self.nodes[&id];
+            (&node.state, &node.control)
+        })
+    }
+
+    pub fn inspect_discovered(&self) -> impl Iterator<Item = &PositionOf<M>> {
+        self.positions.keys()
+    }
+
+    fn key(&self, id: usize) -> Key<M::Cost> {
+        let node = &self.nodes[&id];
+        (node.f, Reverse(node.depth), id)
+    }
+
+    /// Whether `position` is on the trajectory from the start to the node `id`
+    fn on_trajectory(&self, mut id: usize, position: &PositionOf<M>) -> bool {
+        loop {
+            let node = &self.nodes[&id];
+            if node.state.grid_position() == *position {
+                return true;
+            }
+            match node.parent {
+                Some(parent) => id = parent,
+                None => return false,
+            }
+        }
+    }
+
+    /// Drop the leaf `id`, backing its estimate up into its parent
+    ///
+    /// An estimate of `None` means the leaf cannot lead to the goal at all.  A parent which is
+    /// left without successors becomes a leaf again, or is dropped as well when none of its
+    /// successors could lead to the goal.
+    fn forget(&mut self, id: usize, estimate: Option<M::Cost>) {
+        let key = self.key(id);
+        self.leaves.remove(&key);
+        let node = match self.nodes.remove(&id) {
+            Some(node) => node,
+            None => return,
+        };
+
+        let position = node.state.grid_position();
+        if self.positions.get(&position) == Some(&id) {
+            self.positions.remove(&position);
+        }
+
+        let parent_id = match node.parent {
+            Some(parent) => parent,
+            None => return,
+        };
+        let parent = self.nodes.get_mut(&parent_id).expect("parents outlive their children");
+        parent.children -= 1;
+        if let Some(estimate) = estimate {
+            parent.forgotten = Some(parent.forgotten.map_or(estimate, |f| f.min(estimate)));
+        }
+
+        if parent.children == 0 {
+            match parent.forgotten {
+                Some(forgotten) => {
+                    parent.f = parent.f.max(forgotten);
+                    let key = self.key(parent_id);
+                    self.leaves.insert(key);
+                }
+                None => self.forget(parent_id, None),
+            }
+        }
+    }
+
+    /// Generate the successors of the leaf `id`, making room for them where it is worthwhile
+    fn expand<S>(&mut self, model: &M, goal: &M::State, sampler: &mut S, id: usize)
+    where
+        S: Sampler<M>,
+    {
+        let key = self.key(id);
+        self.leaves.remove(&key);
+        self.stats.expanded += 1;
+
+        let (state, g, f, depth) = {
+            let node = self.nodes.get_mut(&id).expect("leaves are held in memory");
+            node.forgotten = None;
+            (node.state.clone(), node.g, node.f, node.depth)
+        };
+
+        let mut successors = Vec::new();
+        for control in sampler.sample(model, &state) {
+            if let Some(child) = model.integrate(&state, control) {
+                let position = child.grid_position();
+                if self.on_trajectory(id, &position) {
+                    continue;
+                }
+
+                let child_g = g + model.cost(&state, control, &child);
+                if let Some(other) = self.positions.get(&position) {
+                    if self.nodes[other].g <= child_g {
+                        continue;
+                    }
+                }
+                self.stats.generated += 1;
+
+                // The estimate of a successor never drops below the estimate of its parent
+                let child_f = f.max(child_g + model.heuristic(&child, goal));
+                successors.push((child, control.clone(), child_g, child_f));
+            }
+        }
+        successors.sort_by_key(|&(_, _, _, f)| f);
+
+        let mut stuck = false;
+        for (child, control, child_g, child_f) in successors {
+            if self.nodes.len() >= self.max_nodes {
+                match self.leaves.iter().next_back() {
+                    Some(&(worst_f, worst_depth, worst))
+                        if (worst_f, worst_depth) > (child_f, Reverse(depth + 1)) =>
+                    {
+                        self.forget(worst, Some(worst_f));
+                    }
+                    worst => {
+                        // Nothing held is worth less than the successor, so it is dropped
+                        stuck |= worst.is_none();
+                        let node = self.nodes.get_mut(&id).expect("leaves are held in memory");
+                        node.forgotten =
+                            Some(node.forgotten.map_or(child_f, |f| f.min(child_f)));
+                        continue;
+                    }
+                }
+            }
+
+            self.id_counter += 1;
+            let child_id = self.id_counter;
+            self.positions.insert(child.grid_position(), child_id);
+            self.nodes.insert(
+                child_id,
+                Record {
+                    state: child,
+                    control,
+                    parent: Some(id),
+                    g: child_g,
+                    f: child_f,
+                    depth: depth + 1,
+                    children: 0,
+                    forgotten: None,
+                },
+            );
+            self.leaves.insert((child_f, Reverse(depth + 1), child_id));
+            if let Some(node) = self.nodes.get_mut(&id) {
+                node.children += 1;
+            }
+        }
+        self.stats.max_queue_len = self.stats.max_queue_len.max(self.leaves.len());
+
+        let node = &self.nodes[&id];
+        if node.children == 0 {
+            // With only the trajectory to this node in memory, no successor ever fits
+            match node.forgotten {
+                Some(forgotten) if !stuck => {
+                    self.nodes.get_mut(&id).expect("leaves are held in memory").f =
+                        f.max(forgotten);
+                    let key = self.key(id);
+                    self.leaves.insert(key);
+                }
+                _ => self.forget(id, None),
+            }
+        }
+    }
+
+    /// The most promising leaf, which is expanded next
+    fn best(&self) -> Option<usize> {
+        self.leaves.iter().next().map(|&(_, _, id)| id)
+    }
+
+    /// Follow the parents from the node `id` up to the start node
+    fn unwind_trajectory(&self, mut id: usize) -> Trajectory<M> {
+        let cost = self.nodes[&id].g;
+        let mut result = Vec::new();
+
+        while let Some(node) = self.nodes.get(&id) {
+            result.push((node.state.clone(), node.control.clone()));
+            match node.parent {
+                Some(parent) => id = parent,
+                None => break,
+            }
+        }
+
+        result.reverse();
+
+        Trajectory { cost, trajectory: result, expansions: self.stats.expanded }
+    }
+
+    fn seed(&mut self, model: &M, start: &M::State, goal: &M::State) {
+        let f = model.heuristic(start, goal);
+        self.positions.insert(start.grid_position(), 0);
+        self.nodes.insert(
+            0,
+            Record {
+                state: start.clone(),
+                control: Default::default(),
+                parent: None,
+                g: Default::default(),
+                f,
+                depth: 0,
+                children: 0,
+                forgotten: None,
+            },
+        );
+        self.leaves.insert((f, Reverse(0), 0));
+    }
+}
+
+impl<M, S> Optimizer<M, S> for SmaStar<M>
+where
+    M: HeuristicModel,
+    M::Cost: Copy,
+    S: Sampler<M>,
+{
+    /// Expand the most promising leaf, returning the trajectory to the next one
+    fn next_trajectory(
+        &mut self,
+        model: &mut M,
+        start: &M::State,
+        goal: &M::State,
+        sampler: &mut S,
+    ) -> PathResult<M> {
+        use PathFindingErr::*;
+        use PathResult::*;
+
+        if let Some(invalid) = invalid_endpoint(model, start, goal) {
+            return Err(invalid);
+        }
+
+        if self.nodes.is_empty() {
+            self.seed(model, start, goal);
+        }
+
+        if let Some(best) = self.best() {
+            if model.converge(&self.nodes[&best].state, goal) {
+                return Final(self.unwind_trajectory(best));
+            }
+            self.expand(model, goal, sampler, best);
+        }
+
+        match self.best() {
+            Some(best) => Intermediate(self.unwind_trajectory(best)),
+            None => Err(Unreachable),
+        }
+    }
+
+    fn optimize(
+        &mut self,
+        model: &mut M,
+        start: &M::State,
+        goal: &M::State,
+        sampler: &mut S,
+    ) -> PathResult<M> {
+        use PathFindingErr::*;
+        use PathResult::*;
+
+        if let Some(invalid) = invalid_endpoint(model, start, goal) {
+            return Err(invalid);
+        }
+
+        if model.converge(start, goal) {
+            return Final(Trajectory {
+                cost: Default::default(),
+                trajectory: vec![(start.clone(), Default::default())],
+                expansions: 0,
+            });
+        }
+
+        if self.nodes.is_empty() {
+            self.seed(model, start, goal);
+        }
+
+        while let Some(best) = self.best() {
+            if model.converge(&self.nodes[&best].state, goal) {
+                return Final(self.unwind_trajectory(best));
+            }
+            self.expand(model, goal, sampler, best);
+        }
+
+        Err(Unreachable)
+    }
+}
+
+impl<M> Debug for SmaStar<M>
+where
+    M: HeuristicModel,
+    M::State: Debug,
+    M::Control: Debug,
+    M::Cost: Debug + Copy,
+{
+    fn fmt(&self, fmt: &mut Formatter) -> Result<(), std::fmt::Error> {
+        fmt.debug_struct("SmaStar")
+            .field("max_nodes", &self.max_nodes)
+            .field("counter", &self.id_counter)
+            .field("retained", &self.nodes.len())
+            .field("leaves", &self.leaves.len())
+            .field("stats", &self.stats)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::actor::{Heuristic, TurnOptimal, WalkSampler};
+    use crate::map::Tile;
+    use crate::path::astar::AStar;
+    use crate::path::testing::*;
+
+    #[test]
+    fn a_trajectory_is_found_within_less_memory_than_astar_uses() {
+        let mut map = room(20, 20);
+        for y in 3..17 {
+            map[(10, y)] = Tile::WALL;
+        }
+        let mut model = TurnOptimal::new(map);
+        model.set_heuristic(Heuristic::Chebyshev);
+        let (start, goal) = (walker(3, 10), walker(17, 9));
+
+        let mut sampler = WalkSampler::new();
+        let mut astar = AStar::new();
+        let best = expect_final(astar.optimize(&mut model, &start, &goal, &mut sampler)).cost;
+        assert!(astar.inspect_discovered().count() > 60);
+
+        let mut sma = SmaStar::new(60);
+        let t = expect_final(sma.optimize(&mut model, &start, &goal, &mut sampler));
+        assert!(t.cost >= best);
+        assert!(model.converge(&t.trajectory.last().unwrap().0, &goal));
+        // Every step of the trajectory moves to a neighbouring cell
+        for pair in t.trajectory.windows(2) {
+            let (a, b) = (&pair[0].0.pos, &pair[1].0.pos);
+            let (dx, dy) = (a.x as i64 - b.x as i64, a.y as i64 - b.y as i64);
+            assert_eq!(dx.abs().max(dy.abs()), 1);
+        }
+        assert!(sma.retained() <= 60);
+    }
+}
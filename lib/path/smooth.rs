@@ -0,0 +1,156 @@
+//! Post-processing which cleans up the trajectories produced by an optimizer
+
+use super::*;
+
+/// Remove waypoints which can be skipped by connecting their neighbours directly
+///
+/// Starting from the first state, the trajectory is connected to the furthest later state for
+/// which `collision_free` accepts the direct connection, dropping every waypoint in between.
+/// Adjacent states are always connected.  A shortcut keeps the control which led to the state
+/// it connects to, and the cost of every remaining step is recomputed with `model.cost`.
+///
+/// The first and last states are always kept, and trajectories of two or fewer states are
+/// returned unchanged.
+///
+/// ```
+/// # use game_lib::path::grid::{Cell, GridModel, Step};
+/// # use game_lib::path::{smooth::shortcut, Trajectory};
+/// let along = (0..5).map(|x| (Cell::new(x, 0), Step { dx: 1, dy: 0 }));
+/// let jagged = Trajectory::<GridModel> { cost: 8, trajectory: along.collect(), expansions: 0 };
+/// let smooth = shortcut(&GridModel::new(5, 1), &jagged, |a: &Cell, b: &Cell| a.y == b.y);
+/// assert_eq!(smooth.trajectory.len(), 2);
+/// ```
+pub fn shortcut<M: Model>(
+    model: &M,
+    traj: &Trajectory<M>,
+    collision_free: impl Fn(&M::State, &M::State) -> bool,
+) -> Trajectory<M> {
+    let steps = &traj.trajectory;
+    if steps.len() <= 2 {
+        return traj.copy();
+    }
+
+    let mut cost = M::Cost::default();
+    let mut result = vec![steps[0].clone()];
+
+    let mut from = 0;
+    while from < steps.len() - 1 {
+        let mut to = steps.len() - 1;
+        while to > from + 1 && !collision_free(&steps[from].0, &steps[to].0) {
+            to -= 1;
+        }
+
+        let (state, control) = &steps[to];
+        cost = cost + model.cost(&steps[from].0, control, state);
+        result.push(steps[to].clone());
+        from = to;
+    }
+
+    Trajectory { cost, trajectory: result, expansions: traj.expansions }
+}
+
+/// Place waypoints evenly along a trajectory, every `spacing` of accumulated cost
+///
+/// The cost of each step is measured with `model.cost`, and the new waypoints are found with
+/// [`State::interpolate`] between the states on either side.  Each waypoint keeps the control
+/// of the step it falls within.  The first and last states are always kept, so when `spacing`
+/// is at least the total cost of the trajectory only the endpoints are returned.  A `spacing`
+/// of zero or less, which would never get past the first state, returns the trajectory
+/// unchanged.
+///
+/// [`State::interpolate`]: ../trait.State.html#tymethod.interpolate
+pub fn resample<M>(model: &M, traj: &Trajectory<M>, spacing: M::Cost) -> Trajectory<M>
+where
+    M: Model,
+    M::Cost: Copy + Weighted,
+{
+    let steps = &traj.trajectory;
+    if steps.len() < 2 || spacing <= M::Cost::default() {
+        return traj.copy();
+    }
+
+    let spacing = spacing.to_f64();
+    let mut cost = M::Cost::default();
+    let mut result = vec![steps[0].clone()];
+
+    // Distance along the trajectory of the next waypoint to place
+    let mut next = spacing;
+    for pair in steps.windows(2) {
+        let ((from, _), (to, control)) = (&pair[0], &pair[1]);
+        let step = model.cost(from, control, to);
+        let (start, end) = (cost.to_f64(), (cost + step).to_f64());
+
+        while next < end {
+            let t = (next - start) / (end - start);
+            result.push((from.interpolate(to, t), control.clone()));
+            next += spacing;
+        }
+
+        cost = cost + step;
+    }
+
+    result.push(steps[steps.len() - 1].clone());
+
+    Trajectory { cost, trajectory: result, expansions: traj.expansions }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::actor::{Actor, TurnOptimal, WalkSampler};
+    use crate::map::{Map, Tile};
+    use crate::path::astar::AStar;
+    use crate::path::testing::*;
+
+    #[test]
+    fn shortcuts_along_a_corridor_keep_only_its_ends() {
+        let mut map = Map::new(20, 5);
+        for x in 1..19 {
+            map[(x, 2)] = Tile::FLOOR;
+        }
+        let mut model = TurnOptimal::new(map);
+        let (start, goal) = (walker(1, 2), walker(18, 2));
+        let mut sampler = WalkSampler::new();
+        let jagged =
+            expect_final(AStar::new().optimize(&mut model, &start, &goal, &mut sampler));
+
+        // Nothing blocks a straight line along the corridor
+        let smooth = shortcut(&model, &jagged, |a: &Actor, b: &Actor| a.pos.y == b.pos.y);
+        assert_eq!(smooth.trajectory.len(), 2);
+        assert!(smooth.trajectory.len() < jagged.trajectory.len());
+        let endpoints = |t: &Trajectory<TurnOptimal>| {
+            (
+                t.trajectory.first().unwrap().0.pos.clone(),
+                t.trajectory.last().unwrap().0.pos.clone(),
+            )
+        };
+        assert_eq!(endpoints(&smooth), endpoints(&jagged));
+    }
+
+    #[test]
+    fn resampling_spaces_the_waypoints_evenly() {
+        let even = resample(&Line, &line(&[0, 3, 20, 31]), 5);
+        let points: Vec<i64> = even.trajectory.iter().map(|(p, _)| p.0).collect();
+        assert_eq!(points.first(), Some(&0));
+        assert_eq!(points.last(), Some(&31));
+        // Every gap is the spacing, except the remainder before the last state
+        let gaps: Vec<i64> = points.windows(2).map(|pair| pair[1] - pair[0]).collect();
+        let (remainder, gaps) = gaps.split_last().unwrap();
+        assert!(gaps.iter().all(|gap| (gap - 5).abs() <= 1));
+        assert!(*remainder > 0 && *remainder <= 5);
+    }
+
+    #[test]
+    fn resampling_past_the_whole_cost_keeps_the_endpoints() {
+        assert_eq!(resample(&Line, &line(&[0, 3, 20, 31]), 40).trajectory.len(), 2);
+    }
+
+    #[test]
+    fn resampling_without_a_positive_spacing_changes_nothing() {
+        let uneven = line(&[0, 3, 20, 31]);
+        for &spacing in &[0, -5] {
+            let unchanged = resample(&Line, &uneven, spacing);
+            assert_eq!(unchanged.trajectory.len(), uneven.trajectory.len());
+        }
+    }
+}
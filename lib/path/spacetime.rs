@@ -0,0 +1,316 @@
+use std::fmt::{Debug, Formatter};
+
+use super::grid::{Cell, GridModel, GridSampler, Step};
+use super::*;
+
+/// Models with a control which stays in place
+///
+/// Without time in the state, waiting leads back to the same position at a higher cost, and
+/// the optimizers drop it like any other successor which is no improvement.  Waiting only
+/// gets anywhere in a [`SpaceTimeModel`], where the position after waiting is a later one.
+///
+/// ```
+/// # use game_lib::path::astar::AStar;
+/// # use game_lib::path::grid::{Cell, GridModel, GridSampler};
+/// # use game_lib::path::spacetime::{WaitSampler, WaitableModel};
+/// # use game_lib::path::Optimizer;
+/// let (mut grid, start, goal) = (GridModel::new(12, 12), Cell::new(2, 2), Cell::new(9, 6));
+/// let (wait, mut sampler) = (grid.wait(), WaitSampler::new(GridSampler));
+/// let result = AStar::new().optimize(&mut grid, &start, &goal, &mut sampler);
+/// assert!(result.trajectory().unwrap().trajectory[1..].iter().all(|(_, step)| *step != wait));
+/// ```
+///
+/// [`SpaceTimeModel`]: trait.SpaceTimeModel.html
+pub trait WaitableModel: Model {
+    /// The control which stays in place for one step
+    fn wait(&self) -> Self::Control;
+}
+
+/// Models which plan through time as well as space, around obstacles which move
+///
+/// The position of every state includes the time step it is reached at, so the same place at
+/// two different times are separate nodes of the search, and every control advances the time
+/// by one step.  Waiting in place is one of the controls, which lets a trajectory hold back
+/// until an obstacle has passed.
+pub trait SpaceTimeModel: WaitableModel {
+    /// Where a state is, without the time it is there
+    type Place: Eq + Hash + Clone + Debug;
+
+    /// The place of `state`
+    fn place(&self, state: &Self::State) -> Self::Place;
+
+    /// The time step `state` is reached at
+    fn time(&self, state: &Self::State) -> u32;
+
+    /// Whether a moving obstacle occupies the place of `state` at its time
+    fn is_occupied(&self, state: &Self::State) -> bool;
+}
+
+/// A cell of a [`SpaceTimeGrid`] at a time step
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct TimedCell {
+    pub cell: Cell,
+    pub time: u32,
+}
+
+impl TimedCell {
+    pub fn new(x: i64, y: i64, time: u32) -> Self {
+        TimedCell { cell: Cell::new(x, y), time }
+    }
+}
+
+impl State for TimedCell {
+    type Position = (Cell, u32);
+
+    fn grid_position(&self) -> (Cell, u32) {
+        (self.cell, self.time)
+    }
+
+    fn interpolate(&self, other: &Self, t: f64) -> Self {
+        TimedCell {
+            cell: self.cell.interpolate(&other.cell, t),
+            time: self.time + ((other.time as f64 - self.time as f64) * t).round() as u32,
+        }
+    }
+}
+
+/// A [`GridModel`] with obstacles moving across it along known paths
+///
+/// Each obstacle occupies the cell at index `t` of its path at time step `t`, and stays on the
+/// last cell of its path once it has run out.  A step is invalid when it enters a cell that an
+/// obstacle occupies at the time the step arrives, or when it swaps cells with an obstacle.
+/// Waiting costs the same as a straight step, and a trajectory converges when it reaches the
+/// goal cell, whatever the time is.
+///
+/// Time steps after the horizon are never planned into, so that a goal which cannot be reached
+/// does not leave the planner waiting forever.
+///
+/// ```
+/// # use game_lib::path::astar::AStar;
+/// # use game_lib::path::grid::{Cell, GridModel, GridSampler};
+/// # use game_lib::path::spacetime::{SpaceTimeGrid, TimedCell, WaitSampler};
+/// # use game_lib::path::Optimizer;
+/// let mut model = SpaceTimeGrid::new(GridModel::new(9, 5), 40);
+/// // An obstacle crossing the grid from top to bottom
+/// model.add_obstacle((0..5).map(|y| Cell::new(4, y)).collect());
+/// let (start, goal) = (TimedCell::new(0, 2, 0), TimedCell::new(8, 2, 0));
+/// let mut sampler = WaitSampler::new(GridSampler);
+/// assert!(AStar::new().optimize(&mut model, &start, &goal, &mut sampler).is_final());
+/// ```
+#[derive(Clone, Debug)]
+pub struct SpaceTimeGrid {
+    grid: GridModel,
+    obstacles: Vec<Vec<Cell>>,
+    horizon: u32,
+}
+
+impl SpaceTimeGrid {
+    /// Plan over `grid` up to and including the time step `horizon`
+    pub fn new(grid: GridModel, horizon: u32) -> Self {
+        SpaceTimeGrid { grid, obstacles: Vec::new(), horizon }
+    }
+
+    /// Add an obstacle which follows `path`, one cell per time step
+    ///
+    /// An obstacle with an empty path is never anywhere.
+    pub fn add_obstacle(&mut self, path: Vec<Cell>) {
+        self.obstacles.push(path);
+    }
+
+    pub fn grid(&self) -> &GridModel {
+        &self.grid
+    }
+
+    pub fn horizon(&self) -> u32 {
+        self.horizon
+    }
+
+    /// The cells occupied by obstacles at time step `time`
+    pub fn obstacles_at(&self, time: u32) -> impl Iterator<Item = Cell> + '_ {
+        self.obstacles
+            .iter()
+            .filter_map(move |path| path.get(time as usize).or_else(|| path.last()).copied())
+    }
+
+    /// Whether an obstacle moves from `to` into `from` while taking the step between them
+    fn swaps(&self, from: Cell, to: Cell, time: u32) -> bool {
+        self.obstacles.iter().any(|path| {
+            let at = |t: u32| path.get(t as usize).or_else(|| path.last()).copied();
+            at(time) == Some(to) && at(time + 1) == Some(from)
+        })
+    }
+}
+
+impl Model for SpaceTimeGrid {
+    type State = TimedCell;
+    type Control = Step;
+    type Cost = usize;
+
+    fn cost(&self, current: &TimedCell, step: &Step, next: &TimedCell) -> usize {
+        self.grid.cost(&current.cell, step, &next.cell)
+    }
+
+    /// Nothing to do on initialization
+    fn init(&mut self, _: &TimedCell) {}
+
+    fn converge(&self, current: &TimedCell, goal: &TimedCell) -> bool {
+        current.cell == goal.cell
+    }
+
+    fn integrate(&self, previous: &TimedCell, step: &Step) -> Option<TimedCell> {
+        if previous.time >= self.horizon {
+            return None;
+        }
+
+        let next = TimedCell {
+            cell: self.grid.integrate(&previous.cell, step)?,
+            time: previous.time + 1,
+        };
+        if self.is_occupied(&next) || self.swaps(previous.cell, next.cell, previous.time) {
+            None
+        } else {
+            Some(next)
+        }
+    }
+
+    /// Cells which are not blocked on the grid, whatever the moving obstacles do
+    fn is_valid(&self, state: &TimedCell) -> bool {
+        self.grid.is_valid(&state.cell)
+    }
+}
+
+impl HeuristicModel for SpaceTimeGrid {
+    /// The grid's heuristic between the cells, as if no obstacle were in the way
+    fn heuristic(&self, current: &TimedCell, goal: &TimedCell) -> usize {
+        self.grid.heuristic(&current.cell, &goal.cell)
+    }
+}
+
+impl WaitableModel for SpaceTimeGrid {
+    fn wait(&self) -> Step {
+        Step::default()
+    }
+}
+
+impl SpaceTimeModel for SpaceTimeGrid {
+    type Place = Cell;
+
+    fn place(&self, state: &TimedCell) -> Cell {
+        state.cell
+    }
+
+    fn time(&self, state: &TimedCell) -> u32 {
+        state.time
+    }
+
+    fn is_occupied(&self, state: &TimedCell) -> bool {
+        self.obstacles_at(state.time).any(|cell| cell == state.cell)
+    }
+}
+
+impl Sampler<SpaceTimeGrid> for GridSampler {
+    #[inline]
+    fn sample(&mut self, model: &SpaceTimeGrid, current: &TimedCell) -> &[Step] {
+        Sampler::<GridModel>::sample(self, &model.grid, &current.cell)
+    }
+}
+
+/// Samples the controls of another sampler along with waiting in place
+pub struct WaitSampler<M, S>
+where
+    M: WaitableModel,
+    S: Sampler<M>,
+{
+    sampler: S,
+    controls: Vec<M::Control>,
+}
+
+impl<M, S> WaitSampler<M, S>
+where
+    M: WaitableModel,
+    S: Sampler<M>,
+{
+    pub fn new(sampler: S) -> Self {
+        WaitSampler { sampler, controls: Vec::new() }
+    }
+
+    pub fn into_inner(self) -> S {
+        self.sampler
+    }
+}
+
+impl<M, S> Sampler<M> for WaitSampler<M, S>
+where
+    M: WaitableModel,
+    S: Sampler<M>,
+{
+    fn sample(&mut self, model: &M, current: &M::State) -> &[M::Control] {
+        self.sampler.sample_into(model, current, &mut self.controls);
+        self.controls.push(model.wait());
+
+        &self.controls
+    }
+}
+
+impl<M, S> Debug for WaitSampler<M, S>
+where
+    M: WaitableModel,
+    S: Sampler<M> + Debug,
+{
+    fn fmt(&self, fmt: &mut Formatter) -> Result<(), std::fmt::Error> {
+        fmt.debug_struct("WaitSampler").field("sampler", &self.sampler).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::actor::{TurnOptimal, WalkSampler};
+    use crate::path::astar::AStar;
+    use crate::path::testing::*;
+
+    #[test]
+    fn trajectories_wait_for_an_obstacle_to_pass() {
+        // A corridor along the middle row, crossed by a track down the middle column
+        let mut grid = GridModel::new(9, 5);
+        for x in 0..9 {
+            for y in 0..5 {
+                grid.set_blocked(x, y, y != 2 && x != 4);
+            }
+        }
+        // The track is only open past the corridor, so there is no way around the obstacle
+        grid.set_blocked(4, 1, true);
+        grid.set_blocked(4, 3, true);
+
+        let mut model = SpaceTimeGrid::new(grid, 40);
+        // An obstacle which pauses in the corridor on its way across
+        let track = [(4, 0), (4, 0), (4, 0), (4, 2), (4, 2), (4, 2), (4, 4)];
+        model.add_obstacle(track.iter().map(|&(x, y)| Cell::new(x, y)).collect());
+
+        let (start, goal) = (TimedCell::new(0, 2, 0), TimedCell::new(8, 2, 0));
+        let mut sampler = WaitSampler::new(GridSampler);
+        let t = expect_final(AStar::new().optimize(&mut model, &start, &goal, &mut sampler));
+        assert_eq!(t.trajectory.last().unwrap().0.cell, goal.cell);
+        assert!(t.trajectory.iter().all(|(state, _)| !model.is_occupied(state)));
+
+        // Walking straight through would take 8 steps and meet the obstacle at (4, 2)
+        assert!(t.cost > 2 * 8);
+        assert!(t.trajectory[1..].iter().any(|(_, step)| *step == model.wait()));
+    }
+
+    #[test]
+    fn waiting_never_helps_without_time_in_the_state() {
+        let mut model = TurnOptimal::new(room(12, 12));
+        let (start, goal) = (walker(2, 2), walker(9, 6));
+
+        let mut walking = WalkSampler::new();
+        let mut waiting = WaitSampler::new(WalkSampler::new());
+        let walked =
+            expect_final(AStar::new().optimize(&mut model, &start, &goal, &mut walking));
+        let waited =
+            expect_final(AStar::new().optimize(&mut model, &start, &goal, &mut waiting));
+        assert_eq!(waited.cost, walked.cost);
+        let wait = model.wait();
+        assert!(waited.trajectory[1..].iter().all(|(_, control)| *control != wait));
+    }
+}
@@ -0,0 +1,271 @@
+//! Maps, states and checks shared by the unit tests of the optimizers
+
+use crate::actor::{Actor, TurnOptimal};
+pub(crate) use crate::map::fixtures::{divided_room, room};
+use crate::map::{Map, Tile};
+
+use super::bidirectional::ReversibleModel;
+use super::continuous::ContinuousState;
+use super::grid::{Cell, GridModel, GridSampler, Step};
+use super::*;
+
+/// A room of 30 by 30 tiles with a wall down the middle, which misleads any heuristic on the
+/// way from one side to the other
+pub(crate) fn walled_room() -> Map {
+    let mut map = room(30, 30);
+    for y in 5..25 {
+        map[(15, y)] = Tile::WALL;
+    }
+    map
+}
+
+/// A grid of 5 by 5 cells whose middle cell is walled in by its eight neighbours
+pub(crate) fn shut_in() -> GridModel {
+    let mut grid = GridModel::new(5, 5);
+    for &(x, y) in &[(1, 1), (2, 1), (3, 1), (1, 2), (3, 2), (1, 3), (2, 3), (3, 3)] {
+        grid.set_blocked(x, y, true);
+    }
+    grid
+}
+
+/// An actor standing at `(x, y)` without the mana to do more than walk
+pub(crate) fn walker(x: u32, y: u32) -> Actor {
+    Actor::new(x, y, 0, 10)
+}
+
+/// The trajectory of a search which should have reached its goal
+pub(crate) fn expect_final<M: Model>(result: PathResult<M>) -> Trajectory<M> {
+    match result {
+        PathResult::Final(t) => t,
+        PathResult::Intermediate(_) => panic!("the search stopped before the goal"),
+        PathResult::Err(err) => {
+            panic!("the goal is reachable, but the search failed: {}", err)
+        }
+    }
+}
+
+/// Check that a trajectory leaves from `start` and only ever walks to a neighbouring tile
+pub(crate) fn assert_walkable(trajectory: &Trajectory<TurnOptimal>, start: &Actor) {
+    assert_eq!(
+        trajectory.trajectory.first().map(|(s, _)| s.pos.clone()),
+        Some(start.pos.clone())
+    );
+    for pair in trajectory.trajectory.windows(2) {
+        let (a, b) = (&pair[0].0.pos, &pair[1].0.pos);
+        assert!(a.square_dist(b.clone()) <= 2.0, "{:?} is not next to {:?}", a, b);
+    }
+}
+
+/// Check that two trajectories cost the same and walk through the same tiles
+pub(crate) fn assert_same_trajectory(
+    a: &Trajectory<TurnOptimal>,
+    b: &Trajectory<TurnOptimal>,
+) {
+    assert_eq!(a.cost, b.cost);
+    let tiles = |t: &Trajectory<TurnOptimal>| {
+        t.trajectory.iter().map(|(s, _)| s.pos.clone()).collect::<Vec<_>>()
+    };
+    assert_eq!(tiles(a), tiles(b));
+}
+
+/// Points on a line, where moving costs the distance travelled
+#[derive(Debug, Clone)]
+pub(crate) struct Point(pub(crate) i64);
+
+impl State for Point {
+    type Position = i64;
+
+    fn grid_position(&self) -> i64 {
+        self.0
+    }
+
+    fn interpolate(&self, other: &Self, t: f64) -> Self {
+        Point(self.0 + ((other.0 - self.0) as f64 * t).round() as i64)
+    }
+}
+
+/// A model of the points on a line, which plans nothing but measures the cost between points
+pub(crate) struct Line;
+
+impl Model for Line {
+    type State = Point;
+    type Control = ();
+    type Cost = i64;
+
+    fn cost(&self, current: &Point, _: &(), next: &Point) -> i64 {
+        (next.0 - current.0).abs()
+    }
+
+    fn init(&mut self, _: &Point) {}
+
+    fn converge(&self, current: &Point, goal: &Point) -> bool {
+        current.0 == goal.0
+    }
+
+    fn integrate(&self, previous: &Point, _: &()) -> Option<Point> {
+        Some(previous.clone())
+    }
+}
+
+/// A trajectory along the `points` of a line
+pub(crate) fn line(points: &[i64]) -> Trajectory<Line> {
+    let cost = points.windows(2).map(|pair| (pair[1] - pair[0]).abs()).sum();
+    Trajectory {
+        cost,
+        trajectory: points.iter().map(|&p| (Point(p), ())).collect(),
+        expansions: 0,
+    }
+}
+
+/// A grid which is less regular than `GridModel`, for the models the optimizers meet outside
+/// of a plain grid
+pub(crate) struct TestGrid {
+    pub(crate) grid: GridModel,
+    /// Multiplies the cost of the step between two cells
+    pub(crate) weight: fn(&Cell, &Cell) -> usize,
+    /// Whether `integrate` may step from one cell to the other, which `integrate_reverse`
+    /// ignores
+    pub(crate) allow: fn(&Cell, &Cell) -> bool,
+    /// Multiplies the heuristic of the grid, which is only admissible up to the lowest weight
+    pub(crate) inflation: usize,
+    /// Whether `heuristic_opt` gives up on reaching the goal from a cell
+    pub(crate) dead_end: fn(&Cell, &Cell) -> bool,
+}
+
+impl TestGrid {
+    /// An open grid which behaves exactly as `GridModel` until it is changed
+    pub(crate) fn new(width: usize, height: usize) -> Self {
+        TestGrid {
+            grid: GridModel::new(width, height),
+            weight: |_, _| 1,
+            allow: |_, _| true,
+            inflation: 1,
+            dead_end: |_, _| false,
+        }
+    }
+}
+
+impl Model for TestGrid {
+    type State = Cell;
+    type Control = Step;
+    type Cost = usize;
+
+    fn cost(&self, current: &Cell, step: &Step, next: &Cell) -> usize {
+        self.grid.cost(current, step, next) * (self.weight)(current, next)
+    }
+
+    fn init(&mut self, start: &Cell) {
+        self.grid.init(start)
+    }
+
+    fn converge(&self, current: &Cell, goal: &Cell) -> bool {
+        self.grid.converge(current, goal)
+    }
+
+    fn integrate(&self, cell: &Cell, step: &Step) -> Option<Cell> {
+        self.grid.integrate(cell, step).filter(|next| (self.allow)(cell, next))
+    }
+}
+
+impl HeuristicModel for TestGrid {
+    fn heuristic(&self, current: &Cell, goal: &Cell) -> usize {
+        self.inflation * self.grid.heuristic(current, goal)
+    }
+
+    fn heuristic_opt(&self, current: &Cell, goal: &Cell) -> Option<usize> {
+        if (self.dead_end)(current, goal) {
+            None
+        } else {
+            Some(self.heuristic(current, goal))
+        }
+    }
+}
+
+impl ReversibleModel for TestGrid {
+    fn integrate_reverse(&self, cell: &Cell, step: &Step) -> Option<Cell> {
+        self.grid.integrate_reverse(cell, step)
+    }
+}
+
+impl Sampler<TestGrid> for GridSampler {
+    fn sample(&mut self, model: &TestGrid, cell: &Cell) -> &[Step] {
+        Sampler::<GridModel>::sample(self, &model.grid, cell)
+    }
+}
+
+/// A point in the plane, in cells half a unit wide
+#[derive(Clone, Debug)]
+pub(crate) struct Spot(pub(crate) [f64; 2]);
+
+impl ContinuousState for Spot {
+    type Coordinates = [f64; 2];
+
+    fn coordinates(&self) -> [f64; 2] {
+        self.0
+    }
+
+    fn cell_size(&self) -> f64 {
+        0.5
+    }
+
+    fn with_coordinates(&self, coordinates: [f64; 2]) -> Self {
+        Spot(coordinates)
+    }
+}
+
+pub(crate) fn distance([x, y]: [f64; 2], [gx, gy]: [f64; 2]) -> f64 {
+    (x - gx).hypot(y - gy)
+}
+
+/// Moving a unit length in one of sixteen headings inside a square, where the cost is in
+/// hundredths of the distance
+pub(crate) struct Plane {
+    pub(crate) size: f64,
+    /// How close to the goal `converge` accepts, where zero only accepts the goal itself
+    pub(crate) reach: f64,
+}
+
+impl Model for Plane {
+    type State = Spot;
+    type Control = usize;
+    type Cost = u64;
+
+    fn cost(&self, current: &Spot, _: &usize, next: &Spot) -> u64 {
+        (distance(current.0, next.0) * 100.0).round() as u64
+    }
+
+    fn init(&mut self, _: &Spot) {}
+
+    fn converge(&self, current: &Spot, goal: &Spot) -> bool {
+        current.0 == goal.0 || distance(current.0, goal.0) < self.reach
+    }
+
+    fn converge_within(&self, current: &Spot, goal: &Spot, tolerance: f64) -> bool {
+        distance(current.0, goal.0) <= tolerance
+    }
+
+    fn integrate(&self, previous: &Spot, heading: &usize) -> Option<Spot> {
+        let angle = *heading as f64 * std::f64::consts::PI / 8.0;
+        let [x, y] = previous.0;
+        let next = [x + angle.cos(), y + angle.sin()];
+        if next.iter().all(|c| (0.0..self.size).contains(c)) {
+            Some(Spot(next))
+        } else {
+            None
+        }
+    }
+}
+
+impl HeuristicModel for Plane {
+    fn heuristic(&self, current: &Spot, goal: &Spot) -> u64 {
+        ((distance(current.0, goal.0) - 1.0).max(0.0) * 100.0) as u64
+    }
+}
+
+pub(crate) struct Headings;
+
+impl Sampler<Plane> for Headings {
+    fn sample(&mut self, _: &Plane, _: &Spot) -> &[usize] {
+        &[0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15]
+    }
+}
@@ -0,0 +1,359 @@
+use std::fmt::{Debug, Formatter};
+
+use fnv::{FnvHashMap, FnvHashSet};
+use std::collections::hash_map::Entry;
+use std::collections::BinaryHeap;
+
+use super::astar::{Id, Node};
+use super::*;
+
+/// Theta*, an any-angle variant of A*
+///
+/// When a successor is generated, Theta* first tries to connect it straight to the parent of
+/// the node being expanded, using [`Model::line_of_sight`].  If the connection is clear the
+/// intermediate node is skipped, so trajectories are no longer bound to the directions of the
+/// controls.  A skipped-over step keeps the control which generated the successor.
+///
+/// The model's cost must be the cost of travelling directly between _any_ two states for the
+/// shortcuts to be measured correctly.  With the default `line_of_sight` this is plain A*.
+///
+/// ```
+/// # use game_lib::path::grid::{Cell, GridModel, GridSampler};
+/// # use game_lib::path::{astar::AStar, theta::ThetaStar, Optimizer};
+/// // The grid keeps the default `line_of_sight`, which never allows a shortcut
+/// let (mut grid, start, goal) = (GridModel::new(20, 20), Cell::new(1, 1), Cell::new(18, 7));
+/// let theta = ThetaStar::new().optimize(&mut grid, &start, &goal, &mut GridSampler);
+/// let astar = AStar::new().optimize(&mut grid, &start, &goal, &mut GridSampler);
+/// assert_eq!(theta.trajectory().map(|t| t.cost), astar.trajectory().map(|t| t.cost));
+/// ```
+///
+/// [`Model::line_of_sight`]: ../trait.Model.html#method.line_of_sight
+pub struct ThetaStar<M>
+where
+    M: HeuristicModel,
+    M::Cost: Copy,
+{
+    queue: BinaryHeap<Node<M>>,
+    parent_map: FnvHashMap<Id<M>, Node<M>>,
+    grid: FnvHashMap<<<M as Model>::State as State>::Position, Id<M>>,
+    closed: FnvHashSet<<<M as Model>::State as State>::Position>,
+    id_counter: usize,
+    stats: Stats,
+}
+
+impl<M> ThetaStar<M>
+where
+    M: HeuristicModel,
+    M::Cost: Copy,
+{
+    /// Create a new Theta* optimizer
+    pub fn new() -> Self {
+        ThetaStar {
+            queue: BinaryHeap::new(),
+            parent_map: FnvHashMap::default(),
+            grid: FnvHashMap::default(),
+            closed: FnvHashSet::default(),
+            id_counter: 0,
+            stats: Stats::default(),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.queue.clear();
+        self.parent_map.clear();
+        self.grid.clear();
+        self.closed.clear();
+        self.stats = Stats::default();
+    }
+
+    /// Counters describing the work done since the optimizer was created or last cleared
+    pub fn stats(&self) -> &Stats {
+        &self.stats
+    }
+
+    pub fn inspect_queue(&self) -> impl Iterator<Item = (&M::State, &M::Control)> {
+        self.queue.iter().map(|node| (&node.state, &node.control))
+    }
+
+    pub fn inspect_discovered(
+        &self,
+    ) -> impl Iterator<Item = &<<M as Model>::State as State>::Position> {
+        self.grid.keys()
+    }
+
+    #[inline(always)]
+    fn step<S>(
+        &mut self,
+        current: &Node<M>,
+        model: &mut M,
+        goal: &M::State,
+        sampler: &mut S,
+    ) -> bool
+    where
+        S: Sampler<M>,
+    {
+        if model.converge(&current.state, goal) {
+            return true;
+        }
+
+        if !self.closed.insert(current.state.grid_position()) {
+            return false;
+        }
+        self.stats.expanded += 1;
+
+        let grandparent = self.parent_map.get(&current.id).cloned();
+        for control in sampler.sample(model, &current.state) {
+            if let Some(child_state) = model.integrate(&current.state, control) {
+                self.id_counter += 1;
+                self.stats.generated += 1;
+
+                // Skip over the current node when its parent can see the successor
+                let (parent, cost) = match &grandparent {
+                    Some(p) if model.line_of_sight(&p.state, &child_state) => {
+                        (p, p.id.g() + model.cost(&p.state, control, &child_state))
+                    }
+                    _ => (
+                        current,
+                        current.id.g() + model.cost(&current.state, control, &child_state),
+                    ),
+                };
+                let heuristic = model.heuristic(&child_state, goal);
+
+                let child = Node::<M> {
+                    id: Id::new(self.id_counter, cost + heuristic, cost),
+                    state: child_state,
+                    control: control.clone(),
+                };
+
+                match self.grid.entry(child.state.grid_position()) {
+                    Entry::Occupied(mut best) => {
+                        let best = best.get_mut();
+                        if best.g <= child.id.g {
+                            continue;
+                        } else {
+                            *best = child.id.clone();
+                        }
+                    }
+                    Entry::Vacant(empty) => {
+                        empty.insert(child.id.clone());
+                    }
+                }
+
+                self.parent_map.insert(child.id.clone(), parent.clone());
+                self.queue.push(child);
+                self.stats.max_queue_len = self.stats.max_queue_len.max(self.queue.len());
+            }
+        }
+
+        false
+    }
+
+    /// Pop the next node from the queue, discarding stale entries along the way
+    fn pop(&mut self) -> Option<Node<M>> {
+        while let Some(node) = self.queue.pop() {
+            let stale = self
+                .grid
+                .get(&node.state.grid_position())
+                .is_some_and(|best| best.g < node.id.g);
+
+            if !stale {
+                return Some(node);
+            }
+        }
+
+        None
+    }
+
+    /// Follow the parents from the goal node up to the start node
+    fn unwind_trajectory(&self, mut current: Node<M>) -> Trajectory<M> {
+        let cost = current.id.g();
+        let mut result = Vec::new();
+        result.push((current.state.clone(), current.control.clone()));
+
+        while let Some(p) = self.parent_map.get(&current.id) {
+            current = (*p).clone();
+            result.push((current.state.clone(), current.control.clone()));
+        }
+
+        result.reverse();
+
+        Trajectory { cost, trajectory: result, expansions: self.stats.expanded }
+    }
+
+    fn seed(&mut self, model: &M, start: &M::State, goal: &M::State) {
+        let start_id = Id::new(0, model.heuristic(start, goal), Default::default());
+        self.queue.push(Node {
+            id: start_id,
+            state: start.clone(),
+            control: Default::default(),
+        });
+    }
+}
+
+impl<M, S> Optimizer<M, S> for ThetaStar<M>
+where
+    M: HeuristicModel,
+    M::Cost: Copy,
+    S: Sampler<M>,
+{
+    fn next_trajectory(
+        &mut self,
+        model: &mut M,
+        start: &M::State,
+        goal: &M::State,
+        sampler: &mut S,
+    ) -> PathResult<M> {
+        use PathFindingErr::*;
+        use PathResult::*;
+
+        if let Some(invalid) = invalid_endpoint(model, start, goal) {
+            return Err(invalid);
+        }
+
+        if self.parent_map.is_empty() && self.queue.is_empty() {
+            self.seed(model, start, goal);
+        }
+
+        if let Some(current) = self.pop() {
+            if self.step(&current, model, goal, sampler) {
+                Final(self.unwind_trajectory(current))
+            } else {
+                Intermediate(self.unwind_trajectory(current))
+            }
+        } else {
+            Err(Unreachable)
+        }
+    }
+
+    fn optimize(
+        &mut self,
+        model: &mut M,
+        start: &M::State,
+        goal: &M::State,
+        sampler: &mut S,
+    ) -> PathResult<M> {
+        use PathFindingErr::*;
+        use PathResult::*;
+
+        if let Some(invalid) = invalid_endpoint(model, start, goal) {
+            return Err(invalid);
+        }
+
+        if model.converge(start, goal) {
+            return Final(Trajectory {
+                cost: Default::default(),
+                trajectory: vec![(start.clone(), Default::default())],
+                expansions: 0,
+            });
+        }
+
+        if self.parent_map.is_empty() && self.queue.is_empty() {
+            self.seed(model, start, goal);
+        }
+
+        while let Some(current) = self.pop() {
+            if self.step(&current, model, goal, sampler) {
+                return Final(self.unwind_trajectory(current));
+            }
+        }
+
+        Err(Unreachable)
+    }
+}
+
+impl<M> Debug for ThetaStar<M>
+where
+    M: HeuristicModel,
+    M::State: Debug,
+    M::Control: Debug,
+    M::Cost: Debug + Copy,
+{
+    fn fmt(&self, fmt: &mut Formatter) -> Result<(), std::fmt::Error> {
+        fmt.debug_struct("ThetaStar")
+            .field("counter", &self.id_counter)
+            .field("next", &self.queue.peek())
+            .field("queue", &self.queue)
+            .field("grid", &self.grid)
+            .field("closed", &self.closed)
+            .field("stats", &self.stats)
+            .field("parent_map", &self.parent_map)
+            .finish()
+    }
+}
+
+impl<M> Default for ThetaStar<M>
+where
+    M: HeuristicModel,
+    M::Cost: Copy,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::actor::Direction;
+    use crate::path::astar::AStar;
+    use crate::path::testing::*;
+    use crate::Position;
+
+    /// An open grid where travel costs ten times the straight-line distance
+    struct Open;
+
+    impl Model for Open {
+        type State = Position;
+        type Control = Direction;
+        type Cost = u64;
+
+        fn cost(&self, current: &Position, _: &Direction, next: &Position) -> u64 {
+            (10.0 * current.dist(next.clone())).round() as u64
+        }
+
+        fn init(&mut self, _: &Position) {}
+
+        fn converge(&self, current: &Position, goal: &Position) -> bool {
+            current == goal
+        }
+
+        fn integrate(&self, previous: &Position, control: &Direction) -> Option<Position> {
+            let next: Position = control.step_from(previous.x, previous.y).into();
+            if next.x >= 1 && next.y >= 1 && next.x < 20 && next.y < 20 {
+                Some(next)
+            } else {
+                None
+            }
+        }
+
+        fn line_of_sight(&self, _: &Position, _: &Position) -> bool {
+            true
+        }
+    }
+
+    impl HeuristicModel for Open {
+        fn heuristic(&self, current: &Position, goal: &Position) -> u64 {
+            (10.0 * current.dist(goal.clone())).floor() as u64
+        }
+    }
+
+    struct Octile;
+
+    impl Sampler<Open> for Octile {
+        fn sample(&mut self, _: &Open, _: &Position) -> &[Direction] {
+            use Direction::*;
+            &[North, NorthEast, East, SouthEast, South, SouthWest, West, NorthWest]
+        }
+    }
+
+    #[test]
+    fn clear_lines_of_sight_connect_the_ends_directly() {
+        let (start, goal) = (Position::new(1, 1), Position::new(18, 7));
+        let grid = expect_final(AStar::new().optimize(&mut Open, &start, &goal, &mut Octile));
+        let any_angle =
+            expect_final(ThetaStar::new().optimize(&mut Open, &start, &goal, &mut Octile));
+        assert!(any_angle.cost < grid.cost);
+        // Nothing is in the way, so the corners are connected directly
+        assert_eq!(any_angle.trajectory.len(), 2);
+    }
+}
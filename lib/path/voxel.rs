@@ -0,0 +1,335 @@
+use super::bucket::BoundedCostModel;
+use super::*;
+
+/// Cost of a step to a neighbour sharing a face
+const STRAIGHT: usize = 10;
+/// Cost of a step to a neighbour sharing an edge, close to `STRAIGHT` times the square root
+/// of two
+const EDGE: usize = 14;
+/// Cost of a step to a neighbour sharing a corner, close to `STRAIGHT` times the square root
+/// of three
+const CORNER: usize = 17;
+
+/// Every step to a neighbour, those sharing a face first, then those sharing an edge, then
+/// those sharing a corner, so that each connectivity samples a prefix
+static STEPS: [Step; 26] = [
+    Step { dx: 1, dy: 0, dz: 0 },
+    Step { dx: -1, dy: 0, dz: 0 },
+    Step { dx: 0, dy: 1, dz: 0 },
+    Step { dx: 0, dy: -1, dz: 0 },
+    Step { dx: 0, dy: 0, dz: 1 },
+    Step { dx: 0, dy: 0, dz: -1 },
+    Step { dx: 1, dy: 1, dz: 0 },
+    Step { dx: 1, dy: -1, dz: 0 },
+    Step { dx: -1, dy: 1, dz: 0 },
+    Step { dx: -1, dy: -1, dz: 0 },
+    Step { dx: 1, dy: 0, dz: 1 },
+    Step { dx: 1, dy: 0, dz: -1 },
+    Step { dx: -1, dy: 0, dz: 1 },
+    Step { dx: -1, dy: 0, dz: -1 },
+    Step { dx: 0, dy: 1, dz: 1 },
+    Step { dx: 0, dy: 1, dz: -1 },
+    Step { dx: 0, dy: -1, dz: 1 },
+    Step { dx: 0, dy: -1, dz: -1 },
+    Step { dx: 1, dy: 1, dz: 1 },
+    Step { dx: 1, dy: 1, dz: -1 },
+    Step { dx: 1, dy: -1, dz: 1 },
+    Step { dx: 1, dy: -1, dz: -1 },
+    Step { dx: -1, dy: 1, dz: 1 },
+    Step { dx: -1, dy: 1, dz: -1 },
+    Step { dx: -1, dy: -1, dz: 1 },
+    Step { dx: -1, dy: -1, dz: -1 },
+];
+
+/// How the voxels of a grid connect to their neighbours
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Connectivity {
+    /// Only the six neighbours sharing a face can be reached in one step
+    Six,
+    /// The twelve neighbours sharing an edge can be reached in one step as well
+    Eighteen,
+    /// Every neighbour can be reached in one step, including the eight sharing a corner
+    TwentySix,
+}
+
+impl Connectivity {
+    fn steps(self) -> &'static [Step] {
+        match self {
+            Connectivity::Six => &STEPS[..6],
+            Connectivity::Eighteen => &STEPS[..18],
+            Connectivity::TwentySix => &STEPS[..],
+        }
+    }
+}
+
+/// A voxel of a [`VoxelModel`]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Voxel {
+    pub x: i64,
+    pub y: i64,
+    pub z: i64,
+}
+
+impl Voxel {
+    pub fn new(x: i64, y: i64, z: i64) -> Self {
+        Voxel { x, y, z }
+    }
+}
+
+impl State for Voxel {
+    type Position = Voxel;
+
+    fn grid_position(&self) -> Voxel {
+        *self
+    }
+
+    fn interpolate(&self, other: &Self, t: f64) -> Self {
+        let lerp = |a: i64, b: i64| a + ((b - a) as f64 * t).round() as i64;
+        Voxel { x: lerp(self.x, other.x), y: lerp(self.y, other.y), z: lerp(self.z, other.z) }
+    }
+}
+
+impl BoundedPosition for Voxel {
+    fn within(&self, min: &Self, max: &Self) -> bool {
+        (min.x..=max.x).contains(&self.x)
+            && (min.y..=max.y).contains(&self.y)
+            && (min.z..=max.z).contains(&self.z)
+    }
+}
+
+/// A step from a voxel to one of its neighbours, where each offset is -1, 0, or 1
+///
+/// The default step stays in place, and is the control of the first state of a trajectory.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Step {
+    pub dx: i64,
+    pub dy: i64,
+    pub dz: i64,
+}
+
+/// A three dimensional grid of voxels which are either open or blocked
+///
+/// This is the counterpart of the [`grid::GridModel`] for actors which move up and down as
+/// freely as across, such as drones, or anything else which navigates a volume rather than a
+/// floor.  Steps to a neighbour sharing a face cost 10, those to a neighbour sharing an edge
+/// cost 14 and those to a neighbour sharing a corner cost 17, close to the distances between
+/// the centres of the voxels.  The heuristic is the cost of the cheapest trajectory on an
+/// open grid with the same connectivity, which is admissible and exact when nothing is in
+/// the way.  A trajectory converges when it reaches the goal voxel.
+///
+/// As with the grid, stepping into a voxel only depends on whether that voxel is
+/// blocked, so steps which are not straight may cut past blocked edges and corners.
+///
+/// [`grid::GridModel`]: ../grid/struct.GridModel.html
+///
+/// ```
+/// # use game_lib::path::astar::AStar;
+/// # use game_lib::path::voxel::{Connectivity, Voxel, VoxelModel, VoxelSampler};
+/// # use game_lib::path::Optimizer;
+/// let mut model = VoxelModel::new(10, 10, 10, Connectivity::TwentySix);
+/// let (start, goal) = (Voxel::new(2, 2, 1), Voxel::new(3, 3, 2));
+/// let result = AStar::new().optimize(&mut model, &start, &goal, &mut VoxelSampler);
+/// assert_eq!(result.trajectory().map(|t| t.cost), Some(17));
+/// ```
+#[derive(Clone, Debug)]
+pub struct VoxelModel {
+    width: usize,
+    height: usize,
+    depth: usize,
+    blocked: Vec<bool>,
+    connectivity: Connectivity,
+}
+
+impl VoxelModel {
+    /// Create an open grid where the voxels connect to their neighbours as given
+    pub fn new(width: usize, height: usize, depth: usize, connectivity: Connectivity) -> Self {
+        let blocked = vec![false; width * height * depth];
+        Self::with_occupancy(width, height, depth, blocked, connectivity)
+    }
+
+    /// Create a grid from an occupancy map, where `true` marks a blocked voxel
+    ///
+    /// The map holds one layer after the other, each of them in row-major order, so the voxel
+    /// at `(x, y, z)` is found at `(z * height + y) * width + x`.
+    ///
+    /// # Panics
+    ///
+    /// When the occupancy map does not hold exactly `width * height * depth` voxels.
+    pub fn with_occupancy(
+        width: usize,
+        height: usize,
+        depth: usize,
+        blocked: Vec<bool>,
+        connectivity: Connectivity,
+    ) -> Self {
+        assert_eq!(
+            blocked.len(),
+            width * height * depth,
+            "the occupancy map must cover the grid"
+        );
+
+        VoxelModel { width, height, depth, blocked, connectivity }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    pub fn connectivity(&self) -> Connectivity {
+        self.connectivity
+    }
+
+    /// Whether the voxel at `(x, y, z)` cannot be entered, which includes every voxel off the
+    /// grid
+    pub fn is_blocked(&self, x: i64, y: i64, z: i64) -> bool {
+        self.index(x, y, z).is_none_or(|i| self.blocked[i])
+    }
+
+    /// Block or open the voxel at `(x, y, z)`
+    ///
+    /// # Panics
+    ///
+    /// When the voxel is not on the grid.
+    pub fn set_blocked(&mut self, x: i64, y: i64, z: i64, blocked: bool) {
+        let i = self.index(x, y, z).expect("the voxel must be on the grid");
+        self.blocked[i] = blocked;
+    }
+
+    fn index(&self, x: i64, y: i64, z: i64) -> Option<usize> {
+        let within = |v: i64, size: usize| v >= 0 && (v as u64) < size as u64;
+        if !within(x, self.width) || !within(y, self.height) || !within(z, self.depth) {
+            return None;
+        }
+
+        Some((z as usize * self.height + y as usize) * self.width + x as usize)
+    }
+}
+
+impl Model for VoxelModel {
+    type State = Voxel;
+    type Control = Step;
+    type Cost = usize;
+
+    fn cost(&self, _current: &Voxel, step: &Step, _next: &Voxel) -> usize {
+        match step.dx.abs() + step.dy.abs() + step.dz.abs() {
+            0 | 1 => STRAIGHT,
+            2 => EDGE,
+            _ => CORNER,
+        }
+    }
+
+    /// Nothing to do on initialization
+    fn init(&mut self, _: &Voxel) {}
+
+    fn converge(&self, current: &Voxel, goal: &Voxel) -> bool {
+        current == goal
+    }
+
+    fn integrate(&self, previous: &Voxel, step: &Step) -> Option<Voxel> {
+        let next =
+            Voxel::new(previous.x + step.dx, previous.y + step.dy, previous.z + step.dz);
+
+        if self.is_blocked(next.x, next.y, next.z) {
+            None
+        } else {
+            Some(next)
+        }
+    }
+
+    /// Voxels on the grid which are not blocked
+    fn is_valid(&self, voxel: &Voxel) -> bool {
+        !self.is_blocked(voxel.x, voxel.y, voxel.z)
+    }
+}
+
+impl HeuristicModel for VoxelModel {
+    /// The cost of the cheapest trajectory to the goal if nothing were blocked
+    fn heuristic(&self, current: &Voxel, goal: &Voxel) -> usize {
+        let mut offsets = [
+            (current.x - goal.x).unsigned_abs() as usize,
+            (current.y - goal.y).unsigned_abs() as usize,
+            (current.z - goal.z).unsigned_abs() as usize,
+        ];
+        offsets.sort_unstable();
+        let [short, middle, long] = offsets;
+
+        match self.connectivity {
+            Connectivity::Six => STRAIGHT * (short + middle + long),
+            Connectivity::Eighteen => {
+                // Each step along an edge covers two axes, as many times as they allow
+                let total = short + middle + long;
+                let edges = (short + middle).min(total / 2);
+                EDGE * edges + STRAIGHT * (total - 2 * edges)
+            }
+            Connectivity::TwentySix => {
+                CORNER * short + EDGE * (middle - short) + STRAIGHT * (long - middle)
+            }
+        }
+    }
+}
+
+impl BoundedCostModel for VoxelModel {
+    fn max_edge_cost(&self) -> usize {
+        match self.connectivity {
+            Connectivity::Six => STRAIGHT,
+            Connectivity::Eighteen => EDGE,
+            Connectivity::TwentySix => CORNER,
+        }
+    }
+}
+
+/// Samples a step to every neighbour the grid's connectivity allows
+#[derive(Clone, Copy, Debug, Default)]
+pub struct VoxelSampler;
+
+impl Sampler<VoxelModel> for VoxelSampler {
+    #[inline]
+    fn sample(&mut self, model: &VoxelModel, _: &Voxel) -> &[Step] {
+        model.connectivity.steps()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::path::astar::AStar;
+    use crate::path::testing::expect_final;
+
+    #[test]
+    fn trajectories_only_pass_through_open_voxels() {
+        let mut model = VoxelModel::new(10, 10, 10, Connectivity::TwentySix);
+        // A floor between the levels, with a shaft through it in one corner
+        for (x, y) in (0..10).flat_map(|x| (0..10).map(move |y| (x, y))) {
+            if x < 8 || y < 8 {
+                model.set_blocked(x, y, 5, true);
+            }
+        }
+        let (start, goal) = (Voxel::new(2, 2, 1), Voxel::new(2, 2, 8));
+
+        let mut astar = AStar::new();
+        astar.set_consistency_checks(true);
+        let t = expect_final(astar.optimize(&mut model, &start, &goal, &mut VoxelSampler));
+        assert_eq!(t.trajectory.last().unwrap().0, goal);
+        assert!(t.states().all(|v| !model.is_blocked(v.x, v.y, v.z)));
+        // The only way through the floor is up the shaft, across all three axes
+        assert!(t.states().filter(|v| v.z == 5).all(|v| v.x >= 8 && v.y >= 8));
+        assert!(t.cost > model.heuristic(&start, &goal));
+
+        // Without the corner shaft the levels are cut off from each other
+        for (x, y) in (8..10).flat_map(|x| (8..10).map(move |y| (x, y))) {
+            model.set_blocked(x, y, 5, true);
+        }
+        match AStar::new().optimize(&mut model, &start, &goal, &mut VoxelSampler) {
+            PathResult::Err(_) => {}
+            _ => panic!("the floor is sealed"),
+        }
+    }
+}
@@ -146,11 +146,7 @@ impl App {
     }
 
     pub fn trajectory(&self) -> Trajectory<TurnOptimal> {
-        match &self.trajectory {
-            PathResult::Intermediate(t) => t.clone(),
-            PathResult::Final(t) => t.clone(),
-            _ => Trajectory::default(),
-        }
+        self.trajectory.trajectory().cloned().unwrap_or_default()
     }
 
     pub fn visualization(&self) -> Visualization {